@@ -0,0 +1,182 @@
+//! Animated GIF export revealing the candles of a chart progressively
+//!
+//! Every frame is the full chart cropped to the candles up to that point, so
+//! axes stay fixed while candles appear one period at a time. Frames are
+//! rasterized by the software renderer and encoded as a looping GIF whose
+//! colors are quantized to the 216-color web palette. Mostly transparent
+//! pixels, e.g. of a `--transparent` background, map to a transparent entry.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use tiny_skia::Pixmap;
+
+use crate::raster;
+use crate::render_model::{AxisRange, RenderModel};
+
+/// Largest number of frames of an animation; longer series reveal several
+/// candles per frame
+pub const MAX_FRAMES: usize = 200;
+
+/// Shortest frame delay most GIF viewers honor, in hundredths of a second
+const MIN_DELAY: u16 = 2;
+
+/// Number of levels per color channel of the web palette
+const LEVELS: usize = 6;
+
+/// Color table entry marked transparent, outside the web palette
+const TRANSPARENT_INDEX: u8 = 255;
+
+/// Builds the frames revealing a chart's candles
+///
+/// Each frame keeps the geometry up to and including one more candle (or
+/// several for series longer than `max_frames`), with the axis ranges of
+/// the full chart. The last frame always shows the whole chart.
+///
+/// # Arguments
+/// * `model` - Full chart
+/// * `max_frames` - Largest number of frames to build, at least 1
+///
+/// # Returns
+/// * `Vec<RenderModel>` - Frames in display order
+pub fn frames(model: &RenderModel, max_frames: usize) -> Vec<RenderModel> {
+    let first = (model.x_range.min + 0.5).ceil().max(0.0) as usize;
+    let last = (model.x_range.max - 0.5).floor().max(first as f64) as usize;
+    let count = last - first + 1;
+    let stride = count.div_ceil(max_frames.max(1));
+    
+    let mut ends: Vec<usize> = (first..=last).skip(stride - 1).step_by(stride).collect();
+    if ends.last() != Some(&last) {
+        ends.push(last);
+    }
+    
+    ends.into_iter()
+        .map(|end| {
+            let mut frame = model.clone();
+            frame.crop(AxisRange { min: model.x_range.min, max: end as f64 + 0.5 });
+            frame.x_range = model.x_range;
+            frame
+        })
+        .collect()
+}
+
+/// Renders frames and writes them as a looping animated GIF
+///
+/// # Arguments
+/// * `frames` - Charts to show in order, all of the same size
+/// * `fps` - Frames per second
+/// * `path` - Destination file
+///
+/// # Returns
+/// * `Result<()>` - Ok if the file was written
+///
+/// # Errors
+/// * Returns error if there are no frames, a frame cannot be rendered or
+///   is larger than GIF allows, or writing fails
+pub fn write_gif(frames: &[RenderModel], fps: u32, path: &Path) -> Result<()> {
+    let pixmaps = frames.iter().map(raster::render).collect::<Result<Vec<_>>>()?;
+    let bytes = encode_gif(&pixmaps, fps)?;
+    
+    fs::write(path, bytes).context(format!("Failed to write animation: {}", path.display()))?;
+    log::info!("Wrote {} frame animation to {}", frames.len(), path.display());
+    Ok(())
+}
+
+/// Encodes canvases of equal size as a looping GIF89a
+fn encode_gif(pixmaps: &[Pixmap], fps: u32) -> Result<Vec<u8>> {
+    let first = pixmaps.first().ok_or_else(|| anyhow!("Animation has no frames"))?;
+    let width = u16::try_from(first.width()).context("Animation is wider than GIF allows")?;
+    let height = u16::try_from(first.height()).context("Animation is taller than GIF allows")?;
+    let delay = (100 / fps.max(1)).clamp(MIN_DELAY as u32, u16::MAX as u32) as u16;
+    
+    let palette: Vec<u8> = (0..256)
+        .flat_map(|index| match index < LEVELS.pow(3) {
+            true => [index / 36, index / 6 % 6, index % 6].map(|level| (level * 51) as u8),
+            false => [0, 0, 0],
+        })
+        .collect();
+    let mut encoder = gif::Encoder::new(Vec::new(), width, height, &palette).context("Failed to start animation")?;
+    encoder.set_repeat(gif::Repeat::Infinite).context("Failed to write animation loop")?;
+    
+    for pixmap in pixmaps {
+        if (pixmap.width(), pixmap.height()) != (first.width(), first.height()) {
+            return Err(anyhow!("Animation frames differ in size"));
+        }
+        let indices: Vec<u8> = pixmap.pixels().iter().map(|pixel| palette_index(pixel.demultiply())).collect();
+        let transparent = indices.contains(&TRANSPARENT_INDEX).then_some(TRANSPARENT_INDEX);
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, indices, transparent);
+        frame.delay = delay;
+        // Transparent frames clear to the background so earlier frames don't show through
+        if transparent.is_some() {
+            frame.dispose = gif::DisposalMethod::Background;
+        }
+        encoder.write_frame(&frame).context("Failed to encode animation frame")?;
+    }
+    
+    encoder.into_inner().context("Failed to finish animation")
+}
+
+/// Maps a color to the nearest entry of the web palette, or the transparent entry
+fn palette_index(color: tiny_skia::ColorU8) -> u8 {
+    if color.alpha() < 128 {
+        return TRANSPARENT_INDEX;
+    }
+    let level = |channel: u8| (channel as usize + 25) / 51;
+    (level(color.red()) * 36 + level(color.green()) * 6 + level(color.blue())) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processor::DataProcessor;
+    use crate::plotter::Plotter;
+    
+    /// Test progressive frames, striding and the fixed axes
+    #[test]
+    fn test_frames() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let model = Plotter::new().render_model(processor.get_data())?;
+        
+        let all = frames(&model, MAX_FRAMES);
+        assert_eq!(all.iter().map(|frame| frame.candles.len()).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(all.iter().all(|frame| frame.x_range == model.x_range && frame.y_range == model.y_range));
+        
+        let strided = frames(&model, 2);
+        assert_eq!(strided.iter().map(|frame| frame.candles.len()).collect::<Vec<_>>(), vec![2, 3]);
+        
+        Ok(())
+    }
+    
+    /// Test the GIF structure and that frames decode to palette indices
+    #[test]
+    fn test_encode_gif() -> Result<()> {
+        let mut pixmap = Pixmap::new(3, 2).ok_or_else(|| anyhow!("no pixmap"))?;
+        pixmap.fill(tiny_skia::Color::from_rgba8(255, 0, 0, 255));
+        let gif = encode_gif(&[pixmap.clone(), pixmap], 10)?;
+        assert_eq!(&gif[..6], b"GIF89a");
+        
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.clone().read_info(gif.as_slice())?;
+        assert_eq!((decoder.width(), decoder.height()), (3, 2));
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame()? {
+            assert_eq!(frame.delay, 10);
+            assert_eq!(frame.transparent, None);
+            assert_eq!(frame.buffer.as_ref(), &[180; 6]);
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        
+        assert!(encode_gif(&[], 10).is_err());
+        
+        let transparent = encode_gif(&[Pixmap::new(3, 2).ok_or_else(|| anyhow!("no pixmap"))?], 10)?;
+        let mut decoder = options.read_info(transparent.as_slice())?;
+        let frame = decoder.read_next_frame()?.ok_or_else(|| anyhow!("no frame"))?;
+        assert_eq!(frame.transparent, Some(TRANSPARENT_INDEX));
+        assert_eq!(frame.dispose, gif::DisposalMethod::Background);
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! Saving and replaying command line options as TOML
+//!
+//! A config file maps argument ids (the snake_case field names of `Args`) to
+//! the values given on the command line. Loading a config turns it back into
+//! command line tokens that are parsed underneath the real command line, so
+//! explicit options still take precedence and validation stays in clap.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command};
+
+/// Arguments that control config handling itself and are never stored
+const EXCLUDED: [&str; 4] = ["config", "save_config", "help", "version"];
+
+/// Writes every explicitly given option of a parsed command line to a TOML file
+///
+/// Defaulted options and positional arguments are left out, so replaying the
+/// file reproduces the same effective options.
+///
+/// # Arguments
+/// * `path` - Destination file
+/// * `command` - Command the matches were parsed with
+/// * `matches` - Parsed command line
+///
+/// # Returns
+/// * `Result<()>` - Ok if the file was written
+///
+/// # Errors
+/// * Returns error if serialization or writing fails
+pub fn save(path: &Path, command: &Command, matches: &ArgMatches) -> Result<()> {
+    let mut table = toml::Table::new();
+    
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if arg.is_positional()
+            || EXCLUDED.contains(&id)
+            || matches.value_source(id) != Some(ValueSource::CommandLine)
+        {
+            continue;
+        }
+        
+        let value = if matches!(arg.get_action(), ArgAction::SetTrue) {
+            toml::Value::Boolean(matches.get_flag(id))
+        } else {
+            let values: Vec<toml::Value> = matches
+                .get_raw(id)
+                .into_iter()
+                .flatten()
+                .map(|raw| toml::Value::String(raw.to_string_lossy().into_owned()))
+                .collect();
+            if takes_many(arg) {
+                toml::Value::Array(values)
+            } else {
+                values.into_iter().next_back().unwrap_or(toml::Value::Boolean(true))
+            }
+        };
+        table.insert(id.to_string(), value);
+    }
+    
+    let text = toml::to_string_pretty(&table).context("Failed to serialize config")?;
+    fs::write(path, text).context(format!("Failed to write config: {}", path.display()))?;
+    log::info!("Wrote config to {}", path.display());
+    Ok(())
+}
+
+/// Reads a TOML config file into command line tokens
+///
+/// # Arguments
+/// * `path` - Config file written by `save` or by hand
+/// * `command` - Command whose options the keys must name
+///
+/// # Returns
+/// * `Result<Vec<OsString>>` - Tokens such as `--csv-file=data.csv`, without
+///   the program name
+///
+/// # Errors
+/// * Returns error if the file cannot be read or parsed, a key names no
+///   option, or a value has an unsupported type
+pub fn load(path: &Path, command: &Command) -> Result<Vec<OsString>> {
+    let text = fs::read_to_string(path).context(format!("Failed to read config: {}", path.display()))?;
+    let table: toml::Table = text.parse().context(format!("Invalid TOML in config: {}", path.display()))?;
+    
+    tokens(&table, command, path)
+}
+
+/// Reads a batch manifest into the command line tokens of each chart
+///
+/// A `.json` manifest is an array of objects, any other a TOML file with one
+/// `[[chart]]` table per chart. Every entry uses the keys of a config file.
+///
+/// # Arguments
+/// * `path` - Manifest file
+/// * `command` - Command whose options the keys must name
+///
+/// # Returns
+/// * `Result<Vec<Vec<OsString>>>` - Tokens per chart in manifest order
+///
+/// # Errors
+/// * Returns error if the file cannot be read or parsed, or any entry is
+///   not a valid config
+pub fn load_batch(path: &Path, command: &Command) -> Result<Vec<Vec<OsString>>> {
+    let text = fs::read_to_string(path).context(format!("Failed to read manifest: {}", path.display()))?;
+    let charts: Vec<toml::Table> = if path.extension().is_some_and(|extension| extension == "json") {
+        serde_json::from_str(&text).context(format!("Invalid JSON in manifest: {}", path.display()))?
+    } else {
+        let mut table: toml::Table = text.parse().context(format!("Invalid TOML in manifest: {}", path.display()))?;
+        match table.remove("chart") {
+            Some(charts) => charts.try_into().context(format!("'chart' must be an array of tables in {}", path.display()))?,
+            None => Vec::new(),
+        }
+    };
+    
+    charts
+        .iter()
+        .enumerate()
+        .map(|(index, table)| tokens(table, command, path).context(format!("Invalid chart {} in manifest", index + 1)))
+        .collect()
+}
+
+/// Converts a config table into command line tokens
+fn tokens(table: &toml::Table, command: &Command, path: &Path) -> Result<Vec<OsString>> {
+    let mut tokens = Vec::new();
+    for (key, value) in table {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id().as_str() == key && !arg.is_positional() && !EXCLUDED.contains(&key.as_str()))
+            .ok_or_else(|| anyhow!("Unknown config key '{}' in {}", key, path.display()))?;
+        let long = format!("--{}", arg.get_long().unwrap_or(key));
+        
+        match value {
+            toml::Value::Boolean(true) => tokens.push(long.into()),
+            toml::Value::Boolean(false) => {}
+            toml::Value::Array(items) => {
+                let items = items.iter().map(scalar).collect::<Result<Vec<_>>>()
+                    .context(format!("Invalid value for config key '{}'", key))?;
+                if !matches!(arg.get_action(), ArgAction::Append) {
+                    tokens.push(long.into());
+                    tokens.extend(items.into_iter().map(OsString::from));
+                } else {
+                    tokens.extend(items.into_iter().map(|item| OsString::from(format!("{}={}", long, item))));
+                }
+            }
+            other => {
+                let item = scalar(other).context(format!("Invalid value for config key '{}'", key))?;
+                tokens.push(format!("{}={}", long, item).into());
+            }
+        }
+    }
+    
+    Ok(tokens)
+}
+
+/// Returns whether an option accepts several values per occurrence
+fn takes_many(arg: &clap::Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Append)
+        || arg.get_num_args().is_some_and(|range| range.max_values() > 1)
+}
+
+/// Converts a TOML scalar into its command line text
+fn scalar(value: &toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(text) => Ok(text.clone()),
+        toml::Value::Integer(number) => Ok(number.to_string()),
+        toml::Value::Float(number) => Ok(number.to_string()),
+        toml::Value::Boolean(flag) => Ok(flag.to_string()),
+        other => Err(anyhow!("unsupported value {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+    use tempfile::TempDir;
+    
+    /// Builds a small command covering flags, options, lists and value pairs
+    fn command() -> Command {
+        Command::new("test")
+            .args_override_self(true)
+            .arg(Arg::new("input").required(true))
+            .arg(Arg::new("last").long("last"))
+            .arg(Arg::new("opacity").long("opacity").default_value("1.0"))
+            .arg(Arg::new("wick_ratios").long("wick-ratios").action(ArgAction::SetTrue))
+            .arg(Arg::new("color_map").long("color-map").action(ArgAction::Append))
+            .arg(Arg::new("compare").long("compare").num_args(2))
+            .arg(Arg::new("save_config").long("save-config"))
+    }
+    
+    /// Test that a saved config replays to the same explicit options
+    #[test]
+    fn test_save_and_load_roundtrip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("chart.toml");
+        
+        let matches = command().try_get_matches_from([
+            "test", "in", "--last", "5", "--wick-ratios", "--color-map", "a=#ff0000",
+            "--color-map", "b=#00ff00", "--compare", "x.csv", "y.csv", "--save-config", "ignored.toml",
+        ])?;
+        save(&path, &command(), &matches)?;
+        
+        let text = fs::read_to_string(&path)?;
+        assert!(!text.contains("opacity"));
+        assert!(!text.contains("save_config"));
+        
+        let mut tokens: Vec<OsString> = vec!["test".into()];
+        tokens.extend(load(&path, &command())?);
+        tokens.extend(["in".into(), "--last".into(), "7".into()]);
+        let replayed = command().try_get_matches_from(tokens)?;
+        
+        assert_eq!(replayed.get_one::<String>("last").map(String::as_str), Some("7"));
+        assert!(replayed.get_flag("wick_ratios"));
+        assert_eq!(replayed.get_many::<String>("color_map").unwrap().count(), 2);
+        assert_eq!(replayed.get_many::<String>("compare").unwrap().collect::<Vec<_>>(), ["x.csv", "y.csv"]);
+        assert_eq!(replayed.value_source("opacity"), Some(ValueSource::DefaultValue));
+        
+        Ok(())
+    }
+    
+    /// Test that unknown keys are rejected
+    #[test]
+    fn test_load_unknown_key() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("bad.toml");
+        fs::write(&path, "bogus = 1\n")?;
+        
+        let err = load(&path, &command()).unwrap_err();
+        assert!(err.to_string().contains("Unknown config key 'bogus'"));
+        
+        Ok(())
+    }
+    
+    /// Test reading TOML and JSON manifests into tokens per chart
+    #[test]
+    fn test_load_batch() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("nightly.toml");
+        fs::write(&path, "[[chart]]\nlast = 5\n\n[[chart]]\nwick_ratios = true\ncolor_map = [\"a=#ff0000\"]\n")?;
+        
+        let charts = load_batch(&path, &command())?;
+        assert_eq!(charts, vec![
+            vec![OsString::from("--last=5")],
+            vec![OsString::from("--color-map=a=#ff0000"), OsString::from("--wick-ratios")],
+        ]);
+        
+        let path = temp_dir.path().join("nightly.json");
+        fs::write(&path, r#"[{"last": "3"}, {"bogus": 1}]"#)?;
+        let err = load_batch(&path, &command()).unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid chart 2 in manifest: Unknown config key 'bogus'"));
+        
+        fs::write(&path, r#"[{"last": "3"}]"#)?;
+        assert_eq!(load_batch(&path, &command())?, vec![vec![OsString::from("--last=3")]]);
+        
+        Ok(())
+    }
+}
@@ -1,41 +1,239 @@
 //! Data processing module for handling CSV data and financial calculations
 
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Write;
+use std::ops::Range;
 use std::path::Path;
-use csv::ReaderBuilder;
-use serde::Deserialize;
-use anyhow::{Result, Context};
-use chrono::{DateTime, NaiveDateTime, Utc};
+use csv::{ReaderBuilder, WriterBuilder};
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context, anyhow};
+use chrono::{DateTime, Datelike, Duration, Utc};
+
+use crate::AppError;
+use crate::indicators;
+use crate::patterns::{Signal, SignalSide};
+use crate::utils::{date_utils, file_utils, stats_utils};
+use crate::utils::date_utils::TimestampFormat;
+use crate::utils::locale_utils::Locale;
+
+/// Column names consumed by the OHLCV fields of `HistoricalData`
+pub const OHLCV_COLUMNS: [&str; 6] = ["Timestamp", "Open", "High", "Low", "Close", "Volume"];
+
+/// Version of the candle CSV and render model JSON export formats
+///
+/// Written as a `# schema_version=N` comment above the CSV header and as the
+/// `schema_version` field of exported JSON, so consumers can detect format
+/// changes. Bump it whenever columns or fields are renamed, removed or change
+/// meaning; adding optional JSON fields does not require a bump.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Id of the element holding the candles embedded in an exported chart
+pub const EMBEDDED_DATA_ID: &str = "candle-data";
+
+/// Largest series `DataProcessor::fill_missing` produces before giving up,
+/// so a far outlying timestamp cannot exhaust memory
+pub const MAX_FILLED_CANDLES: usize = 1_000_000;
 
 /// Represents a single data point from the CSV file
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct HistoricalData {
     #[allow(unused)]
-    #[serde(rename = "Timestamp")]
+    #[serde(rename = "Timestamp", alias = "timestamp")]
     pub timestamp: String,
     
     #[allow(unused)]
-    #[serde(rename = "Open")]
+    #[serde(rename = "Open", alias = "open")]
     pub open: f64,
     
     #[allow(unused)]
-    #[serde(rename = "High")]
+    #[serde(rename = "High", alias = "high")]
     pub high: f64,
     
     #[allow(unused)]
-    #[serde(rename = "Low")]
+    #[serde(rename = "Low", alias = "low")]
     pub low: f64,
     
     #[allow(unused)]
-    #[serde(rename = "Close")]
+    #[serde(rename = "Close", alias = "close")]
     pub close: f64,
     
     #[allow(unused)]
-    #[serde(rename = "Volume")]
+    #[serde(rename = "Volume", alias = "volume")]
+    pub volume: f64,
+    
+    /// Additional columns beyond OHLCV, keyed by header name
+    #[serde(skip)]
+    pub extra: HashMap<String, String>,
+    
+    /// Inserted by `DataProcessor::fill_missing` rather than read from the input
+    #[serde(skip)]
+    pub synthetic: bool,
+}
+
+impl HistoricalData {
+    /// Parses the timestamp column as a UTC date-time
+    ///
+    /// Purely numeric timestamps are read as epoch seconds or milliseconds
+    /// depending on their digit count.
+    ///
+    /// # Returns
+    /// * `Result<DateTime<Utc>>` - Parsed timestamp
+    ///
+    /// # Errors
+    /// * Returns error if the timestamp is neither in `%Y-%m-%d %H:%M:%S`
+    ///   format, optionally with fractional seconds, nor a 10 or 13 digit
+    ///   epoch timestamp
+    pub fn parsed_timestamp(&self) -> Result<DateTime<Utc>> {
+        date_utils::parse_timestamp(&self.timestamp, TimestampFormat::Auto)
+    }
+}
+
+/// A dated annotation such as an earnings report or a split
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Event {
+    #[serde(rename = "Timestamp", alias = "timestamp")]
+    pub timestamp: String,
+    
+    /// Text shown next to the event line
+    #[serde(rename = "Label", alias = "label")]
+    pub label: String,
+    
+    /// Category selecting the event color, e.g. `earnings` or `split`
+    #[serde(rename = "Kind", alias = "kind")]
+    pub kind: String,
+}
+
+/// Completed long trade produced by `DataProcessor::simulate`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub entry_time: DateTime<Utc>,
+    pub entry_price: f64,
+    pub exit_time: DateTime<Utc>,
+    pub exit_price: f64,
+}
+
+impl Trade {
+    /// Percentage gain or loss from entry to exit
+    pub fn return_pct(&self) -> f64 {
+        (self.exit_price - self.entry_price) / self.entry_price * 100.0
+    }
+}
+
+/// Counts behind a score of `DataProcessor::quality_score`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct QualityReport {
+    pub candles: usize,
+    /// Intervals between consecutive candles wider than the smallest one
+    pub gaps: usize,
+    /// Consecutive candles sharing a timestamp
+    pub duplicates: usize,
+    /// Candles with any non-finite price or volume
+    pub non_finite: usize,
+    /// Finite candles whose open or close lies outside the high-low range,
+    /// whose high is below the low or whose volume is negative
+    pub ohlc_violations: usize,
+}
+
+impl QualityReport {
+    /// Weight of each check in points, in the order gaps, duplicates,
+    /// non-finite values and OHLC violations; together they make up 100
+    pub const WEIGHTS: [f64; 4] = [10.0, 20.0, 40.0, 30.0];
+    
+    /// Points lost to each check, in the order of `WEIGHTS`
+    ///
+    /// Each check loses its weight scaled by the fraction of affected
+    /// candles, or of affected intervals for gaps and duplicates.
+    pub fn penalties(&self) -> [f64; 4] {
+        let fraction = |count: usize, total: usize| match total {
+            0 => 0.0,
+            _ => (count as f64 / total as f64).min(1.0),
+        };
+        let intervals = self.candles.saturating_sub(1);
+        let fractions = [
+            fraction(self.gaps, intervals),
+            fraction(self.duplicates, intervals),
+            fraction(self.non_finite, self.candles),
+            fraction(self.ohlc_violations, self.candles),
+        ];
+        
+        std::array::from_fn(|i| QualityReport::WEIGHTS[i] * fractions[i])
+    }
+    
+    /// Score from 0 (unusable) to 100 (no issues), 0 for an empty series
+    pub fn score(&self) -> f64 {
+        match self.candles {
+            0 => 0.0,
+            _ => (100.0 - self.penalties().iter().sum::<f64>()).max(0.0),
+        }
+    }
+}
+
+/// Candle opening beyond the previous candle's range, found by `DataProcessor::price_gaps`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceGap {
+    /// Index of the candle opening beyond the previous range
+    pub index: usize,
+    /// Whether the candle opened above the previous high rather than below the low
+    pub up: bool,
+    /// Previous high for gaps up, previous low for gaps down
+    pub level: f64,
+    /// Index of the first candle from `index` on trading back through `level`
+    pub filled_at: Option<usize>,
+}
+
+/// How often and how fast price gaps were filled, from `DataProcessor::gap_fill_stats`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GapFillStats {
+    pub gaps: usize,
+    /// Gaps filled at some point
+    pub filled: usize,
+    /// Gaps filled by the candle that opened the gap
+    pub filled_same_candle: usize,
+    /// Gaps filled by the end of the candle after the gap, including the same candle
+    pub filled_next_candle: usize,
+    /// Average time from the gap candle to the filling candle over filled gaps
+    pub mean_time_to_fill: Option<Duration>,
+}
+
+impl GapFillStats {
+    /// Fraction of gaps filled by the gap candle itself, `None` without gaps
+    pub fn same_candle_rate(&self) -> Option<f64> {
+        (self.gaps > 0).then(|| self.filled_same_candle as f64 / self.gaps as f64)
+    }
+    
+    /// Fraction of gaps filled by the end of the next candle, `None` without gaps
+    pub fn next_candle_rate(&self) -> Option<f64> {
+        (self.gaps > 0).then(|| self.filled_next_candle as f64 / self.gaps as f64)
+    }
+}
+
+/// Key figures of a candle series produced by `DataProcessor::summary`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+    /// Open of the first candle
+    pub open: f64,
+    /// Close of the last candle
+    pub close: f64,
+    pub high: f64,
+    pub low: f64,
+    /// Total volume of all candles
     pub volume: f64,
 }
 
+impl Summary {
+    /// Percentage change from the first open to the last close
+    pub fn change_pct(&self) -> f64 {
+        (self.close - self.open) / self.open * 100.0
+    }
+}
+
 /// Represents a candlestick for plotting
-#[derive(Debug, Clone)]
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
 pub struct CandleStick {
     pub timestamp: DateTime<Utc>,
     pub open: f64,
@@ -43,11 +241,511 @@ pub struct CandleStick {
     pub low: f64,
     pub close: f64,
     pub volume: f64,
+    /// Inserted by `DataProcessor::fill_missing` rather than read from the input
+    pub synthetic: bool,
+}
+
+/// Candle with prices stored as integer multiples of a tick size
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickCandle {
+    pub timestamp: DateTime<Utc>,
+    pub open: i64,
+    pub high: i64,
+    pub low: i64,
+    pub close: i64,
+    /// Volume is left unscaled
+    pub volume: f64,
+}
+
+impl From<&CandleStick> for HistoricalData {
+    fn from(candle: &CandleStick) -> Self {
+        HistoricalData {
+            timestamp: date_utils::format_timestamp(&candle.timestamp),
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+            synthetic: candle.synthetic,
+            ..Default::default()
+        }
+    }
+}
+
+/// Delimiter and decimal mark used when writing CSV files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvFormat {
+    /// Field separator, must be a single ASCII character
+    pub delimiter: char,
+    /// Decimal mark of exported numbers
+    pub decimal: char,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        CsvFormat { delimiter: ',', decimal: '.' }
+    }
+}
+
+impl CsvFormat {
+    /// Picks the decimal mark of a locale and a delimiter that does not clash with it
+    ///
+    /// # Arguments
+    /// * `locale` - Locale of the exported numbers
+    ///
+    /// # Returns
+    /// * `CsvFormat` - `;` and `,` for locales with a decimal comma, otherwise
+    ///   `,` and `.`
+    pub fn for_locale(locale: &Locale) -> CsvFormat {
+        let delimiter = if locale.decimal == ',' { ';' } else { ',' };
+        CsvFormat { delimiter, decimal: locale.decimal }
+    }
+    
+    /// Formats a number with the configured decimal mark
+    ///
+    /// # Arguments
+    /// * `value` - Number to format
+    ///
+    /// # Returns
+    /// * `String` - Shortest round-tripping representation of `value`
+    pub fn format_number(&self, value: f64) -> String {
+        let text = value.to_string();
+        if self.decimal == '.' { text } else { text.replace('.', &self.decimal.to_string()) }
+    }
+}
+
+/// Delimiters considered when detecting the delimiter of a file, in order of preference
+pub const DELIMITER_CANDIDATES: [char; 3] = [',', ';', '\t'];
+
+/// Input field separator chosen with `--delimiter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    /// Always split fields at this character
+    Char(char),
+    /// Detect the delimiter of each file from its header line
+    Auto,
+}
+
+impl Delimiter {
+    /// Parses a delimiter argument
+    ///
+    /// # Arguments
+    /// * `text` - `auto`, `tab`, `\t` or a single ASCII character such as `;`
+    ///
+    /// # Returns
+    /// * `Result<Delimiter>` - Parsed delimiter
+    ///
+    /// # Errors
+    /// * Returns error if the text is neither a keyword nor one ASCII character
+    pub fn parse(text: &str) -> Result<Delimiter> {
+        match text {
+            "auto" => Ok(Delimiter::Auto),
+            "tab" | "\\t" => Ok(Delimiter::Char('\t')),
+            _ => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_ascii() => Ok(Delimiter::Char(c)),
+                    _ => Err(anyhow!("Invalid delimiter '{}': expected auto, tab or a single ASCII character", text)),
+                }
+            }
+        }
+    }
+}
+
+/// Loader options for one source's CSV layout
+///
+/// Profile files are TOML with one table per profile name; omitted keys keep
+/// the standard layout, e.g.
+///
+/// ```toml
+/// [mybroker]
+/// delimiter = ";"
+/// timestamp = "Time"
+/// date_format = "%d.%m.%Y %H:%M"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvProfile {
+    /// Field separator, must be a single ASCII character
+    pub delimiter: char,
+    /// Header of the timestamp column
+    pub timestamp: String,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+    /// How numeric timestamps are read when `date_format` is not set
+    pub timestamp_format: TimestampFormat,
+    /// `chrono` format of textual timestamps; date-only formats give midnight
+    pub date_format: Option<String>,
+    /// Lines starting with this ASCII character are comments; leading ones
+    /// may hold `key=value` metadata such as `# symbol=AAPL exchange=NASDAQ`
+    pub comment_char: char,
+    /// Number of lines, e.g. export notes, skipped before the header line
+    pub header_row: usize,
+    /// Pick the delimiter of each file from its header line instead of
+    /// using `delimiter`, see `CsvProfile::delimiter_for`
+    pub detect_delimiter: bool,
+}
+
+impl Default for CsvProfile {
+    fn default() -> Self {
+        let [timestamp, open, high, low, close, volume] = OHLCV_COLUMNS.map(String::from);
+        CsvProfile {
+            delimiter: ',',
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            timestamp_format: TimestampFormat::Auto,
+            date_format: None,
+            comment_char: '#',
+            header_row: 0,
+            detect_delimiter: false,
+        }
+    }
+}
+
+/// Built-in profiles selectable with `--profile`
+///
+/// * `binance` - Kline exports with `open_time` in epoch milliseconds
+/// * `yahoo` - Yahoo Finance history downloads with `Date` as `YYYY-MM-DD`
+/// * `ibkr` - Interactive Brokers bar exports with dates like `20230103  09:30:00`
+fn builtin_profiles() -> Vec<(&'static str, CsvProfile)> {
+    let columns = |names: [&str; 6]| {
+        let [timestamp, open, high, low, close, volume] = names.map(String::from);
+        CsvProfile { timestamp, open, high, low, close, volume, ..CsvProfile::default() }
+    };
+    
+    vec![
+        ("binance", CsvProfile {
+            timestamp_format: TimestampFormat::EpochMs,
+            ..columns(["open_time", "open", "high", "low", "close", "volume"])
+        }),
+        ("ibkr", CsvProfile {
+            date_format: Some("%Y%m%d %H:%M:%S".to_string()),
+            ..columns(["date", "open", "high", "low", "close", "volume"])
+        }),
+        ("yahoo", CsvProfile {
+            date_format: Some("%Y-%m-%d".to_string()),
+            ..columns(["Date", "Open", "High", "Low", "Close", "Volume"])
+        }),
+    ]
+}
+
+impl CsvProfile {
+    /// Looks up a profile, preferring a user profile over a built-in one
+    ///
+    /// # Arguments
+    /// * `name` - Profile name
+    /// * `user_profiles` - Optional TOML file with user profiles
+    ///
+    /// # Returns
+    /// * `Result<CsvProfile>` - Resolved profile
+    ///
+    /// # Errors
+    /// * Returns error if the profile file cannot be read or parsed, or no
+    ///   profile has the given name
+    pub fn resolve(name: &str, user_profiles: Option<&Path>) -> Result<CsvProfile> {
+        let mut user: HashMap<String, CsvProfile> = HashMap::new();
+        if let Some(path) = user_profiles {
+            let text = std::fs::read_to_string(path)
+                .context(format!("Failed to read profiles: {}", path.display()))?;
+            user = toml::from_str(&text).context(format!("Invalid profiles file: {}", path.display()))?;
+        }
+        
+        if let Some(profile) = user.remove(name) {
+            return Ok(profile);
+        }
+        
+        let builtins = builtin_profiles();
+        match builtins.iter().find(|(builtin, _)| *builtin == name) {
+            Some((_, profile)) => Ok(profile.clone()),
+            None => {
+                let mut names: Vec<&str> = builtins.iter().map(|(builtin, _)| *builtin).chain(user.keys().map(String::as_str)).collect();
+                names.sort();
+                names.dedup();
+                Err(anyhow!("Unknown profile '{}', expected one of: {}", name, names.join(", ")))
+            }
+        }
+    }
+    
+    /// Maps a source header to the standard OHLCV column it names, if any
+    ///
+    /// # Arguments
+    /// * `header` - Header as written in the source file
+    ///
+    /// # Returns
+    /// * `Option<&'static str>` - Standard column name such as `Close`
+    pub fn standard_column(&self, header: &str) -> Option<&'static str> {
+        let sources = [&self.timestamp, &self.open, &self.high, &self.low, &self.close, &self.volume];
+        sources
+            .iter()
+            .position(|source| source.trim().eq_ignore_ascii_case(header.trim()))
+            .map(|i| OHLCV_COLUMNS[i])
+    }
+    
+    /// Resolves the delimiter of a file of this profile
+    ///
+    /// With `detect_delimiter`, the first line that is not a comment is taken
+    /// as the header and the candidate of `DELIMITER_CANDIDATES` occurring
+    /// most often in it wins, earlier candidates breaking ties; a header
+    /// without any candidate keeps `delimiter`.
+    ///
+    /// # Arguments
+    /// * `text` - File contents from the header line on
+    ///
+    /// # Returns
+    /// * `char` - Delimiter to split fields at
+    pub fn delimiter_for(&self, text: &str) -> char {
+        if !self.detect_delimiter {
+            return self.delimiter;
+        }
+        let Some(header) = text.lines().find(|line| !line.trim_start().starts_with(self.comment_char)) else {
+            return self.delimiter;
+        };
+        
+        let mut best = (0, self.delimiter);
+        for candidate in DELIMITER_CANDIDATES {
+            let count = header.matches(candidate).count();
+            if count > best.0 {
+                best = (count, candidate);
+            }
+        }
+        log::debug!("Detected delimiter {:?} from header '{}'", best.1, header);
+        best.1
+    }
+    
+    /// Reads a file of this profile from its header line on
+    ///
+    /// # Arguments
+    /// * `path` - Path to the CSV file
+    ///
+    /// # Returns
+    /// * `Result<String>` - File contents without the `header_row` lines
+    ///   before the header
+    ///
+    /// # Errors
+    /// * Returns error if the file cannot be read, or `header_row` is set and
+    ///   the header line is blank or past the end of the file
+    pub fn read_from_header(&self, path: &Path) -> Result<String> {
+        let text = std::fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        if self.header_row == 0 {
+            return Ok(text);
+        }
+        
+        let body = text.split_inclusive('\n').skip(self.header_row).collect::<String>();
+        match body.lines().next() {
+            None => Err(anyhow!("Header row {} is past the end of {}", self.header_row, path.display())),
+            Some(header) if header.trim().is_empty() => Err(anyhow!("Header row {} of {} is blank", self.header_row, path.display())),
+            Some(_) => Ok(body),
+        }
+    }
+    
+    /// Parses a source timestamp as written in files of this profile
+    ///
+    /// # Arguments
+    /// * `timestamp` - Timestamp text from the file
+    ///
+    /// # Returns
+    /// * `Result<DateTime<Utc>>` - Parsed timestamp
+    ///
+    /// # Errors
+    /// * Returns error if the text matches neither `date_format` nor
+    ///   `timestamp_format`
+    pub fn parse_timestamp(&self, timestamp: &str) -> Result<DateTime<Utc>> {
+        match &self.date_format {
+            Some(format) => date_utils::parse_with_format(timestamp, format),
+            None => date_utils::parse_timestamp(timestamp, self.timestamp_format),
+        }
+    }
+    
+    /// Rewrites a source timestamp in `%Y-%m-%d %H:%M:%S` form
+    ///
+    /// Timestamps of the standard layout are kept as they are.
+    fn normalize_timestamp(&self, timestamp: &str) -> Result<String> {
+        if self.date_format.is_none() && self.timestamp_format == TimestampFormat::Auto {
+            return Ok(timestamp.to_string());
+        }
+        Ok(date_utils::format_timestamp(&self.parse_timestamp(timestamp)?))
+    }
+}
+
+/// Which edge of a resampling bucket labels the resulting candle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LabelTime {
+    /// Start of the bucket, as used by most charting platforms
+    #[default]
+    Open,
+    /// End of the bucket (the start of the next one)
+    Close,
+}
+
+/// Calendar grouping of returns for seasonality statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SeasonBy {
+    /// Day of the week, Monday first
+    Dow,
+    /// Month of the year, January first
+    Month,
+}
+
+impl SeasonBy {
+    /// Names of the groups in calendar order
+    pub fn names(self) -> &'static [&'static str] {
+        match self {
+            SeasonBy::Dow => &["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+            SeasonBy::Month => &["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"],
+        }
+    }
+    
+    /// Position of the group of a timestamp in `names`
+    pub fn group(self, timestamp: &DateTime<Utc>) -> usize {
+        match self {
+            SeasonBy::Dow => timestamp.weekday().num_days_from_monday() as usize,
+            SeasonBy::Month => timestamp.month0() as usize,
+        }
+    }
+}
+
+/// Where the volume of mid-price candles comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum VolumeSource {
+    /// Bid plus ask volume
+    #[default]
+    Sum,
+    /// Volume of the bid candle
+    Bid,
+    /// Volume of the ask candle
+    Ask,
+}
+
+/// How the values of one field are combined into a resampled candle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    First,
+    Last,
+    Max,
+    Min,
+    Sum,
+    Mean,
+    Median,
+}
+
+impl Aggregation {
+    /// Names accepted by `AggSpec::parse`, in the order of the variants
+    const NAMES: [(&'static str, Aggregation); 7] = [
+        ("first", Aggregation::First),
+        ("last", Aggregation::Last),
+        ("max", Aggregation::Max),
+        ("min", Aggregation::Min),
+        ("sum", Aggregation::Sum),
+        ("mean", Aggregation::Mean),
+        ("median", Aggregation::Median),
+    ];
+    
+    /// Combines the values of a bucket, which must not be empty
+    fn apply(self, values: &[f64]) -> f64 {
+        match self {
+            Aggregation::First => values[0],
+            Aggregation::Last => values[values.len() - 1],
+            Aggregation::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregation::Sum => values.iter().sum(),
+            Aggregation::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregation::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let middle = sorted.len() / 2;
+                match sorted.len() % 2 {
+                    0 => (sorted[middle - 1] + sorted[middle]) / 2.0,
+                    _ => sorted[middle],
+                }
+            }
+        }
+    }
+}
+
+/// Aggregation of every OHLCV field used when resampling
+///
+/// The default is the standard OHLC rule: first open, highest high, lowest
+/// low, last close and summed volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggSpec {
+    pub open: Aggregation,
+    pub high: Aggregation,
+    pub low: Aggregation,
+    pub close: Aggregation,
+    pub volume: Aggregation,
+}
+
+impl Default for AggSpec {
+    fn default() -> Self {
+        AggSpec {
+            open: Aggregation::First,
+            high: Aggregation::Max,
+            low: Aggregation::Min,
+            close: Aggregation::Last,
+            volume: Aggregation::Sum,
+        }
+    }
+}
+
+impl AggSpec {
+    /// Parses `field=function` pairs such as `volume=max,close=median`
+    ///
+    /// Fields not mentioned keep their default aggregation.
+    ///
+    /// # Arguments
+    /// * `text` - Comma-separated pairs of a field and an aggregation name
+    ///
+    /// # Returns
+    /// * `Result<AggSpec>` - Default spec with the given fields replaced
+    ///
+    /// # Errors
+    /// * Returns error if a pair is malformed or names an unknown field or function
+    pub fn parse(text: &str) -> Result<AggSpec> {
+        let mut spec = AggSpec::default();
+        
+        for pair in text.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (field, function) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid aggregation '{}': expected FIELD=FUNCTION", pair))?;
+            let (field, function) = (field.trim().to_lowercase(), function.trim().to_lowercase());
+            
+            let aggregation = Aggregation::NAMES
+                .iter()
+                .find(|(name, _)| *name == function)
+                .map(|(_, aggregation)| *aggregation)
+                .ok_or_else(|| anyhow!(
+                    "Unknown aggregation '{}' for {}, expected one of: {}",
+                    function,
+                    field,
+                    Aggregation::NAMES.map(|(name, _)| name).join(", ")
+                ))?;
+            let target = match field.as_str() {
+                "open" => &mut spec.open,
+                "high" => &mut spec.high,
+                "low" => &mut spec.low,
+                "close" => &mut spec.close,
+                "volume" => &mut spec.volume,
+                _ => return Err(anyhow!("Unknown field '{}', expected one of: open, high, low, close, volume", field)),
+            };
+            *target = aggregation;
+        }
+        
+        Ok(spec)
+    }
 }
 
 /// Processes and manages financial data
 pub struct DataProcessor {
     data: Vec<HistoricalData>,
+    profile: CsvProfile,
+    metadata: HashMap<String, String>,
 }
 
 impl DataProcessor {
@@ -56,7 +754,29 @@ impl DataProcessor {
     /// # Returns
     /// * `DataProcessor` - New instance
     pub fn new() -> Self {
-        DataProcessor { data: Vec::new() }
+        Self::with_profile(CsvProfile::default())
+    }
+    
+    /// Creates a DataProcessor reading CSV files laid out as in `profile`
+    ///
+    /// # Arguments
+    /// * `profile` - Column names, delimiter and timestamp format of the files
+    ///
+    /// # Returns
+    /// * `DataProcessor` - New instance
+    pub fn with_profile(profile: CsvProfile) -> Self {
+        DataProcessor { data: Vec::new(), profile, metadata: HashMap::new() }
+    }
+    
+    /// Creates a DataProcessor holding rows that were loaded elsewhere
+    ///
+    /// # Arguments
+    /// * `data` - Rows to process
+    ///
+    /// # Returns
+    /// * `DataProcessor` - Instance with the default profile and no metadata
+    pub fn with_data(data: Vec<HistoricalData>) -> Self {
+        DataProcessor { data, ..Self::new() }
     }
     
     /// Loads CSV data from the specified file path
@@ -77,15 +797,35 @@ impl DataProcessor {
             // Create sample data for testing if file doesn't exist
             self.generate_sample_data()
         } else {
+            if !self.profile.comment_char.is_ascii() {
+                return Err(anyhow!("CSV comment character '{}' must be an ASCII character", self.profile.comment_char));
+            }
+            let text = self.profile.read_from_header(path)?;
+            let delimiter = self.profile.delimiter_for(&text);
+            if !delimiter.is_ascii() {
+                return Err(anyhow!("CSV delimiter '{}' must be an ASCII character", delimiter));
+            }
+            self.metadata = Self::read_metadata(&text, self.profile.comment_char);
             let mut rdr = ReaderBuilder::new()
                 .has_headers(true)
-                .from_path(path)
-                .context("Failed to create CSV reader")?;
+                .delimiter(delimiter as u8)
+                .comment(Some(self.profile.comment_char as u8))
+                .from_reader(text.as_bytes());
             
+            let headers: csv::StringRecord = rdr.headers()
+                .context("Failed to read CSV headers")?
+                .iter()
+                .map(|header| self.profile.standard_column(header).unwrap_or(header))
+                .collect();
             let mut data = Vec::new();
             
-            for result in rdr.deserialize() {
-                let record: HistoricalData = result.context("Failed to deserialize CSV record")?;
+            for result in rdr.records() {
+                let row = result.context("Failed to read CSV record")?;
+                let mut record: HistoricalData = row.deserialize(Some(&headers))
+                    .context("Failed to deserialize CSV record")?;
+                record.timestamp = self.profile.normalize_timestamp(&record.timestamp)
+                    .context(format!("Invalid timestamp in {}", path.display()))?;
+                record.extra = Self::extra_columns(&headers, &row);
                 data.push(record);
             }
             
@@ -94,133 +834,2529 @@ impl DataProcessor {
         }
     }
     
-    /// Generates sample data for testing purposes
+    /// Returns the metadata of the last loaded file
     ///
     /// # Returns
-    /// * `Result<Vec<HistoricalData>>` - Generated sample data
-    fn generate_sample_data(&mut self) -> Result<Vec<HistoricalData>> {
-        let sample_data = vec![
-            HistoricalData {
-                timestamp: "2023-01-01 00:00:00".to_string(),
-                open: 100.0,
-                high: 105.0,
-                low: 95.0,
-                close: 102.0,
-                volume: 1000.0,
-            },
-            HistoricalData {
-                timestamp: "2023-01-02 00:00:00".to_string(),
-                open: 102.0,
-                high: 108.0,
-                low: 101.0,
-                close: 106.0,
-                volume: 1200.0,
-            },
-            HistoricalData {
-                timestamp: "2023-01-03 00:00:00".to_string(),
-                open: 106.0,
-                high: 110.0,
-                low: 104.0,
-                close: 108.0,
-                volume: 1500.0,
-            },
-        ];
-        
-        self.data = sample_data.clone();
-        Ok(sample_data)
+    /// * `&HashMap<String, String>` - `key=value` pairs from the leading
+    ///   comment lines, empty if there were none
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
     }
     
-    /// Converts historical data to candlestick format
+    /// Parses `key=value` pairs from the comment lines at the top of a file
     ///
-    /// # Returns
-    /// * `Result<Vec<CandleStick>>` - Vector of candlestick data
-    pub fn to_candlesticks(&self) -> Result<Vec<CandleStick>> {
-        let mut candlesticks = Vec::new();
+    /// Keys are lowercased; words without `=` are ignored, and later pairs
+    /// override earlier ones with the same key.
+    fn read_metadata(text: &str, comment_char: char) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
         
-        for data in &self.data {
-            let timestamp = NaiveDateTime::parse_from_str(&data.timestamp, "%Y-%m-%d %H:%M:%S")
-                .context("Failed to parse timestamp")?;
-            let datetime = DateTime::<Utc>::from_naive_utc_and_offset(timestamp, Utc);
-            
-            candlesticks.push(CandleStick {
-                timestamp: datetime,
-                open: data.open,
-                high: data.high,
-                low: data.low,
-                close: data.close,
-                volume: data.volume,
+        for line in text.lines() {
+            let Some(comment) = line.trim_start().strip_prefix(comment_char) else {
+                break;
+            };
+            for (key, value) in comment.split_whitespace().filter_map(|word| word.split_once('=')) {
+                metadata.insert(key.to_lowercase(), value.to_string());
+            }
+        }
+        
+        metadata
+    }
+    
+    /// Loads chart annotations from a `Timestamp,Label,Kind` CSV file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the events file
+    ///
+    /// # Returns
+    /// * `Result<Vec<Event>>` - Events in file order
+    ///
+    /// # Errors
+    /// * Returns error if the file cannot be read, a record is malformed or a
+    ///   timestamp cannot be parsed
+    pub fn load_events(path: &Path) -> Result<Vec<Event>> {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .from_path(path)
+            .context(format!("Failed to read events file: {}", path.display()))?;
+        
+        let mut events = Vec::new();
+        for result in rdr.deserialize() {
+            let event: Event = result.context("Failed to deserialize event record")?;
+            date_utils::parse_timestamp(&event.timestamp, TimestampFormat::Auto)
+                .context(format!("Invalid event '{}'", event.label))?;
+            events.push(event);
+        }
+        
+        Ok(events)
+    }
+    
+    /// Loads a wide CSV file holding one close column per symbol
+    ///
+    /// The first column holds the timestamp of each row. Every requested
+    /// symbol column becomes its own series of close-only candles (open, high,
+    /// low and close all equal the close, volume is zero); empty cells are
+    /// skipped so symbols may have gaps.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the wide CSV file
+    /// * `symbol_cols` - Names of the columns to pivot into series
+    ///
+    /// # Returns
+    /// * `Result<HashMap<String, Vec<CandleStick>>>` - One series per symbol column
+    ///
+    /// # Errors
+    /// * Returns error if the file cannot be read, a symbol column is missing,
+    ///   or a timestamp or close value cannot be parsed
+    pub fn load_wide(path: &Path, symbol_cols: &[&str]) -> Result<HashMap<String, Vec<CandleStick>>> {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .from_path(path)
+            .context(format!("Failed to open wide CSV: {}", path.display()))?;
+        
+        let headers = rdr.headers()
+            .context("Failed to read CSV headers")?
+            .clone();
+        let columns = symbol_cols
+            .iter()
+            .map(|symbol| {
+                headers.iter()
+                    .position(|header| header == *symbol)
+                    .map(|index| (symbol.to_string(), index))
+                    .ok_or_else(|| anyhow!("Symbol column '{}' not found in {}", symbol, path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut series: HashMap<String, Vec<CandleStick>> = symbol_cols
+            .iter()
+            .map(|symbol| (symbol.to_string(), Vec::new()))
+            .collect();
+        
+        for result in rdr.records() {
+            let row = result.context("Failed to read CSV record")?;
+            let timestamp = HistoricalData { timestamp: row.get(0).unwrap_or_default().to_string(), ..Default::default() }
+                .parsed_timestamp()?;
+            
+            for (symbol, index) in &columns {
+                let cell = row.get(*index).unwrap_or_default().trim();
+                if cell.is_empty() {
+                    continue;
+                }
+                
+                let close: f64 = cell.parse()
+                    .context(format!("Invalid close '{}' for {} at {}", cell, symbol, timestamp))?;
+                series.entry(symbol.clone()).or_default().push(CandleStick {
+                    timestamp,
+                    open: close,
+                    high: close,
+                    low: close,
+                    close,
+                    volume: 0.0,
+                    synthetic: false,
+                });
+            }
+        }
+        
+        Ok(series)
+    }
+    
+    /// Loads historical data from the result of a SQLite query
+    ///
+    /// Result columns are matched to the OHLCV fields by name, ignoring case
+    /// (`Timestamp`, `Open`, `High`, `Low`, `Close`, `Volume`); other columns
+    /// are kept as extra text columns. Integer timestamps are read as Unix
+    /// seconds.
+    ///
+    /// # Arguments
+    /// * `db` - Path to the SQLite database file
+    /// * `query` - SQL query returning one row per candle
+    ///
+    /// # Returns
+    /// * `Result<Vec<HistoricalData>>` - Vector of queried historical data
+    ///
+    /// # Errors
+    /// * Returns error if the database cannot be opened, the query fails, or
+    ///   required columns are missing (all missing columns are listed)
+    #[cfg(feature = "sqlite")]
+    pub fn load_sqlite(&mut self, db: &Path, query: &str) -> Result<Vec<HistoricalData>> {
+        use rusqlite::types::ValueRef;
+        
+        let connection = rusqlite::Connection::open_with_flags(db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context(format!("Failed to open SQLite database: {}", db.display()))?;
+        let mut statement = connection.prepare(query).context("Failed to prepare SQLite query")?;
+        
+        let names: Vec<String> = statement.column_names().iter().map(|name| name.to_string()).collect();
+        let position = |column: &str| names.iter().position(|name| name.eq_ignore_ascii_case(column));
+        let missing: Vec<&str> = OHLCV_COLUMNS.iter().copied().filter(|column| position(column).is_none()).collect();
+        if !missing.is_empty() {
+            return Err(anyhow!("SQLite query is missing required columns: {}", missing.join(", ")));
+        }
+        let [timestamp, open, high, low, close, volume] = OHLCV_COLUMNS.map(|column| position(column).unwrap_or_default());
+        
+        let mut rows = statement.query([]).context("Failed to run SQLite query")?;
+        let mut data = Vec::new();
+        
+        while let Some(row) = rows.next().context("Failed to read SQLite row")? {
+            let timestamp = match row.get_ref(timestamp)? {
+                ValueRef::Integer(seconds) => DateTime::from_timestamp(seconds, 0)
+                    .ok_or_else(|| anyhow!("Timestamp {} is out of range", seconds))?
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+                _ => row.get(timestamp).context("Failed to read timestamp column")?,
+            };
+            let extra = names
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| !OHLCV_COLUMNS.iter().any(|column| name.eq_ignore_ascii_case(column)))
+                .map(|(index, name)| {
+                    let value = match row.get_ref(index)? {
+                        ValueRef::Null => String::new(),
+                        ValueRef::Integer(number) => number.to_string(),
+                        ValueRef::Real(number) => number.to_string(),
+                        ValueRef::Text(text) | ValueRef::Blob(text) => String::from_utf8_lossy(text).into_owned(),
+                    };
+                    Ok((name.clone(), value))
+                })
+                .collect::<Result<HashMap<_, _>>>()?;
+            
+            data.push(HistoricalData {
+                timestamp,
+                open: row.get(open).context("Failed to read Open column")?,
+                high: row.get(high).context("Failed to read High column")?,
+                low: row.get(low).context("Failed to read Low column")?,
+                close: row.get(close).context("Failed to read Close column")?,
+                volume: row.get(volume).context("Failed to read Volume column")?,
+                extra,
+                synthetic: false,
             });
         }
         
-        Ok(candlesticks)
+        self.data = data.clone();
+        Ok(data)
+    }
+    
+    /// Loads every CSV file in a directory whose name matches a pattern
+    ///
+    /// Files are read in name order and merged chronologically; a timestamp
+    /// present in several files keeps the row from the file read last.
+    /// Metadata keys keep the value of the first file that has them.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory to scan (not recursive)
+    /// * `pattern` - Wildcard file name pattern such as `*.csv`
+    ///
+    /// # Returns
+    /// * `Result<Vec<HistoricalData>>` - Merged historical data
+    ///
+    /// # Errors
+    /// * Returns error if the directory cannot be read, no file matches, or a
+    ///   matching file fails to load
+    pub fn load_dir(&mut self, dir: &Path, pattern: &str) -> Result<Vec<HistoricalData>> {
+        let mut files = Vec::new();
+        
+        for entry in std::fs::read_dir(dir).context(format!("Failed to read directory: {}", dir.display()))? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+            
+            if path.is_file() && file_utils::matches_pattern(&name, pattern) {
+                files.push(path);
+            } else {
+                log::debug!("Skipping {} (does not match {})", path.display(), pattern);
+            }
+        }
+        
+        if files.is_empty() {
+            return Err(anyhow!("No files matching '{}' in {}", pattern, dir.display()));
+        }
+        
+        files.sort();
+        self.data.clear();
+        
+        self.metadata.clear();
+        
+        for file in &files {
+            let mut processor = DataProcessor::with_profile(self.profile.clone());
+            let rows = processor
+                .load_csv_data(&file.to_string_lossy())
+                .context(format!("Failed to load {}", file.display()))?;
+            log::info!("Including {} ({} rows)", file.display(), rows.len());
+            for (key, value) in processor.metadata {
+                self.metadata.entry(key).or_insert(value);
+            }
+            self.append_rows(rows, None)?;
+        }
+        
+        Ok(self.data.clone())
+    }
+    
+    /// Serializes rows as JSON for embedding in an exported chart
+    ///
+    /// `<`, `>` and `&` are written as unicode escapes, so the text can sit
+    /// in an HTML `<script>` or an SVG `<metadata>` element without closing
+    /// it or needing markup escapes. Extra columns are not included.
+    ///
+    /// # Arguments
+    /// * `data` - Rows to embed
+    ///
+    /// # Returns
+    /// * `Result<String>` - JSON array of rows with the CSV column names as keys
+    ///
+    /// # Errors
+    /// * Returns error if serialization fails
+    pub fn embed_json(data: &[HistoricalData]) -> Result<String> {
+        let json = serde_json::to_string(data).context("Failed to serialize candle data")?;
+        Ok(json.replace('<', "\\u003c").replace('>', "\\u003e").replace('&', "\\u0026"))
+    }
+    
+    /// Loads the rows embedded in an exported chart
+    ///
+    /// Reads the content of the element with id `EMBEDDED_DATA_ID`, as written
+    /// by `embed_json` into HTML reports and SVG charts.
+    ///
+    /// # Arguments
+    /// * `path` - HTML or SVG file exported with embedded data
+    ///
+    /// # Returns
+    /// * `Result<Vec<HistoricalData>>` - Embedded historical data
+    ///
+    /// # Errors
+    /// * Returns error if the file cannot be read, has no embedded data or
+    ///   the data is not valid JSON
+    pub fn load_embedded(&mut self, path: &Path) -> Result<Vec<HistoricalData>> {
+        let text = std::fs::read_to_string(path).context(format!("Failed to read chart: {}", path.display()))?;
+        let marker = format!("id=\"{}\"", EMBEDDED_DATA_ID);
+        let json = text
+            .find(&marker)
+            .map(|start| &text[start..])
+            .and_then(|element| element.find('>').map(|end| &element[end + 1..]))
+            .and_then(|content| content.find("</").map(|end| &content[..end]))
+            .ok_or_else(|| anyhow!("No embedded candle data in {}, export it with --embed-data", path.display()))?;
+        
+        let data: Vec<HistoricalData> = serde_json::from_str(json)
+            .context(format!("Invalid embedded candle data in {}", path.display()))?;
+        log::info!("Loaded {} embedded rows from {}", data.len(), path.display());
+        self.data = data.clone();
+        self.metadata.clear();
+        Ok(data)
+    }
+    
+    /// Writes candles as an OHLCV CSV file
+    ///
+    /// With the default format the file is readable by `load_csv_data`; other
+    /// formats target spreadsheet imports, e.g. `;` and `,` for German locales.
+    /// A leading `# schema_version=N` comment records `EXPORT_SCHEMA_VERSION`
+    /// and is read back as metadata.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data to export
+    /// * `path` - Destination file
+    /// * `format` - Field delimiter and decimal mark
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the file was written
+    ///
+    /// # Errors
+    /// * Returns error if the delimiter is not ASCII, equals the decimal mark,
+    ///   or the file cannot be created or written
+    pub fn write_csv(candles: &[CandleStick], path: &Path, format: &CsvFormat) -> Result<()> {
+        if !format.delimiter.is_ascii() {
+            return Err(anyhow!("CSV delimiter '{}' must be an ASCII character", format.delimiter));
+        }
+        if format.delimiter == format.decimal {
+            return Err(anyhow!("CSV delimiter and decimal mark must differ, both are '{}'", format.delimiter));
+        }
+        
+        let mut file = File::create(path)
+            .context(format!("Failed to create CSV file: {}", path.display()))?;
+        writeln!(file, "# schema_version={}", EXPORT_SCHEMA_VERSION)
+            .context(format!("Failed to write CSV file: {}", path.display()))?;
+        let mut writer = WriterBuilder::new()
+            .delimiter(format.delimiter as u8)
+            .from_writer(file);
+        writer.write_record(OHLCV_COLUMNS)?;
+        
+        for candle in candles {
+            writer.write_record([
+                candle.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                format.format_number(candle.open),
+                format.format_number(candle.high),
+                format.format_number(candle.low),
+                format.format_number(candle.close),
+                format.format_number(candle.volume),
+            ])?;
+        }
+        
+        writer.flush().context(format!("Failed to write CSV file: {}", path.display()))?;
+        log::info!("Wrote {} candles to {}", candles.len(), path.display());
+        Ok(())
+    }
+    
+    /// Computes the signed length of the up or down run ending at each candle
+    ///
+    /// A candle is up when its close is above the previous close and down
+    /// when below. The first candle has no previous close and an unchanged
+    /// close ends any run; both get 0.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data
+    ///
+    /// # Returns
+    /// * `Vec<(usize, i32)>` - Candle index and streak length, positive for up
+    ///   runs and negative for down runs
+    pub fn streaks(candles: &[CandleStick]) -> Vec<(usize, i32)> {
+        let mut streaks = Vec::with_capacity(candles.len());
+        let mut current = 0;
+        
+        for (index, candle) in candles.iter().enumerate() {
+            current = match index.checked_sub(1).map(|previous| candle.close.total_cmp(&candles[previous].close)) {
+                Some(std::cmp::Ordering::Greater) => current.max(0) + 1,
+                Some(std::cmp::Ordering::Less) => current.min(0) - 1,
+                _ => 0,
+            };
+            streaks.push((index, current));
+        }
+        
+        streaks
+    }
+    
+    /// Scores the quality of a candle series from 0 to 100
+    ///
+    /// Gaps, duplicate timestamps, non-finite values and OHLC violations
+    /// are weighted 10, 20, 40 and 30 points, each scaled by the fraction of
+    /// candles or intervals it affects; see `QualityReport::penalties`.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    ///
+    /// # Returns
+    /// * `f64` - Score, 100 without any issue and 0 for no candles
+    pub fn quality_score(candles: &[CandleStick]) -> f64 {
+        DataProcessor::quality_report(candles).score()
+    }
+    
+    /// Counts the issues a quality score is computed from
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    ///
+    /// # Returns
+    /// * `QualityReport` - Number of candles and of each kind of issue
+    pub fn quality_report(candles: &[CandleStick]) -> QualityReport {
+        let interval = DataProcessor::min_interval(candles);
+        let spacings = candles.windows(2).map(|pair| pair[1].timestamp - pair[0].timestamp);
+        let finite = |c: &&CandleStick| [c.open, c.high, c.low, c.close, c.volume].iter().all(|v| v.is_finite());
+        
+        QualityReport {
+            candles: candles.len(),
+            gaps: spacings.clone().filter(|spacing| interval.is_some_and(|interval| *spacing > interval)).count(),
+            duplicates: spacings.filter(|spacing| spacing.is_zero()).count(),
+            non_finite: candles.iter().filter(|c| !finite(c)).count(),
+            ohlc_violations: candles
+                .iter()
+                .filter(finite)
+                .filter(|c| {
+                    c.high < c.low
+                        || c.open.max(c.close) > c.high
+                        || c.open.min(c.close) < c.low
+                        || c.volume < 0.0
+                })
+                .count(),
+        }
+    }
+    
+    /// Computes the close-to-close return of every candle after the first
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    ///
+    /// # Returns
+    /// * `Vec<f64>` - Returns as fractions, e.g. 0.01 for 1%, skipping
+    ///   candles whose previous close is zero or whose return is not finite
+    pub fn returns(candles: &[CandleStick]) -> Vec<f64> {
+        candles
+            .windows(2)
+            .map(|pair| (pair[1].close - pair[0].close) / pair[0].close)
+            .filter(|value| value.is_finite())
+            .collect()
+    }
+    
+    /// Counts the close-to-close returns falling into equal-width bins
+    ///
+    /// The bins span the lowest to the highest return. When all returns are
+    /// equal, including the case of a single return, one bin holds them all.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    /// * `bins` - Number of bins
+    ///
+    /// # Returns
+    /// * `Vec<(f64, usize)>` - `(bin center return, count)` from the lowest
+    ///   bin up, empty for fewer than two candles or zero bins
+    pub fn returns_histogram(candles: &[CandleStick], bins: usize) -> Vec<(f64, usize)> {
+        let returns = DataProcessor::returns(candles);
+        if returns.is_empty() || bins == 0 {
+            return Vec::new();
+        }
+        
+        let low = returns.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = returns.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if high <= low {
+            return vec![(low, returns.len())];
+        }
+        
+        let step = (high - low) / bins as f64;
+        let mut histogram: Vec<(f64, usize)> = (0..bins).map(|i| (low + step * (i as f64 + 0.5), 0)).collect();
+        for value in &returns {
+            let bin = (((value - low) / step) as usize).min(bins - 1);
+            histogram[bin].1 += 1;
+        }
+        
+        histogram
+    }
+    
+    /// Averages close-to-close returns by day of the week or month
+    ///
+    /// Each return is grouped by the UTC date of the candle it ends on.
+    /// Groups without any return, such as weekends of stock data or months
+    /// missing from a short series, are left out.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    /// * `by` - Calendar grouping
+    ///
+    /// # Returns
+    /// * `Vec<(String, f64)>` - Group name such as `Mon` or `Jan` and its
+    ///   mean return as a fraction, in calendar order
+    pub fn seasonality(candles: &[CandleStick], by: SeasonBy) -> Vec<(String, f64)> {
+        let mut groups = vec![(0.0, 0usize); by.names().len()];
+        
+        for pair in candles.windows(2) {
+            let value = (pair[1].close - pair[0].close) / pair[0].close;
+            if value.is_finite() {
+                let group = &mut groups[by.group(&pair[1].timestamp)];
+                group.0 += value;
+                group.1 += 1;
+            }
+        }
+        
+        by.names()
+            .iter()
+            .zip(groups)
+            .filter(|(_, (_, count))| *count > 0)
+            .map(|(name, (sum, count))| (name.to_string(), sum / count as f64))
+            .collect()
+    }
+    
+    /// Caps extreme prices at percentiles of the series
+    ///
+    /// This alters the data: capped candles no longer show what traded, so
+    /// use it to calm indicators rather than to report prices. Closes are
+    /// clamped between the `lower_pct` and `upper_pct` percentiles of all
+    /// closes; with `include_range`, highs and lows are clamped likewise to
+    /// the percentiles of all highs and all lows. Highs and lows are then
+    /// widened where needed to still contain the open and close.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data
+    /// * `lower_pct` - Percentile of the lower cap in `0.0..=100.0`
+    /// * `upper_pct` - Percentile of the upper cap in `0.0..=100.0`
+    /// * `include_range` - Whether highs and lows are capped too
+    ///
+    /// # Returns
+    /// * `Vec<CandleStick>` - Capped copy of the candles; the input is left unchanged
+    pub fn winsorize(candles: &[CandleStick], lower_pct: f64, upper_pct: f64, include_range: bool) -> Vec<CandleStick> {
+        let bounds = |field: fn(&CandleStick) -> f64| {
+            let values: Vec<f64> = candles.iter().map(field).collect();
+            stats_utils::percentile(&values, lower_pct).zip(stats_utils::percentile(&values, upper_pct))
+        };
+        let clamp = |value: f64, bounds: Option<(f64, f64)>| match bounds {
+            Some((low, high)) if value.is_finite() => value.clamp(low, high),
+            _ => value,
+        };
+        let closes = bounds(|c| c.close);
+        let (highs, lows) = match include_range {
+            true => (bounds(|c| c.high), bounds(|c| c.low)),
+            false => (None, None),
+        };
+        
+        candles
+            .iter()
+            .map(|candle| {
+                let close = clamp(candle.close, closes);
+                CandleStick {
+                    close,
+                    high: clamp(candle.high, highs).max(candle.open).max(close),
+                    low: clamp(candle.low, lows).min(candle.open).min(close),
+                    ..candle.clone()
+                }
+            })
+            .collect()
+    }
+    
+    /// Finds candles opening above the previous high or below the previous low
+    ///
+    /// A gap up is filled once a candle from the gap candle on trades at or
+    /// below the previous high again, a gap down once one trades at or above
+    /// the previous low.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    ///
+    /// # Returns
+    /// * `Vec<PriceGap>` - Gaps in time order
+    pub fn price_gaps(candles: &[CandleStick]) -> Vec<PriceGap> {
+        candles
+            .windows(2)
+            .enumerate()
+            .filter_map(|(i, pair)| {
+                let (previous, candle) = (&pair[0], &pair[1]);
+                let up = candle.open > previous.high;
+                if !up && candle.open >= previous.low {
+                    return None;
+                }
+                
+                let level = if up { previous.high } else { previous.low };
+                let filled_at = candles[i + 1..]
+                    .iter()
+                    .position(|c| if up { c.low <= level } else { c.high >= level })
+                    .map(|offset| i + 1 + offset);
+                Some(PriceGap { index: i + 1, up, level, filled_at })
+            })
+            .collect()
+    }
+    
+    /// Computes how often and how fast price gaps were filled
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    ///
+    /// # Returns
+    /// * `GapFillStats` - Counts over all gaps of `price_gaps`
+    pub fn gap_fill_stats(candles: &[CandleStick]) -> GapFillStats {
+        let gaps = DataProcessor::price_gaps(candles);
+        let fills: Vec<(usize, usize)> = gaps
+            .iter()
+            .filter_map(|gap| gap.filled_at.map(|filled_at| (gap.index, filled_at)))
+            .collect();
+        let total: Duration = fills
+            .iter()
+            .map(|&(gap, filled_at)| candles[filled_at].timestamp - candles[gap].timestamp)
+            .sum();
+        
+        GapFillStats {
+            gaps: gaps.len(),
+            filled: fills.len(),
+            filled_same_candle: fills.iter().filter(|(gap, filled_at)| filled_at == gap).count(),
+            filled_next_candle: fills.iter().filter(|(gap, filled_at)| filled_at - gap <= 1).count(),
+            mean_time_to_fill: (!fills.is_empty()).then(|| total / fills.len() as i32),
+        }
+    }
+    
+    /// Summarizes a candle series
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in time order
+    ///
+    /// # Returns
+    /// * `Option<Summary>` - Key figures, `None` if there are no candles
+    pub fn summary(candles: &[CandleStick]) -> Option<Summary> {
+        let (first, last) = (candles.first()?, candles.last()?);
+        
+        Some(Summary {
+            count: candles.len(),
+            first: first.timestamp,
+            last: last.timestamp,
+            open: first.open,
+            close: last.close,
+            high: candles.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max),
+            low: candles.iter().map(|c| c.low).fold(f64::INFINITY, f64::min),
+            volume: candles.iter().map(|c| c.volume).sum(),
+        })
+    }
+    
+    /// Joins the closes of a second series onto candles by exact timestamp
+    ///
+    /// # Arguments
+    /// * `candles` - Series defining the x positions
+    /// * `other` - Series to align, e.g. a benchmark index
+    ///
+    /// # Returns
+    /// * `Vec<Option<f64>>` - One entry per candle, `None` where `other` has
+    ///   no candle at the same timestamp
+    pub fn align_closes(candles: &[CandleStick], other: &[CandleStick]) -> Vec<Option<f64>> {
+        let closes: HashMap<DateTime<Utc>, f64> = other.iter().map(|c| (c.timestamp, c.close)).collect();
+        candles.iter().map(|c| closes.get(&c.timestamp).copied()).collect()
+    }
+    
+    /// Divides candle prices by a benchmark's close at the same timestamp
+    ///
+    /// # Arguments
+    /// * `candles` - Candles to express relative to the benchmark
+    /// * `benchmark` - Series to divide by, e.g. an index
+    ///
+    /// # Returns
+    /// * `Vec<Option<CandleStick>>` - One entry per candle with open, high,
+    ///   low and close as ratios, `None` where the benchmark has no close at
+    ///   that timestamp or its close is zero
+    pub fn ratio_candles(candles: &[CandleStick], benchmark: &[CandleStick]) -> Vec<Option<CandleStick>> {
+        candles
+            .iter()
+            .zip(Self::align_closes(candles, benchmark))
+            .map(|(candle, close)| {
+                let close = close.filter(|close| *close != 0.0 && close.is_finite())?;
+                Some(CandleStick {
+                    open: candle.open / close,
+                    high: candle.high / close,
+                    low: candle.low / close,
+                    close: candle.close / close,
+                    ..candle.clone()
+                })
+            })
+            .collect()
+    }
+    
+    /// Averages bid and ask candles with equal timestamps into mid-price candles
+    ///
+    /// Each of open, high, low and close is the mean of the bid and ask
+    /// field, so the mid high and low are approximate when the two sides
+    /// peaked at different moments.
+    ///
+    /// # Arguments
+    /// * `bid` - Bid candles in chronological order
+    /// * `ask` - Ask candles
+    /// * `volume` - Which side's volume the mid candles carry
+    /// * `drop_unmatched` - Leave out candles without a counterpart instead
+    ///   of failing
+    ///
+    /// # Returns
+    /// * `Result<Vec<CandleStick>>` - One mid candle per matched timestamp, in
+    ///   bid order
+    ///
+    /// # Errors
+    /// * Returns error if a candle of one side has no candle at the same
+    ///   timestamp on the other side and `drop_unmatched` is false
+    pub fn mid_candles(bid: &[CandleStick], ask: &[CandleStick], volume: VolumeSource, drop_unmatched: bool) -> Result<Vec<CandleStick>> {
+        let asks: HashMap<DateTime<Utc>, &CandleStick> = ask.iter().map(|c| (c.timestamp, c)).collect();
+        let bids: HashMap<DateTime<Utc>, &CandleStick> = bid.iter().map(|c| (c.timestamp, c)).collect();
+        let unmatched_bids = bid.iter().filter(|c| !asks.contains_key(&c.timestamp)).count();
+        let unmatched_asks = ask.iter().filter(|c| !bids.contains_key(&c.timestamp)).count();
+        
+        if !drop_unmatched {
+            let format = |c: &CandleStick| c.timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
+            if let Some(candle) = bid.iter().find(|c| !asks.contains_key(&c.timestamp)) {
+                return Err(anyhow!("Bid candle at {} has no ask candle", format(candle)));
+            }
+            if let Some(candle) = ask.iter().find(|c| !bids.contains_key(&c.timestamp)) {
+                return Err(anyhow!("Ask candle at {} has no bid candle", format(candle)));
+            }
+        } else if unmatched_bids + unmatched_asks > 0 {
+            log::warn!("Dropped {} bid and {} ask candles without a counterpart", unmatched_bids, unmatched_asks);
+        }
+        
+        Ok(bid
+            .iter()
+            .filter_map(|b| asks.get(&b.timestamp).map(|a| (b, a)))
+            .map(|(b, a)| CandleStick {
+                timestamp: b.timestamp,
+                open: (b.open + a.open) / 2.0,
+                high: (b.high + a.high) / 2.0,
+                low: (b.low + a.low) / 2.0,
+                close: (b.close + a.close) / 2.0,
+                volume: match volume {
+                    VolumeSource::Sum => b.volume + a.volume,
+                    VolumeSource::Bid => b.volume,
+                    VolumeSource::Ask => a.volume,
+                },
+                synthetic: false,
+            })
+            .collect())
+    }
+    
+    /// Simulates a long-only strategy trading at the close of signal candles
+    ///
+    /// An entry opens a position when flat and an exit closes it; signals
+    /// that do not change the position are ignored. A position still open
+    /// after the last signal is not reported.
+    ///
+    /// # Arguments
+    /// * `signals` - Entry and exit signals in index order
+    /// * `candles` - Candles the signal indices refer to
+    ///
+    /// # Returns
+    /// * `Vec<Trade>` - Completed trades in chronological order
+    pub fn simulate(signals: &[Signal], candles: &[CandleStick]) -> Vec<Trade> {
+        let mut trades = Vec::new();
+        let mut open: Option<&CandleStick> = None;
+        
+        for signal in signals {
+            let Some(candle) = candles.get(signal.index) else { continue };
+            
+            match (signal.side, open) {
+                (SignalSide::Entry, None) => open = Some(candle),
+                (SignalSide::Exit, Some(entry)) => {
+                    trades.push(Trade {
+                        entry_time: entry.timestamp,
+                        entry_price: entry.close,
+                        exit_time: candle.timestamp,
+                        exit_price: candle.close,
+                    });
+                    open = None;
+                }
+                _ => {}
+            }
+        }
+        
+        trades
+    }
+    
+    /// Writes trades as a CSV trade log
+    ///
+    /// # Arguments
+    /// * `trades` - Trades to export
+    /// * `path` - Destination file
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the file was written
+    ///
+    /// # Errors
+    /// * Returns error if the file cannot be created or written
+    pub fn write_trade_log(trades: &[Trade], path: &Path) -> Result<()> {
+        let mut writer = WriterBuilder::new()
+            .from_path(path)
+            .context(format!("Failed to create trade log: {}", path.display()))?;
+        writer.write_record(["entry_time", "entry_price", "exit_time", "exit_price", "return_pct"])?;
+        
+        for trade in trades {
+            writer.write_record([
+                trade.entry_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                trade.entry_price.to_string(),
+                trade.exit_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                trade.exit_price.to_string(),
+                format!("{:.4}", trade.return_pct()),
+            ])?;
+        }
+        
+        writer.flush().context(format!("Failed to write trade log: {}", path.display()))?;
+        log::info!("Wrote {} trades to {}", trades.len(), path.display());
+        Ok(())
+    }
+    
+    /// Writes the candles where signals fired as CSV with a signal column
+    ///
+    /// # Arguments
+    /// * `candles` - Candles the signal indices refer to
+    /// * `signals` - Names of the signals raised at each candle index
+    /// * `path` - Destination file
+    /// * `combine` - Write one row per candle with the names joined by `;`
+    ///   instead of one row per signal
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the file was written
+    ///
+    /// # Errors
+    /// * Returns error if the file cannot be created or written
+    pub fn write_signals(candles: &[CandleStick], signals: &BTreeMap<usize, Vec<String>>, path: &Path, combine: bool) -> Result<()> {
+        let mut writer = WriterBuilder::new()
+            .from_path(path)
+            .context(format!("Failed to create signals file: {}", path.display()))?;
+        writer.write_record(OHLCV_COLUMNS.iter().chain(&["Signal"]))?;
+        
+        let mut rows = 0;
+        for (candle, names) in signals.iter().filter_map(|(index, names)| Some((candles.get(*index)?, names))) {
+            let joined = [names.join(";")];
+            let names = if combine { &joined[..] } else { &names[..] };
+            for name in names {
+                writer.write_record([
+                    candle.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    candle.open.to_string(),
+                    candle.high.to_string(),
+                    candle.low.to_string(),
+                    candle.close.to_string(),
+                    candle.volume.to_string(),
+                    name.clone(),
+                ])?;
+                rows += 1;
+            }
+        }
+        
+        writer.flush().context(format!("Failed to write signals file: {}", path.display()))?;
+        log::info!("Wrote {} signal rows to {}", rows, path.display());
+        Ok(())
+    }
+    
+    /// Converts prices to integer ticks by multiplying with a scale
+    ///
+    /// Products are rounded half to even, so ties do not bias a long series
+    /// up or down. The conversion is lossy: the scale must be at least
+    /// `10^decimals` of the input prices for `ticks / scale` to recover them.
+    ///
+    /// # Arguments
+    /// * `candles` - Candles to convert
+    /// * `scale` - Ticks per price unit, e.g. 100 for cents
+    ///
+    /// # Returns
+    /// * `Result<Vec<TickCandle>>` - One tick candle per input candle
+    ///
+    /// # Errors
+    /// * Returns error if the scale is not a positive finite number or a
+    ///   scaled price is not finite or does not fit into 64 bits
+    pub fn to_ticks(candles: &[CandleStick], scale: f64) -> Result<Vec<TickCandle>> {
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(anyhow!("Tick scale must be a positive number, got {}", scale));
+        }
+        let ticks = |price: f64, candle: &CandleStick| -> Result<i64> {
+            let scaled = (price * scale).round_ties_even();
+            if !scaled.is_finite() || scaled.abs() >= i64::MAX as f64 {
+                return Err(anyhow!("Price {} at {} does not fit into ticks at scale {}", price, candle.timestamp, scale));
+            }
+            Ok(scaled as i64)
+        };
+        
+        candles
+            .iter()
+            .map(|candle| Ok(TickCandle {
+                timestamp: candle.timestamp,
+                open: ticks(candle.open, candle)?,
+                high: ticks(candle.high, candle)?,
+                low: ticks(candle.low, candle)?,
+                close: ticks(candle.close, candle)?,
+                volume: candle.volume,
+            }))
+            .collect()
+    }
+    
+    /// Writes tick candles as CSV with the OHLCV column names
+    ///
+    /// # Arguments
+    /// * `candles` - Candles converted by `to_ticks`
+    /// * `path` - Destination file
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the file was written
+    ///
+    /// # Errors
+    /// * Returns error if the file cannot be created or written
+    pub fn write_ticks(candles: &[TickCandle], path: &Path) -> Result<()> {
+        let mut writer = WriterBuilder::new()
+            .from_path(path)
+            .context(format!("Failed to create ticks file: {}", path.display()))?;
+        writer.write_record(OHLCV_COLUMNS)?;
+        
+        for candle in candles {
+            writer.write_record([
+                candle.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                candle.open.to_string(),
+                candle.high.to_string(),
+                candle.low.to_string(),
+                candle.close.to_string(),
+                candle.volume.to_string(),
+            ])?;
+        }
+        
+        writer.flush().context(format!("Failed to write ticks file: {}", path.display()))?;
+        log::info!("Wrote {} tick candles to {}", candles.len(), path.display());
+        Ok(())
+    }
+    
+    /// Collects the values of all non-OHLCV columns of a record
+    ///
+    /// # Arguments
+    /// * `headers` - Header record of the CSV file
+    /// * `row` - Data record to extract values from
+    ///
+    /// # Returns
+    /// * `HashMap<String, String>` - Extra column values keyed by header name
+    fn extra_columns(headers: &csv::StringRecord, row: &csv::StringRecord) -> HashMap<String, String> {
+        headers
+            .iter()
+            .zip(row.iter())
+            .filter(|(header, _)| !OHLCV_COLUMNS.contains(header))
+            .map(|(header, value)| (header.to_string(), value.trim().to_string()))
+            .collect()
+    }
+    
+    /// Generates sample data for testing purposes
+    ///
+    /// # Returns
+    /// * `Result<Vec<HistoricalData>>` - Generated sample data
+    fn generate_sample_data(&mut self) -> Result<Vec<HistoricalData>> {
+        let sample_data = vec![
+            HistoricalData {
+                timestamp: "2023-01-01 00:00:00".to_string(),
+                open: 100.0,
+                high: 105.0,
+                low: 95.0,
+                close: 102.0,
+                volume: 1000.0,
+                ..Default::default()
+            },
+            HistoricalData {
+                timestamp: "2023-01-02 00:00:00".to_string(),
+                open: 102.0,
+                high: 108.0,
+                low: 101.0,
+                close: 106.0,
+                volume: 1200.0,
+                ..Default::default()
+            },
+            HistoricalData {
+                timestamp: "2023-01-03 00:00:00".to_string(),
+                open: 106.0,
+                high: 110.0,
+                low: 104.0,
+                close: 108.0,
+                volume: 1500.0,
+                ..Default::default()
+            },
+        ];
+        
+        self.data = sample_data.clone();
+        Ok(sample_data)
+    }
+    
+    /// Converts historical data to candlestick format
+    ///
+    /// # Returns
+    /// * `Result<Vec<CandleStick>>` - Vector of candlestick data
+    #[allow(dead_code)]
+    pub fn to_candlesticks(&self) -> Result<Vec<CandleStick>> {
+        Self::candles_from(&self.data)
+    }
+    
+    /// Computes the simple moving average of the loaded close prices
+    ///
+    /// # Arguments
+    /// * `period` - Number of candles in the averaging window
+    ///
+    /// # Returns
+    /// * `Result<Vec<Option<f64>>>` - Average per loaded row, `None` for the
+    ///   first `period - 1`
+    ///
+    /// # Errors
+    /// * Returns `AppError::DataProcessing` if `period` is zero or exceeds the
+    ///   number of rows
+    /// * Returns error if a timestamp cannot be parsed
+    pub fn simple_moving_average(&self, period: usize) -> Result<Vec<Option<f64>>> {
+        self.check_period(period)?;
+        Ok(indicators::sma(&self.to_candlesticks()?, period))
+    }
+    
+    /// Computes the exponential moving average of the loaded close prices
+    ///
+    /// The average is seeded with the SMA of the first `period` closes, see
+    /// `indicators::ema`.
+    ///
+    /// # Arguments
+    /// * `period` - Number of candles in the smoothing window
+    ///
+    /// # Returns
+    /// * `Result<Vec<Option<f64>>>` - Average per loaded row, `None` for the
+    ///   first `period - 1`
+    ///
+    /// # Errors
+    /// * Returns `AppError::DataProcessing` if `period` is zero or exceeds the
+    ///   number of rows
+    /// * Returns error if a timestamp cannot be parsed
+    pub fn exponential_moving_average(&self, period: usize) -> Result<Vec<Option<f64>>> {
+        self.check_period(period)?;
+        Ok(indicators::ema(&self.to_candlesticks()?, period))
+    }
+    
+    /// Rejects averaging periods that no window of the loaded rows can fill
+    fn check_period(&self, period: usize) -> Result<()> {
+        if period == 0 || period > self.data.len() {
+            return Err(AppError::DataProcessing(format!(
+                "Moving average period must be between 1 and {}, got {}",
+                self.data.len(),
+                period
+            ))
+            .into());
+        }
+        Ok(())
+    }
+    
+    /// Converts arbitrary historical data rows to candlestick format
+    ///
+    /// # Arguments
+    /// * `data` - Historical data rows to convert
+    ///
+    /// # Returns
+    /// * `Result<Vec<CandleStick>>` - Vector of candlestick data
+    ///
+    /// # Errors
+    /// * Returns error if any timestamp cannot be parsed
+    pub fn candles_from(data: &[HistoricalData]) -> Result<Vec<CandleStick>> {
+        let mut candlesticks = Vec::new();
+        
+        for data in data {
+            candlesticks.push(CandleStick {
+                timestamp: data.parsed_timestamp()?,
+                open: data.open,
+                high: data.high,
+                low: data.low,
+                close: data.close,
+                volume: data.volume,
+                synthetic: data.synthetic,
+            });
+        }
+        
+        Ok(candlesticks)
+    }
+    
+    /// Rewrites every timestamp in `%Y-%m-%d %H:%M:%S` form
+    ///
+    /// Used after loading so that epoch timestamps are shown and processed
+    /// like textual ones. Fractional seconds are kept when a timestamp has them.
+    ///
+    /// # Arguments
+    /// * `data` - Rows whose timestamps are rewritten in place
+    /// * `format` - How the loaded timestamps are interpreted
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if every timestamp was parsed
+    ///
+    /// # Errors
+    /// * Returns error naming the first timestamp that does not match `format`
+    pub fn normalize_timestamps(data: &mut [HistoricalData], format: TimestampFormat) -> Result<()> {
+        for row in data {
+            row.timestamp = date_utils::format_timestamp(&date_utils::parse_timestamp(&row.timestamp, format)?);
+        }
+        Ok(())
+    }
+    
+    /// Verifies that every row describes a possible candle
+    ///
+    /// Rows must satisfy `low <= open <= high`, `low <= close <= high` and
+    /// `volume >= 0`; values that are not numbers fail the rule they appear in.
+    ///
+    /// # Arguments
+    /// * `data` - Rows in input order
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if every row satisfies all rules
+    ///
+    /// # Errors
+    /// * Returns `AppError::DataProcessing` naming the index of the first
+    ///   offending row and the rule it breaks
+    pub fn check_ohlc(data: &[HistoricalData]) -> Result<()> {
+        for (index, row) in data.iter().enumerate() {
+            let rule = if !(row.low..=row.high).contains(&row.open) {
+                format!("low <= open <= high (low {}, open {}, high {})", row.low, row.open, row.high)
+            } else if !(row.low..=row.high).contains(&row.close) {
+                format!("low <= close <= high (low {}, close {}, high {})", row.low, row.close, row.high)
+            } else if !(0.0..).contains(&row.volume) {
+                format!("volume >= 0 (volume {})", row.volume)
+            } else {
+                continue;
+            };
+            return Err(AppError::DataProcessing(format!("Invalid candle: row {} ({}) breaks {}", index, row.timestamp, rule)).into());
+        }
+        
+        Ok(())
+    }
+    
+    /// Verifies that timestamps never decrease, without reordering anything
+    ///
+    /// # Arguments
+    /// * `data` - Rows in input order
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if every timestamp is at or after the previous one
+    ///
+    /// # Errors
+    /// * Returns error naming the index of the first row earlier than its
+    ///   predecessor, or the first timestamp that cannot be parsed
+    pub fn check_sorted(data: &[HistoricalData]) -> Result<()> {
+        let mut previous: Option<DateTime<Utc>> = None;
+        
+        for (index, row) in data.iter().enumerate() {
+            let timestamp = row.parsed_timestamp()?;
+            if previous.is_some_and(|previous| timestamp < previous) {
+                return Err(anyhow!(
+                    "Input is not sorted: row {} ({}) is earlier than row {} ({})",
+                    index,
+                    row.timestamp,
+                    index - 1,
+                    data[index - 1].timestamp
+                ));
+            }
+            previous = Some(timestamp);
+        }
+        
+        Ok(())
+    }
+    
+    /// Merges new rows into the loaded data, keeping it ordered by timestamp
+    ///
+    /// A row with the same timestamp as an existing row replaces it, which is
+    /// how updates to a still-forming candle arrive.
+    ///
+    /// # Arguments
+    /// * `rows` - Rows to merge
+    /// * `max_rows` - If set, drop the oldest rows beyond this count
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if all rows were merged
+    ///
+    /// # Errors
+    /// * Returns error if a new row's timestamp cannot be parsed
+    pub fn append_rows(&mut self, rows: Vec<HistoricalData>, max_rows: Option<usize>) -> Result<()> {
+        for row in rows {
+            let timestamp = row.parsed_timestamp()?;
+            let position = self.data
+                .partition_point(|existing| existing.parsed_timestamp().map(|t| t < timestamp).unwrap_or(true));
+            
+            let replaces = self.data
+                .get(position)
+                .is_some_and(|existing| existing.parsed_timestamp().is_ok_and(|t| t == timestamp));
+            
+            if replaces {
+                self.data[position] = row;
+            } else {
+                self.data.insert(position, row);
+            }
+        }
+        
+        if let Some(max_rows) = max_rows {
+            let excess = self.data.len().saturating_sub(max_rows);
+            self.data.drain(..excess);
+        }
+        
+        Ok(())
+    }
+    
+    /// Aggregates candles into fixed-length time buckets
+    ///
+    /// Buckets are aligned to the Unix epoch, except that whole-week intervals
+    /// start on Mondays. Each field of a bucket is combined as `agg` says, by
+    /// default the first open, highest high, lowest low, last close and summed
+    /// volume of its candles, which are expected in chronological order.
+    /// Empty buckets produce no candle.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in chronological order
+    /// * `interval` - Bucket length
+    /// * `label` - Whether a bucket is stamped with its start or end time
+    /// * `agg` - Aggregation of each field
+    ///
+    /// # Returns
+    /// * `Vec<CandleStick>` - One candle per non-empty bucket
+    pub fn resample(candles: &[CandleStick], interval: Duration, label: LabelTime, agg: &AggSpec) -> Vec<CandleStick> {
+        let mut buckets: Vec<(DateTime<Utc>, Vec<&CandleStick>)> = Vec::new();
+        
+        for candle in candles {
+            let bucket = Self::bucket_start(candle.timestamp, interval);
+            
+            match buckets.last_mut() {
+                Some((start, members)) if *start == bucket => members.push(candle),
+                _ => buckets.push((bucket, vec![candle])),
+            }
+        }
+        
+        buckets
+            .into_iter()
+            .map(|(bucket, members)| {
+                let field = |aggregation: Aggregation, value: fn(&CandleStick) -> f64| {
+                    aggregation.apply(&members.iter().map(|candle| value(candle)).collect::<Vec<_>>())
+                };
+                CandleStick {
+                    timestamp: match label {
+                        LabelTime::Open => bucket,
+                        LabelTime::Close => bucket + interval,
+                    },
+                    open: field(agg.open, |candle| candle.open),
+                    high: field(agg.high, |candle| candle.high),
+                    low: field(agg.low, |candle| candle.low),
+                    close: field(agg.close, |candle| candle.close),
+                    volume: field(agg.volume, |candle| candle.volume),
+                    synthetic: members.iter().all(|candle| candle.synthetic),
+                }
+            })
+            .collect()
+    }
+    
+    /// Inserts flat candles for periods missing from a series
+    ///
+    /// Wherever consecutive candles are more than `interval` apart, a
+    /// synthetic candle with open, high, low and close at the previous close
+    /// and zero volume is inserted at every missing step. Inserted candles are
+    /// flagged `synthetic` so renderers can draw them faintly.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in chronological order
+    /// * `interval` - Expected spacing of the candles
+    ///
+    /// # Returns
+    /// * `Result<Vec<CandleStick>>` - Series without gaps, unchanged if
+    ///   `interval` is not positive
+    ///
+    /// # Errors
+    /// * Returns `AppError::DataProcessing` if the filled series would exceed
+    ///   `MAX_FILLED_CANDLES` candles
+    pub fn fill_missing(candles: &[CandleStick], interval: chrono::Duration) -> Result<Vec<CandleStick>> {
+        if interval <= chrono::Duration::zero() {
+            return Ok(candles.to_vec());
+        }
+        
+        let step = interval.num_milliseconds().max(1);
+        let mut filled: Vec<CandleStick> = Vec::with_capacity(candles.len());
+        for candle in candles {
+            if let Some(previous) = filled.last().cloned() {
+                let missing = ((candle.timestamp - previous.timestamp).num_milliseconds().max(0) / step) as usize;
+                if filled.len().saturating_add(missing) > MAX_FILLED_CANDLES {
+                    return Err(AppError::DataProcessing(format!(
+                        "Filling the gap before {} needs about {} candles, more than the limit of {}",
+                        candle.timestamp, missing, MAX_FILLED_CANDLES
+                    )).into());
+                }
+                let mut timestamp = previous.timestamp + interval;
+                while timestamp < candle.timestamp {
+                    filled.push(CandleStick {
+                        timestamp,
+                        open: previous.close,
+                        high: previous.close,
+                        low: previous.close,
+                        close: previous.close,
+                        volume: 0.0,
+                        synthetic: true,
+                    });
+                    timestamp += interval;
+                }
+            }
+            filled.push(candle.clone());
+        }
+        
+        Ok(filled)
+    }
+    
+    /// Finds the smallest spacing between consecutive candles
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in chronological order
+    ///
+    /// # Returns
+    /// * `Option<chrono::Duration>` - Smallest positive spacing, `None` for
+    ///   fewer than two distinct timestamps
+    pub fn min_interval(candles: &[CandleStick]) -> Option<chrono::Duration> {
+        candles
+            .windows(2)
+            .map(|pair| pair[1].timestamp - pair[0].timestamp)
+            .filter(|spacing| *spacing > chrono::Duration::zero())
+            .min()
+    }
+    
+    /// Synthesizes range bars from the close sequence
+    ///
+    /// A bar completes once its high-low span reaches `range`; it closes at
+    /// that boundary and the next bar opens there, so a single large move can
+    /// complete several bars. Each bar's timestamp is the close time, i.e.
+    /// the timestamp of the candle whose close completed it. The still
+    /// forming last bar is included with the timestamp of the last candle
+    /// once price has moved within it.
+    /// Volume is credited to the bar that is open when a candle arrives.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in chronological order
+    /// * `range` - Price span of a completed bar
+    ///
+    /// # Returns
+    /// * `Vec<CandleStick>` - Synthesized bars, empty if `range` is not positive
+    pub fn range_bars(candles: &[CandleStick], range: f64) -> Vec<CandleStick> {
+        let mut bars = Vec::new();
+        if !(range > 0.0 && range.is_finite()) {
+            return bars;
+        }
+        
+        let start = |price: f64, timestamp| CandleStick {
+            timestamp,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0.0,
+            synthetic: false,
+        };
+        let mut current: Option<CandleStick> = None;
+        
+        for candle in candles {
+            let price = candle.close;
+            let bar = current.get_or_insert_with(|| start(price, candle.timestamp));
+            bar.volume += candle.volume;
+            
+            loop {
+                let boundary = if price - bar.low >= range {
+                    bar.low + range
+                } else if bar.high - price >= range {
+                    bar.high - range
+                } else {
+                    break;
+                };
+                
+                bar.high = bar.high.max(boundary);
+                bar.low = bar.low.min(boundary);
+                bar.close = boundary;
+                bar.timestamp = candle.timestamp;
+                bars.push(bar.clone());
+                *bar = start(boundary, candle.timestamp);
+            }
+            
+            bar.high = bar.high.max(price);
+            bar.low = bar.low.min(price);
+            bar.close = price;
+            bar.timestamp = candle.timestamp;
+        }
+        
+        bars.extend(current.filter(|bar| bar.high > bar.low || bars.is_empty()));
+        bars
+    }
+    
+    /// Spreads values computed on resampled candles back onto the candles
+    ///
+    /// Every candle takes the value of the bucket it falls into, so the result
+    /// is constant within a bucket and steps between buckets. A partial final
+    /// bucket is treated like any other: its value covers the candles seen so
+    /// far and changes as the bucket fills up.
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in chronological order
+    /// * `interval` - Bucket length the values were computed at
+    /// * `values` - One value per candle of `resample(candles, interval, ..)`
+    ///
+    /// # Returns
+    /// * `Vec<Option<f64>>` - One value per candle, `None` where its bucket has none
+    pub fn upsample(candles: &[CandleStick], interval: Duration, values: &[Option<f64>]) -> Vec<Option<f64>> {
+        let mut bucket: Option<(DateTime<Utc>, usize)> = None;
+        
+        candles
+            .iter()
+            .map(|candle| {
+                let start = Self::bucket_start(candle.timestamp, interval);
+                let index = match bucket {
+                    Some((current, index)) if current == start => index,
+                    Some((_, index)) => index + 1,
+                    None => 0,
+                };
+                bucket = Some((start, index));
+                values.get(index).copied().flatten()
+            })
+            .collect()
+    }
+    
+    /// Computes the start of the bucket containing a timestamp
+    fn bucket_start(timestamp: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+        let length = interval.num_seconds().max(1);
+        // 1969-12-29 was a Monday
+        let origin = if length % Duration::weeks(1).num_seconds() == 0 { -3 * 86_400 } else { 0 };
+        let start = origin + (timestamp.timestamp() - origin).div_euclid(length) * length;
+        DateTime::from_timestamp(start, 0).unwrap_or(timestamp)
+    }
+    
+    /// Groups consecutive candles into trading sessions by UTC calendar day
+    ///
+    /// # Arguments
+    /// * `candles` - Candlestick data in chronological order
+    ///
+    /// # Returns
+    /// * `Vec<Range<usize>>` - Index range of each session, in order
+    pub fn sessions(candles: &[CandleStick]) -> Vec<Range<usize>> {
+        let mut sessions: Vec<Range<usize>> = Vec::new();
+        
+        for (i, candle) in candles.iter().enumerate() {
+            match sessions.last_mut() {
+                Some(session) if candles[session.start].timestamp.date_naive() == candle.timestamp.date_naive() => {
+                    session.end = i + 1;
+                }
+                _ => sessions.push(i..i + 1),
+            }
+        }
+        
+        sessions
+    }
+    
+    /// Keeps the first `n` entries of a series
+    ///
+    /// # Arguments
+    /// * `candles` - Series to truncate
+    /// * `n` - Number of leading entries to keep
+    ///
+    /// # Returns
+    /// * `Vec<T>` - Leading entries, or all of them if `n` exceeds the length
+    pub fn head<T: Clone>(candles: &[T], n: usize) -> Vec<T> {
+        candles[..n.min(candles.len())].to_vec()
+    }
+    
+    /// Keeps the last `n` entries of a series
+    ///
+    /// # Arguments
+    /// * `candles` - Series to truncate
+    /// * `n` - Number of trailing entries to keep
+    ///
+    /// # Returns
+    /// * `Vec<T>` - Trailing entries, or all of them if `n` exceeds the length
+    pub fn tail<T: Clone>(candles: &[T], n: usize) -> Vec<T> {
+        candles[candles.len().saturating_sub(n)..].to_vec()
+    }
+    
+    /// Keeps only the rows whose timestamp lies in `[start, end)`
+    ///
+    /// # Arguments
+    /// * `data` - Historical data rows to filter
+    /// * `start` - Inclusive lower bound
+    /// * `end` - Exclusive upper bound
+    ///
+    /// # Returns
+    /// * `Result<Vec<HistoricalData>>` - Rows inside the range
+    ///
+    /// # Errors
+    /// * Returns error if any timestamp cannot be parsed
+    pub fn filter_range(
+        data: &[HistoricalData],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<HistoricalData>> {
+        let mut filtered = Vec::new();
+        
+        for row in data {
+            let timestamp = row.parsed_timestamp()?;
+            if timestamp >= start && timestamp < end {
+                filtered.push(row.clone());
+            }
+        }
+        
+        Ok(filtered)
+    }
+    
+    /// Gets the loaded data
+    ///
+    /// # Returns
+    /// * `&Vec<HistoricalData>` - Reference to the loaded data
+    #[allow(dead_code)]
+    pub fn get_data(&self) -> &Vec<HistoricalData> {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use tempfile::NamedTempFile;
+    use std::io::Write;
+    
+    /// Test DataProcessor creation
+    #[test]
+    fn test_data_processor_new() {
+        let processor = DataProcessor::new();
+        assert!(processor.get_data().is_empty());
+    }
+    
+    /// Test sample data generation
+    #[test]
+    fn test_generate_sample_data() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.generate_sample_data()?;
+        
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0].open, 100.0);
+        assert_eq!(data[1].close, 106.0);
+        assert_eq!(data[2].volume, 1500.0);
+        
+        Ok(())
+    }
+    
+    /// Test candlestick conversion
+    #[test]
+    fn test_to_candlesticks() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.generate_sample_data()?;
+        
+        let candlesticks = processor.to_candlesticks()?;
+        
+        assert_eq!(candlesticks.len(), 3);
+        assert_eq!(candlesticks[0].open, 100.0);
+        assert_eq!(candlesticks[1].close, 106.0);
+        assert_eq!(candlesticks[2].volume, 1500.0);
+        
+        Ok(())
+    }
+    
+    /// Test moving averages of the sample closes and period validation
+    #[test]
+    fn test_moving_averages() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.generate_sample_data()?;
+        
+        assert_eq!(processor.simple_moving_average(2)?, vec![None, Some(104.0), Some(107.0)]);
+        assert_eq!(processor.simple_moving_average(1)?, vec![Some(102.0), Some(106.0), Some(108.0)]);
+        
+        let ema = processor.exponential_moving_average(2)?;
+        assert_eq!(ema[..2], [None, Some(104.0)]);
+        assert!((ema[2].unwrap() - 320.0 / 3.0).abs() < 1e-9);
+        
+        for period in [0, 4] {
+            let err = processor.simple_moving_average(period).unwrap_err();
+            assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::DataProcessing(_))));
+            assert!(processor.exponential_moving_average(period).is_err());
+        }
+        
+        Ok(())
+    }
+    
+    /// Test CSV loading with temporary file
+    #[test]
+    fn test_load_csv_data() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Timestamp,Open,High,Low,Close,Volume")?;
+        writeln!(file, "2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0")?;
+        writeln!(file, "2023-01-02 00:00:00,102.0,108.0,101.0,106.0,1200.0")?;
+        
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data(file.path().to_str().unwrap())?;
+        
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].open, 100.0);
+        assert_eq!(data[1].close, 106.0);
+        
+        Ok(())
+    }
+    
+    /// Test loading semicolon and tab separated files with a fixed and a detected delimiter
+    #[test]
+    fn test_load_csv_data_delimiter() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "# symbol=ABC")?;
+        writeln!(file, "Timestamp;Open;High;Low;Close;Volume")?;
+        writeln!(file, "2023-01-01 00:00:00;100.0;105.0;95.0;102.0;1000.0")?;
+        let path = file.path().to_str().unwrap();
+        
+        let fixed = CsvProfile { delimiter: ';', ..CsvProfile::default() };
+        let data = DataProcessor::with_profile(fixed).load_csv_data(path)?;
+        assert_eq!((data.len(), data[0].close), (1, 102.0));
+        assert!(DataProcessor::new().load_csv_data(path).is_err());
+        
+        let auto = CsvProfile { detect_delimiter: true, ..CsvProfile::default() };
+        let data = DataProcessor::with_profile(auto.clone()).load_csv_data(path)?;
+        assert_eq!(data[0].volume, 1000.0);
+        assert_eq!(auto.delimiter_for("Timestamp\tOpen\tHigh,Low\tClose\tVolume\n"), '\t');
+        assert_eq!(auto.delimiter_for("Timestamp;Open,High\n"), ',');
+        assert_eq!(auto.delimiter_for("Timestamp\n"), ',');
+        assert_eq!(DataProcessor::with_profile(auto).load_csv_data("non_existent_file.csv")?.len(), 3);
+        
+        assert_eq!(Delimiter::parse("auto")?, Delimiter::Auto);
+        assert_eq!(Delimiter::parse("tab")?, Delimiter::Char('\t'));
+        assert_eq!(Delimiter::parse(";")?, Delimiter::Char(';'));
+        assert!(Delimiter::parse(";;").is_err());
+        assert!(Delimiter::parse("").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test that non-OHLCV columns are captured into the extra map
+    #[test]
+    fn test_load_csv_data_extra_columns() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Timestamp,Open,High,Low,Close,Volume,Regime")?;
+        writeln!(file, "2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0,bull")?;
+        writeln!(file, "2023-01-02 00:00:00,102.0,108.0,101.0,106.0,1200.0,")?;
+        
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data(file.path().to_str().unwrap())?;
+        
+        assert_eq!(data[0].extra.get("Regime").map(String::as_str), Some("bull"));
+        assert_eq!(data[1].extra.get("Regime").map(String::as_str), Some(""));
+        assert!(!data[0].extra.contains_key("Close"));
+        
+        Ok(())
+    }
+    
+    /// Test reading metadata from leading comments and skipping comment lines
+    #[test]
+    fn test_load_csv_data_metadata() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "# symbol=AAPL exchange=NASDAQ")?;
+        writeln!(file, "# Exported daily bars currency=USD")?;
+        writeln!(file, "Timestamp,Open,High,Low,Close,Volume")?;
+        writeln!(file, "2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0")?;
+        writeln!(file, "# trailing=ignored")?;
+        writeln!(file, "2023-01-02 00:00:00,102.0,108.0,101.0,106.0,1200.0")?;
+        
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data(file.path().to_str().unwrap())?;
+        
+        assert_eq!(data.len(), 2);
+        assert_eq!(processor.metadata().get("symbol").map(String::as_str), Some("AAPL"));
+        assert_eq!(processor.metadata().get("exchange").map(String::as_str), Some("NASDAQ"));
+        assert_eq!(processor.metadata().get("currency").map(String::as_str), Some("USD"));
+        assert!(!processor.metadata().contains_key("trailing"));
+        
+        let mut semicolon = NamedTempFile::new()?;
+        writeln!(semicolon, "; symbol=MSFT")?;
+        writeln!(semicolon, "Timestamp,Open,High,Low,Close,Volume")?;
+        writeln!(semicolon, "2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0")?;
+        let mut processor = DataProcessor::with_profile(CsvProfile { comment_char: ';', ..CsvProfile::default() });
+        assert_eq!(processor.load_csv_data(semicolon.path().to_str().unwrap())?.len(), 1);
+        assert_eq!(processor.metadata().get("symbol").map(String::as_str), Some("MSFT"));
+        
+        Ok(())
+    }
+    
+    /// Test skipping preamble lines before the header, with and without comments
+    #[test]
+    fn test_load_csv_data_header_row() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Exported by Example Broker")?;
+        writeln!(file, "Account: 1234, Currency: USD")?;
+        writeln!(file, "# symbol=AAPL")?;
+        writeln!(file, "Timestamp,Open,High,Low,Close,Volume")?;
+        writeln!(file, "2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0")?;
+        
+        let path = file.path().to_str().unwrap();
+        let mut processor = DataProcessor::with_profile(CsvProfile { header_row: 2, ..CsvProfile::default() });
+        assert_eq!(processor.load_csv_data(path)?[0].close, 102.0);
+        assert_eq!(processor.metadata().get("symbol").map(String::as_str), Some("AAPL"));
+        
+        assert!(DataProcessor::with_profile(CsvProfile { header_row: 5, ..CsvProfile::default() }).load_csv_data(path).is_err());
+        
+        let mut blank = NamedTempFile::new()?;
+        writeln!(blank, "Exported by Example Broker")?;
+        writeln!(blank)?;
+        writeln!(blank, "Timestamp,Open,High,Low,Close,Volume")?;
+        let error = DataProcessor::with_profile(CsvProfile { header_row: 1, ..CsvProfile::default() })
+            .load_csv_data(blank.path().to_str().unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("is blank"));
+        
+        Ok(())
+    }
+    
+    /// Test date range filtering with inclusive start and exclusive end
+    #[test]
+    fn test_filter_range() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.generate_sample_data()?;
+        
+        let start = NaiveDateTime::parse_from_str("2023-01-02 00:00:00", "%Y-%m-%d %H:%M:%S")?.and_utc();
+        let end = NaiveDateTime::parse_from_str("2023-01-03 00:00:00", "%Y-%m-%d %H:%M:%S")?.and_utc();
+        let filtered = DataProcessor::filter_range(&data, start, end)?;
+        
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].timestamp, "2023-01-02 00:00:00");
+        
+        Ok(())
+    }
+    
+    /// Test merging, replacing and capping appended rows
+    #[test]
+    fn test_append_rows() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.generate_sample_data()?;
+        
+        let row = |timestamp: &str, close: f64| HistoricalData {
+            timestamp: timestamp.to_string(),
+            close,
+            ..Default::default()
+        };
+        
+        processor.append_rows(vec![row("2023-01-04 00:00:00", 1.0)], None)?;
+        processor.append_rows(vec![row("2023-01-04 00:00:00", 2.0)], None)?;
+        processor.append_rows(vec![row("2023-01-01 12:00:00", 3.0)], Some(4))?;
+        
+        let timestamps: Vec<&str> = processor.get_data().iter().map(|r| r.timestamp.as_str()).collect();
+        assert_eq!(
+            timestamps,
+            ["2023-01-01 12:00:00", "2023-01-02 00:00:00", "2023-01-03 00:00:00", "2023-01-04 00:00:00"]
+        );
+        assert_eq!(processor.get_data()[3].close, 2.0);
+        
+        Ok(())
+    }
+    
+    /// Test loading and merging a directory of CSV files
+    #[test]
+    fn test_load_dir() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let header = "Timestamp,Open,High,Low,Close,Volume\n";
+        std::fs::write(
+            temp_dir.path().join("b.csv"),
+            format!("{}2023-01-03 00:00:00,3,3,3,3,3\n2023-01-02 00:00:00,9,9,9,9,9\n", header),
+        )?;
+        std::fs::write(
+            temp_dir.path().join("a.csv"),
+            format!("{}2023-01-01 00:00:00,1,1,1,1,1\n2023-01-02 00:00:00,2,2,2,2,2\n", header),
+        )?;
+        std::fs::write(temp_dir.path().join("notes.txt"), "not a csv")?;
+        
+        let mut processor = DataProcessor::new();
+        let data = processor.load_dir(temp_dir.path(), "*.csv")?;
+        
+        let closes: Vec<f64> = data.iter().map(|r| r.close).collect();
+        assert_eq!(closes, vec![1.0, 9.0, 3.0]);
+        assert!(processor.load_dir(temp_dir.path(), "*.json").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test head and tail including n larger than the series
+    #[test]
+    fn test_head_and_tail() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let candles = processor.generate_sample_data()
+            .and_then(|data| DataProcessor::candles_from(&data))?;
+        
+        let head = DataProcessor::head(&candles, 2);
+        assert_eq!(head.len(), 2);
+        assert_eq!(head[0].open, 100.0);
+        assert_eq!(DataProcessor::head(&candles, 10).len(), 3);
+        assert!(DataProcessor::head(&candles, 0).is_empty());
+        
+        let tail = DataProcessor::tail(&candles, 2);
+        assert_eq!(tail[0].open, 102.0);
+        assert_eq!(DataProcessor::tail(&candles, 10).len(), 3);
+        
+        assert_eq!(crate::indicators::sma(&head, 2)[1], Some(104.0));
+        
+        Ok(())
+    }
+    
+    /// Test that loading, exporting and reloading preserves the candles
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "Timestamp,Open,High,Low,Close,Volume")?;
+        writeln!(file, "2023-01-01 09:30:00,100.1,105.25,95.125,102.3333333333,1000")?;
+        writeln!(file, "2023-01-02 16:00:59,0.1,0.30000000000000004,0.000001,0.2,1e9")?;
+        
+        let original = DataProcessor::new()
+            .load_csv_data(file.path().to_str().unwrap())
+            .and_then(|data| DataProcessor::candles_from(&data))?;
+        
+        let exported = NamedTempFile::new()?;
+        DataProcessor::write_csv(&original, exported.path(), &CsvFormat::default())?;
+        let reloaded = DataProcessor::new()
+            .load_csv_data(exported.path().to_str().unwrap())
+            .and_then(|data| DataProcessor::candles_from(&data))?;
+        
+        assert_eq!(reloaded.len(), original.len());
+        for (a, b) in original.iter().zip(&reloaded) {
+            assert_eq!(a.timestamp, b.timestamp);
+            for (x, y) in [(a.open, b.open), (a.high, b.high), (a.low, b.low), (a.close, b.close), (a.volume, b.volume)] {
+                assert!((x - y).abs() <= f64::EPSILON * x.abs().max(1.0), "{} != {}", x, y);
+            }
+        }
+        
+        Ok(())
     }
     
-    /// Gets the loaded data
-    ///
-    /// # Returns
-    /// * `&Vec<HistoricalData>` - Reference to the loaded data
-    pub fn get_data(&self) -> &Vec<HistoricalData> {
-        &self.data
+    /// Test export formats derived from locales
+    #[test]
+    fn test_csv_format_for_locale() -> Result<()> {
+        assert_eq!(CsvFormat::for_locale(&Locale::default()), CsvFormat::default());
+        assert_eq!(CsvFormat::for_locale(&Locale::from_tag("de-DE")?), CsvFormat { delimiter: ';', decimal: ',' });
+        assert_eq!(CsvFormat::for_locale(&Locale::from_tag("ja-JP")?), CsvFormat::default());
+        
+        Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::NamedTempFile;
-    use std::io::Write;
     
-    /// Test DataProcessor creation
+    /// Test that exported CSV files record the schema version as metadata
     #[test]
-    fn test_data_processor_new() {
-        let processor = DataProcessor::new();
-        assert!(processor.get_data().is_empty());
+    fn test_write_csv_schema_version() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let candles = processor.load_csv_data("non_existent_file.csv")
+            .and_then(|data| DataProcessor::candles_from(&data))?;
+        
+        let exported = NamedTempFile::new()?;
+        DataProcessor::write_csv(&candles, exported.path(), &CsvFormat::default())?;
+        processor.load_csv_data(exported.path().to_str().unwrap())?;
+        
+        assert_eq!(processor.metadata().get("schema_version"), Some(&EXPORT_SCHEMA_VERSION.to_string()));
+        
+        Ok(())
     }
     
-    /// Test sample data generation
+    /// Test inserting flat synthetic candles into gaps
     #[test]
-    fn test_generate_sample_data() -> Result<()> {
+    fn test_fill_missing() -> Result<()> {
+        let candle = |day: u32, close: f64| -> Result<CandleStick> {
+            Ok(CandleStick {
+                timestamp: NaiveDateTime::parse_from_str(&format!("2023-01-{:02} 00:00:00", day), "%Y-%m-%d %H:%M:%S")?.and_utc(),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 10.0,
+                ..Default::default()
+            })
+        };
+        let candles = vec![candle(1, 100.0)?, candle(2, 101.0)?, candle(5, 104.0)?];
+        let interval = DataProcessor::min_interval(&candles).ok_or_else(|| anyhow!("no interval"))?;
+        assert_eq!(interval, chrono::Duration::days(1));
+        
+        let filled = DataProcessor::fill_missing(&candles, interval)?;
+        assert_eq!(filled.len(), 5);
+        assert_eq!(filled.iter().map(|c| c.synthetic).collect::<Vec<_>>(), vec![false, false, true, true, false]);
+        assert_eq!((filled[3].open, filled[3].close, filled[3].volume), (101.0, 101.0, 0.0));
+        assert_eq!(filled[3].timestamp, candle(4, 0.0)?.timestamp);
+        assert_eq!(DataProcessor::fill_missing(&candles, chrono::Duration::zero())?.len(), 3);
+        
+        let err = DataProcessor::fill_missing(&candles, chrono::Duration::milliseconds(1))
+            .err()
+            .ok_or_else(|| anyhow!("expected fill limit error"))?;
+        assert!(err.to_string().contains("more than the limit of 1000000"));
+        
+        Ok(())
+    }
+    
+    /// Test grouping intraday candles into daily sessions
+    #[test]
+    fn test_sessions() -> Result<()> {
+        let candle = |timestamp: &str| -> Result<CandleStick> {
+            Ok(CandleStick {
+                timestamp: NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")?.and_utc(),
+                ..Default::default()
+            })
+        };
+        let candles = vec![
+            candle("2023-01-02 09:00:00")?,
+            candle("2023-01-02 15:00:00")?,
+            candle("2023-01-03 09:00:00")?,
+            candle("2023-01-05 23:59:00")?,
+        ];
+        
+        assert_eq!(DataProcessor::sessions(&candles), vec![0..2, 2..3, 3..4]);
+        assert!(DataProcessor::sessions(&[]).is_empty());
+        
+        Ok(())
+    }
+    
+    /// Test reading embedded rows back from a chart file
+    #[test]
+    fn test_load_embedded() -> Result<()> {
         let mut processor = DataProcessor::new();
-        let data = processor.generate_sample_data()?;
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        let json = DataProcessor::embed_json(&[HistoricalData { timestamp: "<2023>".to_string(), ..data[0].clone() }])?;
+        assert!(!json.contains('<'));
         
-        assert_eq!(data.len(), 3);
-        assert_eq!(data[0].open, 100.0);
-        assert_eq!(data[1].close, 106.0);
-        assert_eq!(data[2].volume, 1500.0);
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("chart.html");
+        let embedded = DataProcessor::embed_json(&data)?;
+        std::fs::write(&path, format!("<body><script type=\"application/json\" id=\"{}\">{}</script></body>", EMBEDDED_DATA_ID, embedded))?;
+        
+        let loaded = processor.load_embedded(&path)?;
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[1].timestamp, data[1].timestamp);
+        assert_eq!((loaded[1].open, loaded[1].close, loaded[1].volume), (102.0, 106.0, 1200.0));
+        assert_eq!(processor.get_data().len(), 3);
+        
+        std::fs::write(&path, "<body></body>")?;
+        assert!(processor.load_embedded(&path).is_err());
         
         Ok(())
     }
     
-    /// Test candlestick conversion
+    /// Test spreading weekly values over daily candles, including a partial last week
     #[test]
-    fn test_to_candlesticks() -> Result<()> {
+    fn test_upsample() -> Result<()> {
+        // Monday 2023-01-02 to Wednesday 2023-01-18
+        let candles: Vec<CandleStick> = (0..17)
+            .map(|day| CandleStick {
+                timestamp: DateTime::from_timestamp(1_672_617_600 + day * 86_400, 0).unwrap_or_default(),
+                close: day as f64,
+                ..Default::default()
+            })
+            .collect();
+        let weekly = DataProcessor::resample(&candles, Duration::weeks(1), LabelTime::Open, &AggSpec::default());
+        assert_eq!(weekly.len(), 3);
+        
+        let values = DataProcessor::upsample(&candles, Duration::weeks(1), &[None, Some(1.0), Some(2.0)]);
+        assert_eq!(values[..7], [None; 7]);
+        assert_eq!(values[7..14], [Some(1.0); 7]);
+        assert_eq!(values[14..], [Some(2.0); 3]);
+        assert_eq!(DataProcessor::upsample(&candles, Duration::weeks(1), &[]), vec![None; 17]);
+        
+        Ok(())
+    }
+    
+    /// Test daily resampling with open and close labeling
+    #[test]
+    fn test_resample() -> Result<()> {
+        let candle = |timestamp: &str, open: f64, close: f64| -> Result<CandleStick> {
+            Ok(CandleStick {
+                timestamp: NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")?.and_utc(),
+                open,
+                high: open.max(close),
+                low: open.min(close),
+                close,
+                volume: 10.0,
+                ..Default::default()
+            })
+        };
+        let candles = vec![
+            candle("2023-01-02 09:00:00", 10.0, 12.0)?,
+            candle("2023-01-02 15:00:00", 12.0, 8.0)?,
+            candle("2023-01-03 09:00:00", 8.0, 9.0)?,
+        ];
+        
+        let daily = DataProcessor::resample(&candles, Duration::days(1), LabelTime::Open, &AggSpec::default());
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[0].timestamp.to_string(), "2023-01-02 00:00:00 UTC");
+        assert_eq!((daily[0].open, daily[0].high, daily[0].low, daily[0].close), (10.0, 12.0, 8.0, 8.0));
+        assert_eq!(daily[0].volume, 20.0);
+        
+        let labeled_close = DataProcessor::resample(&candles, Duration::days(1), LabelTime::Close, &AggSpec::default());
+        assert_eq!(labeled_close[0].timestamp.to_string(), "2023-01-03 00:00:00 UTC");
+        
+        let weekly = DataProcessor::resample(&candles, Duration::weeks(1), LabelTime::Open, &AggSpec::default());
+        assert_eq!(weekly.len(), 1);
+        assert_eq!(weekly[0].timestamp.to_string(), "2023-01-02 00:00:00 UTC");
+        
+        let agg = AggSpec::parse("volume=max, close=median")?;
+        assert_eq!((agg.close, agg.volume, agg.open), (Aggregation::Median, Aggregation::Max, Aggregation::First));
+        let custom = DataProcessor::resample(&candles, Duration::weeks(1), LabelTime::Open, &agg);
+        assert_eq!((custom[0].close, custom[0].volume), (9.0, 10.0));
+        
+        assert!(AggSpec::parse("price=max").is_err_and(|err| err.to_string().contains("Unknown field 'price'")));
+        assert!(AggSpec::parse("close=mode").is_err());
+        assert!(AggSpec::parse("close").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test pivoting a wide CSV into close-only series with gaps
+    #[test]
+    fn test_load_wide() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("wide.csv");
+        std::fs::write(&path, "Date,AAA,BBB,CCC\n2023-01-01 00:00:00,10,20,30\n2023-01-02 00:00:00,11,,31\n")?;
+        
+        let series = DataProcessor::load_wide(&path, &["AAA", "BBB"])?;
+        assert_eq!(series.len(), 2);
+        assert_eq!(series["AAA"].len(), 2);
+        assert_eq!(series["AAA"][1].close, 11.0);
+        assert_eq!(series["AAA"][1].high, 11.0);
+        assert_eq!(series["BBB"].len(), 1);
+        assert!(DataProcessor::load_wide(&path, &["ZZZ"]).is_err());
+        
+        Ok(())
+    }
+    
+    /// Test signed up and down streaks, flat closes and the first candle
+    #[test]
+    fn test_streaks() {
+        let candles: Vec<CandleStick> = [10.0, 11.0, 12.0, 12.0, 11.0, 10.0, 9.0, 10.0]
+            .iter()
+            .map(|&close| CandleStick { close, ..Default::default() })
+            .collect();
+        
+        let lengths: Vec<i32> = DataProcessor::streaks(&candles).into_iter().map(|(_, length)| length).collect();
+        assert_eq!(lengths, vec![0, 1, 2, 0, -1, -2, -3, 1]);
+        assert!(DataProcessor::streaks(&[]).is_empty());
+    }
+    
+    /// Test capping closes and optionally highs and lows at percentiles
+    #[test]
+    fn test_winsorize() {
+        let candles: Vec<CandleStick> = [10.0, 11.0, 12.0, 13.0, 100.0]
+            .iter()
+            .map(|&close| CandleStick { open: 11.0, high: close + 1.0, low: close - 1.0, close, ..Default::default() })
+            .collect();
+        
+        let capped = DataProcessor::winsorize(&candles, 0.0, 75.0, false);
+        assert_eq!(capped.iter().map(|c| c.close).collect::<Vec<_>>(), vec![10.0, 11.0, 12.0, 13.0, 13.0]);
+        assert_eq!((capped[4].high, capped[4].low), (101.0, 11.0));
+        assert_eq!(candles[4].close, 100.0);
+        
+        let capped = DataProcessor::winsorize(&candles, 25.0, 75.0, true);
+        assert_eq!((capped[0].close, capped[0].high, capped[0].low), (11.0, 12.0, 10.0));
+        assert_eq!((capped[4].close, capped[4].high, capped[4].low), (13.0, 14.0, 11.0));
+        assert!(DataProcessor::winsorize(&[], 1.0, 99.0, true).is_empty());
+    }
+    
+    /// Test accepting equal timestamps and reporting the first row out of order
+    #[test]
+    fn test_check_sorted() -> Result<()> {
         let mut processor = DataProcessor::new();
-        processor.generate_sample_data()?;
+        let mut data = processor.load_csv_data("non_existent_file.csv")?;
+        DataProcessor::check_sorted(&data)?;
         
-        let candlesticks = processor.to_candlesticks()?;
+        data[2].timestamp = data[1].timestamp.clone();
+        DataProcessor::check_sorted(&data)?;
         
-        assert_eq!(candlesticks.len(), 3);
-        assert_eq!(candlesticks[0].open, 100.0);
-        assert_eq!(candlesticks[1].close, 106.0);
-        assert_eq!(candlesticks[2].volume, 1500.0);
+        data.swap(0, 1);
+        let err = DataProcessor::check_sorted(&data).err().ok_or_else(|| anyhow!("unsorted data accepted"))?;
+        assert_eq!(err.to_string(), "Input is not sorted: row 1 (2023-01-01 00:00:00) is earlier than row 0 (2023-01-02 00:00:00)");
+        assert!(DataProcessor::check_sorted(&[]).is_ok());
         
         Ok(())
     }
     
-    /// Test CSV loading with temporary file
+    /// Test rewriting epoch timestamps, keeping fractional seconds
     #[test]
-    fn test_load_csv_data() -> Result<()> {
-        let mut file = NamedTempFile::new()?;
-        writeln!(file, "Timestamp,Open,High,Low,Close,Volume")?;
-        writeln!(file, "2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0")?;
-        writeln!(file, "2023-01-02 00:00:00,102.0,108.0,101.0,106.0,1200.0")?;
+    fn test_normalize_timestamps() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let mut data = processor.load_csv_data("non_existent_file.csv")?;
+        data[0].timestamp = "1672531200".to_string();
+        data[1].timestamp = "1672617600250".to_string();
+        DataProcessor::normalize_timestamps(&mut data, TimestampFormat::Auto)?;
         
+        assert_eq!(data[0].timestamp, "2023-01-01 00:00:00");
+        assert_eq!(data[1].timestamp, "2023-01-02 00:00:00.250");
+        assert_eq!(data[2].timestamp, "2023-01-03 00:00:00");
+        assert_eq!(data[1].parsed_timestamp()?.timestamp_subsec_millis(), 250);
+        
+        Ok(())
+    }
+    
+    /// Test that each OHLC rule is reported with the index of the first offending row
+    #[test]
+    fn test_check_ohlc() -> Result<()> {
         let mut processor = DataProcessor::new();
-        let data = processor.load_csv_data(file.path().to_str().unwrap())?;
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        DataProcessor::check_ohlc(&data)?;
+        assert!(DataProcessor::check_ohlc(&[]).is_ok());
         
+        let broken = |index: usize, change: fn(&mut HistoricalData)| {
+            let mut data = data.clone();
+            change(&mut data[index]);
+            DataProcessor::check_ohlc(&data).unwrap_err()
+        };
+        
+        let err = broken(1, |row| row.low = 110.0);
+        assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::DataProcessing(_))));
+        assert_eq!(err.to_string(), "Data processing error: Invalid candle: row 1 (2023-01-02 00:00:00) breaks low <= open <= high (low 110, open 102, high 108)");
+        assert!(broken(2, |row| row.close = 111.0).to_string().contains("row 2 (2023-01-03 00:00:00) breaks low <= close <= high"));
+        assert!(broken(0, |row| row.volume = -1.0).to_string().contains("row 0 (2023-01-01 00:00:00) breaks volume >= 0 (volume -1)"));
+        assert!(broken(0, |row| row.volume = f64::NAN).to_string().contains("volume >= 0"));
+        
+        Ok(())
+    }
+    
+    /// Test mean returns per weekday and month, leaving out empty groups
+    #[test]
+    fn test_seasonality() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let mut candles = DataProcessor::candles_from(&processor.load_csv_data("non_existent_file.csv")?)?;
+        
+        // 2023-01-01 is a Sunday, so the two returns end on Monday and Tuesday
+        let by_day = DataProcessor::seasonality(&candles, SeasonBy::Dow);
+        assert_eq!(by_day.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec!["Mon", "Tue"]);
+        assert!((by_day[0].1 - 4.0 / 102.0).abs() < 1e-12);
+        
+        candles[2].timestamp += Duration::days(7);
+        let by_day = DataProcessor::seasonality(&candles, SeasonBy::Dow);
+        assert_eq!(by_day.len(), 2);
+        
+        candles[2].timestamp += Duration::days(31);
+        let by_month = DataProcessor::seasonality(&candles, SeasonBy::Month);
+        assert_eq!(by_month.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec!["Jan", "Feb"]);
+        assert!(DataProcessor::seasonality(&candles[..1], SeasonBy::Month).is_empty());
+        
+        Ok(())
+    }
+    
+    /// Test dividing by benchmark closes, skipping missing and zero closes
+    #[test]
+    fn test_ratio_candles() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let candles = DataProcessor::candles_from(&processor.load_csv_data("non_existent_file.csv")?)?;
+        let mut benchmark: Vec<CandleStick> = candles.iter().map(|c| CandleStick { close: 50.0, ..c.clone() }).collect();
+        benchmark[1].close = 0.0;
+        benchmark.pop();
+        
+        let ratios = DataProcessor::ratio_candles(&candles, &benchmark);
+        assert_eq!(ratios.len(), 3);
+        let first = ratios[0].as_ref().ok_or_else(|| anyhow!("first candle dropped"))?;
+        assert_eq!((first.open, first.high, first.low, first.close, first.volume), (2.0, 2.1, 1.9, 2.04, 1000.0));
+        assert!(ratios[1].is_none());
+        assert!(ratios[2].is_none());
+        
+        Ok(())
+    }
+    
+    /// Test averaging bid and ask candles, volume sources and unmatched candles
+    #[test]
+    fn test_mid_candles() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let bid = DataProcessor::candles_from(&processor.load_csv_data("non_existent_file.csv")?)?;
+        let ask: Vec<CandleStick> = bid
+            .iter()
+            .map(|c| CandleStick { open: c.open + 2.0, high: c.high + 2.0, low: c.low + 2.0, close: c.close + 2.0, volume: 10.0, ..c.clone() })
+            .collect();
+        
+        let mid = DataProcessor::mid_candles(&bid, &ask, VolumeSource::Sum, false)?;
+        assert_eq!(mid.len(), 3);
+        assert_eq!((mid[0].open, mid[0].high, mid[0].low, mid[0].close), (101.0, 106.0, 96.0, 103.0));
+        assert_eq!(mid[0].volume, 1010.0);
+        assert_eq!(DataProcessor::mid_candles(&bid, &ask, VolumeSource::Ask, false)?[0].volume, 10.0);
+        assert_eq!(DataProcessor::mid_candles(&bid, &ask, VolumeSource::Bid, false)?[0].volume, 1000.0);
+        
+        let err = DataProcessor::mid_candles(&bid, &ask[1..], VolumeSource::Sum, false)
+            .err()
+            .ok_or_else(|| anyhow!("unmatched bid accepted"))?;
+        assert_eq!(err.to_string(), "Bid candle at 2023-01-01 00:00:00 has no ask candle");
+        assert!(DataProcessor::mid_candles(&bid[..2], &ask, VolumeSource::Sum, false).is_err());
+        
+        let mid = DataProcessor::mid_candles(&bid, &ask[1..], VolumeSource::Sum, true)?;
+        assert_eq!(mid.iter().map(|c| c.close).collect::<Vec<_>>(), vec![107.0, 109.0]);
+        
+        Ok(())
+    }
+    
+    /// Test finding price gaps and how soon they were filled
+    #[test]
+    fn test_gap_fill_stats() -> Result<()> {
+        let day = |days: i64, open: f64, high: f64, low: f64| -> Result<CandleStick> {
+            Ok(CandleStick {
+                timestamp: date_utils::parse_timestamp("2023-01-01 00:00:00", TimestampFormat::Auto)? + Duration::days(days),
+                open,
+                high,
+                low,
+                close: open,
+                ..Default::default()
+            })
+        };
+        let candles = vec![
+            day(0, 10.0, 11.0, 9.0)?,
+            // Gap up over 11, filled by the same candle
+            day(1, 12.0, 13.0, 10.5)?,
+            // Gap up over 13, filled two candles later
+            day(2, 14.0, 15.0, 13.5)?,
+            day(3, 15.0, 16.0, 14.0)?,
+            day(4, 14.0, 14.5, 12.0)?,
+            // Gap down below 12, never filled
+            day(5, 11.0, 11.5, 10.0)?,
+        ];
+        
+        let gaps = DataProcessor::price_gaps(&candles);
+        assert_eq!(gaps.iter().map(|gap| (gap.index, gap.up, gap.filled_at)).collect::<Vec<_>>(), vec![
+            (1, true, Some(1)),
+            (2, true, Some(4)),
+            (5, false, None),
+        ]);
+        assert_eq!(gaps[2].level, 12.0);
+        
+        let stats = DataProcessor::gap_fill_stats(&candles);
+        assert_eq!((stats.gaps, stats.filled, stats.filled_same_candle, stats.filled_next_candle), (3, 2, 1, 1));
+        assert_eq!(stats.same_candle_rate(), Some(1.0 / 3.0));
+        assert_eq!(stats.mean_time_to_fill, Some(Duration::days(1)));
+        assert_eq!(DataProcessor::gap_fill_stats(&candles[..1]), GapFillStats::default());
+        assert_eq!(GapFillStats::default().next_candle_rate(), None);
+        
+        Ok(())
+    }
+    
+    /// Test the issue counts and weighting of the quality score
+    #[test]
+    fn test_quality_score() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        assert_eq!(DataProcessor::quality_score(&processor.to_candlesticks()?), 100.0);
+        assert_eq!(DataProcessor::quality_score(&[]), 0.0);
+        
+        let day = |days: i64, open: f64, high: f64| -> Result<CandleStick> {
+            Ok(CandleStick {
+                timestamp: date_utils::parse_timestamp("2023-01-01 00:00:00", TimestampFormat::Auto)? + Duration::days(days),
+                open,
+                high,
+                low: 1.0,
+                close: 2.0,
+                volume: 10.0,
+                synthetic: false,
+            })
+        };
+        let candles = vec![
+            day(0, 2.0, 3.0)?,
+            day(1, 2.0, 3.0)?,
+            day(1, 2.0, 3.0)?,
+            day(3, f64::NAN, 3.0)?,
+            day(4, 5.0, 3.0)?,
+        ];
+        let report = DataProcessor::quality_report(&candles);
+        assert_eq!(report, QualityReport { candles: 5, gaps: 1, duplicates: 1, non_finite: 1, ohlc_violations: 1 });
+        assert_eq!(report.penalties(), [2.5, 5.0, 8.0, 6.0]);
+        assert_eq!(DataProcessor::quality_score(&candles), 78.5);
+        
+        Ok(())
+    }
+    
+    /// Test binning returns, including a single return and too few candles
+    #[test]
+    fn test_returns_histogram() {
+        let candles: Vec<CandleStick> = [100.0, 110.0, 99.0, 99.0, 0.0, 10.0]
+            .iter()
+            .map(|&close| CandleStick { close, ..Default::default() })
+            .collect();
+        
+        assert_eq!(DataProcessor::returns(&candles), vec![0.1, -0.1, 0.0, -1.0]);
+        let histogram = DataProcessor::returns_histogram(&candles, 4);
+        for ((center, _), expected) in histogram.iter().zip([-0.8625, -0.5875, -0.3125, -0.0375]) {
+            assert!((center - expected).abs() < 1e-12, "{} != {}", center, expected);
+        }
+        assert_eq!(histogram.iter().map(|(_, count)| *count).collect::<Vec<_>>(), vec![1, 0, 0, 3]);
+        
+        assert_eq!(DataProcessor::returns_histogram(&candles[..2], 4), vec![(0.1, 1)]);
+        assert!(DataProcessor::returns_histogram(&candles[..1], 4).is_empty());
+        assert!(DataProcessor::returns_histogram(&candles, 0).is_empty());
+    }
+    
+    /// Test the summary of the sample data and of an empty series
+    #[test]
+    fn test_summary() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let summary = DataProcessor::summary(&processor.to_candlesticks()?).ok_or_else(|| anyhow!("no summary"))?;
+        
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.first.to_rfc3339(), "2023-01-01T00:00:00+00:00");
+        assert_eq!(summary.last.to_rfc3339(), "2023-01-03T00:00:00+00:00");
+        assert_eq!((summary.open, summary.close, summary.high, summary.low), (100.0, 108.0, 110.0, 95.0));
+        assert_eq!(summary.volume, 3700.0);
+        assert_eq!(summary.change_pct(), 8.0);
+        assert!(DataProcessor::summary(&[]).is_none());
+        
+        Ok(())
+    }
+    
+    /// Test the long-only simulator and the trade log it writes
+    #[test]
+    fn test_simulate_and_write_trade_log() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let candles = processor.to_candlesticks()?;
+        let signal = |index, side| Signal { index, side };
+        
+        let signals = [
+            signal(0, SignalSide::Exit),
+            signal(0, SignalSide::Entry),
+            signal(1, SignalSide::Entry),
+            signal(1, SignalSide::Exit),
+            signal(2, SignalSide::Entry),
+        ];
+        let trades = DataProcessor::simulate(&signals, &candles);
+        
+        assert_eq!(trades.len(), 1);
+        assert_eq!((trades[0].entry_price, trades[0].exit_price), (102.0, 106.0));
+        assert!((trades[0].return_pct() - 3.9216).abs() < 1e-4);
+        
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("trades.csv");
+        DataProcessor::write_trade_log(&trades, &path)?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "entry_time,entry_price,exit_time,exit_price,return_pct\n\
+             2023-01-01 00:00:00,102,2023-01-02 00:00:00,106,3.9216\n"
+        );
+        
+        Ok(())
+    }
+    
+    /// Test one row per signal and combined rows for candles with several signals
+    #[test]
+    fn test_write_signals() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let candles = processor.to_candlesticks()?;
+        let signals = BTreeMap::from([
+            (0, vec!["fractal_up".to_string(), "ma_cross_entry".to_string()]),
+            (2, vec!["ma_cross_exit".to_string()]),
+            (7, vec!["out_of_range".to_string()]),
+        ]);
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("signals.csv");
+        
+        DataProcessor::write_signals(&candles, &signals, &path, false)?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "Timestamp,Open,High,Low,Close,Volume,Signal\n\
+             2023-01-01 00:00:00,100,105,95,102,1000,fractal_up\n\
+             2023-01-01 00:00:00,100,105,95,102,1000,ma_cross_entry\n\
+             2023-01-03 00:00:00,106,110,104,108,1500,ma_cross_exit\n"
+        );
+        
+        DataProcessor::write_signals(&candles, &signals, &path, true)?;
+        let text = std::fs::read_to_string(&path)?;
+        assert_eq!(text.lines().nth(1), Some("2023-01-01 00:00:00,100,105,95,102,1000,fractal_up;ma_cross_entry"));
+        assert_eq!(text.lines().count(), 3);
+        
+        Ok(())
+    }
+    
+    /// Test scaling to ticks with ties rounded to even, and rejected scales
+    #[test]
+    fn test_to_ticks() -> Result<()> {
+        let candle = |prices: [f64; 4]| CandleStick {
+            open: prices[0],
+            high: prices[1],
+            low: prices[2],
+            close: prices[3],
+            volume: 1.5,
+            ..Default::default()
+        };
+        
+        // Ties 0.5, 1.5, 2.5 and -0.5 go to the even neighbour
+        let ticks = DataProcessor::to_ticks(&[candle([0.25, 0.75, 1.25, -0.25])], 2.0)?;
+        assert_eq!((ticks[0].open, ticks[0].high, ticks[0].low, ticks[0].close), (0, 2, 2, 0));
+        assert_eq!(ticks[0].volume, 1.5);
+        
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("ticks.csv");
+        DataProcessor::write_ticks(&DataProcessor::to_ticks(&[candle([100.0, 105.25, 95.5, 102.1])], 100.0)?, &path)?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "Timestamp,Open,High,Low,Close,Volume\n1970-01-01 00:00:00,10000,10525,9550,10210,1.5\n"
+        );
+        
+        assert!(DataProcessor::to_ticks(&[], 0.0).is_err());
+        assert!(DataProcessor::to_ticks(&[], f64::NAN).is_err());
+        assert!(DataProcessor::to_ticks(&[candle([1e300; 4])], 100.0).is_err());
+        
+        Ok(())
+    }
+    
+    /// Test reading files laid out as in the built-in profiles
+    #[test]
+    fn test_load_with_builtin_profiles() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let cases = [
+            ("yahoo", "Date,Open,High,Low,Close,Adj Close,Volume\n2023-01-03,10,12,9,11,10.9,500\n"),
+            ("binance", "open_time,open,high,low,close,volume,close_time\n1672704000000,10,12,9,11,500,1672790399999\n"),
+            ("ibkr", "date,open,high,low,close,volume\n20230103  00:00:00,10,12,9,11,500\n"),
+        ];
+        
+        for (name, contents) in cases {
+            let path = temp_dir.path().join(format!("{}.csv", name));
+            std::fs::write(&path, contents)?;
+            
+            let mut processor = DataProcessor::with_profile(CsvProfile::resolve(name, None)?);
+            let data = processor.load_csv_data(&path.to_string_lossy())?;
+            assert_eq!(data[0].timestamp, "2023-01-03 00:00:00", "{}", name);
+            assert_eq!((data[0].close, data[0].volume), (11.0, 500.0), "{}", name);
+        }
+        
+        Ok(())
+    }
+    
+    /// Test that user profiles override built-ins and unknown names are rejected
+    #[test]
+    fn test_resolve_user_profiles() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let profiles = temp_dir.path().join("profiles.toml");
+        std::fs::write(&profiles, "[yahoo]\ndelimiter = \";\"\n\n[mybroker]\ntimestamp = \"Time\"\ndate_format = \"%d.%m.%Y %H:%M\"\n")?;
+        
+        let yahoo = CsvProfile::resolve("yahoo", Some(&profiles))?;
+        assert_eq!((yahoo.delimiter, yahoo.timestamp.as_str(), yahoo.date_format), (';', "Timestamp", None));
+        
+        let path = temp_dir.path().join("mybroker.csv");
+        std::fs::write(&path, "Time,Open,High,Low,Close,Volume\n03.01.2023 14:30,10,12,9,11,500\n")?;
+        let mut processor = DataProcessor::with_profile(CsvProfile::resolve("mybroker", Some(&profiles))?);
+        assert_eq!(processor.load_csv_data(&path.to_string_lossy())?[0].timestamp, "2023-01-03 14:30:00");
+        
+        let err = CsvProfile::resolve("nasdaq", Some(&profiles)).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown profile 'nasdaq', expected one of: binance, ibkr, mybroker, yahoo");
+        
+        Ok(())
+    }
+    
+    /// Test loading events and rejecting unparseable timestamps
+    #[test]
+    fn test_load_events() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("events.csv");
+        std::fs::write(&path, "Timestamp,Label,Kind\n2023-01-02 00:00:00,Q4 earnings,earnings\n1672704000,2:1 split,split\n")?;
+        
+        let events = DataProcessor::load_events(&path)?;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].label, "Q4 earnings");
+        assert_eq!(events[1].kind, "split");
+        
+        std::fs::write(&path, "Timestamp,Label,Kind\nsoon,Launch,product\n")?;
+        assert!(DataProcessor::load_events(&path).is_err());
+        
+        Ok(())
+    }
+    
+    /// Test loading from SQLite, including missing column reporting
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_load_sqlite() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let db = temp_dir.path().join("prices.db");
+        let connection = rusqlite::Connection::open(&db)?;
+        connection.execute_batch(
+            "CREATE TABLE prices (ts INTEGER, open REAL, high REAL, low REAL, close REAL, volume INTEGER, regime TEXT);
+             INSERT INTO prices VALUES (1672531200, 100, 105, 95, 102, 1000, 'bull');
+             INSERT INTO prices VALUES (1672617600, 102, 108, 101, 106, 1200, NULL);",
+        )?;
+        drop(connection);
+        
+        let mut processor = DataProcessor::new();
+        let data = processor.load_sqlite(
+            &db,
+            "SELECT ts AS Timestamp, open, high, low, close, volume, regime FROM prices ORDER BY ts",
+        )?;
         assert_eq!(data.len(), 2);
-        assert_eq!(data[0].open, 100.0);
-        assert_eq!(data[1].close, 106.0);
+        assert_eq!(data[0].timestamp, "2023-01-01 00:00:00");
+        assert_eq!(data[1].volume, 1200.0);
+        assert_eq!(data[0].extra["regime"], "bull");
+        assert_eq!(processor.get_data().len(), 2);
+        
+        let err = processor.load_sqlite(&db, "SELECT ts, open, close FROM prices").unwrap_err();
+        assert_eq!(err.to_string(), "SQLite query is missing required columns: Timestamp, High, Low, Volume");
+        
+        Ok(())
+    }
+    
+    /// Test exporting a German-locale CSV with semicolons and decimal commas
+    #[test]
+    fn test_write_csv_german_format() -> Result<()> {
+        let candles = vec![CandleStick {
+            timestamp: NaiveDateTime::parse_from_str("2023-01-01 00:00:00", "%Y-%m-%d %H:%M:%S")?.and_utc(),
+            open: 100.5,
+            high: 105.25,
+            low: 95.0,
+            close: 102.75,
+            volume: 1000.0,
+            ..Default::default()
+        }];
+        let german = CsvFormat { delimiter: ';', decimal: ',' };
+        
+        let exported = NamedTempFile::new()?;
+        DataProcessor::write_csv(&candles, exported.path(), &german)?;
+        
+        assert_eq!(
+            std::fs::read_to_string(exported.path())?,
+            "# schema_version=1\nTimestamp;Open;High;Low;Close;Volume\n2023-01-01 00:00:00;100,5;105,25;95;102,75;1000\n"
+        );
+        assert!(DataProcessor::write_csv(&candles, exported.path(), &CsvFormat { delimiter: ',', decimal: ',' }).is_err());
+        
+        Ok(())
+    }
+    
+    /// Test range bars including a move spanning several bars
+    #[test]
+    fn test_range_bars() -> Result<()> {
+        let candles: Vec<CandleStick> = [100.0, 101.0, 99.5, 103.5, 103.0]
+            .iter()
+            .enumerate()
+            .map(|(hour, &close)| -> Result<CandleStick> {
+                Ok(CandleStick {
+                    timestamp: NaiveDateTime::parse_from_str(&format!("2023-01-01 {:02}:00:00", hour), "%Y-%m-%d %H:%M:%S")?.and_utc(),
+                    close,
+                    volume: 10.0,
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<_>>()?;
+        
+        let bars = DataProcessor::range_bars(&candles, 2.0);
+        let ohlc: Vec<(f64, f64, f64, f64)> = bars.iter().map(|b| (b.open, b.high, b.low, b.close)).collect();
+        assert_eq!(ohlc, vec![
+            (100.0, 101.5, 99.5, 101.5),
+            (101.5, 103.5, 101.5, 103.5),
+            (103.5, 103.5, 103.0, 103.0),
+        ]);
+        assert_eq!(bars[0].timestamp.to_string(), "2023-01-01 03:00:00 UTC");
+        assert_eq!(bars[0].volume, 40.0);
+        assert_eq!(bars[2].timestamp.to_string(), "2023-01-01 04:00:00 UTC");
+        assert!(DataProcessor::range_bars(&candles, 0.0).is_empty());
         
         Ok(())
     }
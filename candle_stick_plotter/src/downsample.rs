@@ -0,0 +1,253 @@
+//! Thinning of series with more candles than the chart has pixels for
+//!
+//! Every strategy splits the series into consecutive buckets and draws one row
+//! per bucket. The thinned rows come with the range of source rows each one
+//! stands for, so callers can map a drawn candle back to the candles it
+//! replaced, and always carry the bucket's summed volume so volume totals are
+//! kept whichever strategy picks the prices.
+
+use std::ops::Range;
+
+use crate::data_processor::HistoricalData;
+
+/// Narrowest candle, in pixels, before a series is thinned
+pub const MIN_CANDLE_PIXELS: u32 = 2;
+
+/// How candles are combined when a series is thinned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ThinStrategy {
+    /// Merge adjacent candles into one proper OHLC candle with summed volume
+    OhlcMerge,
+    /// Keep every n-th candle and drop the others
+    Skip,
+    /// Keep the candles whose closes best preserve the close line's shape
+    /// (largest triangle three buckets), intended for close-line charts
+    Lttb,
+}
+
+/// Result of thinning a series
+#[derive(Debug, Clone)]
+pub struct Thinned {
+    /// Rows to draw
+    pub data: Vec<HistoricalData>,
+    /// Source rows represented by each drawn row; together they cover the
+    /// whole input without gaps
+    pub sources: Vec<Range<usize>>,
+}
+
+/// Computes how many candles fit a chart of the given width
+///
+/// # Arguments
+/// * `width` - Chart width in pixels
+///
+/// # Returns
+/// * `usize` - Largest candle count drawn without thinning, at least 1
+pub fn max_candles(width: u32) -> usize {
+    (width / MIN_CANDLE_PIXELS).max(1) as usize
+}
+
+/// Scales the candle budget of a series of which only part is shown
+///
+/// Zooming keeps the whole series for indicator warmup, so the budget of
+/// the visible part is stretched over the whole series. A visible part that
+/// fits the chart needs no thinning at all.
+///
+/// # Arguments
+/// * `total` - Number of candles in the series
+/// * `visible` - Number of candles shown
+/// * `max` - Largest number of candles drawn without thinning
+///
+/// # Returns
+/// * `usize` - Budget to pass to `thin` for the whole series
+pub fn zoomed_budget(total: usize, visible: usize, max: usize) -> usize {
+    match visible > max {
+        true => (max * total).div_ceil(visible),
+        false => total.max(max),
+    }
+}
+
+/// Reduces a series to at most `max` rows
+///
+/// Series that already fit are returned unchanged.
+///
+/// # Arguments
+/// * `data` - Rows in chronological order
+/// * `max` - Largest number of rows to keep
+/// * `strategy` - How rows are combined or selected
+///
+/// # Returns
+/// * `Thinned` - Kept rows and the source ranges they represent
+pub fn thin(data: &[HistoricalData], max: usize, strategy: ThinStrategy) -> Thinned {
+    if data.len() <= max || max == 0 {
+        return Thinned { data: data.to_vec(), sources: (0..data.len()).map(|i| i..i + 1).collect() };
+    }
+    
+    match strategy {
+        ThinStrategy::OhlcMerge => ohlc_merge(data, max),
+        ThinStrategy::Skip => {
+            let size = data.len().div_ceil(max);
+            select(data, (0..data.len()).step_by(size).map(|start| (start, start..(start + size).min(data.len()))).collect())
+        }
+        ThinStrategy::Lttb => select(data, lttb_buckets(data, max)),
+    }
+}
+
+/// Merges consecutive groups of rows into single OHLC rows
+fn ohlc_merge(data: &[HistoricalData], max: usize) -> Thinned {
+    let size = data.len().div_ceil(max);
+    let mut thinned = Thinned { data: Vec::new(), sources: Vec::new() };
+    
+    for (chunk_index, chunk) in data.chunks(size).enumerate() {
+        let first = &chunk[0];
+        thinned.data.push(HistoricalData {
+            high: chunk.iter().map(|row| row.high).fold(f64::NEG_INFINITY, f64::max),
+            low: chunk.iter().map(|row| row.low).fold(f64::INFINITY, f64::min),
+            close: chunk[chunk.len() - 1].close,
+            volume: chunk.iter().map(|row| row.volume).sum(),
+            ..first.clone()
+        });
+        let start = chunk_index * size;
+        thinned.sources.push(start..start + chunk.len());
+    }
+    
+    thinned
+}
+
+/// Keeps one row per bucket, carrying the bucket's summed volume
+fn select(data: &[HistoricalData], picks: Vec<(usize, Range<usize>)>) -> Thinned {
+    let mut thinned = Thinned { data: Vec::new(), sources: Vec::new() };
+    
+    for (index, bucket) in picks {
+        thinned.data.push(HistoricalData {
+            volume: data[bucket.clone()].iter().map(|row| row.volume).sum(),
+            ..data[index].clone()
+        });
+        thinned.sources.push(bucket);
+    }
+    
+    thinned
+}
+
+/// Picks one row per bucket with the largest triangle three buckets algorithm on closes
+///
+/// The first and last rows form buckets of their own; the rows between are
+/// split into `max - 2` buckets.
+fn lttb_buckets(data: &[HistoricalData], max: usize) -> Vec<(usize, Range<usize>)> {
+    let last = data.len() - 1;
+    match max {
+        1 => return vec![(0, 0..data.len())],
+        2 => return vec![(0, 0..last), (last, last..data.len())],
+        _ => {}
+    }
+    
+    let bucket_size = (data.len() - 2) as f64 / (max - 2) as f64;
+    let point = |i: usize| (i as f64, data[i].close);
+    let mut picks = vec![(0, 0..1)];
+    
+    for bucket in 0..max - 2 {
+        let start = (bucket as f64 * bucket_size) as usize + 1;
+        let end = (((bucket + 1) as f64 * bucket_size) as usize + 1).min(data.len() - 1);
+        
+        let next_start = end;
+        let next_end = (((bucket + 2) as f64 * bucket_size) as usize + 1).min(data.len());
+        let next = &data[next_start..next_end.max(next_start + 1)];
+        let average = (
+            next_start as f64 + (next.len() - 1) as f64 / 2.0,
+            next.iter().map(|row| row.close).sum::<f64>() / next.len() as f64,
+        );
+        
+        let previous = point(picks[picks.len() - 1].0);
+        let area = |i: usize| {
+            let (x, y) = point(i);
+            ((previous.0 - average.0) * (y - previous.1) - (previous.0 - x) * (average.1 - previous.1)).abs()
+        };
+        let best = (start..end.max(start + 1)).fold(start, |best, i| if area(i) > area(best) { i } else { best });
+        picks.push((best, start..end));
+    }
+    
+    picks.push((last, last..data.len()));
+    picks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Builds rows with the given closes and unit volume
+    fn rows(closes: &[f64]) -> Vec<HistoricalData> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| HistoricalData {
+                timestamp: format!("2023-01-{:02} 00:00:00", i + 1),
+                open: close - 1.0,
+                high: close + 1.0,
+                low: close - 2.0,
+                close,
+                volume: 1.0,
+                ..Default::default()
+            })
+            .collect()
+    }
+    
+    /// Test that short series are left unchanged
+    #[test]
+    fn test_thin_within_limit() {
+        let data = rows(&[1.0, 2.0]);
+        let thinned = thin(&data, 5, ThinStrategy::OhlcMerge);
+        
+        assert_eq!(thinned.data.len(), 2);
+        assert_eq!(thinned.sources, vec![0..1, 1..2]);
+        assert_eq!(max_candles(1200), 600);
+    }
+    
+    /// Test stretching the budget of a zoomed-in part over the series
+    #[test]
+    fn test_zoomed_budget() {
+        assert_eq!(zoomed_budget(2000, 24, 600), 2000);
+        assert_eq!(zoomed_budget(2000, 1000, 500), 1000);
+        assert_eq!(zoomed_budget(100, 100, 600), 600);
+    }
+    
+    /// Test merging adjacent candles into proper OHLC
+    #[test]
+    fn test_thin_ohlc_merge() {
+        let thinned = thin(&rows(&[10.0, 12.0, 11.0, 15.0, 14.0]), 2, ThinStrategy::OhlcMerge);
+        
+        assert_eq!(thinned.sources, vec![0..3, 3..5]);
+        let first = &thinned.data[0];
+        assert_eq!((first.open, first.high, first.low, first.close), (9.0, 13.0, 8.0, 11.0));
+        assert_eq!(first.volume, 3.0);
+        assert_eq!(first.timestamp, "2023-01-01 00:00:00");
+    }
+    
+    /// Test skipping and LTTB selection keep original candles
+    #[test]
+    fn test_thin_skip_and_lttb() {
+        let data = rows(&[1.0, 1.0, 9.0, 1.0, 1.0, 1.0, 1.0]);
+        
+        let skipped = thin(&data, 3, ThinStrategy::Skip);
+        assert_eq!(skipped.sources, vec![0..3, 3..6, 6..7]);
+        assert_eq!(skipped.data.iter().map(|row| row.close).collect::<Vec<_>>(), vec![1.0, 1.0, 1.0]);
+        
+        let lttb = thin(&data, 3, ThinStrategy::Lttb);
+        assert_eq!(lttb.sources, vec![0..1, 1..6, 6..7]);
+        assert_eq!(lttb.data[1].close, 9.0);
+    }
+    
+    /// Test that every strategy keeps the volume total and covers all rows
+    #[test]
+    fn test_thin_keeps_volume_totals() {
+        let data = rows(&[3.0, 1.0, 4.0, 1.0, 5.0, 9.0, 2.0, 6.0, 5.0, 3.0, 5.0]);
+        
+        for strategy in [ThinStrategy::OhlcMerge, ThinStrategy::Skip, ThinStrategy::Lttb] {
+            for max in 1..data.len() {
+                let thinned = thin(&data, max, strategy);
+                assert_eq!(thinned.data.len(), thinned.sources.len());
+                assert!(thinned.data.len() <= max, "{:?} {}", strategy, max);
+                assert_eq!(thinned.data.iter().map(|row| row.volume).sum::<f64>(), 11.0, "{:?} {}", strategy, max);
+                assert_eq!(thinned.sources.iter().map(|range| range.len()).sum::<usize>(), data.len());
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Standalone HTML report embedding the SVG chart
+//!
+//! The page needs no scripts or external resources: the chart is inlined as
+//! SVG and followed by a legend of the drawn series, a table of summary
+//! figures and a table of the input file's metadata. The candles can be
+//! embedded as a JSON data block so the report carries its own source data.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context};
+
+use crate::data_processor::{Summary, EMBEDDED_DATA_ID};
+use crate::render_model::{RenderModel, TextRole};
+use crate::svg;
+use crate::utils::color_utils;
+use crate::utils::locale_utils::Locale;
+use crate::utils::string_utils::escape_markup;
+
+/// Page title used when the chart has no title
+const DEFAULT_TITLE: &str = "Candle Stick Chart";
+
+/// Styles of the legend swatches and tables
+const STYLE: &str = "body { font-family: sans-serif; margin: 16px; }
+.legend { list-style: none; padding: 0; }
+.legend li { display: inline-block; margin-right: 16px; }
+.swatch { display: inline-block; width: 12px; height: 12px; margin-right: 4px; vertical-align: middle; }
+table { border-collapse: collapse; margin-top: 12px; }
+th, td { border: 1px solid #cccccc; padding: 4px 8px; text-align: left; }";
+
+/// Builds the HTML report of a chart
+///
+/// # Arguments
+/// * `model` - Chart to embed
+/// * `summary` - Key figures of the charted candles, omitted if `None`
+/// * `metadata` - `key=value` pairs read from the input, listed by key
+/// * `locale` - Number and date conventions of the tables
+/// * `embedded` - Candle JSON from `DataProcessor::embed_json`, omitted if `None`
+///
+/// # Returns
+/// * `String` - Complete HTML document
+pub fn document(
+    model: &RenderModel,
+    summary: Option<&Summary>,
+    metadata: &HashMap<String, String>,
+    locale: &Locale,
+    embedded: Option<&str>,
+) -> String {
+    let title = model.texts
+        .iter()
+        .find(|text| text.role == TextRole::Title)
+        .map_or(DEFAULT_TITLE.to_string(), |text| text.lines.join(" "));
+    
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(html, "<title>{}</title>", escape_markup(&title));
+    let _ = writeln!(html, "<style>\n{}\n</style>\n</head>\n<body>", STYLE);
+    html.push_str(&svg::render(model));
+    if let Some(json) = embedded {
+        let _ = writeln!(html, "<script type=\"application/json\" id=\"{}\">{}</script>", EMBEDDED_DATA_ID, json);
+    }
+    
+    let mut legend: Vec<(&str, String)> = Vec::new();
+    for (name, color) in model.lines.iter().map(|line| (line.name.as_str(), line.color))
+        .chain(model.markers.iter().map(|marker| (marker.name.as_str(), marker.color)))
+    {
+        if !name.is_empty() && legend.iter().all(|(known, _)| *known != name) {
+            legend.push((name, color_utils::to_hex(color)));
+        }
+    }
+    if !legend.is_empty() {
+        html.push_str("<ul class=\"legend\">\n");
+        for (name, color) in legend {
+            let _ = writeln!(
+                html,
+                "<li><span class=\"swatch\" style=\"background: {}\"></span>{}</li>",
+                color, escape_markup(name)
+            );
+        }
+        html.push_str("</ul>\n");
+    }
+    
+    if let Some(summary) = summary {
+        let price = |value: f64| locale.format_number(value, model.price_precision);
+        let rows = [
+            ("Candles", summary.count.to_string()),
+            ("From", locale.format_date(&summary.first)),
+            ("To", locale.format_date(&summary.last)),
+            ("Open", price(summary.open)),
+            ("Close", price(summary.close)),
+            ("Change", format!("{}%", locale.format_number(summary.change_pct(), 2))),
+            ("High", price(summary.high)),
+            ("Low", price(summary.low)),
+            ("Volume", locale.format_number(summary.volume, 0)),
+        ];
+        table(&mut html, "Summary", rows.iter().map(|(key, value)| (*key, value.as_str())));
+    }
+    
+    if !metadata.is_empty() {
+        let sorted: BTreeMap<&String, &String> = metadata.iter().collect();
+        table(&mut html, "Metadata", sorted.into_iter().map(|(key, value)| (key.as_str(), value.as_str())));
+    }
+    
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Writes the HTML report of a chart
+///
+/// # Arguments
+/// * `model` - Chart to embed
+/// * `summary` - Key figures of the charted candles, omitted if `None`
+/// * `metadata` - `key=value` pairs read from the input
+/// * `locale` - Number and date conventions of the tables
+/// * `embedded` - Candle JSON written after the chart, omitted if `None`
+/// * `path` - Destination file
+///
+/// # Returns
+/// * `Result<()>` - Ok if the file was written
+///
+/// # Errors
+/// * Returns error if writing fails
+pub fn write_html(
+    model: &RenderModel,
+    summary: Option<&Summary>,
+    metadata: &HashMap<String, String>,
+    locale: &Locale,
+    embedded: Option<&str>,
+    path: &Path,
+) -> Result<()> {
+    fs::write(path, document(model, summary, metadata, locale, embedded))
+        .context(format!("Failed to write HTML report: {}", path.display()))?;
+    log::info!("Wrote HTML report to {}", path.display());
+    Ok(())
+}
+
+/// Appends a two-column table with a caption
+fn table<'a>(html: &mut String, caption: &str, rows: impl Iterator<Item = (&'a str, &'a str)>) {
+    let _ = writeln!(html, "<table>\n<caption>{}</caption>", escape_markup(caption));
+    for (key, value) in rows {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", escape_markup(key), escape_markup(value));
+    }
+    html.push_str("</table>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processor::DataProcessor;
+    use crate::plotter::{Overlay, PlotConfig, Plotter};
+    
+    /// Test the embedded chart, legend, summary and escaped metadata
+    #[test]
+    fn test_document() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let config = PlotConfig { overlays: vec![Overlay::Momentum(1)], ..Default::default() };
+        let model = Plotter::with_config(config).render_model(processor.get_data())?;
+        let summary = DataProcessor::summary(&processor.to_candlesticks()?);
+        let metadata = HashMap::from([("source".to_string(), "<exchange> & co".to_string())]);
+        
+        let html = document(&model, summary.as_ref(), &metadata, &Locale::default(), None);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Candle Stick Chart</title>"));
+        assert!(html.contains("<svg xmlns="));
+        assert!(!html.contains("<script"));
+        assert!(html.contains("</span>Momentum(1)</li>"));
+        assert!(html.contains("<tr><th>Candles</th><td>3</td></tr>"));
+        assert!(html.contains("<tr><th>From</th><td>01/01/2023</td></tr>"));
+        assert!(html.contains("<tr><th>Change</th><td>8.00%</td></tr>"));
+        assert!(html.contains("<tr><th>Volume</th><td>3,700</td></tr>"));
+        assert!(html.contains("<tr><th>source</th><td>&lt;exchange&gt; &amp; co</td></tr>"));
+        
+        let bare = document(&model, None, &HashMap::new(), &Locale::default(), None);
+        assert!(!bare.contains("<table>"));
+        
+        let embedded = document(&model, None, &HashMap::new(), &Locale::default(), Some("[]"));
+        assert!(embedded.contains("<script type=\"application/json\" id=\"candle-data\">[]</script>"));
+        
+        Ok(())
+    }
+}
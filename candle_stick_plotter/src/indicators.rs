@@ -0,0 +1,972 @@
+//! Technical indicators computed over candlestick series
+//!
+//! Every indicator returns one entry per input candle so the output can be
+//! plotted on the same x-axis. Entries without enough history are `None`,
+//! and a series shorter than the required period yields only `None` entries
+//! rather than an error or a panic.
+
+use std::ops::Range;
+
+use crate::data_processor::{CandleStick, DataProcessor};
+use crate::patterns::{self, FractalKind};
+use crate::utils::stats_utils;
+
+/// Indicator values aligned with the input candles
+pub type Series = Vec<Option<f64>>;
+
+/// Computes the simple moving average of close prices
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles in the averaging window
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - Average per candle, `None` until the window is full
+pub fn sma(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; candles.len()];
+
+    if period == 0 || period > candles.len() {
+        return values;
+    }
+
+    let mut window_sum: f64 = candles[..period].iter().map(|c| c.close).sum();
+    values[period - 1] = Some(window_sum / period as f64);
+
+    for i in period..candles.len() {
+        window_sum += candles[i].close - candles[i - period].close;
+        values[i] = Some(window_sum / period as f64);
+    }
+
+    values
+}
+
+/// Computes the time-weighted average price over a sliding window
+///
+/// Unlike VWAP every candle counts the same, which keeps the average usable
+/// when volume data is missing or unreliable.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles in the averaging window
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - Mean typical price `(high + low + close) / 3` per
+///   candle, `None` until the window is full
+pub fn twap(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; candles.len()];
+
+    if period == 0 || period > candles.len() {
+        return values;
+    }
+
+    let typical: Vec<f64> = candles.iter().map(|c| (c.high + c.low + c.close) / 3.0).collect();
+    let mut window_sum: f64 = typical[..period].iter().sum();
+    values[period - 1] = Some(window_sum / period as f64);
+
+    for i in period..candles.len() {
+        window_sum += typical[i] - typical[i - period];
+        values[i] = Some(window_sum / period as f64);
+    }
+
+    values
+}
+
+/// Computes the exponential moving average of close prices
+///
+/// The average is seeded with the SMA of the first `period` closes and then
+/// smoothed with `alpha = 2 / (period + 1)`.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles in the smoothing window
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - Average per candle, `None` for the first `period - 1`
+pub fn ema(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    ema_values(&closes, period)
+}
+
+/// Computes an exponential moving average over raw values
+fn ema_values(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+
+    if period == 0 || period > values.len() {
+        return result;
+    }
+
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut current = values[..period].iter().sum::<f64>() / period as f64;
+    result[period - 1] = Some(current);
+
+    for (i, value) in values.iter().enumerate().skip(period) {
+        current += alpha * (value - current);
+        result[i] = Some(current);
+    }
+
+    result
+}
+
+/// Computes the average true range using Wilder smoothing
+///
+/// The true range of the first candle is its high-low span because it has no
+/// previous close.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles in the smoothing window
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - ATR per candle, `None` for the first `period - 1`
+pub fn atr(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; candles.len()];
+
+    if period == 0 || period > candles.len() {
+        return values;
+    }
+
+    let true_ranges = true_ranges(candles);
+    let mut current = true_ranges[..period].iter().sum::<f64>() / period as f64;
+    values[period - 1] = Some(current);
+
+    for (i, tr) in true_ranges.iter().enumerate().skip(period) {
+        current = (current * (period - 1) as f64 + tr) / period as f64;
+        values[i] = Some(current);
+    }
+
+    values
+}
+
+/// Flags candles whose range expands beyond the recent average range
+///
+/// Ranges are true ranges as in `atr`, so a gap from the previous close
+/// counts towards the range.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of prior candles the average range is taken over
+/// * `factor` - Multiple of the average range a candle's range must exceed
+///
+/// # Returns
+/// * `Vec<Option<bool>>` - Whether each candle expands the range, `None` for
+///   the first `period` candles
+pub fn range_expansion(candles: &[CandleStick], period: usize, factor: f64) -> Vec<Option<bool>> {
+    let mut values = vec![None; candles.len()];
+
+    if period == 0 || period >= candles.len() {
+        return values;
+    }
+
+    let true_ranges = true_ranges(candles);
+    let mut window_sum: f64 = true_ranges[..period].iter().sum();
+
+    for i in period..candles.len() {
+        values[i] = Some(true_ranges[i] > factor * window_sum / period as f64);
+        window_sum += true_ranges[i] - true_ranges[i - period];
+    }
+
+    values
+}
+
+/// Computes the true range of each candle
+///
+/// The true range of the first candle is its high-low span because it has no
+/// previous close.
+fn true_ranges(candles: &[CandleStick]) -> Vec<f64> {
+    candles
+        .iter()
+        .enumerate()
+        .map(|(i, c)| match i.checked_sub(1).map(|p| candles[p].close) {
+            Some(prev_close) => (c.high - c.low)
+                .max((c.high - prev_close).abs())
+                .max((c.low - prev_close).abs()),
+            None => c.high - c.low,
+        })
+        .collect()
+}
+
+/// MACD line, signal line and histogram aligned with the input candles
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macd {
+    pub macd: Vec<Option<f64>>,
+    pub signal: Vec<Option<f64>>,
+    pub histogram: Vec<Option<f64>>,
+}
+
+/// Computes the moving average convergence/divergence of close prices
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `fast` - Period of the fast EMA
+/// * `slow` - Period of the slow EMA
+/// * `signal` - Period of the EMA applied to the MACD line
+///
+/// # Returns
+/// * `Macd` - Series that are `None` until their inputs are defined
+pub fn macd(candles: &[CandleStick], fast: usize, slow: usize, signal: usize) -> Macd {
+    let fast_ema = ema(candles, fast);
+    let slow_ema = ema(candles, slow);
+
+    let macd: Vec<Option<f64>> = fast_ema
+        .iter()
+        .zip(&slow_ema)
+        .map(|(f, s)| Some((*f)? - (*s)?))
+        .collect();
+
+    let mut signal_line = vec![None; candles.len()];
+    if let Some(start) = macd.iter().position(Option::is_some) {
+        let defined: Vec<f64> = macd[start..].iter().flatten().copied().collect();
+        for (offset, value) in ema_values(&defined, signal).into_iter().enumerate() {
+            signal_line[start + offset] = value;
+        }
+    }
+
+    let histogram = macd
+        .iter()
+        .zip(&signal_line)
+        .map(|(m, s)| Some((*m)? - (*s)?))
+        .collect();
+
+    Macd { macd, signal: signal_line, histogram }
+}
+
+/// Computes the Donchian channel of high and low prices
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles in the rolling window
+///
+/// # Returns
+/// * `(upper, lower, mid)` - Rolling max high, rolling min low and their
+///   average, each `None` until the window is full
+pub fn donchian(candles: &[CandleStick], period: usize) -> (Series, Series, Series) {
+    let mut upper = vec![None; candles.len()];
+    let mut lower = vec![None; candles.len()];
+    let mut mid = vec![None; candles.len()];
+
+    if period == 0 || period > candles.len() {
+        return (upper, lower, mid);
+    }
+
+    for (offset, window) in candles.windows(period).enumerate() {
+        let high = window.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max);
+        let low = window.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+        let i = offset + period - 1;
+        upper[i] = Some(high);
+        lower[i] = Some(low);
+        mid[i] = Some((high + low) / 2.0);
+    }
+
+    (upper, lower, mid)
+}
+
+/// Fits a trend channel through the recent swing highs and swing lows
+///
+/// Swing points are the fractals found by `patterns::fractals`. Each side is
+/// a least-squares line through the fractal highs (or lows) among the last
+/// `lookback` candles, drawn from its first swing point to the last candle.
+/// A side with fewer than two swing points is left out.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `lookback` - Number of trailing candles searched for swing points
+///
+/// # Returns
+/// * `(upper, lower)` - Channel line values per candle, `None` before the
+///   first swing point and for a side without enough swing points
+pub fn trend_channel(candles: &[CandleStick], lookback: usize) -> (Series, Series) {
+    let start = candles.len().saturating_sub(lookback);
+    let swings = patterns::fractals(candles);
+
+    let side = |kind: FractalKind, price: fn(&CandleStick) -> f64| {
+        let points: Vec<(f64, f64)> = swings
+            .iter()
+            .filter(|(index, found)| *found == kind && *index >= start)
+            .map(|&(index, _)| (index as f64, price(&candles[index])))
+            .collect();
+        let mut values = vec![None; candles.len()];
+        if let (Some(&(first, _)), Some((slope, intercept))) = (points.first(), fit_line(&points)) {
+            for (i, value) in values.iter_mut().enumerate().skip(first as usize) {
+                *value = Some(intercept + slope * i as f64);
+            }
+        }
+        values
+    };
+
+    (side(FractalKind::Up, |c| c.high), side(FractalKind::Down, |c| c.low))
+}
+
+/// Least-squares line through points, `None` for fewer than two distinct x
+fn fit_line(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+    if points.len() < 2 || variance <= 0.0 {
+        return None;
+    }
+    let slope = covariance / variance;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+/// Distributes volume over price levels using each candle's typical price
+///
+/// The typical price `(high + low + close) / 3` of every candle selects one of
+/// `bins` equal-width levels between the lowest and highest typical price.
+/// When all candles share one price, a single level holds all volume.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `bins` - Number of price levels
+///
+/// # Returns
+/// * `Vec<(f64, f64)>` - `(level center price, volume)` from the lowest level
+///   up, empty for no candles or zero bins
+pub fn volume_profile(candles: &[CandleStick], bins: usize) -> Vec<(f64, f64)> {
+    if candles.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+
+    let typical: Vec<f64> = candles.iter().map(|c| (c.high + c.low + c.close) / 3.0).collect();
+    let low = typical.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = typical.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if high <= low {
+        return vec![(low, candles.iter().map(|c| c.volume).sum())];
+    }
+
+    let step = (high - low) / bins as f64;
+    let mut profile: Vec<(f64, f64)> = (0..bins).map(|i| (low + step * (i as f64 + 0.5), 0.0)).collect();
+
+    for (price, candle) in typical.iter().zip(candles) {
+        let bin = (((price - low) / step) as usize).min(bins - 1);
+        profile[bin].1 += candle.volume;
+    }
+
+    profile
+}
+
+/// Computes the midpoint of each candle body
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+///
+/// # Returns
+/// * `Vec<f64>` - `(open + close) / 2` per candle
+pub fn body_midpoint(candles: &[CandleStick]) -> Vec<f64> {
+    candles.iter().map(|c| (c.open + c.close) / 2.0).collect()
+}
+
+/// Computes the upper wick length as a fraction of each candle's range
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+///
+/// # Returns
+/// * `Vec<f64>` - `(high - max(open, close)) / (high - low)`, 0 for doji candles
+pub fn upper_wick_ratio(candles: &[CandleStick]) -> Vec<f64> {
+    candles
+        .iter()
+        .map(|c| wick_fraction(c.high - c.open.max(c.close), c))
+        .collect()
+}
+
+/// Computes the lower wick length as a fraction of each candle's range
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+///
+/// # Returns
+/// * `Vec<f64>` - `(min(open, close) - low) / (high - low)`, 0 for doji candles
+pub fn lower_wick_ratio(candles: &[CandleStick]) -> Vec<f64> {
+    candles
+        .iter()
+        .map(|c| wick_fraction(c.open.min(c.close) - c.low, c))
+        .collect()
+}
+
+/// Divides a wick length by the candle range, treating a near-zero range as 0
+fn wick_fraction(wick: f64, candle: &CandleStick) -> f64 {
+    let range = candle.high - candle.low;
+    if range <= f64::EPSILON * candle.high.abs().max(1.0) {
+        0.0
+    } else {
+        wick / range
+    }
+}
+
+/// Computes the relative strength index of close prices using Wilder smoothing
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of price changes in the smoothing window
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - RSI in the range 0–100 per candle, `None` for the
+///   first `period` candles
+pub fn rsi(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; candles.len()];
+
+    if period == 0 || candles.len() <= period {
+        return values;
+    }
+
+    let changes: Vec<f64> = candles.windows(2).map(|w| w[1].close - w[0].close).collect();
+    let mut avg_gain = changes[..period].iter().map(|c| c.max(0.0)).sum::<f64>() / period as f64;
+    let mut avg_loss = changes[..period].iter().map(|c| (-c).max(0.0)).sum::<f64>() / period as f64;
+    values[period] = Some(rsi_from_averages(avg_gain, avg_loss));
+
+    for (i, change) in changes.iter().enumerate().skip(period) {
+        avg_gain = (avg_gain * (period - 1) as f64 + change.max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period - 1) as f64 + (-change).max(0.0)) / period as f64;
+        values[i + 1] = Some(rsi_from_averages(avg_gain, avg_loss));
+    }
+
+    values
+}
+
+/// Computes the absolute momentum of close prices
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles to look back
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - `close_t - close_{t-period}` per candle, `None` for
+///   the first `period`
+pub fn momentum(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    lookback(candles, period, |close, past| Some(close - past))
+}
+
+/// Computes the rate of change of close prices in percent
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles to look back
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - `(close_t / close_{t-period} - 1) * 100` per candle,
+///   `None` for the first `period` and where the past close is zero
+pub fn rate_of_change(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    lookback(candles, period, |close, past| (past != 0.0).then(|| (close / past - 1.0) * 100.0))
+}
+
+/// Computes the percentile rank of each close within its trailing window
+///
+/// The close gets its rank `r` from 1 to `period` among the window's closes,
+/// tied closes sharing the average of their ranks, and the percentile is
+/// `(r - 1) / (period - 1) * 100`: 0 for the lowest close, 100 for the highest.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of closes in the window including the current one, at least 2
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - Percentile rank from 0 to 100 per candle, `None`
+///   for the first `period - 1` candles
+pub fn percentile_rank(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; candles.len()];
+
+    if period < 2 {
+        return values;
+    }
+    for (i, window) in candles.windows(period).enumerate() {
+        let close = window[period - 1].close;
+        let below = window.iter().filter(|candle| candle.close < close).count();
+        let equal = window.iter().filter(|candle| candle.close == close).count();
+        let rank = below as f64 + (equal + 1) as f64 / 2.0;
+        values[i + period - 1] = Some((rank - 1.0) / (period - 1) as f64 * 100.0);
+    }
+
+    values
+}
+
+/// Computes how far each close sits below the highest high, in percent
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of candles in the rolling window including the
+///   current one, or 0 for the running all-time high
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - `(high - close) / high * 100` per candle, 0 at a new
+///   high, `None` for the first `period - 1` candles and where the high is
+///   not positive
+pub fn pct_from_high(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut running = f64::MIN;
+
+    candles
+        .iter()
+        .enumerate()
+        .map(|(i, candle)| {
+            running = running.max(candle.high);
+            let high = match period {
+                0 => running,
+                _ if i + 1 < period => return None,
+                _ => candles[i + 1 - period..=i].iter().map(|c| c.high).fold(f64::MIN, f64::max),
+            };
+            (high > 0.0).then(|| ((high - candle.close) / high * 100.0).max(0.0))
+        })
+        .collect()
+}
+
+/// Computes the Ulcer Index, the root mean square drawdown of close prices
+///
+/// Within each window of `period` closes the peak is tracked from the first
+/// close on, and every close contributes its percentage drawdown
+/// `D = (close - peak) / peak * 100`. The index is `sqrt(mean(D²))`, so it
+/// stays at zero while prices make new highs and grows with both the depth
+/// and the length of declines.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `period` - Number of closes in the window
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - Ulcer Index in percent per candle, `None` for the
+///   first `period - 1` candles and where a peak is not positive
+pub fn ulcer_index(candles: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; candles.len()];
+
+    if period == 0 {
+        return values;
+    }
+    for (i, window) in candles.windows(period).enumerate() {
+        let mut peak = f64::MIN;
+        let mut squares = 0.0;
+        for candle in window {
+            peak = peak.max(candle.close);
+            if peak <= 0.0 {
+                break;
+            }
+            squares += ((candle.close - peak) / peak * 100.0).powi(2);
+        }
+        values[i + period - 1] = (peak > 0.0).then(|| (squares / period as f64).sqrt());
+    }
+
+    values
+}
+
+/// Compares every close with the close `period` candles earlier
+fn lookback(candles: &[CandleStick], period: usize, compare: impl Fn(f64, f64) -> Option<f64>) -> Vec<Option<f64>> {
+    let mut values = vec![None; candles.len()];
+
+    if period == 0 {
+        return values;
+    }
+    for i in period..candles.len() {
+        values[i] = compare(candles[i].close, candles[i - period].close);
+    }
+
+    values
+}
+
+/// Computes the rolling regression beta of a symbol's returns on a benchmark's
+///
+/// Benchmark closes are matched to the symbol's candles by timestamp and both
+/// series are turned into log returns. A window only counts when both returns
+/// exist for all of its candles.
+///
+/// # Arguments
+/// * `symbol` - Candles of the analyzed series
+/// * `benchmark` - Candles of the benchmark, e.g. an index
+/// * `period` - Number of returns in the rolling window, at least 2
+///
+/// # Returns
+/// * `Vec<Option<f64>>` - `cov(symbol, benchmark) / var(benchmark)` per
+///   candle of `symbol`, `None` until a full window of overlapping returns
+///   exists and where the benchmark did not move
+pub fn rolling_beta(symbol: &[CandleStick], benchmark: &[CandleStick], period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; symbol.len()];
+    if period < 2 {
+        return values;
+    }
+
+    let closes: Vec<f64> = symbol.iter().map(|c| c.close).collect();
+    let aligned: Vec<f64> = DataProcessor::align_closes(symbol, benchmark)
+        .into_iter()
+        .map(|close| close.unwrap_or(f64::NAN))
+        .collect();
+    let pairs: Vec<Option<(f64, f64)>> = stats_utils::log_returns(&closes)
+        .into_iter()
+        .zip(stats_utils::log_returns(&aligned))
+        .map(|(s, b)| s.zip(b))
+        .collect();
+
+    for (offset, window) in pairs.windows(period).enumerate() {
+        let Some(window) = window.iter().copied().collect::<Option<Vec<(f64, f64)>>>() else {
+            continue;
+        };
+        let n = period as f64;
+        let mean_s = window.iter().map(|(s, _)| s).sum::<f64>() / n;
+        let mean_b = window.iter().map(|(_, b)| b).sum::<f64>() / n;
+        let covariance: f64 = window.iter().map(|(s, b)| (s - mean_s) * (b - mean_b)).sum();
+        let variance: f64 = window.iter().map(|(_, b)| (b - mean_b).powi(2)).sum();
+        if variance > 0.0 {
+            values[offset + period - 1] = Some(covariance / variance);
+        }
+    }
+
+    values
+}
+
+/// Classic floor pivot levels derived from one period's high, low and close
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pivots {
+    pub pivot: f64,
+    pub r1: f64,
+    pub s1: f64,
+    pub r2: f64,
+    pub s2: f64,
+}
+
+impl Pivots {
+    /// Names of the values returned by `levels`
+    pub const NAMES: [&'static str; 5] = ["Pivot", "R1", "S1", "R2", "S2"];
+
+    /// Levels from the pivot outwards, ordered P, R1, S1, R2, S2
+    pub fn levels(&self) -> [f64; 5] {
+        [self.pivot, self.r1, self.s1, self.r2, self.s2]
+    }
+}
+
+/// Computes floor pivots from a high, low and close
+///
+/// # Arguments
+/// * `high` - Highest price of the period
+/// * `low` - Lowest price of the period
+/// * `close` - Last price of the period
+///
+/// # Returns
+/// * `Pivots` - `P = (H + L + C) / 3`, `R1 = 2P - L`, `S1 = 2P - H`,
+///   `R2 = P + (H - L)` and `S2 = P - (H - L)`
+pub fn pivots(high: f64, low: f64, close: f64) -> Pivots {
+    let pivot = (high + low + close) / 3.0;
+    let range = high - low;
+
+    Pivots {
+        pivot,
+        r1: 2.0 * pivot - low,
+        s1: 2.0 * pivot - high,
+        r2: pivot + range,
+        s2: pivot - range,
+    }
+}
+
+/// Computes the pivots of every session from the previous session's HLC
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+/// * `sessions` - Consecutive index ranges covering the candles, e.g. from
+///   `DataProcessor::sessions`
+///
+/// # Returns
+/// * `Vec<Option<Pivots>>` - Pivots per candle, `None` in the first session
+///   because it has no prior session
+pub fn session_pivots(candles: &[CandleStick], sessions: &[Range<usize>]) -> Vec<Option<Pivots>> {
+    let mut values = vec![None; candles.len()];
+
+    for pair in sessions.windows(2) {
+        let (previous, current) = (&candles[pair[0].clone()], pair[1].clone());
+        let (Some(first), Some(last)) = (previous.first(), previous.last()) else {
+            continue;
+        };
+        let high = previous.iter().map(|c| c.high).fold(first.high, f64::max);
+        let low = previous.iter().map(|c| c.low).fold(first.low, f64::min);
+        let levels = pivots(high, low, last.close);
+        values[current].fill(Some(levels));
+    }
+
+    values
+}
+
+/// Converts average gain and loss into an RSI value
+fn rsi_from_averages(avg_gain: f64, avg_loss: f64) -> f64 {
+    if avg_loss == 0.0 {
+        if avg_gain == 0.0 { 50.0 } else { 100.0 }
+    } else {
+        100.0 - 100.0 / (1.0 + avg_gain / avg_loss)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+
+    /// Builds candles with the given close prices
+    fn candles_with_closes(closes: &[f64]) -> Vec<CandleStick> {
+        closes
+            .iter()
+            .map(|&close| CandleStick {
+                open: close,
+                high: close,
+                low: close,
+                close,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Test SMA against a known sequence
+    #[test]
+    fn test_sma() {
+        let candles = candles_with_closes(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        assert_eq!(sma(&candles, 3), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+        assert_eq!(sma(&candles, 6), vec![None; 5]);
+        assert_eq!(sma(&candles, 0), vec![None; 5]);
+    }
+
+    /// Test TWAP of typical prices against a hand-computed series
+    #[test]
+    fn test_twap() {
+        let candles: Vec<CandleStick> = [(12.0, 6.0, 9.0), (15.0, 9.0, 12.0), (18.0, 12.0, 18.0), (9.0, 6.0, 6.0)]
+            .iter()
+            .map(|&(high, low, close)| CandleStick { open: close, high, low, close, ..Default::default() })
+            .collect();
+
+        // Typical prices 9, 12, 16 and 7
+        assert_eq!(twap(&candles, 2), vec![None, Some(10.5), Some(14.0), Some(11.5)]);
+        assert_eq!(twap(&candles, 4), vec![None, None, None, Some(11.0)]);
+        assert_eq!(twap(&candles, 5), vec![None; 4]);
+        assert_eq!(twap(&candles, 0), vec![None; 4]);
+    }
+
+    /// Test flagging ranges above a multiple of the prior average, gaps included
+    #[test]
+    fn test_range_expansion() {
+        let candles: Vec<CandleStick> = [(11.0, 9.0, 10.0), (11.0, 9.0, 10.0), (14.0, 9.0, 13.0), (13.5, 12.5, 13.0), (20.0, 19.0, 19.5)]
+            .iter()
+            .map(|&(high, low, close)| CandleStick { open: close, high, low, close, ..Default::default() })
+            .collect();
+
+        // True ranges 2, 2, 5, 1 and 7 with the gap from 13 to 19
+        assert_eq!(range_expansion(&candles, 2, 2.0), vec![None, None, Some(true), Some(false), Some(true)]);
+        assert_eq!(range_expansion(&candles, 2, 3.0)[2], Some(false));
+        assert_eq!(range_expansion(&candles, 5, 2.0), vec![None; 5]);
+        assert_eq!(range_expansion(&candles, 0, 2.0), vec![None; 5]);
+    }
+
+    /// Test EMA seeding and smoothing
+    #[test]
+    fn test_ema() {
+        let candles = candles_with_closes(&[1.0, 2.0, 3.0, 4.0]);
+        let values = ema(&candles, 3);
+
+        assert_eq!(values[..2], [None, None]);
+        assert_eq!(values[2], Some(2.0));
+        assert_eq!(values[3], Some(3.0));
+    }
+
+    /// Test ATR including gaps against the previous close
+    #[test]
+    fn test_atr() {
+        let mut candles = candles_with_closes(&[10.0, 10.0, 10.0]);
+        candles[0].high = 11.0;
+        candles[0].low = 9.0;
+        candles[1].high = 14.0;
+        candles[1].low = 12.0;
+        candles[1].close = 13.0;
+        candles[2].high = 13.5;
+        candles[2].low = 12.5;
+
+        assert_eq!(atr(&candles, 2), vec![None, Some(3.0), Some(2.0)]);
+    }
+
+    /// Test that MACD is defined only after the slow and signal windows fill
+    #[test]
+    fn test_macd() {
+        let candles = candles_with_closes(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let result = macd(&candles, 2, 3, 2);
+
+        assert_eq!(result.macd.iter().position(Option::is_some), Some(2));
+        assert_eq!(result.signal.iter().position(Option::is_some), Some(3));
+        assert_eq!(result.histogram.iter().position(Option::is_some), Some(3));
+        assert!(result.macd[5].unwrap() > 0.0);
+    }
+
+    /// Test the Donchian channel over a rolling window
+    #[test]
+    fn test_donchian() {
+        let mut candles = candles_with_closes(&[10.0, 12.0, 11.0, 9.0]);
+        candles[1].high = 15.0;
+        candles[3].low = 5.0;
+
+        let (upper, lower, mid) = donchian(&candles, 2);
+        assert_eq!(upper, vec![None, Some(15.0), Some(15.0), Some(11.0)]);
+        assert_eq!(lower, vec![None, Some(10.0), Some(11.0), Some(5.0)]);
+        assert_eq!(mid, vec![None, Some(12.5), Some(13.0), Some(8.0)]);
+    }
+
+    /// Test channel lines through swing highs and lows, skipping sparse sides
+    #[test]
+    fn test_trend_channel() {
+        let candles = candles_with_closes(&[1.0, 2.0, 5.0, 2.0, 1.0, 2.0, 6.0, 2.0, 1.0, 2.0, 7.0, 2.0, 1.0]);
+
+        let (upper, lower) = trend_channel(&candles, candles.len());
+        assert_eq!(upper[..2], [None, None]);
+        assert_eq!(upper[2], Some(5.0));
+        assert_eq!(upper[12], Some(7.5));
+        assert_eq!(lower[..4], [None; 4]);
+        assert!(lower[4..].iter().all(|value| *value == Some(1.0)));
+
+        let (upper, lower) = trend_channel(&candles, 5);
+        assert_eq!(upper, vec![None; candles.len()]);
+        assert_eq!(lower, vec![None; candles.len()]);
+    }
+
+    /// Test absolute momentum and rate of change
+    #[test]
+    fn test_momentum_and_rate_of_change() {
+        let candles = candles_with_closes(&[10.0, 15.0, 30.0, 0.0, 15.0]);
+
+        assert_eq!(momentum(&candles, 2), vec![None, None, Some(20.0), Some(-15.0), Some(-15.0)]);
+        assert_eq!(rate_of_change(&candles, 1), vec![None, Some(50.0), Some(100.0), Some(-100.0), None]);
+        assert_eq!(momentum(&candles, 5), vec![None; 5]);
+        assert_eq!(rate_of_change(&candles, 0), vec![None; 5]);
+    }
+
+    /// Test percentile ranks including tied closes
+    #[test]
+    fn test_percentile_rank() {
+        let candles = candles_with_closes(&[1.0, 2.0, 3.0, 2.0, 1.0]);
+
+        assert_eq!(percentile_rank(&candles, 3), vec![None, None, Some(100.0), Some(25.0), Some(0.0)]);
+        assert_eq!(percentile_rank(&candles_with_closes(&[5.0, 5.0, 5.0]), 3)[2], Some(50.0));
+        assert_eq!(percentile_rank(&candles, 1), vec![None; 5]);
+        assert_eq!(percentile_rank(&candles, 6), vec![None; 5]);
+    }
+
+    /// Test the distance below a rolling and the all-time high
+    #[test]
+    fn test_pct_from_high() {
+        let candles: Vec<CandleStick> = [(100.0, 90.0), (50.0, 40.0), (60.0, 60.0), (80.0, 90.0)]
+            .iter()
+            .map(|&(high, close)| CandleStick { high, close, ..Default::default() })
+            .collect();
+
+        assert_eq!(pct_from_high(&candles, 2), vec![None, Some(60.0), Some(0.0), Some(0.0)]);
+        assert_eq!(pct_from_high(&candles, 0), vec![Some(10.0), Some(60.0), Some(40.0), Some(10.0)]);
+        assert_eq!(pct_from_high(&candles, 5), vec![None; 4]);
+    }
+
+    /// Test the Ulcer Index of a steadily declining series and a rising one
+    #[test]
+    fn test_ulcer_index() {
+        let declining = ulcer_index(&candles_with_closes(&[100.0, 90.0, 80.0, 70.0]), 3);
+        assert_eq!(declining[..2], [None, None]);
+        // Drawdowns of 0, -10 and -20 percent from the peak of 100
+        assert!(declining[2].is_some_and(|value| (value - (500.0f64 / 3.0).sqrt()).abs() < 1e-9));
+        // Drawdowns of 0, -1/9 and -2/9 from the peak of 90
+        let expected = ((100.0f64 / 81.0 + 400.0 / 81.0) * 100.0 / 3.0).sqrt();
+        assert!(declining[3].is_some_and(|value| (value - expected).abs() < 1e-9));
+
+        assert_eq!(ulcer_index(&candles_with_closes(&[1.0, 2.0, 3.0]), 2), vec![None, Some(0.0), Some(0.0)]);
+        assert_eq!(ulcer_index(&candles_with_closes(&[0.0, 0.0]), 2), vec![None, None]);
+        assert_eq!(ulcer_index(&candles_with_closes(&[1.0]), 2), vec![None]);
+    }
+
+    /// Test beta of a scaled copy, a window broken by a missing benchmark candle and a flat benchmark
+    #[test]
+    fn test_rolling_beta() {
+        let dated = |closes: &[f64]| -> Vec<CandleStick> {
+            candles_with_closes(closes)
+                .into_iter()
+                .enumerate()
+                .map(|(day, candle)| CandleStick { timestamp: DateTime::UNIX_EPOCH + Duration::days(day as i64), ..candle })
+                .collect()
+        };
+        let benchmark = dated(&[100.0, 110.0, 99.0, 108.9, 119.79]);
+        // Squaring the benchmark doubles every log return
+        let symbol = dated(&benchmark.iter().map(|c| c.close * c.close).collect::<Vec<_>>());
+
+        let beta = rolling_beta(&symbol, &benchmark, 3);
+        assert_eq!(beta[..3], [None, None, None]);
+        assert!(beta[3..].iter().all(|value| value.is_some_and(|b| (b - 2.0).abs() < 1e-9)));
+
+        let gapped: Vec<CandleStick> = benchmark.iter().filter(|c| c.close != 99.0).cloned().collect();
+        assert_eq!(rolling_beta(&symbol, &gapped, 2), vec![None; 5]);
+        assert_eq!(rolling_beta(&symbol, &dated(&[5.0; 5]), 2), vec![None; 5]);
+        assert_eq!(rolling_beta(&symbol, &benchmark, 1), vec![None; 5]);
+    }
+
+    /// Test floor pivots and their assignment from the previous session
+    #[test]
+    fn test_session_pivots() {
+        let levels = pivots(110.0, 90.0, 100.0);
+        assert_eq!(levels, Pivots { pivot: 100.0, r1: 110.0, s1: 90.0, r2: 120.0, s2: 80.0 });
+
+        let mut candles = candles_with_closes(&[100.0, 104.0, 101.0, 99.0]);
+        candles[0].high = 106.0;
+        candles[1].low = 95.0;
+        let values = session_pivots(&candles, &[0..2, 2..3, 3..4]);
+
+        assert_eq!(values[..2], [None, None]);
+        assert_eq!(values[2], Some(pivots(106.0, 95.0, 104.0)));
+        assert_eq!(values[3], Some(pivots(101.0, 101.0, 101.0)));
+    }
+
+    /// Test volume bucketing by typical price, including a single price level
+    #[test]
+    fn test_volume_profile() {
+        let mut candles = candles_with_closes(&[10.0, 11.0, 19.0, 20.0]);
+        for (candle, volume) in candles.iter_mut().zip([100.0, 200.0, 300.0, 400.0]) {
+            candle.volume = volume;
+        }
+
+        assert_eq!(volume_profile(&candles, 2), vec![(12.5, 300.0), (17.5, 700.0)]);
+        assert_eq!(volume_profile(&candles[..1], 5), vec![(10.0, 100.0)]);
+        assert!(volume_profile(&candles, 0).is_empty());
+        assert!(volume_profile(&[], 3).is_empty());
+    }
+
+    /// Test wick ratios and body midpoints including a doji
+    #[test]
+    fn test_wick_ratios() {
+        let candles = vec![
+            CandleStick { open: 10.0, high: 14.0, low: 8.0, close: 12.0, ..Default::default() },
+            CandleStick { open: 10.0, high: 10.0, low: 10.0, close: 10.0, ..Default::default() },
+        ];
+
+        assert_eq!(upper_wick_ratio(&candles), vec![2.0 / 6.0, 0.0]);
+        assert_eq!(lower_wick_ratio(&candles), vec![2.0 / 6.0, 0.0]);
+        assert_eq!(body_midpoint(&candles), vec![11.0, 10.0]);
+    }
+
+    /// Test every indicator against empty and single-candle input
+    #[test]
+    fn test_indicators_on_tiny_inputs() {
+        for len in [0, 1] {
+            let candles = candles_with_closes(&vec![100.0; len]);
+            let all_none = |values: &[Option<f64>]| values.len() == len && values.iter().all(Option::is_none);
+
+            assert!(all_none(&sma(&candles, 2)));
+            assert!(all_none(&ema(&candles, 2)));
+            assert!(all_none(&rsi(&candles, 14)));
+            assert!(all_none(&rsi(&candles, 1)));
+            assert!(all_none(&atr(&candles, 2)));
+            assert!(all_none(&donchian(&candles, 2).0));
+
+            let result = macd(&candles, 12, 26, 9);
+            assert!(all_none(&result.macd));
+            assert!(all_none(&result.signal));
+            assert!(all_none(&result.histogram));
+        }
+    }
+
+    /// Test RSI for strictly rising, strictly falling and mixed series
+    #[test]
+    fn test_rsi() {
+        let rising = candles_with_closes(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(rsi(&rising, 2), vec![None, None, Some(100.0), Some(100.0)]);
+
+        let falling = candles_with_closes(&[4.0, 3.0, 2.0]);
+        assert_eq!(rsi(&falling, 2), vec![None, None, Some(0.0)]);
+
+        let mixed = candles_with_closes(&[1.0, 2.0, 1.0]);
+        assert_eq!(rsi(&mixed, 2), vec![None, None, Some(50.0)]);
+    }
+}
@@ -0,0 +1,418 @@
+//! Placement of texts and panels on the chart canvas
+//!
+//! Text is laid out in pixels before any renderer sees it: lines are wrapped
+//! to the canvas width using an estimated glyph width, and the space they
+//! take is reserved as margins above and below the plot area. Below the top
+//! margin the price panel takes the free height, followed by the lower
+//! indicator panel and the volume panel when the model has them.
+
+use crate::render_model::{AxisLabels, AxisRange, CanvasText, Margins, RenderModel, TextAlign, TextRole};
+
+/// Font size of the title in pixels
+const TITLE_SIZE: f32 = 20.0;
+
+/// Font size of the subtitle in pixels
+const SUBTITLE_SIZE: f32 = 14.0;
+
+/// Font size of the caption in pixels
+const CAPTION_SIZE: f32 = 11.0;
+
+/// Average glyph width as a fraction of the font size
+const GLYPH_WIDTH: f64 = 0.55;
+
+/// Line height as a multiple of the font size
+pub const LINE_HEIGHT: f64 = 1.25;
+
+/// Gap between the canvas edges, the texts and the plot area in pixels
+const TEXT_PADDING: f64 = 8.0;
+
+/// Lines a text may wrap to before the rest is truncated
+const MAX_TEXT_LINES: usize = 2;
+
+/// Gap between the canvas edges and the panels in pixels
+pub const PANEL_PADDING: f32 = 8.0;
+
+/// Height of the lower indicator and volume panels as a fraction of the plot height
+const SUB_PANEL_FRACTION: f32 = 0.2;
+
+/// Width kept free right of the panels for a colorbar and its labels in pixels
+pub const COLORBAR_MARGIN: f64 = 72.0;
+
+/// Width of the colorbar itself in pixels
+const COLORBAR_WIDTH: f32 = 12.0;
+
+/// Font size of x-axis tick labels and the price axis title in pixels
+pub const AXIS_LABEL_SIZE: f32 = 11.0;
+
+/// Half size of pattern markers in pixels
+pub const MARKER_SIZE: f32 = 5.0;
+
+/// Font size of event labels in pixels
+pub const EVENT_FONT_SIZE: f32 = 11.0;
+
+/// Smallest distance between neighbouring x-axis ticks in pixels
+const MIN_TICK_SPACING: f64 = 64.0;
+
+/// Rotations tried in order when x-axis labels are rotated automatically
+const AUTO_ROTATIONS: [f32; 3] = [0.0, -45.0, -90.0];
+
+/// Share of the canvas width a watermark spans before rotation
+const WATERMARK_SPAN: f64 = 0.6;
+
+/// Pixel area of a panel together with the data ranges mapped onto it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub left: f32,
+    pub top: f32,
+    pub width: f32,
+    pub height: f32,
+    pub x_range: AxisRange,
+    pub y_range: AxisRange,
+}
+
+impl Viewport {
+    /// Converts data coordinates to pixel coordinates
+    ///
+    /// # Arguments
+    /// * `x` - Candle index
+    /// * `y` - Value on the panel's axis
+    ///
+    /// # Returns
+    /// * `(f32, f32)` - Pixel position from the top left canvas corner
+    pub fn map(&self, x: f64, y: f64) -> (f32, f32) {
+        let fraction = |value: f64, range: &AxisRange| match range.max - range.min {
+            span if span > 0.0 => ((value - range.min) / span) as f32,
+            _ => 0.5,
+        };
+        (
+            self.left + fraction(x, &self.x_range) * self.width,
+            self.top + (1.0 - fraction(y, &self.y_range)) * self.height,
+        )
+    }
+}
+
+/// Pixel areas of the panels of a chart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Panels {
+    pub price: Viewport,
+    /// Lower indicator panel, present when the model has a lower y-range
+    pub lower: Option<Viewport>,
+    /// Volume panel, present when the model has a volume y-range
+    pub volume: Option<Viewport>,
+}
+
+/// Lays out the panels of a chart on its `RenderModel::size` canvas
+///
+/// # Arguments
+/// * `model` - Chart whose ranges and margins determine the panels
+///
+/// # Returns
+/// * `Panels` - Price panel and the sub-panels stacked below it
+pub fn panels(model: &RenderModel) -> Panels {
+    let (width, height) = (model.size.0 as f32, model.size.1 as f32);
+    let plot_top = model.margins.top as f32 + PANEL_PADDING;
+    let plot_height = (height - model.margins.bottom as f32 - PANEL_PADDING - plot_top).max(1.0);
+    let sub_height = plot_height * SUB_PANEL_FRACTION;
+    let sub_panels = [model.lower_y_range, model.volume_y_range].iter().flatten().count() as f32;
+    
+    let viewport = |top: f32, height: f32, y_range: AxisRange| Viewport {
+        left: PANEL_PADDING,
+        top,
+        width: (width - 2.0 * PANEL_PADDING - model.margins.right as f32).max(1.0),
+        height,
+        x_range: model.x_range,
+        y_range,
+    };
+    let price = viewport(plot_top, plot_height - sub_height * sub_panels, model.y_range);
+    let mut next_top = price.top + price.height;
+    let lower = model.lower_y_range.map(|range| {
+        let lower = viewport(next_top, sub_height, range);
+        next_top += sub_height;
+        lower
+    });
+    let volume = model.volume_y_range.map(|range| viewport(next_top, sub_height, range));
+    
+    Panels { price, lower, volume }
+}
+
+/// Chooses the font size of a watermark spanning a fixed share of the canvas width
+///
+/// # Arguments
+/// * `width` - Canvas width in pixels
+/// * `text` - Watermark text
+///
+/// # Returns
+/// * `f32` - Font size in pixels, between the caption and eight times the title size
+pub fn watermark_font_size(width: f32, text: &str) -> f32 {
+    let chars = text.chars().count().max(1) as f64;
+    let size = width as f64 * WATERMARK_SPAN / (chars * GLYPH_WIDTH);
+    (size as f32).clamp(CAPTION_SIZE, 8.0 * TITLE_SIZE)
+}
+
+/// Picks the x-axis ticks of a chart and the rotation of their labels
+///
+/// Ticks are spaced at least `MIN_TICK_SPACING` pixels apart. Without a
+/// fixed rotation, the first of 0°, -45° and -90° at which neighbouring
+/// labels no longer collide is used, judged by the widest label measured
+/// with the estimated glyph width.
+///
+/// # Arguments
+/// * `model` - Chart whose x-range and panel width the ticks are spread over
+/// * `candidates` - Candle index and label text of every visible candle, in order
+/// * `rotation` - Fixed clockwise rotation in degrees, `None` to choose one
+///
+/// # Returns
+/// * `AxisLabels` - Labeled ticks with their rotation and the height they need
+pub fn x_axis_labels(model: &RenderModel, candidates: &[(f64, String)], rotation: Option<f32>) -> AxisLabels {
+    let span = (model.x_range.max - model.x_range.min).max(1.0);
+    let candle_width = panels(model).price.width as f64 / span;
+    let stride = (MIN_TICK_SPACING / candle_width).ceil().max(1.0) as usize;
+    let ticks: Vec<(f64, String)> = candidates.iter().step_by(stride).cloned().collect();
+    
+    let spacing = stride as f64 * candle_width;
+    let widest = ticks.iter().map(|(_, text)| text.chars().count()).max().unwrap_or(0) as f64
+        * AXIS_LABEL_SIZE as f64
+        * GLYPH_WIDTH;
+    let line = AXIS_LABEL_SIZE as f64 * LINE_HEIGHT;
+    // Parallel labels are apart if they clear each other along the axis or across their baselines
+    let fits = |degrees: f32| {
+        let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+        ticks.len() < 2 || widest * cos.abs() + line * sin.abs() + TEXT_PADDING <= spacing || spacing * sin.abs() >= line
+    };
+    let rotation = rotation.unwrap_or_else(|| AUTO_ROTATIONS.into_iter().find(|&degrees| fits(degrees)).unwrap_or(-90.0));
+    
+    let (sin, cos) = (rotation as f64).to_radians().sin_cos();
+    AxisLabels {
+        ticks,
+        rotation,
+        font_size: AXIS_LABEL_SIZE,
+        height: widest * sin.abs() + line * cos.abs(),
+    }
+}
+
+/// Vertical position of the x-axis labels, just below the lowest panel
+///
+/// # Arguments
+/// * `model` - Chart whose bottom margin includes the labels
+///
+/// # Returns
+/// * `f32` - Top of horizontal labels or anchor of rotated ones in pixels
+pub fn x_axis_top(model: &RenderModel) -> f32 {
+    let panels = panels(model);
+    let lowest = panels.volume.or(panels.lower).unwrap_or(panels.price);
+    lowest.top + lowest.height + PANEL_PADDING
+}
+
+/// Extra bottom margin in pixels for x-axis labels of the given height
+pub fn x_axis_margin(labels: &AxisLabels) -> f64 {
+    labels.height + TEXT_PADDING
+}
+
+/// Lays out the colorbar of a chart right of its price panel
+///
+/// # Arguments
+/// * `model` - Chart with a colorbar margin
+///
+/// # Returns
+/// * `Option<(f32, f32, f32, f32)>` - Left, top, width and height of the bar
+///   in pixels, `None` without a colorbar; labels go right of the bar
+pub fn colorbar_area(model: &RenderModel) -> Option<(f32, f32, f32, f32)> {
+    model.colorbar.as_ref()?;
+    let price = panels(model).price;
+    
+    Some((price.left + price.width + PANEL_PADDING, price.top, COLORBAR_WIDTH, price.height))
+}
+
+/// Makes room for pattern markers and event labels that would be cut off
+///
+/// Markers closer than `padding` to the top or bottom of the price panel
+/// widen its y-range until they fit. Event labels and markers running past
+/// the right canvas edge grow the canvas and its right margin by the
+/// overflow, so the panels keep their size and centered texts stay centered.
+/// Horizontal lines such as the last price span the panel and never overflow.
+///
+/// # Arguments
+/// * `model` - Laid out chart, changed in place
+/// * `padding` - Free pixels kept between annotations and the edges
+pub fn fit_annotations(model: &mut RenderModel, padding: f64) {
+    let price = panels(model).price;
+    let need = (MARKER_SIZE as f64 + padding) / price.height as f64;
+    if need < 0.5 {
+        let (min, max) = (model.y_range.min, model.y_range.max);
+        for marker in &model.markers {
+            // Solves (max' - y) / (max' - min) = need for the new maximum, and likewise below
+            model.y_range.max = model.y_range.max.max((marker.y - need * min) / (1.0 - need));
+            model.y_range.min = model.y_range.min.min((marker.y - need * max) / (1.0 - need));
+        }
+    }
+    
+    let price = panels(model).price;
+    let markers = model.markers.iter().map(|marker| price.map(marker.x, marker.y).0 as f64 + MARKER_SIZE as f64);
+    let labels = model.events.iter().map(|event| {
+        let chars = event.label.chars().count() as f64;
+        price.map(event.x, price.y_range.max).0 as f64 + 2.0 + chars * EVENT_FONT_SIZE as f64 * GLYPH_WIDTH
+    });
+    let right = markers.chain(labels).fold(f64::MIN, f64::max) + padding;
+    let overflow = (right - model.size.0 as f64).ceil();
+    if overflow > 0.0 {
+        model.size.0 += overflow as u32;
+        model.margins.right += overflow;
+        for text in model.texts.iter_mut().filter(|text| text.align == TextAlign::Center) {
+            text.x += overflow / 2.0;
+        }
+    }
+}
+
+/// Lays out the canvas texts of a chart
+///
+/// The title is centered at the top in bold, the subtitle centered below it
+/// and the caption at the bottom left.
+///
+/// # Arguments
+/// * `size` - Canvas width and height in pixels
+/// * `title` - Title text
+/// * `subtitle` - Subtitle text
+/// * `caption` - Caption text
+///
+/// # Returns
+/// * `(Vec<CanvasText>, Margins)` - Positioned texts and the space they need
+pub fn layout_texts(
+    size: (u32, u32),
+    title: Option<&str>,
+    subtitle: Option<&str>,
+    caption: Option<&str>,
+) -> (Vec<CanvasText>, Margins) {
+    let (width, height) = (size.0 as f64, size.1 as f64);
+    let text_width = width - 2.0 * TEXT_PADDING;
+    let mut texts = Vec::new();
+    let mut margins = Margins::default();
+    
+    let mut y = TEXT_PADDING;
+    for (role, text, font_size, bold) in [
+        (TextRole::Title, title, TITLE_SIZE, true),
+        (TextRole::Subtitle, subtitle, SUBTITLE_SIZE, false),
+    ] {
+        let Some(text) = text else { continue };
+        let lines = wrap(text, text_width, font_size, MAX_TEXT_LINES);
+        let block = block_height(&lines, font_size);
+        texts.push(CanvasText { role, lines, x: width / 2.0, y, font_size, bold, align: TextAlign::Center });
+        y += block;
+    }
+    if !texts.is_empty() {
+        margins.top = y + TEXT_PADDING;
+    }
+    
+    if let Some(caption) = caption {
+        let lines = wrap(caption, text_width, CAPTION_SIZE, MAX_TEXT_LINES);
+        let block = block_height(&lines, CAPTION_SIZE);
+        texts.push(CanvasText {
+            role: TextRole::Caption,
+            lines,
+            x: TEXT_PADDING,
+            y: height - TEXT_PADDING - block,
+            font_size: CAPTION_SIZE,
+            bold: false,
+            align: TextAlign::Left,
+        });
+        margins.bottom = block + 2.0 * TEXT_PADDING;
+    }
+    
+    (texts, margins)
+}
+
+/// Wraps text at word boundaries to an estimated pixel width
+///
+/// Words longer than a line are broken; lines beyond `max_lines` are dropped
+/// and the last kept line ends in an ellipsis.
+///
+/// # Arguments
+/// * `text` - Text to wrap
+/// * `width` - Available width in pixels
+/// * `font_size` - Font size in pixels
+/// * `max_lines` - Largest number of lines to return, at least 1
+///
+/// # Returns
+/// * `Vec<String>` - Wrapped lines, empty for blank text
+pub fn wrap(text: &str, width: f64, font_size: f32, max_lines: usize) -> Vec<String> {
+    let max_chars = ((width / (font_size as f64 * GLYPH_WIDTH)) as usize).max(1);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let used = line.chars().count();
+        
+        if used > 0 && used + 1 + word.len() <= max_chars {
+            line.push(' ');
+            line.extend(word);
+            continue;
+        }
+        if used > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.len() > max_chars {
+            lines.push(word.drain(..max_chars).collect());
+        }
+        line = word.into_iter().collect();
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    
+    let max_lines = max_lines.max(1);
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = &mut lines[max_lines - 1];
+        let kept: String = last.chars().take(max_chars.saturating_sub(1)).collect();
+        *last = format!("{}…", kept.trim_end());
+    }
+    
+    lines
+}
+
+/// Height in pixels of a block of lines
+fn block_height(lines: &[String], font_size: f32) -> f64 {
+    lines.len() as f64 * font_size as f64 * LINE_HEIGHT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Test wrapping at words, breaking long words and truncating extra lines
+    #[test]
+    fn test_wrap() {
+        // 10 characters per line at font size 10
+        assert_eq!(wrap("daily close prices", 55.0, 10.0, 3), vec!["daily", "close", "prices"]);
+        assert_eq!(wrap("a b c", 55.0, 10.0, 3), vec!["a b c"]);
+        assert_eq!(wrap("abcdefghijklmn", 55.0, 10.0, 3), vec!["abcdefghij", "klmn"]);
+        assert_eq!(wrap("one two three four five six", 55.0, 10.0, 2), vec!["one two", "three fou…"]);
+        assert!(wrap("   ", 55.0, 10.0, 2).is_empty());
+    }
+    
+    /// Test text positions and the margins they reserve
+    #[test]
+    fn test_layout_texts() {
+        let (texts, margins) = layout_texts((800, 600), Some("BTC/USD"), Some("Daily"), Some("Source: exchange"));
+        
+        assert_eq!(texts.len(), 3);
+        assert_eq!((texts[0].role, texts[0].x, texts[0].y, texts[0].bold), (TextRole::Title, 400.0, 8.0, true));
+        assert_eq!(texts[1].y, 8.0 + 25.0);
+        assert_eq!(margins.top, 8.0 + 25.0 + 17.5 + 8.0);
+        assert_eq!((texts[2].align, texts[2].x), (TextAlign::Left, 8.0));
+        assert!((texts[2].y + 13.75 - (600.0 - 8.0)).abs() < 1e-9);
+        assert!((margins.bottom - (13.75 + 16.0)).abs() < 1e-9);
+        
+        let (texts, margins) = layout_texts((800, 600), None, None, None);
+        assert!(texts.is_empty());
+        assert_eq!(margins, Margins::default());
+    }
+    
+    /// Test sizing watermarks to the canvas width within the font size limits
+    #[test]
+    fn test_watermark_font_size() {
+        assert!((watermark_font_size(550.0, "DRAFT") - 120.0).abs() < 1e-4);
+        assert_eq!(watermark_font_size(10.0, "DRAFT"), CAPTION_SIZE);
+        assert_eq!(watermark_font_size(10_000.0, "X"), 8.0 * TITLE_SIZE);
+    }
+}
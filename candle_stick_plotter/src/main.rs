@@ -3,23 +3,68 @@
 //! This program processes CSV data, converts input strings to uppercase,
 //! and displays candlestick plots using egui/eframe.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
 use std::io;
-use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
 use anyhow::{Result, Context};
+use chrono::{DateTime, FixedOffset, Utc};
 use thiserror::Error;
 
+mod animation;
+mod config;
 mod data_processor;
+mod downsample;
+mod html;
+mod indicators;
+mod layout;
+mod patterns;
 mod plotter;
+mod raster;
+mod render_model;
+mod repl;
+#[cfg(feature = "stream")]
+mod stream;
+mod svg;
 mod utils;
+mod validation;
+mod window;
 
-use data_processor::DataProcessor;
-use plotter::Plotter;
-use utils::file_utils;
+use data_processor::{
+    AggSpec, CandleStick, CsvFormat, CsvProfile, DataProcessor, Delimiter, HistoricalData, LabelTime, QualityReport, SeasonBy,
+    VolumeSource,
+};
+use downsample::ThinStrategy;
+use eframe::egui::Color32;
+use plotter::{
+    Aspect, Backend, ChartType, LabelRotation, MovingAverage, Overlay, PlotConfig, Plotter, PriceGrid, TimeframeAverage, VolumeHighlight,
+    DEFAULT_SERIES,
+};
+use patterns::{FractalKind, SignalSide};
+use raster::PngOptions;
+use render_model::{Watermark, WatermarkPosition};
+use utils::{color_utils, date_utils, file_utils, memory_utils, stats_utils};
+use utils::date_utils::{BusinessCalendar, Session, SessionTable, TimestampFormat};
+use utils::color_utils::Palette;
+use utils::locale_utils::Locale;
+
+/// Exit code table shown at the end of `--help`
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  Other failure
+  2  Invalid command line usage
+  3  CSV error
+  4  Data processing error
+  5  Plotting error
+  6  I/O error, including CSV files that cannot be read";
 
 /// Command line arguments structure
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES_HELP)]
+#[command(group(clap::ArgGroup::new("chart_export").multiple(true)))]
 struct Args {
     /// Input string to convert to uppercase
     input_string: String,
@@ -28,9 +73,634 @@ struct Args {
     #[arg(short, long, default_value = "HistoricalData_1756580762948.csv")]
     csv_file: String,
     
+    /// Bid OHLC file averaged with --ask into mid-price candles instead of --csv-file
+    #[arg(long, value_name = "FILE", requires = "ask")]
+    bid: Option<PathBuf>,
+    
+    /// Ask OHLC file averaged with --bid into mid-price candles
+    #[arg(long, value_name = "FILE", requires = "bid")]
+    ask: Option<PathBuf>,
+    
+    /// Leave out bid or ask candles without a candle at the same timestamp on
+    /// the other side instead of failing
+    #[arg(long, requires = "bid")]
+    drop_unmatched: bool,
+    
+    /// Volume of the mid-price candles: sum, bid or ask
+    #[arg(long, value_enum, value_name = "SOURCE", default_value = "sum", requires = "bid")]
+    volume_source: VolumeSource,
+    
+    /// Load and merge all matching CSV files in this directory instead of --csv-file
+    #[arg(long, value_name = "DIR")]
+    csv_dir: Option<PathBuf>,
+    
+    /// Load the candles embedded in a chart exported with --embed-data instead of --csv-file
+    #[arg(long, value_name = "FILE", conflicts_with = "csv_dir")]
+    extract_data: Option<PathBuf>,
+    
+    /// File name pattern selecting the files read from --csv-dir
+    #[arg(long, default_value = "*.csv", value_name = "PATTERN", requires = "csv_dir")]
+    csv_pattern: String,
+    
+    /// How input timestamps are read: auto (text, or epoch seconds/millis by digit count), epoch_s or epoch_ms
+    /// (default: auto, or the format of --profile)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    timestamp_format: Option<TimestampFormat>,
+    
+    /// Character starting comment lines in the input; leading comments may hold
+    /// metadata such as "# symbol=AAPL exchange=NASDAQ" (default: #, or the --profile's)
+    #[arg(long, value_name = "CHAR")]
+    comment_char: Option<char>,
+    
+    /// Field separator of the input: a single character such as ';', tab, or
+    /// auto to pick the most frequent of ',', ';' and tab in each file's
+    /// header line (default: ',', or the --profile's)
+    #[arg(long, value_name = "CHAR", value_parser = Delimiter::parse)]
+    delimiter: Option<Delimiter>,
+    
+    /// Skip this many lines, e.g. export notes, before the header line (0-based
+    /// line number of the header; default: 0, or the --profile's)
+    #[arg(long, value_name = "N")]
+    header_row: Option<usize>,
+    
+    /// Preset column names, delimiter and timestamp format of a source: binance, yahoo, ibkr, or a --profiles entry
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+    
+    /// Only check that --csv-file matches the OHLCV schema of the profile, print PASS or FAIL and exit
+    #[arg(long)]
+    validate_only: bool,
+    
+    /// Check at most N evenly spaced rows when validating
+    #[arg(long, value_name = "N", requires = "validate_only", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    sample_rows: Option<usize>,
+    
+    /// TOML file of user profiles, one table per name; a user profile overrides a built-in one of the same name
+    #[arg(long, value_name = "FILE", requires = "profile")]
+    profiles: Option<PathBuf>,
+    
+    /// Treat --csv-file as wide: a timestamp column plus one close column per symbol
+    #[arg(long, requires = "symbols")]
+    wide: bool,
+    
+    /// Comma-separated symbol columns plotted from a --wide file
+    #[arg(long, value_delimiter = ',', value_name = "A,B,C", requires = "wide")]
+    symbols: Vec<String>,
+    
+    /// Aggregate candles into buckets of this length (e.g. 1h, 1d, 1w)
+    #[arg(long, value_name = "INTERVAL", value_parser = date_utils::parse_interval)]
+    resample: Option<chrono::Duration>,
+    
+    /// Label resampled candles by the open (start) or close (end) of their bucket
+    #[arg(long, value_enum, default_value_t = LabelTime::Open, requires = "resample")]
+    label_time: LabelTime,
+    
+    /// Aggregate resampled fields differently, e.g. "volume=max,close=median"
+    /// (first, last, max, min, sum, mean or median; others keep the OHLC rules)
+    #[arg(long, value_name = "FIELD=FUNCTION,...", value_parser = AggSpec::parse, requires = "resample")]
+    agg: Option<AggSpec>,
+    
+    /// Insert flat, zero-volume candles for missing periods, spaced by
+    /// --resample or else the smallest spacing in the data
+    #[arg(long)]
+    fill_missing: bool,
+    
+    /// Replace time-based candles by range bars spanning this price amount
+    #[arg(long, value_name = "AMOUNT")]
+    range_bars: Option<f64>,
+    
+    /// Keep only the first N candles
+    #[arg(long, value_name = "N", conflicts_with = "last")]
+    first: Option<usize>,
+    
+    /// Keep only the last N candles
+    #[arg(long, value_name = "N")]
+    last: Option<usize>,
+    
+    /// Show only the candles in FROM:TO (dates or months, TO included) while
+    /// indicators are still computed over the whole series
+    #[arg(long, value_name = "FROM:TO", value_parser = date_utils::parse_period_range)]
+    zoom_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    
     /// Output directory for generated files
     #[arg(short, long, default_value = "output")]
     output_dir: String,
+    
+    /// Extra CSV column whose values select the candle colors
+    #[arg(long, value_name = "COLUMN")]
+    color_by: Option<String>,
+    
+    /// Color candles by log return on a diverging scale from the down to the up color, with a colorbar
+    #[arg(long, conflicts_with = "color_by")]
+    color_by_return: bool,
+    
+    /// Draw candles whose body is smaller than this percent of the open in a
+    /// neutral gray instead of the up or down color (default: 0, off)
+    #[arg(long, value_name = "PCT", default_value_t = 0.0)]
+    neutral_threshold: f64,
+    
+    /// Fill color of candles inside the --neutral-threshold band (default: #8c8c8c)
+    #[arg(long, value_name = "#HEX", value_parser = color_utils::parse_hex)]
+    neutral_color: Option<Color32>,
+    
+    /// Fill color of doji candles, whose close equals their open, with or
+    /// without a --neutral-threshold (default: colored like other candles)
+    #[arg(long, value_name = "#HEX", value_parser = color_utils::parse_hex)]
+    doji_color: Option<Color32>,
+    
+    /// Darken candle colors that are too pale for the white background until
+    /// they reach a 3:1 contrast ratio, keeping their hue; applies to palette
+    /// and custom colors alike
+    #[arg(long)]
+    auto_contrast: bool,
+    
+    /// Color override for a category value, e.g. bull=#00ff00 (repeatable)
+    #[arg(long, value_name = "VALUE=#HEX", value_parser = color_utils::parse_color_mapping)]
+    color_map: Vec<(String, Color32)>,
+    
+    /// Chart style: candlestick, or baseline (close line filled to --baseline)
+    #[arg(long, value_enum, default_value_t = ChartType::Candlestick)]
+    chart_type: ChartType,
+    
+    /// Price a baseline chart is filled to (default: first close)
+    #[arg(long, value_name = "PRICE")]
+    baseline: Option<f64>,
+    
+    /// Chart width in pixels (default: 1200, or from --height and --aspect);
+    /// series with more candles than fit are thinned
+    #[arg(long, value_name = "PX")]
+    width: Option<u32>,
+    
+    /// Chart height in pixels (default: 800, or from --width and --aspect)
+    #[arg(long, value_name = "PX")]
+    height: Option<u32>,
+    
+    /// Keep the chart at this width to height ratio, e.g. 16:9; ignored when
+    /// both --width and --height are given
+    #[arg(long, value_name = "W:H", value_parser = Aspect::parse)]
+    aspect: Option<Aspect>,
+    
+    /// Title drawn bold above the chart, also used as the window title
+    /// (default: symbol and exchange from the input's comment metadata)
+    #[arg(long)]
+    title: Option<String>,
+    
+    /// Subtitle drawn below the title
+    #[arg(long)]
+    subtitle: Option<String>,
+    
+    /// Caption drawn at the bottom left, e.g. the data source
+    #[arg(long)]
+    caption: Option<String>,
+    
+    /// How candles are thinned to fit --width (default: ohlc-merge, lttb for baseline charts)
+    #[arg(long, value_enum)]
+    thin_strategy: Option<ThinStrategy>,
+    
+    /// Decimals of price axis labels
+    #[arg(long, default_value_t = 2, value_name = "DIGITS")]
+    price_precision: usize,
+    
+    /// Decimals of prices in hover tooltips, independent of --price-precision
+    #[arg(long, default_value_t = 4, value_name = "DIGITS")]
+    tooltip_precision: usize,
+    
+    /// Color scheme: classic green/red, or cb-safe orange/blue with hollow up candles
+    #[arg(long, value_enum, default_value_t = Palette::Classic)]
+    palette: Palette,
+    
+    /// Opacity of candle body fills (0.0-1.0, out-of-range values are clamped)
+    #[arg(long, default_value_t = 1.0, value_name = "0.0-1.0")]
+    candle_opacity: f32,
+    
+    /// Opacity of candle wicks (0.0-1.0, out-of-range values are clamped)
+    #[arg(long, default_value_t = 1.0, value_name = "0.0-1.0")]
+    wick_opacity: f32,
+    
+    /// Share of each candle slot left as whitespace beside the body, strictly
+    /// between 0 and 1; lower values pack candles more densely
+    #[arg(long, value_name = "RATIO", default_value_t = plotter::DEFAULT_SPACING, value_parser = stats_utils::parse_unit_fraction)]
+    spacing: f64,
+    
+    /// Write the loaded candles as an OHLCV CSV file to this path
+    #[arg(long, value_name = "PATH")]
+    export_csv: Option<PathBuf>,
+    
+    /// Write the candles of --export-csv as they were before --winsorize
+    #[arg(long, requires = "export_csv")]
+    export_raw: bool,
+    
+    /// Leave out the leading rows of --export-csv until every indicator line
+    /// such as --sma or --momentum is defined
+    #[arg(long, requires = "export_csv")]
+    trim_warmup: bool,
+    
+    /// Field delimiter of --export-csv, e.g. ';' for spreadsheet imports
+    /// (default: ';' for --locale with a decimal comma, otherwise ',')
+    #[arg(long, value_name = "CHAR", requires = "export_csv")]
+    export_delimiter: Option<char>,
+    
+    /// Decimal mark of numbers in --export-csv (default: the --locale's, e.g. ',' for de-DE)
+    #[arg(long, value_name = "CHAR", requires = "export_csv")]
+    export_decimal: Option<char>,
+    
+    /// Text drawn diagonally across exported charts, e.g. DRAFT
+    #[arg(long, value_name = "TEXT")]
+    watermark_text: Option<String>,
+    
+    /// Opacity of --watermark-text (0.0-1.0, out-of-range values are clamped)
+    #[arg(long, default_value_t = 0.15, value_name = "0.0-1.0", requires = "watermark_text")]
+    watermark_text_opacity: f32,
+    
+    /// Point of the chart the watermark is centered on
+    #[arg(long, value_enum, default_value_t = WatermarkPosition::Center, requires = "watermark_text")]
+    watermark_position: WatermarkPosition,
+    
+    /// Clockwise rotation of the watermark in degrees
+    #[arg(long, default_value_t = -30.0, value_name = "DEGREES", allow_negative_numbers = true, requires = "watermark_text")]
+    watermark_rotation: f32,
+    
+    /// Also draw the watermark in the --window, where it is left out by default
+    #[arg(long, requires = "watermark_text")]
+    watermark_in_window: bool,
+    
+    /// Export images and SVG on a transparent background, e.g. for slides
+    #[arg(long)]
+    transparent: bool,
+    
+    /// Clockwise rotation of the exported x-axis labels in degrees, or auto
+    /// to rotate to -45 or -90 only when neighbouring labels would overlap
+    #[arg(long, value_name = "DEGREES|auto", default_value = "auto", allow_negative_numbers = true, value_parser = LabelRotation::parse)]
+    x_label_rotation: LabelRotation,
+    
+    /// Draw horizontal gridlines at multiples of this price step, or auto to
+    /// pick a step of 1, 2 or 5 times a power of ten from the price range
+    #[arg(long, value_name = "STEP|auto", value_parser = PriceGrid::parse)]
+    price_grid: Option<PriceGrid>,
+    
+    /// Price panel layers in draw order from bottom to top, e.g.
+    /// bands,candles,sma,markers: bands, grid, fills, candles, lines (or sma,
+    /// ema), events and markers; layers left out are drawn below, unknown
+    /// names are ignored with a warning
+    #[arg(long, value_delimiter = ',', value_name = "LAYER,...")]
+    overlay_order: Vec<String>,
+    
+    /// Draw a dashed line at the last close with a price tag at the right
+    /// edge, colored by the last candle's direction
+    #[arg(long)]
+    last_price_line: bool,
+    
+    /// Free pixels kept around pattern markers and event labels; exports
+    /// widen the price range or grow the canvas to keep them from being cut off
+    #[arg(long, value_name = "PX", default_value_t = 4.0)]
+    annotation_padding: f64,
+    
+    /// Space daily candles by business days, so weekends and holidays take
+    /// no room; candles dated on a weekend or holiday are left out
+    #[arg(long, group = "calendar_axis")]
+    business_days: bool,
+    
+    /// Space daily candles by calendar days and shade the weekends and
+    /// holidays between them so the eye skips them
+    #[arg(long, group = "calendar_axis")]
+    shade_nontrading: bool,
+    
+    /// File of holiday dates (YYYY-MM-DD, one per line) skipped by
+    /// --business-days or shaded by --shade-nontrading
+    #[arg(long, value_name = "FILE", requires = "calendar_axis")]
+    holidays: Option<PathBuf>,
+    
+    /// Shade the background behind the candles by trading session, from the
+    /// hour of each candle in --session-tz (default: Asia 0-8, Europe 8-13, US 13-21)
+    #[arg(long)]
+    color_sessions: bool,
+    
+    /// Session hours replacing the defaults, e.g. Asia=0-8 or Pacific=22-2
+    /// with the end hour exclusive; earlier sessions win overlaps (repeatable)
+    #[arg(long, value_name = "NAME=START-END", value_parser = Session::parse, requires = "color_sessions")]
+    session: Vec<Session>,
+    
+    /// UTC offset the session hours are given in, e.g. +09:00
+    #[arg(long, value_name = "OFFSET", default_value = "UTC", value_parser = date_utils::parse_utc_offset, requires = "color_sessions")]
+    session_tz: FixedOffset,
+    
+    /// Print the peak and current resident memory to stderr after loading and
+    /// after computing indicators (Linux only)
+    #[arg(long)]
+    profile_memory: bool,
+    
+    /// Write the chart geometry (data coordinates) as JSON to this path
+    #[arg(long, value_name = "PATH")]
+    export_render_model: Option<PathBuf>,
+    
+    /// Write the chart as SVG inside an HTML page with a legend, summary and metadata to this path
+    #[arg(long, value_name = "PATH", group = "chart_export")]
+    export_html: Option<PathBuf>,
+    
+    /// Write the chart as a standalone SVG file to this path
+    #[arg(long, value_name = "PATH", group = "chart_export")]
+    export_svg: Option<PathBuf>,
+    
+    /// Embed the candles as hidden JSON in the --export-html report (a script
+    /// block) and the --export-svg chart (a metadata element), to be read back
+    /// with --extract-data
+    #[arg(long, requires = "chart_export")]
+    embed_data: bool,
+    
+    /// Write an animated GIF revealing the candles one period at a time to this path
+    #[arg(long, value_name = "PATH")]
+    animate_gif: Option<PathBuf>,
+    
+    /// Frames per second of --animate-gif
+    #[arg(long, default_value_t = 10, value_name = "N", requires = "animate_gif")]
+    fps: u32,
+    
+    /// Raise entry/exit signals where the FAST close SMA crosses above/below
+    /// the SLOW one; both periods are positive and FAST is below SLOW
+    #[arg(long, num_args = 2, value_names = ["FAST", "SLOW"], value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    ma_cross: Option<Vec<usize>>,
+    
+    /// Simulate long-only trades on the signals and write them as a CSV trade log
+    #[arg(long, value_name = "PATH", requires = "ma_cross")]
+    trade_log: Option<PathBuf>,
+    
+    /// Write the candles where an --ma-cross or --fractals signal fired as CSV
+    /// with a Signal column
+    #[arg(long, value_name = "PATH")]
+    export_signals: Option<PathBuf>,
+    
+    /// Write one --export-signals row per candle with its signals joined by ";"
+    /// instead of one row per signal
+    #[arg(long, requires = "export_signals")]
+    combine_signals: bool,
+    
+    /// Write ticks.csv to --output-dir with OHLC prices multiplied by this
+    /// scale and rounded half to even; lossy unless the scale covers every
+    /// decimal of the input, e.g. 100 for cents
+    #[arg(long, value_name = "SCALE")]
+    export_ticks: Option<f64>,
+    
+    /// Print the longest runs of consecutive higher and lower closes
+    #[arg(long)]
+    streaks: bool,
+    
+    /// Print how often candles opening beyond the previous range traded back
+    /// through it within the same or next candle, and the average time to fill
+    #[arg(long)]
+    gap_fill_stats: bool,
+    
+    /// Print a histogram of close-to-close returns in this many bins with mean, std dev and skew
+    #[arg(long, value_name = "BINS", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    returns_histogram: Option<usize>,
+    
+    /// Print the mean close-to-close return per day of the week (dow) or month as text bars
+    #[arg(long, value_enum, value_name = "dow|month")]
+    seasonality: Option<SeasonBy>,
+    
+    /// Cap closes at the LOWER and UPPER percentiles, e.g. 1,99. This alters
+    /// the prices drawn and used by indicators, so use it with care
+    #[arg(long, value_name = "LOWER,UPPER", value_parser = stats_utils::parse_percentile_range)]
+    winsorize: Option<(f64, f64)>,
+    
+    /// Also cap highs and lows at the --winsorize percentiles of all highs and lows
+    #[arg(long, requires = "winsorize")]
+    winsorize_range: bool,
+    
+    /// Fail with the index of the first row whose timestamp is earlier than
+    /// the previous one instead of accepting out-of-order input
+    #[arg(long)]
+    sort_check: bool,
+    
+    /// Fail with the index of the first row breaking low <= open/close <= high
+    /// or volume >= 0, instead of only logging a warning
+    #[arg(long)]
+    strict: bool,
+    
+    /// Print a 0-100 score of gaps, duplicates, non-finite values and OHLC violations in the input
+    #[arg(long)]
+    quality: bool,
+    
+    /// Print additional detail, such as the breakdown of --quality
+    #[arg(short, long)]
+    verbose: bool,
+    
+    /// Plot two CSV files side by side instead of the --csv-file
+    #[arg(long, num_args = 2, value_names = ["FILE_A", "FILE_B"])]
+    compare: Option<Vec<String>>,
+    
+    /// In --compare mode, give both panels the same price scale
+    #[arg(long, requires = "compare")]
+    shared_scale: bool,
+    
+    /// In --compare mode, synchronize horizontal zoom and pan between panels
+    #[arg(long, requires = "compare")]
+    link_axes: bool,
+    
+    /// Locale for number and date text in labels and exports (e.g. de-DE)
+    #[arg(long, default_value = "en-US", value_parser = Locale::from_tag)]
+    locale: Locale,
+    
+    /// Load candles from this SQLite database instead of --csv-file
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE", requires = "query")]
+    sqlite: Option<PathBuf>,
+    
+    /// SQL query returning Timestamp, Open, High, Low, Close and Volume columns
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "SQL", requires = "sqlite")]
+    query: Option<String>,
+    
+    /// Read live OHLCV JSON messages from a websocket (ws://) instead of a
+    /// file; with --window the chart window updates as messages arrive
+    #[cfg(feature = "stream")]
+    #[arg(long, value_name = "URL")]
+    stream_url: Option<String>,
+    
+    /// Number of most recent candles kept in memory while streaming
+    #[cfg(feature = "stream")]
+    #[arg(long, default_value_t = 1000, requires = "stream_url")]
+    max_candles: usize,
+    
+    /// Plot upper and lower wick ratios in a lower panel
+    #[arg(long)]
+    wick_ratios: bool,
+    
+    /// Plot the midpoint of each candle body over the candles
+    #[arg(long)]
+    body_midpoints: bool,
+    
+    /// Plot the Donchian channel (rolling high/low) over this many candles
+    #[arg(long, value_name = "PERIOD")]
+    donchian: Option<usize>,
+    
+    /// Draw trend lines through the swing highs and lows among this many
+    /// trailing candles
+    #[arg(long, value_name = "LOOKBACK")]
+    trend_channel: Option<usize>,
+    
+    /// Draw a volume-by-price histogram with this many levels on the right edge
+    #[arg(long, value_name = "BINS")]
+    volume_profile: Option<usize>,
+    
+    /// Plot a simple moving average of the close, e.g. 20 or 20:#ff0000:dashed (repeatable)
+    #[arg(long, value_name = "PERIOD[:COLOR[:STYLE]]", value_parser = MovingAverage::parse)]
+    sma: Vec<MovingAverage>,
+    
+    /// Plot an exponential moving average of the close; styles are solid, dashed or dotted (repeatable)
+    #[arg(long, value_name = "PERIOD[:COLOR[:STYLE]]", value_parser = MovingAverage::parse)]
+    ema: Vec<MovingAverage>,
+    
+    /// Plot a close SMA of candles resampled to a higher timeframe as steps,
+    /// e.g. weekly:20 on daily candles; timeframes are hourly, daily, weekly
+    /// or an interval such as 4h (repeatable)
+    #[arg(long, value_name = "TIMEFRAME:PERIOD", value_parser = TimeframeAverage::parse)]
+    mtf_sma: Vec<TimeframeAverage>,
+    
+    /// Plot the time-weighted average of the typical price over this many
+    /// candles, an alternative to volume-weighted averages when volume is unreliable
+    #[arg(long, value_name = "PERIOD")]
+    twap: Option<usize>,
+    
+    /// Plot the close minus the close this many candles earlier in the lower panel
+    #[arg(long, value_name = "PERIOD")]
+    momentum: Option<usize>,
+    
+    /// Plot the percentage rate of change over this many candles in the lower panel
+    #[arg(long, value_name = "PERIOD")]
+    roc: Option<usize>,
+    
+    /// Plot the percentile rank (0-100) of the close among the last this many
+    /// closes in the lower panel, with guides at 20 and 80
+    #[arg(long, value_name = "PERIOD")]
+    percentile_rank: Option<usize>,
+    
+    /// Plot how far the close is below the highest high of this many candles, in
+    /// percent, in the lower panel; 0 or no value uses the all-time high
+    #[arg(long, value_name = "PERIOD", num_args = 0..=1, default_missing_value = "0")]
+    pct_from_high: Option<usize>,
+    
+    /// Plot the Ulcer Index, the RMS percentage drawdown from the peak within
+    /// this many candles, in the lower panel
+    #[arg(long, value_name = "PERIOD")]
+    ulcer: Option<usize>,
+    
+    /// Draw the last candle faded and outlined because its period is still open
+    #[arg(long)]
+    mark_last_incomplete: bool,
+    
+    /// Whether the last candle really is incomplete, for callers that know (true/false)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL", requires = "mark_last_incomplete")]
+    incomplete: bool,
+    
+    /// Mark Bill Williams fractals (local highs and lows over five candles)
+    #[arg(long)]
+    fractals: bool,
+    
+    /// Mark candles whose true range exceeds this multiple of the average
+    /// true range of the prior --range-expansion-period candles
+    #[arg(long, value_name = "FACTOR")]
+    range_expansion: Option<f64>,
+    
+    /// Prior candles averaged by --range-expansion
+    #[arg(long, value_name = "PERIOD", default_value_t = 14, requires = "range_expansion")]
+    range_expansion_period: usize,
+    
+    /// Plot each day's floor pivots (P, R1, S1, R2, S2) from the previous day's
+    /// high, low and close; the first day has none
+    #[arg(long)]
+    session_pivots: bool,
+    
+    /// Draw this CSV series, e.g. an index, as a faint area behind the candles, rescaled to their price range
+    #[arg(long, value_name = "FILE")]
+    benchmark: Option<PathBuf>,
+    
+    /// Chart the prices divided by the close of this CSV series (a ratio chart),
+    /// matched by timestamp; candles without a nonzero benchmark close are left out
+    #[arg(long, value_name = "FILE")]
+    relative_to: Option<PathBuf>,
+    
+    /// Plot the rolling beta of returns on the --benchmark returns over this
+    /// many candles in the lower panel; candles are matched by timestamp
+    #[arg(long, value_name = "PERIOD", requires = "benchmark")]
+    beta: Option<usize>,
+    
+    /// Draw labeled vertical lines at the events of this Timestamp,Label,Kind CSV file, colored by kind
+    #[arg(long, value_name = "FILE")]
+    events: Option<PathBuf>,
+    
+    /// Emphasize candles whose volume exceeds this absolute threshold
+    #[arg(long, value_name = "THRESHOLD", conflicts_with = "volume_highlight_pct")]
+    volume_highlight: Option<f64>,
+    
+    /// Emphasize candles whose volume exceeds this percentile (0-100) of all volumes
+    #[arg(long, value_name = "PCT")]
+    volume_highlight_pct: Option<f64>,
+    
+    /// Draw only the requested indicators, leaving out candle bodies and wicks
+    #[arg(long)]
+    hide_candles: bool,
+    
+    /// With --hide-candles, scale the price axis to the visible indicator
+    /// values instead of the candle range
+    #[arg(long, requires = "hide_candles")]
+    scale_to_indicators: bool,
+    
+    /// Draw volume bars in a panel below the price chart, thinned together with the candles
+    #[arg(long)]
+    volume_panel: bool,
+    
+    /// Open a native window showing the chart (needs a display and GPU/OpenGL)
+    #[arg(long, conflicts_with = "interactive")]
+    window: bool,
+    
+    /// Deflate level of PNG images from 0 (fastest, largest) to 9 (slowest, smallest)
+    #[arg(long, default_value_t = 6, value_name = "0-9", value_parser = clap::value_parser!(u8).range(0..=9))]
+    png_compression: u8,
+    
+    /// Write PNG images with a color table when they have at most 256 colors
+    #[arg(long)]
+    png_palette: bool,
+    
+    /// Store this resolution in PNG images for print layout tools; the pixel
+    /// size stays --width by --height
+    #[arg(long, value_name = "DPI", value_parser = clap::value_parser!(u32).range(1..))]
+    dpi: Option<u32>,
+    
+    /// Renderer for --window: egui (interactive, needs a display and GPU) or
+    /// software-preview (static PNG without texts, opened in the image viewer)
+    #[arg(long, value_enum, default_value_t = Backend::Egui, requires = "window")]
+    backend: Backend,
+    
+    /// Read exploration commands from stdin and refresh the plot after each
+    #[arg(long)]
+    interactive: bool,
+    
+    /// Load options from a TOML file written by --save-config (command line options take precedence)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    
+    /// Write the effective options, including those from --config, to a TOML file
+    #[arg(long, value_name = "PATH")]
+    save_config: Option<PathBuf>,
+    
+    /// Render every chart of a manifest: [[chart]] tables in TOML or an array
+    /// of objects in JSON, each with config file keys layered over --config and
+    /// under the command line; failed charts are reported after the rest
+    #[arg(long, value_name = "MANIFEST")]
+    batch: Option<PathBuf>,
+    
+    /// Report errors on stderr as text or as a JSON object with error_type and message
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+}
+
+/// How a failure is reported on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    /// `Error: ...` followed by the causes, one per line
+    Text,
+    /// `{"error_type": ..., "message": ...}` on a single line
+    Json,
 }
 
 /// Custom error types for the application
@@ -49,21 +719,160 @@ pub enum AppError {
     Plotting(String),
 }
 
+impl AppError {
+    /// Returns the tag identifying the variant in machine-readable output
+    ///
+    /// CSV errors caused by reading the file count as I/O errors.
+    ///
+    /// # Returns
+    /// * `&'static str` - `io`, `csv`, `data_processing` or `plotting`
+    pub fn type_tag(&self) -> &'static str {
+        match self {
+            AppError::Io(_) => "io",
+            AppError::Csv(err) if err.is_io_error() => "io",
+            AppError::Csv(_) => "csv",
+            AppError::DataProcessing(_) => "data_processing",
+            AppError::Plotting(_) => "plotting",
+        }
+    }
+    
+    /// Returns the process exit code for the variant, see `EXIT_CODES_HELP`
+    ///
+    /// # Returns
+    /// * `u8` - 6 for Io, 3 for Csv, 4 for DataProcessing, 5 for Plotting;
+    ///   2 is left to clap's usage errors
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            AppError::Io(_) => 6,
+            AppError::Csv(err) if err.is_io_error() => 6,
+            AppError::Csv(_) => 3,
+            AppError::DataProcessing(_) => 4,
+            AppError::Plotting(_) => 5,
+        }
+    }
+}
+
 /// Main application entry point
 ///
 /// # Returns
-/// * `Result<()>` - Ok if successful, Err if any error occurs
+/// * `ExitCode` - Success, or failure once the error was reported in the
+///   format selected by `--error-format`
 ///
 /// # Examples
 /// ```
 /// // This would run the main function (not typically tested directly)
 /// ```
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     // Initialize logger
     env_logger::init();
     
-    // Parse command line arguments
-    let args = Args::parse();
+    // Parse command line arguments; config and usage errors come before
+    // --error-format is parsed, so they use the format named on the command line
+    let cli: Vec<OsString> = std::env::args_os().collect();
+    let args = match parse_args(&cli) {
+        Ok(args) => args,
+        Err(err) => {
+            let error_format = requested_error_format(&cli);
+            if let (ErrorFormat::Text, Some(usage)) = (error_format, err.downcast_ref::<clap::Error>()) {
+                usage.exit();
+            }
+            return report_error(&err, error_format);
+        }
+    };
+    let error_format = args.error_format;
+    
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => report_error(&err, error_format),
+    }
+}
+
+/// Finds the `--error-format` on a command line that may not parse
+///
+/// # Arguments
+/// * `cli` - Raw command line tokens
+///
+/// # Returns
+/// * `ErrorFormat` - Last valid format given, `Text` if there is none
+fn requested_error_format(cli: &[OsString]) -> ErrorFormat {
+    let tokens: Vec<String> = cli.iter().map(|token| token.to_string_lossy().into_owned()).collect();
+    
+    tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, token)| match token.strip_prefix("--error-format") {
+            Some("") => tokens.get(i + 1).map(String::as_str),
+            Some(value) => value.strip_prefix('='),
+            None => None,
+        })
+        .filter_map(|value| ErrorFormat::from_str(value, true).ok())
+        .next_back()
+        .unwrap_or(ErrorFormat::Text)
+}
+
+/// Finds the kind of failure in the cause chain of an error
+///
+/// The first `AppError` in the chain decides; bare I/O and CSV errors are
+/// treated like `AppError::Io` and `AppError::Csv`, clap errors as invalid
+/// usage.
+///
+/// # Arguments
+/// * `err` - Error that ended the run
+///
+/// # Returns
+/// * `(&'static str, u8)` - Type tag and exit code, `("other", 1)` if unknown
+fn error_kind(err: &anyhow::Error) -> (&'static str, u8) {
+    for cause in err.chain() {
+        if let Some(app_error) = cause.downcast_ref::<AppError>() {
+            return (app_error.type_tag(), app_error.exit_code());
+        } else if cause.is::<io::Error>() {
+            return ("io", 6);
+        } else if let Some(csv_error) = cause.downcast_ref::<csv::Error>() {
+            return match csv_error.is_io_error() {
+                true => ("io", 6),
+                false => ("csv", 3),
+            };
+        } else if cause.is::<clap::Error>() {
+            return ("usage", 2);
+        }
+    }
+    
+    ("other", 1)
+}
+
+/// Prints an error in the requested format
+///
+/// # Arguments
+/// * `err` - Error that ended the run
+/// * `format` - Output format
+///
+/// # Returns
+/// * `ExitCode` - Exit code of the error kind, the same for every format
+fn report_error(err: &anyhow::Error, format: ErrorFormat) -> ExitCode {
+    let (error_type, exit_code) = error_kind(err);
+    
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {:?}", err),
+        ErrorFormat::Json => {
+            let report = serde_json::json!({ "error_type": error_type, "message": format!("{:#}", err) });
+            eprintln!("{}", report);
+        }
+    }
+    
+    ExitCode::from(exit_code)
+}
+
+/// Runs the application with parsed arguments
+///
+/// # Arguments
+/// * `args` - Effective command line arguments
+///
+/// # Returns
+/// * `Result<()>` - Ok if successful, Err if any error occurs
+fn run(args: Args) -> Result<()> {
+    if let Some(manifest) = &args.batch {
+        return run_batch(&args, manifest);
+    }
     
     // Process input string and output in uppercase
     let uppercase_output = args.input_string.to_uppercase();
@@ -73,29 +882,1035 @@ fn main() -> Result<()> {
     file_utils::ensure_directory_exists(&args.output_dir)
         .context("Failed to create output directory")?;
     
+    let events = match &args.events {
+        Some(path) => DataProcessor::load_events(path).context("Failed to load events")?,
+        None => Vec::new(),
+    };
+    
+    let profile = csv_profile(&args)?;
+    let benchmark = args.benchmark.as_deref().map(|path| load_benchmark(path, &profile)).transpose()?;
+    let y_label = args.relative_to.as_ref().map(|path| {
+        format!("Ratio to {}", path.file_stem().unwrap_or_default().to_string_lossy())
+    });
+    
+    let calendar = match &args.holidays {
+        Some(path) => BusinessCalendar::load(path)?,
+        None => BusinessCalendar::default(),
+    };
+    let business_days = args.business_days.then(|| calendar.clone());
+    let nontrading = args.shade_nontrading.then_some(calendar);
+    let sessions = args.color_sessions.then(|| SessionTable {
+        sessions: match args.session.is_empty() {
+            true => SessionTable::default().sessions,
+            false => args.session.clone(),
+        },
+        offset: args.session_tz,
+    });
+    
+    // Create and display plot
+    let mut plotter = Plotter::with_config(PlotConfig {
+        color_by: args.color_by.clone(),
+        color_map: args.color_map.iter().cloned().collect(),
+        candle_opacity: args.candle_opacity,
+        spacing: args.spacing,
+        wick_opacity: args.wick_opacity,
+        locale: args.locale,
+        overlays: overlays(&args),
+        volume_highlight: args.volume_highlight.map(VolumeHighlight::Absolute)
+            .or(args.volume_highlight_pct.map(VolumeHighlight::Percentile)),
+        palette: args.palette,
+        chart_type: args.chart_type,
+        baseline: args.baseline,
+        last_incomplete: args.mark_last_incomplete && args.incomplete,
+        events,
+        volume_panel: args.volume_panel,
+        price_precision: args.price_precision,
+        tooltip_precision: args.tooltip_precision,
+        benchmark,
+        zoom: args.zoom_range,
+        size: Aspect::chart_size(args.aspect, args.width, args.height),
+        title: args.title.clone(),
+        subtitle: args.subtitle.clone(),
+        caption: args.caption.clone(),
+        hide_candles: args.hide_candles,
+        scale_to_indicators: args.scale_to_indicators,
+        color_by_return: args.color_by_return,
+        neutral_threshold: args.neutral_threshold,
+        neutral_color: args.neutral_color.unwrap_or(args.palette.neutral_color()),
+        doji_color: args.doji_color,
+        auto_contrast: args.auto_contrast,
+        watermark: args.watermark_text.clone().map(|text| Watermark {
+            text,
+            opacity: args.watermark_text_opacity.clamp(0.0, 1.0),
+            position: args.watermark_position,
+            rotation: args.watermark_rotation,
+        }),
+        watermark_in_window: args.watermark_in_window,
+        png: PngOptions { compression: args.png_compression, palette: args.png_palette, dpi: args.dpi },
+        transparent: args.transparent,
+        x_label_rotation: args.x_label_rotation,
+        business_days,
+        nontrading,
+        last_price_line: args.last_price_line,
+        annotation_padding: args.annotation_padding,
+        sessions,
+        data_source: Some(data_source(&args)),
+        price_grid: args.price_grid,
+        layers: Plotter::layer_order(&args.overlay_order),
+        y_label,
+    });
+    
+    if let Some(files) = &args.compare {
+        return run_compare(&args, &mut plotter, &files[0], &files[1]);
+    }
+    
+    #[cfg(feature = "stream")]
+    if let Some(url) = &args.stream_url {
+        return run_stream(&args, &mut plotter, url);
+    }
+    
+    if args.wide {
+        return run_wide(&args, &mut plotter);
+    }
+    
     // Process CSV data
-    let mut processor = DataProcessor::new();
-    let data = processor.load_csv_data(&args.csv_file)
-        .context("Failed to load CSV data")?;
+    if args.validate_only {
+        return run_validate(Path::new(&args.csv_file), &profile, args.sample_rows);
+    }
+    let mut processor = DataProcessor::with_profile(profile.clone());
+    let mut data = load_input(&args, &mut processor).context("Failed to load input data")?;
+    let metadata = processor.metadata();
+    if let (None, Some(title)) = (&args.title, metadata_title(metadata)) {
+        plotter = Plotter::with_config(PlotConfig { title: Some(title), ..plotter.config().clone() });
+    }
+    let series_name = metadata.get("symbol").map_or(DEFAULT_SERIES.to_string(), |symbol| symbol.to_lowercase());
+    DataProcessor::normalize_timestamps(&mut data, TimestampFormat::Auto)
+        .map_err(|err| AppError::DataProcessing(format!("{:#}", err)))?;
+    if args.sort_check {
+        DataProcessor::check_sorted(&data).map_err(|err| AppError::DataProcessing(format!("{:#}", err)))?;
+    }
+    check_candles(&data, args.strict, &data_source(&args))?;
+    if let Some(path) = &args.relative_to {
+        let ratios = DataProcessor::ratio_candles(&DataProcessor::candles_from(&data)?, &load_benchmark(path, &profile)?);
+        let total = data.len();
+        data = data
+            .into_iter()
+            .zip(ratios)
+            .filter_map(|(row, ratio)| {
+                let ratio = ratio?;
+                Some(HistoricalData { open: ratio.open, high: ratio.high, low: ratio.low, close: ratio.close, ..row })
+            })
+            .collect();
+        if data.is_empty() {
+            return Err(AppError::DataProcessing("--relative-to needs a benchmark sharing timestamps with the data".to_string()).into());
+        }
+        if data.len() < total {
+            log::warn!("Left out {} candles without a nonzero benchmark close", total - data.len());
+        }
+    }
+    if args.profile_memory {
+        print_memory("load");
+    }
+    
+    if args.quality {
+        print_quality(&DataProcessor::candles_from(&data)?, args.verbose);
+    }
+    
+    let data = match args.resample {
+        Some(interval) => {
+            let agg = args.agg.unwrap_or_default();
+            DataProcessor::resample(&DataProcessor::candles_from(&data)?, interval, args.label_time, &agg)
+                .iter()
+                .map(HistoricalData::from)
+                .collect()
+        }
+        None => data,
+    };
+    
+    let data = match args.fill_missing {
+        true => {
+            let candles = DataProcessor::candles_from(&data)?;
+            match args.resample.or_else(|| DataProcessor::min_interval(&candles)) {
+                Some(interval) => DataProcessor::fill_missing(&candles, interval)?
+                    .iter()
+                    .map(HistoricalData::from)
+                    .collect(),
+                None => data,
+            }
+        }
+        false => data,
+    };
+    
+    let data = match args.range_bars {
+        Some(amount) if !(amount > 0.0 && amount.is_finite()) => {
+            return Err(AppError::DataProcessing(format!("Range bar amount must be positive, got {}", amount)).into());
+        }
+        Some(amount) => DataProcessor::range_bars(&DataProcessor::candles_from(&data)?, amount)
+            .iter()
+            .map(HistoricalData::from)
+            .collect(),
+        None => data,
+    };
+    
+    let data = match (args.first, args.last) {
+        (Some(n), _) => DataProcessor::head(&data, n),
+        (_, Some(n)) => DataProcessor::tail(&data, n),
+        _ => data,
+    };
+    
+    let raw = data;
+    let data = match args.winsorize {
+        Some((lower, upper)) => {
+            let capped = DataProcessor::winsorize(&DataProcessor::candles_from(&raw)?, lower, upper, args.winsorize_range);
+            // Keep extra columns such as --color-by values
+            raw.iter()
+                .zip(&capped)
+                .map(|(row, candle)| HistoricalData { high: candle.high, low: candle.low, close: candle.close, ..row.clone() })
+                .collect()
+        }
+        None => raw.clone(),
+    };
+    
+    if let (Some(_), Some(benchmark)) = (args.beta, &plotter.config().benchmark)
+        && DataProcessor::align_closes(&DataProcessor::candles_from(&data)?, benchmark).iter().all(Option::is_none)
+    {
+        return Err(AppError::DataProcessing("--beta needs a benchmark sharing timestamps with the data".to_string()).into());
+    }
+    
+    if let Some(path) = &args.export_csv {
+        let localized = CsvFormat::for_locale(&args.locale);
+        let format = CsvFormat {
+            delimiter: args.export_delimiter.unwrap_or(localized.delimiter),
+            decimal: args.export_decimal.unwrap_or(localized.decimal),
+        };
+        let exported = if args.export_raw { &raw } else { &data };
+        let candles = DataProcessor::candles_from(exported)?;
+        let trimmed = if args.trim_warmup { plotter.warmup(&candles) } else { 0 };
+        if args.trim_warmup {
+            log::info!("Trimmed {} warmup rows before all indicators are defined", trimmed);
+        }
+        DataProcessor::write_csv(&candles[trimmed..], path, &format)
+            .context("Failed to export CSV")?;
+    }
+    
+    if let (Some(path), Some(periods)) = (&args.trade_log, &args.ma_cross) {
+        let candles = DataProcessor::candles_from(&data)?;
+        let signals = patterns::crossovers(
+            &indicators::sma(&candles, periods[0]),
+            &indicators::sma(&candles, periods[1]),
+        );
+        DataProcessor::write_trade_log(&DataProcessor::simulate(&signals, &candles), path)
+            .context("Failed to write trade log")?;
+    }
+    
+    if let Some(path) = &args.export_signals {
+        if args.ma_cross.is_none() && !args.fractals {
+            log::warn!("--export-signals writes no rows without --ma-cross or --fractals");
+        }
+        let candles = DataProcessor::candles_from(&data)?;
+        DataProcessor::write_signals(&candles, &collect_signals(&args, &candles), path, args.combine_signals)
+            .context("Failed to export signals")?;
+    }
+    
+    if let Some(scale) = args.export_ticks {
+        let ticks = DataProcessor::to_ticks(&DataProcessor::candles_from(&data)?, scale)?;
+        DataProcessor::write_ticks(&ticks, &Path::new(&args.output_dir).join("ticks.csv"))
+            .context("Failed to export ticks")?;
+    }
+    
+    if args.streaks {
+        print_streaks(&DataProcessor::candles_from(&data)?, &args.locale);
+    }
+    
+    if args.gap_fill_stats {
+        print_gap_fill_stats(&DataProcessor::candles_from(&data)?, &args.locale);
+    }
+    
+    if let Some(bins) = args.returns_histogram {
+        print_returns_histogram(&DataProcessor::candles_from(&data)?, bins, &args.locale);
+    }
+    
+    if let Some(by) = args.seasonality {
+        print_seasonality(&DataProcessor::candles_from(&data)?, by, &args.locale);
+    }
+    
+    let data = match &plotter.config().business_days {
+        Some(calendar) => {
+            let total = data.len();
+            let data: Vec<HistoricalData> = data
+                .into_iter()
+                .filter(|row| row.parsed_timestamp().is_ok_and(|t| calendar.is_business_day(t.date_naive())))
+                .collect();
+            if data.len() < total {
+                log::warn!("Left out {} candles dated on weekends or holidays", total - data.len());
+            }
+            data
+        }
+        None => data,
+    };
+    
+    let strategy = args.thin_strategy.unwrap_or(match args.chart_type {
+        ChartType::Candlestick => ThinStrategy::OhlcMerge,
+        ChartType::Baseline => ThinStrategy::Lttb,
+    });
+    let max_candles = downsample::max_candles(plotter.config().size.0);
+    // Only the zoomed-in candles have to fit the chart width
+    let max_candles = match plotter.config().zoom {
+        Some((start, end)) => {
+            let visible = data
+                .iter()
+                .filter(|row| row.parsed_timestamp().is_ok_and(|t| t >= start && t < end))
+                .count();
+            downsample::zoomed_budget(data.len(), visible, max_candles)
+        }
+        None => max_candles,
+    };
+    let thinned = downsample::thin(&data, max_candles, strategy);
+    if thinned.data.len() < data.len() {
+        log::info!("Thinned {} candles to {} with {:?}", data.len(), thinned.data.len(), strategy);
+    }
+    
+    if args.profile_memory {
+        // Indicators are computed while building the render model
+        plotter.render_model(&thinned.data)?;
+        print_memory("indicators");
+    }
+    
+    if let Some(path) = &args.export_render_model {
+        plotter.render_model(&thinned.data)?
+            .write_json(path)
+            .context("Failed to export render model")?;
+    }
+    
+    let embedded = args.embed_data.then(|| DataProcessor::embed_json(&data)).transpose()?;
+    if let Some(path) = &args.export_svg {
+        svg::write_svg(&plotter.render_model(&thinned.data)?, embedded.as_deref(), path)
+            .context("Failed to export SVG")?;
+    }
+    
+    if let Some(path) = &args.export_html {
+        let summary = DataProcessor::summary(&DataProcessor::candles_from(&data)?);
+        html::write_html(
+            &plotter.render_model(&thinned.data)?,
+            summary.as_ref(),
+            processor.metadata(),
+            &args.locale,
+            embedded.as_deref(),
+            path,
+        )
+            .context("Failed to export HTML")?;
+    }
+    
+    if let Some(path) = &args.animate_gif {
+        let model = plotter.render_model(&thinned.data)?;
+        animation::write_gif(&animation::frames(&model, animation::MAX_FRAMES), args.fps, path)
+            .context("Failed to write animated GIF")?;
+    }
+    
+    if args.interactive {
+        repl::run(io::stdin().lock(), &mut io::stdout(), data, &mut plotter, &args.output_dir)
+            .context("Interactive session failed")?;
+        return Ok(());
+    }
+    
+    if args.window {
+        return match args.backend {
+            Backend::Egui => {
+                let title = plotter.config().title.clone().unwrap_or("Candle Stick Plotter".to_string());
+                plotter.show_window(&data, &thinned, &title)
+            }
+            Backend::SoftwarePreview => plotter
+                .show_preview(
+                    &thinned.data,
+                    &Path::new(&args.output_dir).join("preview.png"),
+                    &plotter.config().png,
+                )
+                .context("Failed to create preview image"),
+        };
+    }
     
     // Store data in HashMap for easy access
     let mut data_map = HashMap::new();
-    data_map.insert("historical_data".to_string(), data);
+    data_map.insert(series_name, thinned.data);
     
-    // Create and display plot
-    let mut plotter = Plotter::new();
     plotter.create_candlestick_plot(&data_map, &args.output_dir)
         .context("Failed to create candlestick plot")?;
     
     Ok(())
 }
 
+/// Parses the command line, replaying a --config file underneath it
+///
+/// Options from the config file are inserted before the actual command line
+/// tokens, so options given explicitly override them. `--help` and
+/// `--version` are printed and exit right away.
+///
+/// # Arguments
+/// * `cli` - Command line tokens including the program name
+///
+/// # Returns
+/// * `Result<Args>` - Effective arguments
+///
+/// # Errors
+/// * Returns the `clap::Error` of invalid usage, or an error if the config
+///   file cannot be loaded or saved
+fn parse_args(cli: &[OsString]) -> Result<Args> {
+    let command = Args::command().args_override_self(true);
+    let parse = |tokens: &[OsString]| match command.clone().try_get_matches_from(tokens) {
+        Err(err) if !err.use_stderr() => err.exit(),
+        result => result,
+    };
+    let mut matches = parse(cli)?;
+    
+    if let Some(path) = matches.get_one::<PathBuf>("config") {
+        let mut tokens = cli[..1].to_vec();
+        tokens.extend(config::load(path, &command).context("Failed to load config")?);
+        tokens.extend(cli[1..].iter().cloned());
+        matches = parse(&tokens)?;
+    }
+    
+    if let Some(path) = matches.get_one::<PathBuf>("save_config") {
+        config::save(path, &command, &matches).context("Failed to save config")?;
+    }
+    
+    Ok(checked_args(&command, Args::from_arg_matches(&matches)?)?)
+}
+
+/// Checks arguments that are only valid together, which clap cannot express
+///
+/// # Arguments
+/// * `command` - Command used to format the usage error
+/// * `args` - Parsed arguments
+///
+/// # Returns
+/// * `Result<Args, clap::Error>` - The arguments unchanged
+///
+/// # Errors
+/// * Returns a value validation error if `--ma-cross` FAST is not below SLOW
+fn checked_args(command: &clap::Command, args: Args) -> Result<Args, clap::Error> {
+    if let Some(&[fast, slow]) = args.ma_cross.as_deref()
+        && fast >= slow
+    {
+        return Err(command.clone().error(
+            clap::error::ErrorKind::ValueValidation,
+            format!("--ma-cross FAST ({}) must be below SLOW ({})", fast, slow),
+        ));
+    }
+    Ok(args)
+}
+
+/// Renders each chart of a batch manifest, continuing after failed charts
+///
+/// # Arguments
+/// * `args` - Arguments of the batch run, whose --config applies to every chart
+/// * `manifest` - TOML or JSON manifest listing the charts
+///
+/// # Returns
+/// * `Result<()>` - Ok if every chart was rendered
+///
+/// # Errors
+/// * Returns error if the manifest cannot be loaded or any chart failed
+fn run_batch(args: &Args, manifest: &Path) -> Result<()> {
+    let command = Args::command().args_override_self(true);
+    let cli: Vec<OsString> = std::env::args_os().collect();
+    let shared = match &args.config {
+        Some(path) => config::load(path, &command).context("Failed to load config")?,
+        None => Vec::new(),
+    };
+    let charts = config::load_batch(manifest, &command).context("Failed to load batch manifest")?;
+    
+    let mut failed = 0;
+    for (index, tokens) in charts.iter().enumerate() {
+        let mut line = cli[..1].to_vec();
+        line.extend(shared.iter().cloned());
+        line.extend(tokens.iter().cloned());
+        line.extend(cli[1..].iter().cloned());
+        
+        let result = command
+            .clone()
+            .try_get_matches_from(line)
+            .map_err(anyhow::Error::from)
+            .and_then(|matches| Ok(checked_args(&command, Args::from_arg_matches(&matches)?)?))
+            .and_then(|chart| run(Args { batch: None, ..chart }));
+        if let Err(err) = result {
+            eprintln!("Chart {} failed: {:#}", index + 1, err);
+            failed += 1;
+        }
+    }
+    
+    println!("Batch finished: {} of {} charts rendered, {} failed", charts.len() - failed, charts.len(), failed);
+    if failed > 0 {
+        anyhow::bail!("{} of {} charts in {} failed", failed, charts.len(), manifest.display());
+    }
+    Ok(())
+}
+
+/// Names the input the candles are loaded from, in the order loading picks it
+fn data_source(args: &Args) -> String {
+    #[cfg(feature = "stream")]
+    if let Some(url) = &args.stream_url {
+        return url.clone();
+    }
+    #[cfg(feature = "sqlite")]
+    if let (Some(db), Some(_)) = (&args.sqlite, &args.query) {
+        return db.display().to_string();
+    }
+    match (&args.csv_dir, &args.bid, &args.ask) {
+        (_, Some(bid), Some(ask)) => format!("{} and {}", bid.display(), ask.display()),
+        (Some(dir), _, _) => dir.display().to_string(),
+        _ => args.extract_data.as_ref().map_or(args.csv_file.clone(), |path| path.display().to_string()),
+    }
+}
+
+/// Loads the rows of the input named by `data_source`
+///
+/// Only the chosen input is read; with --sqlite and --query the CSV file is
+/// not opened.
+///
+/// # Arguments
+/// * `args` - Effective command line arguments
+/// * `processor` - Processor reading the input with its profile
+///
+/// # Returns
+/// * `Result<Vec<HistoricalData>>` - Rows in input order
+///
+/// # Errors
+/// * Returns error if the chosen input cannot be read or parsed
+fn load_input(args: &Args, processor: &mut DataProcessor) -> Result<Vec<HistoricalData>> {
+    #[cfg(feature = "sqlite")]
+    if let (Some(db), Some(query)) = (&args.sqlite, &args.query) {
+        return processor.load_sqlite(db, query);
+    }
+    match (&args.csv_dir, &args.bid, &args.ask) {
+        (_, Some(bid), Some(ask)) => load_mid_candles(processor, bid, ask, args.volume_source, args.drop_unmatched),
+        (Some(dir), _, _) => processor.load_dir(dir, &args.csv_pattern),
+        _ => match &args.extract_data {
+            Some(path) => processor.load_embedded(path),
+            None => processor.load_csv_data(&args.csv_file),
+        },
+    }
+}
+
+/// Resolves the CSV profile of the input from --profile and the layout options
+///
+/// # Arguments
+/// * `args` - Effective command line arguments
+///
+/// # Returns
+/// * `Result<CsvProfile>` - Named or default profile with the options applied
+///
+/// # Errors
+/// * Returns error if --profile names no built-in or user profile
+fn csv_profile(args: &Args) -> Result<CsvProfile> {
+    let mut profile = match &args.profile {
+        Some(name) => CsvProfile::resolve(name, args.profiles.as_deref()).context("Failed to load profile")?,
+        None => CsvProfile::default(),
+    };
+    if let Some(format) = args.timestamp_format {
+        profile.timestamp_format = format;
+        profile.date_format = None;
+    }
+    if let Some(comment_char) = args.comment_char {
+        profile.comment_char = comment_char;
+    }
+    match args.delimiter {
+        Some(Delimiter::Char(delimiter)) => {
+            profile.delimiter = delimiter;
+            profile.detect_delimiter = false;
+        }
+        Some(Delimiter::Auto) => profile.detect_delimiter = true,
+        None => {}
+    }
+    if let Some(header_row) = args.header_row {
+        profile.header_row = header_row;
+    }
+    Ok(profile)
+}
+
+/// Loads a benchmark series such as an index
+///
+/// # Arguments
+/// * `path` - CSV file in the layout of the input
+/// * `profile` - CSV profile the input is read with
+///
+/// # Returns
+/// * `Result<Vec<CandleStick>>` - Benchmark candles in file order
+///
+/// # Errors
+/// * Returns `AppError::Io` if the file does not exist, or an error if it
+///   cannot be parsed
+fn load_benchmark(path: &Path, profile: &CsvProfile) -> Result<Vec<CandleStick>> {
+    if !path.is_file() {
+        let message = format!("Benchmark file not found: {}", path.display());
+        return Err(AppError::Io(io::Error::new(io::ErrorKind::NotFound, message)).into());
+    }
+    let rows = DataProcessor::with_profile(profile.clone())
+        .load_csv_data(&path.to_string_lossy())
+        .context("Failed to load benchmark")?;
+    DataProcessor::candles_from(&rows)
+}
+
+/// Loads bid and ask files and averages them into mid-price candles
+///
+/// # Arguments
+/// * `processor` - Processor reading both files with its profile
+/// * `bid` - Bid OHLC file
+/// * `ask` - Ask OHLC file
+/// * `volume` - Which side's volume the mid candles carry
+/// * `drop_unmatched` - Leave out candles without a counterpart
+///
+/// # Returns
+/// * `Result<Vec<HistoricalData>>` - Mid-price rows in bid order
+///
+/// # Errors
+/// * Returns `AppError::Io` if a file does not exist and
+///   `AppError::DataProcessing` if the timestamps do not match up
+fn load_mid_candles(processor: &mut DataProcessor, bid: &Path, ask: &Path, volume: VolumeSource, drop_unmatched: bool) -> Result<Vec<HistoricalData>> {
+    let mut load = |path: &Path| -> Result<Vec<CandleStick>> {
+        if !path.is_file() {
+            let message = format!("Input file not found: {}", path.display());
+            return Err(AppError::Io(io::Error::new(io::ErrorKind::NotFound, message)).into());
+        }
+        let rows = processor.load_csv_data(&path.to_string_lossy()).context(format!("Failed to load {}", path.display()))?;
+        DataProcessor::candles_from(&rows)
+    };
+    let (bid, ask) = (load(bid)?, load(ask)?);
+    
+    let mid = DataProcessor::mid_candles(&bid, &ask, volume, drop_unmatched)
+        .map_err(|err| AppError::DataProcessing(format!("{:#}", err)))?;
+    Ok(mid.iter().map(HistoricalData::from).collect())
+}
+
+/// Validates an input file and prints the report
+///
+/// # Arguments
+/// * `path` - File to validate
+/// * `profile` - Expected layout of the file
+/// * `sample_rows` - Largest number of rows to check
+///
+/// # Returns
+/// * `Result<()>` - Ok if the file passed
+///
+/// # Errors
+/// * Returns `AppError::Io` if the file does not exist and
+///   `AppError::DataProcessing` if any issue was found
+fn run_validate(path: &Path, profile: &CsvProfile, sample_rows: Option<usize>) -> Result<()> {
+    if !path.is_file() {
+        let message = format!("Input file not found: {}", path.display());
+        return Err(AppError::Io(io::Error::new(io::ErrorKind::NotFound, message)).into());
+    }
+    
+    let report = validation::validate_csv(path, profile, sample_rows)?;
+    println!("{}", report.summary());
+    for issue in &report.issues {
+        println!("  {}", issue);
+    }
+    
+    match report.passed() {
+        true => Ok(()),
+        false => Err(AppError::DataProcessing(format!("{} failed validation", path.display())).into()),
+    }
+}
+
+/// Builds a chart title such as `AAPL (NASDAQ)` from file metadata
+///
+/// # Arguments
+/// * `metadata` - `key=value` pairs read from the input's comment lines
+///
+/// # Returns
+/// * `Option<String>` - Symbol with the exchange if known, `None` without a symbol
+fn metadata_title(metadata: &HashMap<String, String>) -> Option<String> {
+    let symbol = metadata.get("symbol")?;
+    
+    Some(match metadata.get("exchange") {
+        Some(exchange) => format!("{} ({})", symbol, exchange),
+        None => symbol.clone(),
+    })
+}
+
+/// Prints the histogram of returns as text bars, followed by their moments
+///
+/// # Arguments
+/// * `candles` - Candles to take the returns of
+/// * `bins` - Number of histogram bins
+/// * `locale` - Number conventions of the printed returns
+fn print_returns_histogram(candles: &[CandleStick], bins: usize, locale: &Locale) {
+    const BAR_WIDTH: usize = 40;
+    let percent = |value: f64| format!("{}%", locale.format_number(value * 100.0, 2));
+    let histogram = DataProcessor::returns_histogram(candles, bins);
+    let returns = DataProcessor::returns(candles);
+    
+    println!("Returns histogram ({} returns):", returns.len());
+    let max_count = histogram.iter().map(|(_, count)| *count).max().unwrap_or_default().max(1);
+    for (center, count) in &histogram {
+        println!("{:>10} | {:<width$} {}", percent(*center), "#".repeat(count * BAR_WIDTH / max_count), count, width = BAR_WIDTH);
+    }
+    
+    match stats_utils::moments(&returns) {
+        Some((mean, std_dev, skew)) => println!(
+            "Mean: {}, std dev: {}, skew: {}",
+            percent(mean),
+            percent(std_dev),
+            locale.format_number(skew, 2)
+        ),
+        None => println!("Need at least two candles for returns"),
+    }
+}
+
+/// Prints the mean return of each calendar group as text bars
+///
+/// Bars are drawn with `+` for positive and `-` for negative means, scaled
+/// to the largest absolute mean.
+///
+/// # Arguments
+/// * `candles` - Candles to take the returns of
+/// * `by` - Calendar grouping
+/// * `locale` - Number conventions of the printed returns
+fn print_seasonality(candles: &[CandleStick], by: SeasonBy, locale: &Locale) {
+    const BAR_WIDTH: usize = 40;
+    let groups = DataProcessor::seasonality(candles, by);
+    if groups.is_empty() {
+        println!("Need at least two candles for seasonality");
+        return;
+    }
+    
+    println!("Mean return by {}:", match by { SeasonBy::Dow => "day of week", SeasonBy::Month => "month" });
+    let max = groups.iter().map(|(_, mean)| mean.abs()).fold(0.0, f64::max);
+    for (name, mean) in &groups {
+        let length = if max > 0.0 { (mean.abs() / max * BAR_WIDTH as f64).round() as usize } else { 0 };
+        let bar = if *mean < 0.0 { "-" } else { "+" }.repeat(length);
+        println!("{:>4} | {:>8} {}", name, format!("{}%", locale.format_number(mean * 100.0, 2)), bar);
+    }
+}
+
+/// Prints the quality score of the input, with the points lost per check when verbose
+///
+/// # Arguments
+/// * `candles` - Candles as loaded, before resampling or filling
+/// * `verbose` - Whether to print the breakdown
+fn print_quality(candles: &[CandleStick], verbose: bool) {
+    println!("Quality score: {:.1}/100", DataProcessor::quality_score(candles));
+    
+    if verbose {
+        let report = DataProcessor::quality_report(candles);
+        let intervals = report.candles.saturating_sub(1);
+        let checks = [
+            ("gaps", report.gaps, intervals, "intervals"),
+            ("duplicate timestamps", report.duplicates, intervals, "intervals"),
+            ("non-finite values", report.non_finite, report.candles, "candles"),
+            ("OHLC violations", report.ohlc_violations, report.candles, "candles"),
+        ];
+        for ((name, count, total, unit), (penalty, weight)) in checks.iter().zip(report.penalties().iter().zip(QualityReport::WEIGHTS)) {
+            println!("  {}: {} of {} {}, -{:.1} of {} points", name, count, total, unit, penalty, weight);
+        }
+    }
+}
+
+/// Prints the longest up and down streaks with the date they ended
+///
+/// # Arguments
+/// * `candles` - Candles to scan
+/// * `locale` - Date conventions of the printed end dates
+fn print_streaks(candles: &[CandleStick], locale: &Locale) {
+    let streaks = DataProcessor::streaks(candles);
+    let longest = |sign: i32| {
+        streaks
+            .iter()
+            .filter(|(_, length)| length * sign > 0)
+            .max_by_key(|(index, length)| (length.abs(), std::cmp::Reverse(*index)))
+            .map(|&(index, length)| format!("{} candles ending {}", length.abs(), locale.format_date(&candles[index].timestamp)))
+            .unwrap_or_else(|| "none".to_string())
+    };
+    
+    println!("Longest up streak: {}", longest(1));
+    println!("Longest down streak: {}", longest(-1));
+}
+
+/// Prints the gap count, fill rates and average time to fill
+///
+/// # Arguments
+/// * `candles` - Candles to scan
+/// * `locale` - Number conventions of the printed rates
+fn print_gap_fill_stats(candles: &[CandleStick], locale: &Locale) {
+    let stats = DataProcessor::gap_fill_stats(candles);
+    let percent = |rate: Option<f64>| locale.format_number(rate.unwrap_or(0.0) * 100.0, 1) + "%";
+    
+    println!("Gaps: {}, filled: {}", stats.gaps, stats.filled);
+    println!("Filled within the same candle: {}", percent(stats.same_candle_rate()));
+    println!("Filled by the next candle: {}", percent(stats.next_candle_rate()));
+    match stats.mean_time_to_fill {
+        Some(duration) => {
+            let hours = duration.num_seconds() as f64 / 3600.0;
+            match hours >= 24.0 {
+                true => println!("Average time to fill: {} days", locale.format_number(hours / 24.0, 1)),
+                false => println!("Average time to fill: {} hours", locale.format_number(hours, 1)),
+            }
+        }
+        None => println!("Average time to fill: no gap was filled"),
+    }
+}
+
+/// Runs the signal detectors enabled on the command line
+///
+/// # Arguments
+/// * `args` - Parsed command line arguments
+/// * `candles` - Candles to scan
+///
+/// # Returns
+/// * `BTreeMap<usize, Vec<String>>` - Signal names by candle index, in the
+///   order the detectors ran
+fn collect_signals(args: &Args, candles: &[CandleStick]) -> BTreeMap<usize, Vec<String>> {
+    let mut signals: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    
+    if let Some(periods) = &args.ma_cross {
+        for signal in patterns::crossovers(&indicators::sma(candles, periods[0]), &indicators::sma(candles, periods[1])) {
+            let name = match signal.side {
+                SignalSide::Entry => "ma_cross_entry",
+                SignalSide::Exit => "ma_cross_exit",
+            };
+            signals.entry(signal.index).or_default().push(name.to_string());
+        }
+    }
+    if args.fractals {
+        for (index, kind) in patterns::fractals(candles) {
+            let name = match kind {
+                FractalKind::Up => "fractal_up",
+                FractalKind::Down => "fractal_down",
+            };
+            signals.entry(index).or_default().push(name.to_string());
+        }
+    }
+    
+    signals
+}
+
+/// Prints the current and peak resident memory to stderr
+///
+/// # Arguments
+/// * `stage` - Step of the run that just finished, e.g. `load`
+fn print_memory(stage: &str) {
+    let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    match memory_utils::current() {
+        Some(usage) => eprintln!("Memory after {}: peak {:.1} MiB, resident {:.1} MiB", stage, mib(usage.peak), mib(usage.resident)),
+        None => eprintln!("Memory after {}: not available on this platform", stage),
+    }
+}
+
+/// Collects the indicator overlays requested on the command line
+///
+/// # Arguments
+/// * `args` - Parsed command line arguments
+///
+/// # Returns
+/// * `Vec<Overlay>` - Overlays in drawing order
+fn overlays(args: &Args) -> Vec<Overlay> {
+    let mut overlays = Vec::new();
+    
+    if args.wick_ratios {
+        overlays.push(Overlay::WickRatios);
+    }
+    if args.body_midpoints {
+        overlays.push(Overlay::BodyMidpoints);
+    }
+    if let Some(period) = args.donchian {
+        overlays.push(Overlay::Donchian(period));
+    }
+    if args.fractals {
+        overlays.push(Overlay::Fractals);
+    }
+    if let Some(factor) = args.range_expansion {
+        overlays.push(Overlay::RangeExpansion { period: args.range_expansion_period, factor });
+    }
+    if let Some(lookback) = args.trend_channel {
+        overlays.push(Overlay::TrendChannel(lookback));
+    }
+    if let Some(bins) = args.volume_profile {
+        overlays.push(Overlay::VolumeProfile(bins));
+    }
+    if let Some(period) = args.twap {
+        overlays.push(Overlay::Twap(period));
+    }
+    if let Some(period) = args.momentum {
+        overlays.push(Overlay::Momentum(period));
+    }
+    if let Some(period) = args.roc {
+        overlays.push(Overlay::RateOfChange(period));
+    }
+    if let Some(period) = args.percentile_rank {
+        overlays.push(Overlay::PercentileRank(period));
+    }
+    if let Some(period) = args.pct_from_high {
+        overlays.push(Overlay::PctFromHigh(period));
+    }
+    if let Some(period) = args.ulcer {
+        overlays.push(Overlay::UlcerIndex(period));
+    }
+    overlays.extend(args.sma.iter().copied().map(Overlay::Sma));
+    overlays.extend(args.ema.iter().copied().map(Overlay::Ema));
+    overlays.extend(args.mtf_sma.iter().cloned().map(Overlay::TimeframeSma));
+    if args.session_pivots {
+        overlays.push(Overlay::SessionPivots);
+    }
+    if let Some(period) = args.beta {
+        overlays.push(Overlay::Beta(period));
+    }
+    
+    overlays
+}
+
+/// Checks the OHLC invariants of loaded rows
+///
+/// # Arguments
+/// * `data` - Rows as loaded
+/// * `strict` - Fail on a broken row instead of printing a warning to stderr
+/// * `source` - File, symbol or feed the rows came from, named in the message
+///
+/// # Returns
+/// * `Result<()>` - Ok if every row is valid or `strict` is off
+///
+/// # Errors
+/// * Returns `AppError::DataProcessing` from `DataProcessor::check_ohlc` with `strict`
+fn check_candles(data: &[HistoricalData], strict: bool, source: &str) -> Result<()> {
+    match DataProcessor::check_ohlc(data) {
+        Err(err) if strict => Err(err.context(format!("Invalid input: {}", source))),
+        Err(err) => {
+            // Printed rather than logged so it shows without RUST_LOG
+            eprintln!("Warning: {}: {:#} (pass --strict to fail)", source, err);
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+/// Plots two CSV files as side-by-side panels
+///
+/// Both panels are written as one two-panel `candlestick.png`, or shown in
+/// one window with `--window`.
+///
+/// # Arguments
+/// * `args` - Parsed command line arguments
+/// * `plotter` - Plotter used for both panels
+/// * `file_a` - CSV file of the left panel
+/// * `file_b` - CSV file of the right panel
+///
+/// # Returns
+/// * `Result<()>` - Ok if both panels were created
+fn run_compare(args: &Args, plotter: &mut Plotter, file_a: &str, file_b: &str) -> Result<()> {
+    let mut panels = Vec::new();
+    
+    for file in [file_a, file_b] {
+        let data = DataProcessor::new().load_csv_data(file)
+            .context(format!("Failed to load CSV data: {}", file))?;
+        check_candles(&data, args.strict, file)?;
+        panels.push(data);
+    }
+    
+    let comparison = plotter.comparison_model(&panels[0], &panels[1], args.shared_scale, args.link_axes)?;
+    
+    if let Some(path) = &args.export_render_model {
+        comparison.write_json(path).context("Failed to export render model")?;
+    }
+    
+    if args.window {
+        let title = plotter.config().title.clone().unwrap_or(format!("{} vs {}", file_a, file_b));
+        return plotter.show_comparison_window(comparison, &title);
+    }
+    
+    plotter.create_comparison_plot(&comparison, &args.output_dir)
+        .context("Failed to create comparison plot")
+}
+
+/// Plots every symbol column of a wide CSV file as its own series
+///
+/// # Arguments
+/// * `args` - Parsed command line arguments
+/// * `plotter` - Plotter used for all series
+///
+/// # Returns
+/// * `Result<()>` - Ok if every series was plotted
+fn run_wide(args: &Args, plotter: &mut Plotter) -> Result<()> {
+    let symbols: Vec<&str> = args.symbols.iter().map(String::as_str).collect();
+    let series = DataProcessor::load_wide(args.csv_file.as_ref(), &symbols)
+        .context("Failed to load wide CSV data")?;
+    
+    let data_map: HashMap<String, Vec<HistoricalData>> = series
+        .into_iter()
+        .map(|(symbol, candles)| {
+            let data: Vec<HistoricalData> = candles.iter().map(HistoricalData::from).collect();
+            check_candles(&data, args.strict, &symbol)?;
+            let data = match (args.first, args.last) {
+                (Some(n), _) => DataProcessor::head(&data, n),
+                (_, Some(n)) => DataProcessor::tail(&data, n),
+                _ => data,
+            };
+            Ok((symbol, data))
+        })
+        .collect::<Result<_>>()?;
+    
+    plotter.create_candlestick_plot(&data_map, &args.output_dir)
+        .context("Failed to create candlestick plots")
+}
+
+/// Plots candles received from a websocket feed, refreshing after every message
+///
+/// With `--window` the feed is read on a background thread and every refresh
+/// is shown in the chart window; otherwise the exports and the PNG are
+/// rewritten.
+///
+/// # Arguments
+/// * `args` - Parsed command line arguments
+/// * `plotter` - Plotter used for the refreshes
+/// * `url` - Websocket URL of the feed
+///
+/// # Returns
+/// * `Result<()>` - Only returns once the feed cannot be reconnected, or
+///   the window was closed
+#[cfg(feature = "stream")]
+fn run_stream(args: &Args, plotter: &mut Plotter, url: &str) -> Result<()> {
+    let config = stream::StreamConfig {
+        url: url.to_string(),
+        max_candles: args.max_candles,
+        max_reconnects: None,
+    };
+    
+    if args.window {
+        let (sender, updates) = std::sync::mpsc::channel();
+        let feed = Plotter::with_config(plotter.config().clone());
+        let (strict, export) = (args.strict, args.export_render_model.clone());
+        std::thread::spawn(move || {
+            let result = stream::run(&config, &mut DataProcessor::new(), |data| {
+                check_candles(data, strict, &config.url)?;
+                let model = feed.render_model(data)?;
+                if let Some(path) = &export {
+                    model.write_json(path)?;
+                }
+                sender.send(model).map_err(|_| anyhow::anyhow!("Chart window was closed"))
+            });
+            if let Err(err) = result {
+                log::error!("Websocket stream failed: {:#}", err);
+            }
+        });
+        
+        let title = plotter.config().title.clone().unwrap_or(url.to_string());
+        return plotter.show_live_window(updates, &title);
+    }
+    
+    stream::run(&config, &mut DataProcessor::new(), |data| {
+        check_candles(data, args.strict, url)?;
+        if let Some(path) = &args.export_render_model {
+            plotter.render_model(data)?.write_json(path)?;
+        }
+        let data_map = HashMap::from([(DEFAULT_SERIES.to_string(), data.to_vec())]);
+        plotter.create_candlestick_plot(&data_map, &args.output_dir)
+    })
+    .context("Websocket stream failed")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use assert_cmd::Command;
     use predicates::str::contains;
-    use tempfile::TempDir;
     
     /// Test the main function with valid input
     #[test]
@@ -129,4 +1944,124 @@ mod tests {
            .stdout(contains("HELLO@WORLD#123"));
         Ok(())
     }
+    
+    /// Test that --window without a display fails with a hint instead of a panic
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_main_window_without_display() -> Result<()> {
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("window")
+           .arg("--window")
+           .env_remove("DISPLAY")
+           .env_remove("WAYLAND_DISPLAY")
+           .assert()
+           .code(5)
+           .stderr(contains("Plotting error: Could not open the chart window"))
+           .stderr(contains("--export-render-model"));
+        Ok(())
+    }
+    
+    /// Test JSON error output keeps the exit code of the error kind
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_main_error_format_json() -> Result<()> {
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        let output = cmd.arg("window")
+           .arg("--window")
+           .arg("--error-format")
+           .arg("json")
+           .env_remove("DISPLAY")
+           .env_remove("WAYLAND_DISPLAY")
+           .output()?;
+        
+        assert_eq!(output.status.code(), Some(5));
+        let report: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+        assert_eq!(report["error_type"], "plotting");
+        assert!(report["message"].as_str().unwrap_or_default().contains("Could not open the chart window"));
+        Ok(())
+    }
+    
+    /// Test that config and usage errors honor --error-format
+    #[test]
+    fn test_main_error_format_before_parsing() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let config_path = temp_dir.path().join("broken.toml");
+        std::fs::write(&config_path, "not = [valid")?;
+        
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        let output = cmd.arg("config")
+           .arg("--config")
+           .arg(&config_path)
+           .arg("--error-format=json")
+           .output()?;
+        let report: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+        assert!(report["message"].as_str().unwrap_or_default().contains("Invalid TOML"));
+        
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        let output = cmd.arg("usage")
+           .arg("--no-such-option")
+           .arg("--error-format")
+           .arg("json")
+           .output()?;
+        assert_eq!(output.status.code(), Some(2));
+        let report: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+        assert_eq!(report["error_type"], "usage");
+        
+        let cli: Vec<OsString> = ["app", "--error-format", "json", "--error-format=bogus"].map(OsString::from).to_vec();
+        assert_eq!(requested_error_format(&cli), ErrorFormat::Json);
+        assert_eq!(requested_error_format(&cli[..1]), ErrorFormat::Text);
+        Ok(())
+    }
+    
+    /// Test exit codes of error kinds and the documenting help text
+    #[test]
+    fn test_error_kind_exit_codes() -> Result<()> {
+        let plotting = anyhow::Error::from(AppError::Plotting("no window".to_string())).context("Failed to plot");
+        assert_eq!(error_kind(&plotting), ("plotting", 5));
+        
+        let io = anyhow::Error::from(io::Error::other("disk full")).context("Failed to write");
+        assert_eq!(error_kind(&io), ("io", 6));
+        
+        let unreadable = csv::Reader::from_path("no_such_dir/events.csv").map(|_| ()).unwrap_err();
+        assert_eq!(error_kind(&anyhow::Error::from(unreadable).context("Failed to load events")), ("io", 6));
+        let malformed = csv::ReaderBuilder::new().from_reader(&b"a,b\n1\n"[..]).records().find_map(Result::err);
+        let malformed = malformed.ok_or_else(|| anyhow::anyhow!("expected a CSV error"))?;
+        assert_eq!(error_kind(&anyhow::Error::from(malformed)), ("csv", 3));
+        assert_eq!(error_kind(&anyhow::anyhow!("unknown")), ("other", 1));
+        
+        let temp_dir = tempfile::TempDir::new()?;
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("events")
+           .arg("--events")
+           .arg(temp_dir.path().join("missing.csv"))
+           .arg("--output-dir")
+           .arg(temp_dir.path().join("out"))
+           .assert()
+           .code(6);
+        
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("--help")
+           .assert()
+           .success()
+           .stdout(contains("Exit codes:"))
+           .stdout(contains("4  Data processing error"))
+           .stdout(contains("6  I/O error"));
+        Ok(())
+    }
+    
+    /// Test interactive mode reading commands from stdin
+    #[test]
+    fn test_main_interactive() -> Result<()> {
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("repl")
+           .arg("--csv-file")
+           .arg("non_existent_file.csv")
+           .arg("--interactive")
+           .write_stdin("sma 2\nnope\nquit\n")
+           .assert()
+           .success()
+           .stdout(contains("SMA(2): last 107.00"))
+           .stdout(contains("Unknown command: nope"));
+        Ok(())
+    }
 }
@@ -0,0 +1,138 @@
+//! Chart patterns detected in candlestick series
+//!
+//! Unlike indicators, patterns do not produce a value per candle; they return
+//! the indices of the candles where a pattern occurs.
+
+use crate::data_processor::CandleStick;
+
+/// Number of candles on each side a fractal must exceed
+const FRACTAL_SPAN: usize = 2;
+
+/// Direction of a Bill Williams fractal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FractalKind {
+    /// High strictly above the highs of the two candles on each side
+    Up,
+    /// Low strictly below the lows of the two candles on each side
+    Down,
+}
+
+/// Finds Bill Williams fractals
+///
+/// The first and last two candles lack neighbours on one side and are never
+/// fractals. A candle can be an up and a down fractal at once.
+///
+/// # Arguments
+/// * `candles` - Candlestick data
+///
+/// # Returns
+/// * `Vec<(usize, FractalKind)>` - Candle index and kind, in index order
+pub fn fractals(candles: &[CandleStick]) -> Vec<(usize, FractalKind)> {
+    let mut found = Vec::new();
+    
+    for (offset, window) in candles.windows(2 * FRACTAL_SPAN + 1).enumerate() {
+        let center = &window[FRACTAL_SPAN];
+        let neighbours = || window.iter().enumerate().filter(|(i, _)| *i != FRACTAL_SPAN).map(|(_, c)| c);
+        
+        if neighbours().all(|c| center.high > c.high) {
+            found.push((offset + FRACTAL_SPAN, FractalKind::Up));
+        }
+        if neighbours().all(|c| center.low < c.low) {
+            found.push((offset + FRACTAL_SPAN, FractalKind::Down));
+        }
+    }
+    
+    found
+}
+
+/// Whether a signal opens or closes a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalSide {
+    /// Open a long position at the candle's close
+    Entry,
+    /// Close the open position at the candle's close
+    Exit,
+}
+
+/// Trading signal raised at a candle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signal {
+    /// Index of the candle that raised the signal
+    pub index: usize,
+    pub side: SignalSide,
+}
+
+/// Finds the candles where a fast series crosses a slow one
+///
+/// Crossing above gives an entry, crossing below an exit. Candles where
+/// either series is undefined break the comparison, so a crossing needs both
+/// series defined on two consecutive candles.
+///
+/// # Arguments
+/// * `fast` - Faster series, e.g. a short moving average
+/// * `slow` - Slower series aligned with `fast`
+///
+/// # Returns
+/// * `Vec<Signal>` - Signals in index order
+pub fn crossovers(fast: &[Option<f64>], slow: &[Option<f64>]) -> Vec<Signal> {
+    let spread: Vec<Option<f64>> = fast.iter().zip(slow).map(|(f, s)| Some((*f)? - (*s)?)).collect();
+    
+    spread
+        .windows(2)
+        .enumerate()
+        .filter_map(|(offset, pair)| match (pair[0]?, pair[1]?) {
+            (before, after) if before <= 0.0 && after > 0.0 => Some(SignalSide::Entry),
+            (before, after) if before >= 0.0 && after < 0.0 => Some(SignalSide::Exit),
+            _ => None,
+        }.map(|side| Signal { index: offset + 1, side }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Builds candles from (high, low) pairs
+    fn candles(ranges: &[(f64, f64)]) -> Vec<CandleStick> {
+        ranges
+            .iter()
+            .map(|&(high, low)| CandleStick { high, low, open: low, close: high, ..Default::default() })
+            .collect()
+    }
+    
+    /// Test up and down fractals, ties and boundaries
+    #[test]
+    fn test_fractals() {
+        let series = candles(&[
+            (10.0, 5.0),
+            (11.0, 6.0),
+            (15.0, 7.0),
+            (12.0, 6.0),
+            (11.0, 2.0),
+            (12.0, 4.0),
+            (13.0, 5.0),
+            (14.0, 6.0),
+        ]);
+        
+        assert_eq!(fractals(&series), vec![(2, FractalKind::Up), (4, FractalKind::Down)]);
+        
+        let tie = candles(&[(1.0, 0.0), (1.0, 0.0), (2.0, -1.0), (2.0, 0.0), (1.0, 0.0)]);
+        assert_eq!(fractals(&tie), vec![(2, FractalKind::Down)]);
+        
+        assert!(fractals(&series[..4]).is_empty());
+        assert!(fractals(&[]).is_empty());
+    }
+    
+    /// Test crossings in both directions, touches and undefined values
+    #[test]
+    fn test_crossovers() {
+        let fast = [None, Some(1.0), Some(3.0), Some(3.0), Some(1.0), Some(2.0), Some(4.0)];
+        let slow = [Some(2.0), Some(2.0), Some(2.0), Some(3.0), Some(2.0), Some(2.0), None];
+        
+        assert_eq!(
+            crossovers(&fast, &slow),
+            vec![Signal { index: 2, side: SignalSide::Entry }, Signal { index: 4, side: SignalSide::Exit }]
+        );
+        assert!(crossovers(&[], &[]).is_empty());
+    }
+}
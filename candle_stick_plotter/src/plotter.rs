@@ -1,144 +1,3053 @@
 //! Plotting module for creating candlestick charts
 
 use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Command, Stdio};
 // use std::path::Path;
 //org line from prompt
 // use egui_plot::{Plot, PlotPoints, Line, BarChart, Bar};
 use egui_plot::{PlotPoints  };
+use eframe::egui::Color32;
 //org line from prompt
 // use anyhow::{Result, Context};
 use anyhow::{Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 
 
-use crate::data_processor::{CandleStick, HistoricalData};
+use crate::AppError;
+use crate::data_processor::{AggSpec, CandleStick, DataProcessor, Event, HistoricalData, LabelTime};
+use crate::downsample::Thinned;
+use crate::indicators::{self, Pivots};
+use crate::layout;
+use crate::patterns::{self, FractalKind};
+use crate::raster::{self, PngOptions};
+use crate::render_model::{
+    AxisRange, CandleBucket, CandleRect, ColorStop, Colorbar, ComparisonModel, Layer, LineStyle, MarkerShape, Outline, Panel,
+    PriceTag, RenderEvent, RenderBand, RenderFill, RenderLine, RenderMarker, RenderModel, TimeIndex, VolumeBar, Watermark, WickSegment,
+};
+use crate::utils::{color_utils, date_utils, file_utils, stats_utils};
+use crate::utils::date_utils::{BusinessCalendar, SessionTable, TimestampFormat};
+use crate::utils::color_utils::Palette;
+use crate::utils::locale_utils::Locale;
+use crate::window::{ChartWindow, ComparisonWindow};
+
+/// Name of a series without a symbol, plotted as `candlestick.png`
+pub const DEFAULT_SERIES: &str = "historical_data";
+
+/// Share of each candle slot left as whitespace beside the body by default
+pub const DEFAULT_SPACING: f64 = 0.2;
+
+/// Layer names accepted by `Plotter::layer_order`, with aliases for the
+/// indicator lines that all share one layer
+const LAYER_NAMES: [(&str, Layer); 11] = [
+    ("bands", Layer::Bands),
+    ("sessions", Layer::Bands),
+    ("grid", Layer::Grid),
+    ("fills", Layer::Fills),
+    ("candles", Layer::Candles),
+    ("lines", Layer::Lines),
+    ("sma", Layer::Lines),
+    ("ema", Layer::Lines),
+    ("events", Layer::Events),
+    ("markers", Layer::Markers),
+    ("signals", Layer::Markers),
+];
+
+/// Levels of the guides drawn with the percentile rank
+const PERCENTILE_GUIDES: [f64; 2] = [20.0, 80.0];
+
+/// Fraction of the price span added above and below the y-axis range
+const Y_PADDING: f64 = 0.05;
+
+/// Indicator series drawn together with the candles
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlay {
+    /// Upper and lower wick length as a fraction of the range, in the lower panel
+    WickRatios,
+    /// Midpoint of each candle body on the price panel
+    BodyMidpoints,
+    /// Donchian channel over the given period on the price panel
+    Donchian(usize),
+    /// Lines fitted through the swing highs and lows among the given number of
+    /// trailing candles, extended to the last candle
+    TrendChannel(usize),
+    /// Markers at Bill Williams fractals above highs and below lows
+    Fractals,
+    /// Markers above candles whose true range exceeds `factor` times the
+    /// average of the prior `period` candles
+    RangeExpansion { period: usize, factor: f64 },
+    /// Horizontal volume-by-price histogram with the given number of levels
+    /// on the right edge, with the point of control marked
+    VolumeProfile(usize),
+    /// Time-weighted average of the typical price over the given period on the price panel
+    Twap(usize),
+    /// Close minus the close the given number of candles earlier, in the lower panel
+    Momentum(usize),
+    /// Percentage change against the close the given number of candles earlier,
+    /// in the lower panel
+    RateOfChange(usize),
+    /// Percentile rank of the close within the given number of candles, in the
+    /// lower panel with guides at 20 and 80
+    PercentileRank(usize),
+    /// Percentage of the close below the highest high of the given period, or of
+    /// all candles for 0, in the lower panel
+    PctFromHigh(usize),
+    /// Ulcer Index over the given period, the RMS drawdown in percent, in the lower panel
+    UlcerIndex(usize),
+    /// Relative strength index over the given period, in the lower panel
+    Rsi(usize),
+    /// Average true range over the given period, in the lower panel
+    Atr(usize),
+    /// MACD and signal lines from the given EMA periods, in the lower panel
+    Macd { fast: usize, slow: usize, signal: usize },
+    /// Simple moving average of the close on the price panel
+    Sma(MovingAverage),
+    /// Exponential moving average of the close on the price panel
+    Ema(MovingAverage),
+    /// Close SMA of candles resampled to a higher timeframe, drawn as steps
+    /// over the candles of each bucket
+    TimeframeSma(TimeframeAverage),
+    /// Rolling beta of the returns on the benchmark's over the given period,
+    /// in the lower panel; draws nothing without a benchmark
+    Beta(usize),
+    /// Floor pivots of each UTC day from the previous day's HLC, as steps on
+    /// the price panel
+    SessionPivots,
+}
+
+/// Period and line styling of a moving average overlay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovingAverage {
+    pub period: usize,
+    /// Stroke color, `None` to take the next palette color
+    pub color: Option<Color32>,
+    pub style: LineStyle,
+}
+
+impl MovingAverage {
+    /// Parses `PERIOD[:COLOR[:STYLE]]` such as `20:#ff0000:dashed`
+    ///
+    /// The color may be left empty to keep the automatic one, e.g. `20::dotted`.
+    ///
+    /// # Arguments
+    /// * `text` - Period with optional hex color and line style
+    ///
+    /// # Returns
+    /// * `Result<MovingAverage>` - Parsed overlay settings
+    ///
+    /// # Errors
+    /// * Returns error if the period is not a positive integer, the color is
+    ///   not a hex color, the style is unknown or there are extra fields
+    pub fn parse(text: &str) -> Result<MovingAverage> {
+        let mut fields = text.split(':').map(str::trim);
+        let period = fields
+            .next()
+            .and_then(|period| period.parse::<usize>().ok())
+            .filter(|period| *period > 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid moving average '{}': expected PERIOD[:COLOR[:STYLE]]", text))?;
+        let color = match fields.next() {
+            Some(color) if !color.is_empty() => Some(color_utils::parse_hex(color)?),
+            _ => None,
+        };
+        let style = match fields.next() {
+            Some(style) => LineStyle::parse(style)?,
+            None => LineStyle::Solid,
+        };
+        if fields.next().is_some() {
+            return Err(anyhow::anyhow!("Invalid moving average '{}': expected PERIOD[:COLOR[:STYLE]]", text));
+        }
+        
+        Ok(MovingAverage { period, color, style })
+    }
+}
+
+/// Simple moving average computed on a higher timeframe
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeframeAverage {
+    /// Timeframe as given, e.g. `weekly` or `4h`, used in the legend
+    pub timeframe: String,
+    pub interval: Duration,
+    pub period: usize,
+}
+
+impl TimeframeAverage {
+    /// Parses `TIMEFRAME:PERIOD` such as `weekly:20` or `4h:50`
+    ///
+    /// The timeframe is `hourly`, `daily`, `weekly` or an interval such as `2d`.
+    ///
+    /// # Arguments
+    /// * `text` - Timeframe and period separated by a colon
+    ///
+    /// # Returns
+    /// * `Result<TimeframeAverage>` - Parsed overlay settings
+    ///
+    /// # Errors
+    /// * Returns error if the timeframe is unknown or the period is not a
+    ///   positive integer
+    pub fn parse(text: &str) -> Result<TimeframeAverage> {
+        let invalid = || anyhow::anyhow!("Invalid timeframe average '{}': expected TIMEFRAME:PERIOD, e.g. weekly:20", text);
+        let (timeframe, period) = text.split_once(':').ok_or_else(invalid)?;
+        let timeframe = timeframe.trim();
+        let interval = match timeframe.to_ascii_lowercase().as_str() {
+            "hourly" => Duration::hours(1),
+            "daily" => Duration::days(1),
+            "weekly" => Duration::weeks(1),
+            _ => date_utils::parse_interval(timeframe)?,
+        };
+        let period = period.trim().parse::<usize>().ok().filter(|period| *period > 0).ok_or_else(invalid)?;
+        
+        Ok(TimeframeAverage { timeframe: timeframe.to_string(), interval, period })
+    }
+}
+
+/// Kind of chart drawn from the price data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ChartType {
+    /// Candle bodies and wicks
+    #[default]
+    Candlestick,
+    /// Close line with the area to a baseline filled, up color above and down color below
+    Baseline,
+}
+
+/// Renderer used for `--window`
+///
+/// `Egui` opens an interactive native window but needs a display and a GPU
+/// with OpenGL. `SoftwarePreview` rasterizes on the CPU and works anywhere,
+/// but gives a static image without texts, tooltips or zoom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Backend {
+    /// Native egui window with zoom, pan and tooltips
+    #[default]
+    Egui,
+    /// PNG image from the software renderer, opened in the default viewer
+    SoftwarePreview,
+}
+
+/// Rotation of the x-axis labels in exports
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LabelRotation {
+    /// Horizontal unless neighbouring labels would collide, then -45° or -90°
+    #[default]
+    Auto,
+    /// Fixed clockwise rotation in degrees
+    Degrees(f32),
+}
+
+impl LabelRotation {
+    /// Parses `auto` or a number of degrees such as `-45`
+    ///
+    /// # Arguments
+    /// * `text` - `auto` or a finite angle
+    ///
+    /// # Returns
+    /// * `Result<LabelRotation>` - Parsed rotation
+    ///
+    /// # Errors
+    /// * Returns error if the text is neither `auto` nor a finite number
+    pub fn parse(text: &str) -> Result<Self> {
+        if text.eq_ignore_ascii_case("auto") {
+            return Ok(LabelRotation::Auto);
+        }
+        match text.trim().parse::<f32>() {
+            Ok(degrees) if degrees.is_finite() => Ok(LabelRotation::Degrees(degrees)),
+            _ => Err(anyhow::anyhow!("Invalid label rotation '{}': expected degrees or 'auto'", text)),
+        }
+    }
+    
+    /// Fixed rotation in degrees, `None` for automatic rotation
+    pub fn degrees(self) -> Option<f32> {
+        match self {
+            LabelRotation::Auto => None,
+            LabelRotation::Degrees(degrees) => Some(degrees),
+        }
+    }
+}
+
+/// Spacing of horizontal price gridlines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceGrid {
+    /// Nice step of 1, 2 or 5 times a power of ten for about `AUTO_GRID_LINES` lines
+    Auto,
+    /// Fixed step in price units
+    Step(f64),
+}
+
+impl PriceGrid {
+    /// Parses `auto` or a positive step such as `5`
+    ///
+    /// # Arguments
+    /// * `text` - `auto` or a positive finite number
+    ///
+    /// # Returns
+    /// * `Result<PriceGrid>` - Parsed grid spacing
+    ///
+    /// # Errors
+    /// * Returns error if the text is neither `auto` nor a positive number
+    pub fn parse(text: &str) -> Result<Self> {
+        if text.eq_ignore_ascii_case("auto") {
+            return Ok(PriceGrid::Auto);
+        }
+        match text.trim().parse::<f64>() {
+            Ok(step) if step.is_finite() && step > 0.0 => Ok(PriceGrid::Step(step)),
+            _ => Err(anyhow::anyhow!("Invalid price grid step '{}': expected a positive number or 'auto'", text)),
+        }
+    }
+    
+    /// Lists the multiples of the step inside a price range
+    ///
+    /// # Arguments
+    /// * `range` - Price range of the panel
+    ///
+    /// # Returns
+    /// * `Vec<f64>` - Gridline prices in ascending order, empty when the
+    ///   range is empty or would need more than `MAX_GRID_LINES` lines
+    pub fn levels(self, range: &AxisRange) -> Vec<f64> {
+        let step = match self {
+            PriceGrid::Auto => stats_utils::nice_step(range.max - range.min, AUTO_GRID_LINES),
+            PriceGrid::Step(step) => Some(step),
+        };
+        let Some(step) = step else {
+            return Vec::new();
+        };
+        let (first, last) = ((range.min / step).ceil() as i64, (range.max / step).floor() as i64);
+        if last - first >= MAX_GRID_LINES as i64 {
+            log::warn!("Price grid step {} would draw more than {} lines, leaving the grid out", step, MAX_GRID_LINES);
+            return Vec::new();
+        }
+        (first..=last).map(|multiple| multiple as f64 * step).collect()
+    }
+}
+
+/// Volume level above which candles are emphasized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeHighlight {
+    /// Fixed volume threshold
+    Absolute(f64),
+    /// Percentile (0–100) of the plotted volumes
+    Percentile(f64),
+}
+
+/// Direction of a candle used for up/down coloring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    /// Body smaller than the neutral threshold
+    Neutral,
+}
+
+impl Direction {
+    /// Classifies a candle by its body size relative to its open
+    ///
+    /// # Arguments
+    /// * `open` - Opening price
+    /// * `close` - Closing price
+    /// * `neutral_threshold` - Body size in percent of the open below which a
+    ///   candle is neutral; 0 makes every candle up or down
+    ///
+    /// # Returns
+    /// * `Direction` - Neutral inside the band, otherwise up when the close is
+    ///   at or above the open
+    pub fn of(open: f64, close: f64, neutral_threshold: f64) -> Direction {
+        let body = ((close - open) / open).abs() * 100.0;
+        match (body < neutral_threshold, close >= open) {
+            (true, _) => Direction::Neutral,
+            (false, true) => Direction::Up,
+            (false, false) => Direction::Down,
+        }
+    }
+}
+
+/// Chart size in pixels when none is given
+pub const DEFAULT_SIZE: (u32, u32) = (1200, 800);
+
+/// Ratio of chart width to height such as `16:9`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aspect {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Aspect {
+    /// Parses a `W:H` ratio of positive integers
+    ///
+    /// # Arguments
+    /// * `text` - Ratio such as `16:9`
+    ///
+    /// # Returns
+    /// * `Result<Aspect>` - Parsed ratio
+    ///
+    /// # Errors
+    /// * Returns error if the text is not two positive integers separated by `:`
+    pub fn parse(text: &str) -> Result<Aspect> {
+        let parts = text.split_once(':').and_then(|(width, height)| {
+            Some((width.trim().parse::<u32>().ok()?, height.trim().parse::<u32>().ok()?))
+        });
+        
+        match parts {
+            Some((width, height)) if width > 0 && height > 0 => Ok(Aspect { width, height }),
+            _ => Err(anyhow::anyhow!("Invalid aspect ratio '{}': expected W:H, e.g. 16:9", text)),
+        }
+    }
+    
+    /// Resolves the chart size from optional explicit dimensions
+    ///
+    /// A single given dimension determines the other; with neither, the
+    /// default width does. Explicit values always win, so an aspect given
+    /// together with both dimensions is ignored with a warning.
+    ///
+    /// # Arguments
+    /// * `aspect` - Ratio to keep, `None` for no constraint
+    /// * `width` - Explicit width in pixels
+    /// * `height` - Explicit height in pixels
+    ///
+    /// # Returns
+    /// * `(u32, u32)` - Width and height in pixels, each at least 1
+    pub fn chart_size(aspect: Option<Aspect>, width: Option<u32>, height: Option<u32>) -> (u32, u32) {
+        let scale = |value: u32, to: u32, from: u32| ((value as f64 * to as f64 / from as f64).round() as u32).max(1);
+        
+        match (aspect, width, height) {
+            (Some(aspect), Some(width), Some(height)) => {
+                log::warn!(
+                    "Ignoring --aspect {}:{} because --width and --height are both given",
+                    aspect.width, aspect.height
+                );
+                (width, height)
+            }
+            (Some(aspect), Some(width), None) => (width, scale(width, aspect.height, aspect.width)),
+            (Some(aspect), None, Some(height)) => (scale(height, aspect.width, aspect.height), height),
+            (Some(aspect), None, None) => (DEFAULT_SIZE.0, scale(DEFAULT_SIZE.0, aspect.height, aspect.width)),
+            (None, width, height) => (width.unwrap_or(DEFAULT_SIZE.0), height.unwrap_or(DEFAULT_SIZE.1)),
+        }
+    }
+}
+
+/// Stroke width of the emphasis outline around highlighted candles
+const HIGHLIGHT_OUTLINE_WIDTH: f32 = 2.5;
+
+/// Stroke width of hollow candle bodies
+const HOLLOW_OUTLINE_WIDTH: f32 = 1.5;
+
+/// Opacity of the areas of a baseline chart
+const BASELINE_FILL_OPACITY: f32 = 0.4;
+
+/// Opacity factor applied to the colors of an incomplete last candle
+const INCOMPLETE_OPACITY: f32 = 0.4;
+
+/// Opacity factor applied to the colors of candles inserted by `--fill-missing`
+const SYNTHETIC_OPACITY: f32 = 0.3;
+
+/// Longest volume profile bar as a fraction of the x-axis span
+const VOLUME_PROFILE_WIDTH: f64 = 0.25;
+
+/// Opacity of volume profile bars other than the point of control
+const VOLUME_PROFILE_OPACITY: f32 = 0.35;
+
+/// Opacity of the bars in the volume panel
+const VOLUME_BAR_OPACITY: f32 = 0.6;
+
+/// Opacity of the benchmark area behind the candles
+const BENCHMARK_OPACITY: f32 = 0.15;
+
+/// Opacity of the session and non-trading day bands behind the candles
+const SESSION_OPACITY: f32 = 0.12;
+
+/// Most intervals an automatic price grid divides the price range into
+const AUTO_GRID_LINES: usize = 8;
+
+/// Most gridlines drawn before a price grid is left out as too dense
+const MAX_GRID_LINES: usize = 200;
+
+/// Percentile of absolute log returns mapped to the ends of the return
+/// colormap; larger returns are clamped
+const RETURN_CLAMP_PERCENTILE: f64 = 95.0;
+
+/// Number of samples of the return colorbar
+const COLORBAR_STOPS: usize = 9;
+
+/// Rendering options for candlestick plots
+#[derive(Debug, Clone)]
+pub struct PlotConfig {
+    /// Extra CSV column whose values select the candle colors
+    pub color_by: Option<String>,
+    
+    /// Explicit colors for category values, overriding the default palette
+    pub color_map: HashMap<String, Color32>,
+    
+    /// Opacity of candle body fills, 0.0 (transparent) to 1.0 (opaque)
+    pub candle_opacity: f32,
+    
+    /// Opacity of candle wicks, 0.0 (transparent) to 1.0 (opaque)
+    pub wick_opacity: f32,
+    
+    /// Share of each one unit wide candle slot left as whitespace, strictly
+    /// between 0 and 1; bodies and volume bars take the rest
+    pub spacing: f64,
+    
+    /// Number and date conventions for labels and exported text
+    pub locale: Locale,
+    
+    /// Indicator series to draw, in order
+    pub overlays: Vec<Overlay>,
+    
+    /// Emphasize candles whose volume exceeds this level
+    pub volume_highlight: Option<VolumeHighlight>,
+    
+    /// Color scheme for candles and indicator lines
+    pub palette: Palette,
+    
+    /// Kind of chart to draw
+    pub chart_type: ChartType,
+    
+    /// Price a baseline chart is filled to, the first close when `None`
+    pub baseline: Option<f64>,
+    
+    /// The last candle is still forming: it is drawn faded with a solid
+    /// outline and flagged `incomplete` in the render model
+    pub last_incomplete: bool,
+    
+    /// Dated annotations drawn as labeled vertical lines
+    pub events: Vec<Event>,
+    
+    /// Draw one volume bar per candle in a panel below the price chart
+    pub volume_panel: bool,
+    
+    /// Decimals of price axis labels
+    pub price_precision: usize,
+    
+    /// Decimals of prices in tooltips, independent of the axis
+    pub tooltip_precision: usize,
+    
+    /// Second series drawn as a faint area behind the candles, rescaled to
+    /// their price range
+    pub benchmark: Option<Vec<CandleStick>>,
+    
+    /// Time window `[start, end)` the render model is cropped to; indicators
+    /// are still computed over the whole series
+    pub zoom: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    
+    /// Chart width and height in pixels
+    pub size: (u32, u32),
+    
+    /// Bold text centered above the chart
+    pub title: Option<String>,
+    
+    /// Smaller text centered below the title
+    pub subtitle: Option<String>,
+    
+    /// Text at the bottom left, e.g. the data source
+    pub caption: Option<String>,
+    
+    /// Leave out candle bodies and wicks, drawing only indicators
+    pub hide_candles: bool,
+    
+    /// Scale the price axis to the visible indicator values on the price
+    /// panel instead of the candle range
+    pub scale_to_indicators: bool,
+    
+    /// Color candles by their log return through a diverging colormap from
+    /// the down to the up color, with a colorbar legend
+    pub color_by_return: bool,
+    
+    /// Title of the price axis, e.g. for ratio charts
+    pub y_label: Option<String>,
+    
+    /// Body size in percent of the open below which candles get the neutral
+    /// color instead of the up or down color
+    pub neutral_threshold: f64,
+    
+    /// Fill color of candles inside the neutral band
+    pub neutral_color: Color32,
+    
+    /// Fill color of doji candles, whose close equals their open; `None`
+    /// colors them like any other candle
+    pub doji_color: Option<Color32>,
+    
+    /// Nudge candle colors toward black or white until they reach
+    /// `color_utils::MIN_CONTRAST_RATIO` against the background
+    pub auto_contrast: bool,
+    
+    /// Text composited diagonally over the chart in exports
+    pub watermark: Option<Watermark>,
+    
+    /// Also draw the watermark in the native window, where it is left out by default
+    pub watermark_in_window: bool,
+    
+    /// Encoding of the PNG images written by `create_candlestick_plot`
+    pub png: PngOptions,
+    
+    /// Export on a transparent instead of a white background
+    pub transparent: bool,
+    
+    /// Rotation of exported x-axis labels
+    pub x_label_rotation: LabelRotation,
+    
+    /// Place candles by business days elapsed instead of one slot per
+    /// candle, leaving missing business days empty
+    pub business_days: Option<BusinessCalendar>,
+    
+    /// Place candles by calendar days elapsed and shade the weekends and
+    /// holidays of this calendar between them; ignored with `business_days`
+    pub nontrading: Option<BusinessCalendar>,
+    
+    /// Draw a dashed line across the chart at the last close, with a price
+    /// tag at the right edge, colored by the last candle's direction
+    pub last_price_line: bool,
+    
+    /// Free pixels kept around pattern markers and event labels, which widen
+    /// the price range or grow the canvas when they would be cut off
+    pub annotation_padding: f64,
+    
+    /// Shade the background behind the candles by the trading session of
+    /// each candle's hour of day
+    pub sessions: Option<SessionTable>,
+    
+    /// Input the data was loaded from, named by the placeholder of empty charts
+    pub data_source: Option<String>,
+    
+    /// Horizontal gridlines at multiples of a round price step instead of
+    /// the window's automatic grid
+    pub price_grid: Option<PriceGrid>,
+    
+    /// Draw order of the price panel layers from bottom to top, see
+    /// `Plotter::layer_order`
+    pub layers: Vec<Layer>,
+}
+
+impl Default for PlotConfig {
+    fn default() -> Self {
+        PlotConfig {
+            color_by: None,
+            color_map: HashMap::new(),
+            candle_opacity: 1.0,
+            spacing: DEFAULT_SPACING,
+            wick_opacity: 1.0,
+            locale: Locale::default(),
+            overlays: Vec::new(),
+            volume_highlight: None,
+            palette: Palette::default(),
+            chart_type: ChartType::default(),
+            baseline: None,
+            last_incomplete: false,
+            events: Vec::new(),
+            volume_panel: false,
+            price_precision: 2,
+            tooltip_precision: 4,
+            benchmark: None,
+            zoom: None,
+            size: DEFAULT_SIZE,
+            title: None,
+            subtitle: None,
+            caption: None,
+            hide_candles: false,
+            scale_to_indicators: false,
+            color_by_return: false,
+            watermark: None,
+            watermark_in_window: false,
+            transparent: false,
+            x_label_rotation: LabelRotation::Auto,
+            business_days: None,
+            nontrading: None,
+            last_price_line: false,
+            annotation_padding: 4.0,
+            sessions: None,
+            data_source: None,
+            price_grid: None,
+            layers: Layer::DEFAULT_ORDER.to_vec(),
+            neutral_threshold: 0.0,
+            neutral_color: color_utils::NEUTRAL_COLOR,
+            doji_color: None,
+            auto_contrast: false,
+            png: PngOptions::default(),
+            y_label: None,
+        }
+    }
+}
+
+/// Resolved colors for drawing a single candle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CandleStyle {
+    /// Body fill color
+    pub body: Color32,
+    
+    /// Wick (high/low line) color
+    pub wick: Color32,
+    
+    /// Emphasis border drawn around the body
+    pub outline: Option<Outline>,
+}
 
 /// Handles creation and display of financial plots
-pub struct Plotter;
+pub struct Plotter {
+    config: PlotConfig,
+}
 
 impl Plotter {
     /// Creates a new Plotter instance
     ///
     /// # Returns
     /// * `Plotter` - New instance
+    #[allow(dead_code)]
     pub fn new() -> Self {
-        Plotter
+        Self::with_config(PlotConfig::default())
+    }
+    
+    /// Creates a new Plotter instance with the given rendering options
+    ///
+    /// # Arguments
+    /// * `config` - Rendering options
+    ///
+    /// # Returns
+    /// * `Plotter` - New instance
+    pub fn with_config(mut config: PlotConfig) -> Self {
+        config.candle_opacity = Self::clamp_opacity("candle", config.candle_opacity);
+        config.wick_opacity = Self::clamp_opacity("wick", config.wick_opacity);
+        Plotter { config }
+    }
+    
+    /// Clamps an opacity into `0.0..=1.0`, warning when it was out of range
+    fn clamp_opacity(name: &str, opacity: f32) -> f32 {
+        let clamped = if opacity.is_nan() { 1.0 } else { opacity.clamp(0.0, 1.0) };
+        if clamped != opacity {
+            log::warn!("{} opacity {} is outside 0.0-1.0, using {}", name, opacity, clamped);
+        }
+        clamped
+    }
+    
+    /// Gets the rendering options
+    ///
+    /// # Returns
+    /// * `&PlotConfig` - Reference to the rendering options
+    pub fn config(&self) -> &PlotConfig {
+        &self.config
     }
     
-    /// Creates a candlestick plot from the provided data
+    /// Renders each series as a PNG image in the output directory
+    ///
+    /// The chart is drawn by the software renderer, so no display is needed.
+    /// Only a single series named `DEFAULT_SERIES` is saved as
+    /// `candlestick.png`; others, e.g. named after a CSV's symbol, as
+    /// `candlestick_<name>.png`. Empty series still produce an image showing
+    /// the chart placeholder.
     ///
     /// # Arguments
-    /// * `data_map` - HashMap containing financial data
-    /// * `output_dir` - Directory to save plot outputs
+    /// * `data_map` - HashMap of named series, each plotted in name order
+    /// * `output_dir` - Directory to save plot outputs, created if missing
     ///
     /// # Returns
-    /// * `Result<()>` - Ok if successful, Err otherwise
+    /// * `Result<()>` - Ok once every image was written
     ///
     /// # Errors
-    /// * Returns error if plotting fails
+    /// * Returns error if a timestamp cannot be parsed
+    /// * Returns `AppError::Plotting` if the directory or an image cannot be written
     pub fn create_candlestick_plot(
         &mut self, 
         data_map: &HashMap<String, Vec<HistoricalData>>,
         output_dir: &str
     ) -> Result<()> {
-        if let Some(data) = data_map.get("historical_data") {
-            // For now, we'll just log that we would create a plot
-            // In a real implementation, this would create the actual plot
-            log::info!("Creating candlestick plot for {} data points", data.len());
-            log::info!("Output directory: {}", output_dir);
+        let mut names: Vec<&String> = data_map.keys().collect();
+        names.sort();
+        
+        file_utils::ensure_directory_exists(output_dir)
+            .map_err(|err| AppError::Plotting(format!("Cannot create output directory {}: {:#}", output_dir, err)))?;
+        
+        for name in &names {
+            let data = &data_map[*name];
+            if data.is_empty() {
+                log::warn!("No data available for plotting '{}'", name);
+            }
             
-            // Simulate plot creation (would be actual plotting code in production)
-            self.simulate_plot_creation(data)?;
+            let file_name = if names.len() == 1 && name.as_str() == DEFAULT_SERIES {
+                "candlestick.png".to_string()
+            } else {
+                format!("candlestick_{}.png", name)
+            };
+            let path = Path::new(output_dir).join(file_name);
+            
+            let model = self.render_model(data)?;
+            raster::write_png(&model, &path, &self.config.png)
+                .map_err(|err| AppError::Plotting(format!("Cannot write {}: {:#}", path.display(), err)))?;
+            log::info!("Plotted {} candles of '{}' to {}", model.candles.len(), name, path.display());
         }
         
         Ok(())
     }
     
-    /// Simulates plot creation (placeholder for actual plotting logic)
+    /// Builds the two halves of a side by side comparison
+    ///
+    /// Each half is the single-chart render of its data at half the
+    /// configured width, so the comparison fills the configured size.
     ///
     /// # Arguments
-    /// * `data` - Historical data to plot
+    /// * `left` - Historical data of the left panel
+    /// * `right` - Historical data of the right panel
+    /// * `shared_scale` - Give both panels the same y-range
+    /// * `link_axes` - Give both panels the same x-range
     ///
     /// # Returns
-    /// * `Result<()>` - Always returns Ok for simulation
-    fn simulate_plot_creation(&self, data: &[HistoricalData]) -> Result<()> {
-        log::debug!("Simulating plot creation with {} data points", data.len());
+    /// * `Result<ComparisonModel>` - Both panels with aligned axes
+    ///
+    /// # Errors
+    /// * Returns error if a timestamp cannot be parsed
+    pub fn comparison_model(
+        &self,
+        left: &[HistoricalData],
+        right: &[HistoricalData],
+        shared_scale: bool,
+        link_axes: bool,
+    ) -> Result<ComparisonModel> {
+        let (width, height) = self.config.size;
+        let half = Plotter::with_config(PlotConfig { size: ((width / 2).max(1), height), ..self.config.clone() });
         
-        // This would be actual plotting code using egui_plot
-        // For testing purposes, we're just simulating
+        Ok(ComparisonModel::new(half.render_model(left)?, half.render_model(right)?, shared_scale, link_axes))
+    }
+    
+    /// Saves a comparison as one two-panel `candlestick.png`
+    ///
+    /// # Arguments
+    /// * `comparison` - Left and right chart
+    /// * `output_dir` - Directory for the image
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok once the image was written
+    ///
+    /// # Errors
+    /// * Returns `AppError::Plotting` if the directory or the image cannot be written
+    pub fn create_comparison_plot(&self, comparison: &ComparisonModel, output_dir: &str) -> Result<()> {
+        file_utils::ensure_directory_exists(output_dir)
+            .map_err(|err| AppError::Plotting(format!("Cannot create output directory {}: {:#}", output_dir, err)))?;
         
-        if data.is_empty() {
-            log::warn!("No data available for plotting");
-        }
+        let path = Path::new(output_dir).join("candlestick.png");
+        raster::write_comparison_png(comparison, &path, &self.config.png)
+            .map_err(|err| AppError::Plotting(format!("Cannot write {}: {:#}", path.display(), err)))?;
+        log::info!(
+            "Plotted {} and {} candles side by side to {}",
+            comparison.left.candles.len(),
+            comparison.right.candles.len(),
+            path.display()
+        );
         
         Ok(())
     }
     
-    /// Converts historical data to plot points (for future implementation)
+    /// Opens a native window showing both charts of a comparison side by side
+    ///
+    /// Blocks until the window is closed. With `link_axes` horizontal zoom
+    /// and pan are synchronized between the panels, with `shared_scale` the
+    /// vertical ones as well.
     ///
     /// # Arguments
-    /// * `candlesticks` - Candlestick data to convert
+    /// * `comparison` - Left and right chart
+    /// * `title` - Window title
     ///
     /// # Returns
-    /// * `Result<PlotPoints>` - Converted plot points
-    #[allow(dead_code)]
-    fn prepare_plot_data(candlesticks: &[CandleStick]) -> Result<PlotPoints<'_>> {
-        let points: Vec<[f64; 2]> = candlesticks
-            .iter()
-            .enumerate()
-            .map(|(i, candle)| [i as f64, candle.close])
-            .collect();
+    /// * `Result<()>` - Ok once the window was closed
+    ///
+    /// # Errors
+    /// * Returns `AppError::Plotting` with a hint if no window can be opened
+    pub fn show_comparison_window(&self, comparison: ComparisonModel, title: &str) -> Result<()> {
+        if let Some(reason) = Self::missing_display() {
+            return Err(Self::window_error(reason).into());
+        }
         
-        Ok(PlotPoints::from(points))
+        self.run_window(title, ComparisonWindow::new(comparison))
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
     
-    /// Test Plotter creation
-    #[test]
-    fn test_plotter_new() {
-        let plotter = Plotter::new();
-        // Just testing that it can be created
-        assert!(true);
+    /// Opens a native window showing the latest chart received from `updates`
+    ///
+    /// Blocks until the window is closed, redrawing whenever a new model
+    /// arrives, e.g. from a websocket feed.
+    ///
+    /// # Arguments
+    /// * `updates` - Charts replacing the shown one
+    /// * `title` - Window title
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok once the window was closed
+    ///
+    /// # Errors
+    /// * Returns `AppError::Plotting` with a hint if no window can be opened
+    #[cfg(feature = "stream")]
+    pub fn show_live_window(&self, updates: std::sync::mpsc::Receiver<RenderModel>, title: &str) -> Result<()> {
+        if let Some(reason) = Self::missing_display() {
+            return Err(Self::window_error(reason).into());
+        }
+        
+        self.run_window(title, ChartWindow::live(self.render_model(&[])?, updates))
     }
     
-    /// Test candlestick plot creation with empty data
-    #[test]
-    fn test_create_candlestick_plot_empty_data() -> Result<()> {
-        let mut plotter = Plotter::new();
-        let mut data_map = HashMap::new();
-        data_map.insert("historical_data".to_string(), Vec::new());
-        
-        let result = plotter.create_candlestick_plot(&data_map, "test_output");
-        assert!(result.is_ok());
+    /// Runs an eframe application in a window of the configured size
+    fn run_window(&self, title: &str, app: impl eframe::App + 'static) -> Result<()> {
+        let (width, height) = self.config.size;
+        let options = eframe::NativeOptions {
+            viewport: eframe::egui::ViewportBuilder::default().with_inner_size([width as f32, height as f32]),
+            ..Default::default()
+        };
         
-        Ok(())
+        eframe::run_native(
+            title,
+            options,
+            Box::new(|_| Ok(Box::new(app))),
+        )
+        .map_err(|err| Self::window_error(&err.to_string()).into())
     }
     
-    /// Test candlestick plot creation with sample data
-    #[test]
-    fn test_create_candlestick_plot_with_data() -> Result<()> {
+    /// Opens a native window showing the chart of the given data
+    ///
+    /// Blocks until the window is closed. The watermark is only drawn with
+    /// `watermark_in_window`. Tooltips of candles merged by thinning
+    /// summarize their source candles.
+    ///
+    /// # Arguments
+    /// * `data` - Historical data before thinning
+    /// * `thinned` - Rows drawn in the window, thinned from `data`
+    /// * `title` - Window title
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok once the window was closed
+    ///
+    /// # Errors
+    /// * Returns `AppError::Plotting` with a hint if no window can be opened,
+    ///   e.g. without a display or a usable GPU
+    pub fn show_window(&self, data: &[HistoricalData], thinned: &Thinned, title: &str) -> Result<()> {
+        let mut model = self.render_model(&thinned.data)?;
+        model.buckets = self.buckets(data, thinned);
+        if !self.config.watermark_in_window {
+            model.watermark = None;
+        }
+        
+        if let Some(reason) = Self::missing_display() {
+            return Err(Self::window_error(reason).into());
+        }
+        
+        self.run_window(title, ChartWindow::new(model))
+    }
+    
+    /// Summarizes the source rows behind every thinned row for tooltips
+    ///
+    /// # Arguments
+    /// * `data` - Historical data before thinning
+    /// * `thinned` - Thinned rows with their source ranges in `data`
+    ///
+    /// # Returns
+    /// * `Vec<CandleBucket>` - One bucket per thinned row, empty if no rows
+    ///   were merged
+    fn buckets(&self, data: &[HistoricalData], thinned: &Thinned) -> Vec<CandleBucket> {
+        if thinned.sources.iter().all(|range| range.len() <= 1) {
+            return Vec::new();
+        }
+        let date = |row: &HistoricalData| {
+            row.parsed_timestamp().map_or(row.timestamp.clone(), |timestamp| self.config.locale.format_date(&timestamp))
+        };
+        
+        thinned.sources
+            .iter()
+            .filter_map(|range| {
+                let rows = data.get(range.clone())?;
+                let (first, last) = (rows.first()?, rows.last()?);
+                Some(CandleBucket {
+                    count: rows.len(),
+                    first: date(first),
+                    last: date(last),
+                    open: first.open,
+                    high: rows.iter().map(|row| row.high).fold(f64::NEG_INFINITY, f64::max),
+                    low: rows.iter().map(|row| row.low).fold(f64::INFINITY, f64::min),
+                    close: last.close,
+                    volume: rows.iter().map(|row| row.volume).sum(),
+                })
+            })
+            .collect()
+    }
+    
+    /// Renders the chart in software, saves it as PNG and opens it in the default viewer
+    ///
+    /// Canvas texts are not drawn by the software renderer. The image path is
+    /// printed so it can be opened by hand when no viewer starts. The
+    /// watermark is only drawn with `watermark_in_window`.
+    ///
+    /// # Arguments
+    /// * `data` - Historical data to draw
+    /// * `path` - Where the image is saved
+    /// * `png` - Encoding settings of the image
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok once the image was written
+    ///
+    /// # Errors
+    /// * Returns error if the chart cannot be rendered or the image cannot be written
+    pub fn show_preview(&self, data: &[HistoricalData], path: &Path, png: &PngOptions) -> Result<()> {
+        let mut model = self.render_model(data)?;
+        if !self.config.watermark_in_window {
+            model.watermark = None;
+        }
+        raster::write_png(&model, path, png)?;
+        
+        let mut viewer = if cfg!(target_os = "windows") {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        } else if cfg!(target_os = "macos") {
+            Command::new("open")
+        } else {
+            Command::new("xdg-open")
+        };
+        
+        println!("Preview saved to {}", path.display());
+        if let Err(err) = viewer.arg(path).stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+            log::warn!("Could not open an image viewer: {}", err);
+        }
+        
+        Ok(())
+    }
+    
+    /// Reports why no display server is reachable, before eframe tries one
+    fn missing_display() -> Option<&'static str> {
+        let has_display = |name| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        
+        if cfg!(target_os = "linux") && !has_display("DISPLAY") && !has_display("WAYLAND_DISPLAY") {
+            Some("no display found (neither DISPLAY nor WAYLAND_DISPLAY is set)")
+        } else {
+            None
+        }
+    }
+    
+    /// Wraps a window failure into a plotting error with a hint for the likely cause
+    ///
+    /// # Arguments
+    /// * `message` - Description of the underlying failure
+    ///
+    /// # Returns
+    /// * `AppError` - `AppError::Plotting` with the failure and a suggestion
+    pub fn window_error(message: &str) -> AppError {
+        let lower = message.to_lowercase();
+        let export = "run without --window, or export the chart with --export-render-model";
+        
+        let hint = if ["display", "wayland", "x11", "xdg_runtime_dir"].iter().any(|k| lower.contains(k)) {
+            format!("No display is available; {}", export)
+        } else if ["opengl", "glutin", "egl", "gpu", "wgpu", "adapter"].iter().any(|k| lower.contains(k)) {
+            format!("No usable GPU or OpenGL driver was found; {}", export)
+        } else {
+            format!("To plot without a window, {}", export)
+        };
+        
+        AppError::Plotting(format!("Could not open the chart window: {}. {}", message, hint))
+    }
+    
+    /// Resolves the fill color of every candle
+    ///
+    /// When `color_by` names a column, each distinct value is assigned a color
+    /// from `color_map` or, failing that, the next unused palette entry in order
+    /// of first appearance. Candles without a value use up/down coloring.
+    /// With `color_by_return`, every candle is colored by its log return
+    /// instead; the first candle has no return and gets the neutral color.
+    ///
+    /// # Arguments
+    /// * `data` - Historical data to color
+    ///
+    /// # Returns
+    /// * `Vec<Color32>` - One color per data point
+    pub fn candle_colors(&self, data: &[HistoricalData]) -> Vec<Color32> {
+        if self.config.color_by_return {
+            let (returns, limit) = Self::return_scale(data);
+            return returns
+                .iter()
+                .map(|value| self.return_color(value.unwrap_or(0.0), limit))
+                .collect();
+        }
+        
+        let mut assigned: HashMap<String, Color32> = HashMap::new();
+        let mut next_palette_index = 0;
+        
+        data.iter()
+            .map(|row| {
+                let category = self.config.color_by
+                    .as_ref()
+                    .and_then(|column| row.extra.get(column))
+                    .filter(|value| !value.is_empty());
+                
+                match category {
+                    Some(value) => *assigned.entry(value.clone()).or_insert_with(|| {
+                        self.config.color_map.get(value).copied().unwrap_or_else(|| {
+                            let categories = self.config.palette.categories();
+                            let color = categories[next_palette_index % categories.len()];
+                            next_palette_index += 1;
+                            color
+                        })
+                    }),
+                    None => self.direction_color(row.open, row.close),
+                }
+            })
+            .collect()
+    }
+    
+    /// Classifies a candle with the configured neutral threshold
+    fn direction(&self, open: f64, close: f64) -> Direction {
+        Direction::of(open, close, self.config.neutral_threshold)
+    }
+    
+    /// Picks the configured color of a candle's direction, or the doji color
+    /// for candles without a body when one is set
+    fn direction_color(&self, open: f64, close: f64) -> Color32 {
+        if let Some(color) = self.config.doji_color
+            && close == open
+        {
+            return color;
+        }
+        match self.direction(open, close) {
+            Direction::Up => self.config.palette.up_color(),
+            Direction::Down => self.config.palette.down_color(),
+            Direction::Neutral => self.config.neutral_color,
+        }
+    }
+    
+    /// Resolves the body and wick colors of every candle, applying opacity
+    ///
+    /// Bodies use the colors from `candle_colors` faded by `candle_opacity`;
+    /// wicks keep the same hue faded by `wick_opacity`. Candles above the
+    /// volume highlight level get an outline in a lighter shade of their color.
+    /// Palettes with hollow up candles draw those as an outline only.
+    /// An incomplete last candle is faded and outlined in its full color.
+    /// With `auto_contrast`, colors too pale for the background are nudged
+    /// darker before any of this, keeping their hue.
+    ///
+    /// # Arguments
+    /// * `data` - Historical data to style
+    ///
+    /// # Returns
+    /// * `Vec<CandleStyle>` - One style per data point
+    pub fn candle_styles(&self, data: &[HistoricalData]) -> Vec<CandleStyle> {
+        let threshold = self.volume_threshold(data);
+        
+        self.candle_colors(data)
+            .into_iter()
+            .zip(data)
+            .enumerate()
+            .map(|(index, (color, row))| {
+                let color = if self.config.auto_contrast {
+                    color_utils::ensure_contrast(color, color_utils::BACKGROUND_COLOR, color_utils::MIN_CONTRAST_RATIO)
+                } else {
+                    color
+                };
+                let hollow = self.config.palette.hollow_up() && self.direction(row.open, row.close) == Direction::Up;
+                let highlight = threshold.filter(|t| row.volume > *t).map(|_| Outline {
+                    width: HIGHLIGHT_OUTLINE_WIDTH,
+                    color: color_utils::lighten(color, 0.5),
+                });
+                
+                let fade = match (self.config.last_incomplete && index + 1 == data.len(), row.synthetic) {
+                    (true, _) => INCOMPLETE_OPACITY,
+                    (false, true) => SYNTHETIC_OPACITY,
+                    (false, false) => 1.0,
+                };
+                
+                CandleStyle {
+                    body: color_utils::with_opacity(color, if hollow { 0.0 } else { self.config.candle_opacity * fade }),
+                    wick: color_utils::with_opacity(color, self.config.wick_opacity * fade),
+                    outline: highlight.or((hollow || fade < 1.0).then(|| Outline {
+                        width: HOLLOW_OUTLINE_WIDTH,
+                        color: color_utils::with_opacity(color, self.config.candle_opacity),
+                    })),
+                }
+            })
+            .collect()
+    }
+    
+    /// Computes close-to-close log returns and the magnitude mapped to the
+    /// ends of the colormap
+    fn return_scale(data: &[HistoricalData]) -> (Vec<Option<f64>>, f64) {
+        let closes: Vec<f64> = data.iter().map(|row| row.close).collect();
+        let returns = stats_utils::log_returns(&closes);
+        let magnitudes: Vec<f64> = returns.iter().flatten().map(|value| value.abs()).collect();
+        let limit = stats_utils::percentile(&magnitudes, RETURN_CLAMP_PERCENTILE).unwrap_or(0.0);
+        
+        (returns, limit)
+    }
+    
+    /// Maps a log return onto the diverging colormap of the palette
+    fn return_color(&self, value: f64, limit: f64) -> Color32 {
+        color_utils::diverging(value, limit, self.config.palette.down_color(), self.config.palette.up_color())
+    }
+    
+    /// Builds the colorbar of the return colormap
+    fn return_colorbar(&self, data: &[HistoricalData]) -> Colorbar {
+        let (_, limit) = Self::return_scale(data);
+        let stops = (0..COLORBAR_STOPS)
+            .map(|i| {
+                let value = limit * (2.0 * i as f64 / (COLORBAR_STOPS - 1) as f64 - 1.0);
+                ColorStop { value, color: self.return_color(value, limit) }
+            })
+            .collect();
+        
+        Colorbar { title: "Log return".to_string(), stops }
+    }
+    
+    /// Resolves the configured volume highlight into an absolute threshold
+    fn volume_threshold(&self, data: &[HistoricalData]) -> Option<f64> {
+        match self.config.volume_highlight? {
+            VolumeHighlight::Absolute(threshold) => Some(threshold),
+            VolumeHighlight::Percentile(pct) => {
+                let volumes: Vec<f64> = data.iter().map(|row| row.volume).collect();
+                stats_utils::percentile(&volumes, pct)
+            }
+        }
+    }
+    
+    /// Computes the geometry of the chart in data coordinates
+    ///
+    /// # Arguments
+    /// * `data` - Historical data to draw
+    ///
+    /// # Returns
+    /// * `Result<RenderModel>` - Candle bodies, wicks and axis ranges
+    ///
+    /// # Errors
+    /// * Returns error if timestamps cannot be parsed
+    pub fn render_model(&self, data: &[HistoricalData]) -> Result<RenderModel> {
+        let candlesticks = DataProcessor::candles_from(data)?;
+        let styles = self.candle_styles(data);
+        let half_width = (1.0 - self.config.spacing) / 2.0;
+        
+        let mut candles = Vec::with_capacity(candlesticks.len());
+        let mut wicks = Vec::with_capacity(candlesticks.len());
+        
+        for (index, (candle, style)) in candlesticks.iter().zip(&styles).enumerate() {
+            let x = index as f64;
+            candles.push(CandleRect {
+                index,
+                timestamp: candle.timestamp.to_rfc3339(),
+                label: self.config.locale.format_date(&candle.timestamp),
+                left: x - half_width,
+                right: x + half_width,
+                bottom: candle.open.min(candle.close),
+                top: candle.open.max(candle.close),
+                fill: style.body,
+                outline: style.outline,
+                incomplete: self.config.last_incomplete && index + 1 == candlesticks.len(),
+                synthetic: candle.synthetic,
+            });
+            wicks.push(WickSegment {
+                index,
+                x,
+                low: candle.low,
+                high: candle.high,
+                color: style.wick,
+            });
+        }
+        
+        let mut lines: Vec<RenderLine> = self.config.overlays
+            .iter()
+            .enumerate()
+            .flat_map(|(position, overlay)| self.overlay_lines(position, overlay, &candlesticks))
+            .collect();
+        let markers = self.config.overlays
+            .iter()
+            .flat_map(|overlay| self.overlay_markers(overlay, &candlesticks))
+            .collect();
+        
+        let visible = self.visible_candles(&candlesticks)?;
+        let x_range = AxisRange { min: visible.start as f64 - 0.5, max: visible.end.max(1) as f64 - 0.5 };
+        let x_bounds = visible.start as f64..=visible.end.saturating_sub(1) as f64;
+        let indicator_range = lines
+            .iter()
+            .filter(|line| line.panel == Panel::Price)
+            .flat_map(|line| line.points.iter().filter(|[x, _]| x_bounds.contains(x)))
+            .map(|[_, y]| AxisRange { min: *y, max: *y })
+            .reduce(|a, b| a.union(&b));
+        let mut y_range = match indicator_range.filter(|_| self.config.scale_to_indicators) {
+            Some(range) => Self::padded(range.min, range.max),
+            None => Self::price_range(&candlesticks[visible.clone()]),
+        };
+        let mut fills: Vec<RenderFill> = self.config.overlays
+            .iter()
+            .flat_map(|overlay| self.overlay_fills(overlay, &candlesticks, &x_range))
+            .collect();
+        
+        if self.config.chart_type == ChartType::Baseline {
+            let closes: Vec<Option<f64>> = candlesticks.iter().map(|c| Some(c.close)).collect();
+            let close_line = RenderLine::from_series("Close", self.config.palette.categories()[7], Panel::Price, &closes);
+            
+            // Without visible candles there is no span to draw the baseline over
+            if let Some(baseline) = self.config.baseline.or(candlesticks.first().map(|c| c.close))
+                && !visible.is_empty()
+            {
+                fills.splice(0..0, RenderFill::split_at_baseline(
+                    &close_line.points,
+                    baseline,
+                    color_utils::with_opacity(self.config.palette.up_color(), BASELINE_FILL_OPACITY),
+                    color_utils::with_opacity(self.config.palette.down_color(), BASELINE_FILL_OPACITY),
+                ));
+                y_range = y_range.union(&AxisRange { min: baseline, max: baseline });
+                lines.insert(0, RenderLine {
+                    name: "Baseline".to_string(),
+                    color: close_line.color,
+                    panel: Panel::Price,
+                    style: LineStyle::Solid,
+                    points: vec![[visible.start as f64, baseline], [(visible.end - 1) as f64, baseline]],
+                });
+            }
+            lines.insert(0, close_line);
+            candles.clear();
+            wicks.clear();
+        }
+        
+        if self.config.hide_candles {
+            candles.clear();
+            wicks.clear();
+        }
+        
+        if let Some(benchmark) = &self.config.benchmark {
+            fills.splice(0..0, self.benchmark_fills(&candlesticks, benchmark, &y_range));
+        }
+        
+        let volume = match self.config.volume_panel {
+            true => self.volume_bars(data, &candlesticks),
+            false => Vec::new(),
+        };
+        let (texts, mut margins) = layout::layout_texts(
+            self.config.size,
+            self.config.title.as_deref(),
+            self.config.subtitle.as_deref(),
+            self.config.caption.as_deref(),
+        );
+        let colorbar = self.config.color_by_return.then(|| self.return_colorbar(data));
+        if colorbar.is_some() {
+            margins.right = layout::COLORBAR_MARGIN;
+        }
+        
+        let mut model = RenderModel {
+            events: self.event_marks(&candlesticks),
+            volume,
+            volume_y_range: None,
+            candles,
+            wicks,
+            x_range,
+            y_range,
+            lower_y_range: None,
+            lines,
+            markers,
+            fills,
+            bands: self.session_bands(&candlesticks),
+            price_precision: self.config.price_precision,
+            tooltip_precision: self.config.tooltip_precision,
+            size: self.config.size,
+            texts,
+            margins,
+            colorbar,
+            watermark: self.config.watermark.clone(),
+            transparent: self.config.transparent,
+            x_labels: None,
+            price_tag: None,
+            y_label: self.config.y_label.clone(),
+            placeholder: candlesticks.is_empty().then(|| match &self.config.data_source {
+                Some(source) => format!("No data in {}", source),
+                None => "No data".to_string(),
+            }),
+            price_grid: Vec::new(),
+            layers: self.config.layers.clone(),
+            buckets: Vec::new(),
+        };
+        if self.config.zoom.is_some() {
+            model.crop(x_range);
+        }
+        
+        model.lower_y_range = AxisRange::of_lines(model.lines.iter().filter(|l| l.panel == Panel::Lower));
+        model.volume_y_range = model.volume
+            .iter()
+            .map(|bar| bar.volume)
+            .reduce(f64::max)
+            .map(|max| AxisRange { min: 0.0, max: max * (1.0 + Y_PADDING) });
+        
+        let dates: Vec<NaiveDate> = candlesticks.iter().map(|c| c.timestamp.date_naive()).collect();
+        let positions: Vec<f64> = match (&self.config.business_days, &self.config.nontrading) {
+            (Some(calendar), _) => {
+                let positions = calendar.positions(&dates);
+                model.remap_x(&positions);
+                positions
+            }
+            (None, Some(calendar)) => {
+                let positions: Vec<f64> = dates.iter().map(|date| (*date - dates[0]).num_days() as f64).collect();
+                model.remap_x(&positions);
+                if let Some(&origin) = dates.first() {
+                    model.bands.extend(Self::nontrading_bands(calendar, &dates[visible.clone()], origin));
+                }
+                positions
+            }
+            (None, None) => (0..candlesticks.len()).map(|index| index as f64).collect(),
+        };
+        
+        if !visible.is_empty() {
+            let candidates: Vec<(f64, String)> = visible
+                .map(|index| (positions[index], self.config.locale.format_date(&candlesticks[index].timestamp)))
+                .collect();
+            let labels = layout::x_axis_labels(&model, &candidates, self.config.x_label_rotation.degrees());
+            model.margins.bottom += layout::x_axis_margin(&labels);
+            model.x_labels = Some(labels);
+        }
+        
+        if self.config.last_price_line && let Some(last) = candlesticks.last() {
+            let color = self.direction_color(last.open, last.close);
+            model.lines.push(RenderLine {
+                name: "Last price".to_string(),
+                color,
+                panel: Panel::Price,
+                style: LineStyle::Dashed,
+                points: vec![[model.x_range.min, last.close], [model.x_range.max, last.close]],
+            });
+            model.price_tag = Some(PriceTag { price: last.close, label: model.axis_label(last.close), color });
+        }
+        layout::fit_annotations(&mut model, self.config.annotation_padding);
+        if let Some(grid) = self.config.price_grid {
+            model.price_grid = grid.levels(&model.y_range);
+        }
+        
+        Ok(model)
+    }
+    
+    /// Finds the candles inside the zoom window, all candles without one
+    ///
+    /// # Errors
+    /// * Returns `AppError::DataProcessing` if the window contains no candles
+    fn visible_candles(&self, candles: &[CandleStick]) -> Result<Range<usize>> {
+        let Some((start, end)) = self.config.zoom else {
+            return Ok(0..candles.len());
+        };
+        
+        let first = candles.partition_point(|candle| candle.timestamp < start);
+        let last = candles.partition_point(|candle| candle.timestamp < end);
+        if first == last {
+            return Err(AppError::DataProcessing(format!(
+                "Zoom range {} to {} contains no candles",
+                start.format("%Y-%m-%d %H:%M:%S"),
+                end.format("%Y-%m-%d %H:%M:%S")
+            )).into());
+        }
+        Ok(first..last)
+    }
+    
+    /// Builds the area of a benchmark series aligned to the candles
+    ///
+    /// Closes are matched by timestamp and rescaled so the benchmark's range
+    /// spans the candles' low to high. Candles without a benchmark close break
+    /// the area instead of being interpolated.
+    fn benchmark_fills(&self, candles: &[CandleStick], benchmark: &[CandleStick], y_range: &AxisRange) -> Vec<RenderFill> {
+        let aligned = DataProcessor::align_closes(candles, benchmark);
+        let Some((min, max)) = aligned.iter().flatten().fold(None, |range: Option<(f64, f64)>, &close| {
+            Some(range.map_or((close, close), |(min, max)| (min.min(close), max.max(close))))
+        }) else {
+            log::warn!("Benchmark shares no timestamps with the chart");
+            return Vec::new();
+        };
+        
+        let low = candles.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+        let high = candles.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max);
+        let scale = |close: f64| if max > min { low + (close - min) / (max - min) * (high - low) } else { (low + high) / 2.0 };
+        let color = color_utils::with_opacity(self.config.palette.categories()[7], BENCHMARK_OPACITY);
+        
+        let mut fills = Vec::new();
+        let mut run: Vec<[f64; 2]> = Vec::new();
+        for (index, close) in aligned.into_iter().chain(std::iter::once(None)).enumerate() {
+            match close {
+                Some(close) => run.push([index as f64, scale(close)]),
+                None => {
+                    fills.extend(RenderFill::split_at_baseline(&run, y_range.min, color, color));
+                    run.clear();
+                }
+            }
+        }
+        
+        fills
+    }
+    
+    /// Shades runs of candles in the same trading session
+    ///
+    /// Each session takes the palette color of its position in the table;
+    /// candles outside every session are left unshaded.
+    fn session_bands(&self, candles: &[CandleStick]) -> Vec<RenderBand> {
+        let Some(table) = &self.config.sessions else {
+            return Vec::new();
+        };
+        let palette = self.config.palette.categories();
+        let mut bands: Vec<RenderBand> = Vec::new();
+        let mut previous = None;
+        
+        for (index, candle) in candles.iter().enumerate() {
+            let session = table.session_of(&candle.timestamp);
+            match (session, bands.last_mut()) {
+                (Some(_), Some(band)) if previous == session => band.right = index as f64 + 0.5,
+                (Some(current), _) => bands.push(RenderBand {
+                    name: table.sessions[current].name.clone(),
+                    left: index as f64 - 0.5,
+                    right: index as f64 + 0.5,
+                    color: color_utils::with_opacity(palette[current % palette.len()], SESSION_OPACITY),
+                }),
+                (None, _) => {}
+            }
+            previous = session;
+        }
+        
+        bands
+    }
+    
+    /// Shades the weekends and holidays between consecutive candle dates
+    ///
+    /// Bands are placed in calendar days counted from `origin`, merging
+    /// adjacent closed days into one band.
+    fn nontrading_bands(calendar: &BusinessCalendar, dates: &[NaiveDate], origin: NaiveDate) -> Vec<RenderBand> {
+        let color = color_utils::with_opacity(color_utils::NEUTRAL_COLOR, SESSION_OPACITY);
+        let mut bands: Vec<RenderBand> = Vec::new();
+        
+        for pair in dates.windows(2) {
+            let closed = pair[0].iter_days().skip(1).take_while(|day| *day < pair[1]).filter(|day| !calendar.is_business_day(*day));
+            for day in closed {
+                let x = (day - origin).num_days() as f64;
+                match bands.last_mut() {
+                    Some(band) if band.right == x - 0.5 => band.right = x + 0.5,
+                    _ => bands.push(RenderBand { name: "Closed".to_string(), left: x - 0.5, right: x + 0.5, color }),
+                }
+            }
+        }
+        
+        bands
+    }
+    
+    /// Builds one volume bar per candle in the candle's color
+    ///
+    /// The bars come from the same rows as the candles, so a thinned series
+    /// shows exactly one bar per drawn candle with the bucket's summed volume.
+    fn volume_bars(&self, data: &[HistoricalData], candles: &[CandleStick]) -> Vec<VolumeBar> {
+        let half_width = (1.0 - self.config.spacing) / 2.0;
+        
+        self.candle_colors(data)
+            .into_iter()
+            .zip(candles)
+            .enumerate()
+            .map(|(index, (color, candle))| VolumeBar {
+                index,
+                left: index as f64 - half_width,
+                right: index as f64 + half_width,
+                volume: candle.volume,
+                color: color_utils::with_opacity(color, VOLUME_BAR_OPACITY),
+            })
+            .collect()
+    }
+    
+    /// Places the configured events on the candles they fall into
+    ///
+    /// Colors come from `color_map` keyed by kind, otherwise from the
+    /// palette in order of first appearance. Events outside the candles are
+    /// skipped.
+    fn event_marks(&self, candles: &[CandleStick]) -> Vec<RenderEvent> {
+        let time_index = TimeIndex::from_timestamps(candles.iter().map(|c| c.timestamp).collect());
+        let categories = self.config.palette.categories();
+        let mut colors: HashMap<&str, Color32> = HashMap::new();
+        
+        self.config.events
+            .iter()
+            .filter_map(|event| {
+                let located = date_utils::parse_timestamp(&event.timestamp, TimestampFormat::Auto)
+                    .ok()
+                    .and_then(|timestamp| time_index.locate(timestamp));
+                let Some((index, x)) = located else {
+                    log::debug!("Skipping event '{}' at {} outside the chart", event.label, event.timestamp);
+                    return None;
+                };
+                
+                let next_color = categories[colors.len() % categories.len()];
+                let color = *colors.entry(event.kind.as_str()).or_insert_with(|| {
+                    self.config.color_map.get(&event.kind).copied().unwrap_or(next_color)
+                });
+                Some(RenderEvent { index, x, label: event.label.clone(), kind: event.kind.clone(), color })
+            })
+            .collect()
+    }
+    
+    /// Resolves layer names into the draw order of the price panel
+    ///
+    /// The named layers are drawn in the given order on top of the ones left
+    /// out, which keep their default order. Unknown names are logged and
+    /// skipped.
+    ///
+    /// # Arguments
+    /// * `names` - Layer names from bottom to top, e.g. `bands`, `candles`,
+    ///   `sma` and `markers`
+    ///
+    /// # Returns
+    /// * `Vec<Layer>` - Every layer once, from bottom to top
+    pub fn layer_order(names: &[String]) -> Vec<Layer> {
+        let mut named: Vec<Layer> = Vec::new();
+        
+        for name in names {
+            match LAYER_NAMES.iter().find(|(known, _)| known.eq_ignore_ascii_case(name.trim())) {
+                Some(&(_, layer)) if !named.contains(&layer) => named.push(layer),
+                Some(_) => {}
+                None => {
+                    let known: Vec<&str> = LAYER_NAMES.iter().map(|(known, _)| *known).collect();
+                    log::warn!("Ignoring unknown layer '{}', expected one of {}", name, known.join(", "));
+                }
+            }
+        }
+        
+        let mut order: Vec<Layer> = Layer::DEFAULT_ORDER.into_iter().filter(|layer| !named.contains(layer)).collect();
+        order.extend(named);
+        order
+    }
+    
+    /// Counts the leading candles before every configured indicator is defined
+    ///
+    /// Only overlays drawn as lines count; markers and fills have no warmup.
+    ///
+    /// # Arguments
+    /// * `candles` - Candles the indicators are computed on
+    ///
+    /// # Returns
+    /// * `usize` - Longest warmup across the indicator lines, all candles if
+    ///   a line is never defined and 0 without indicator lines
+    pub fn warmup(&self, candles: &[CandleStick]) -> usize {
+        self.config.overlays
+            .iter()
+            .enumerate()
+            .flat_map(|(position, overlay)| self.overlay_lines(position, overlay, candles))
+            .map(|line| match line.points.first() {
+                Some([x, _]) => x.ceil().max(0.0) as usize,
+                None => candles.len(),
+            })
+            .max()
+            .unwrap_or(0)
+            .min(candles.len())
+    }
+    
+    /// Computes the lines drawn for the overlay at `position` in the configuration
+    ///
+    /// Moving averages without a color take the palette colors in turn, in
+    /// the order they are configured.
+    fn overlay_lines(&self, position: usize, overlay: &Overlay, candles: &[CandleStick]) -> Vec<RenderLine> {
+        let series = |values: Vec<f64>| values.into_iter().map(Some).collect::<Vec<_>>();
+        let palette = self.config.palette.categories();
+        let moving_average = |name: &str, average: &MovingAverage, values: &[Option<f64>]| {
+            let earlier = self.config.overlays[..position]
+                .iter()
+                .filter(|overlay| matches!(overlay, Overlay::Sma(_) | Overlay::Ema(_)))
+                .count();
+            let color = average.color.unwrap_or(palette[earlier % palette.len()]);
+            let line = RenderLine::from_series(&format!("{}({})", name, average.period), color, Panel::Price, values);
+            vec![RenderLine { style: average.style, ..line }]
+        };
+        
+        match overlay {
+            Overlay::WickRatios => vec![
+                RenderLine::from_series(
+                    "Upper wick ratio",
+                    palette[0],
+                    Panel::Lower,
+                    &series(indicators::upper_wick_ratio(candles)),
+                ),
+                RenderLine::from_series(
+                    "Lower wick ratio",
+                    palette[1],
+                    Panel::Lower,
+                    &series(indicators::lower_wick_ratio(candles)),
+                ),
+            ],
+            Overlay::BodyMidpoints => vec![RenderLine::from_series(
+                "Body midpoint",
+                palette[4],
+                Panel::Price,
+                &series(indicators::body_midpoint(candles)),
+            )],
+            Overlay::Donchian(period) => {
+                let (upper, lower, mid) = indicators::donchian(candles, *period);
+                vec![
+                    RenderLine::from_series(&format!("Donchian upper({})", period), palette[2], Panel::Price, &upper),
+                    RenderLine::from_series(&format!("Donchian lower({})", period), palette[2], Panel::Price, &lower),
+                    RenderLine::from_series(&format!("Donchian mid({})", period), palette[3], Panel::Price, &mid),
+                ]
+            }
+            Overlay::VolumeProfile(bins) => {
+                let profile = indicators::volume_profile(candles, *bins);
+                let point_of_control = profile.iter().copied().reduce(|a, b| if b.1 > a.1 { b } else { a });
+                
+                point_of_control
+                    .map(|(price, _)| RenderLine {
+                        name: "Point of control".to_string(),
+                        color: palette[1],
+                        panel: Panel::Price,
+                        style: LineStyle::Solid,
+                        points: vec![[-0.5, price], [candles.len() as f64 - 0.5, price]],
+                    })
+                    .into_iter()
+                    .collect()
+            }
+            Overlay::TrendChannel(lookback) => {
+                let (upper, lower) = indicators::trend_channel(candles, *lookback);
+                [("Trend channel upper", upper), ("Trend channel lower", lower)]
+                    .iter()
+                    .map(|(name, values)| RenderLine::from_series(name, palette[4], Panel::Price, values))
+                    .filter(|line| !line.points.is_empty())
+                    .collect()
+            }
+            Overlay::Twap(period) => vec![RenderLine::from_series(
+                &format!("TWAP({})", period),
+                palette[1],
+                Panel::Price,
+                &indicators::twap(candles, *period),
+            )],
+            Overlay::Momentum(period) => vec![RenderLine::from_series(
+                &format!("Momentum({})", period),
+                palette[5],
+                Panel::Lower,
+                &indicators::momentum(candles, *period),
+            )],
+            Overlay::RateOfChange(period) => vec![RenderLine::from_series(
+                &format!("ROC({})", period),
+                palette[6],
+                Panel::Lower,
+                &indicators::rate_of_change(candles, *period),
+            )],
+            Overlay::PercentileRank(period) => {
+                let rank = RenderLine::from_series(
+                    &format!("Percentile rank({})", period),
+                    palette[7],
+                    Panel::Lower,
+                    &indicators::percentile_rank(candles, *period),
+                );
+                let (Some(&[first, _]), Some(&[last, _])) = (rank.points.first(), rank.points.last()) else {
+                    return vec![rank];
+                };
+                let guides = PERCENTILE_GUIDES.map(|level| RenderLine {
+                    // Unnamed so the guides stay out of the legend
+                    name: String::new(),
+                    color: rank.color,
+                    panel: Panel::Lower,
+                    style: LineStyle::Dotted,
+                    points: vec![[first, level], [last, level]],
+                });
+                [vec![rank], guides.to_vec()].concat()
+            }
+            Overlay::PctFromHigh(period) => vec![RenderLine::from_series(
+                &match period {
+                    0 => "% from high".to_string(),
+                    _ => format!("% from high({})", period),
+                },
+                palette[2],
+                Panel::Lower,
+                &indicators::pct_from_high(candles, *period),
+            )],
+            Overlay::UlcerIndex(period) => vec![RenderLine::from_series(
+                &format!("Ulcer({})", period),
+                palette[3],
+                Panel::Lower,
+                &indicators::ulcer_index(candles, *period),
+            )],
+            Overlay::Rsi(period) => vec![RenderLine::from_series(
+                &format!("RSI({})", period),
+                palette[4],
+                Panel::Lower,
+                &indicators::rsi(candles, *period),
+            )],
+            Overlay::Atr(period) => vec![RenderLine::from_series(
+                &format!("ATR({})", period),
+                palette[1],
+                Panel::Lower,
+                &indicators::atr(candles, *period),
+            )],
+            Overlay::Macd { fast, slow, signal } => {
+                let result = indicators::macd(candles, *fast, *slow, *signal);
+                vec![
+                    RenderLine::from_series(&format!("MACD({}, {})", fast, slow), palette[0], Panel::Lower, &result.macd),
+                    RenderLine::from_series(&format!("Signal({})", signal), palette[1], Panel::Lower, &result.signal),
+                ]
+            }
+            Overlay::Sma(average) => moving_average("SMA", average, &indicators::sma(candles, average.period)),
+            Overlay::TimeframeSma(average) => {
+                let resampled = DataProcessor::resample(candles, average.interval, LabelTime::Open, &AggSpec::default());
+                let values = DataProcessor::upsample(candles, average.interval, &indicators::sma(&resampled, average.period));
+                let line = RenderLine {
+                    name: format!("{} SMA({})", average.timeframe, average.period),
+                    color: palette[6],
+                    panel: Panel::Price,
+                    style: LineStyle::Solid,
+                    // Each candle spans its whole slot so buckets join as steps
+                    points: values
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, value)| value.map(|y| (i as f64, y)))
+                        .flat_map(|(x, y)| [[x - 0.5, y], [x + 0.5, y]])
+                        .collect(),
+                };
+                vec![line]
+            }
+            Overlay::Ema(average) => moving_average("EMA", average, &indicators::ema(candles, average.period)),
+            Overlay::Beta(period) => match &self.config.benchmark {
+                Some(benchmark) => vec![RenderLine::from_series(
+                    &format!("Beta({})", period),
+                    palette[7],
+                    Panel::Lower,
+                    &indicators::rolling_beta(candles, benchmark, *period),
+                )],
+                None => Vec::new(),
+            },
+            Overlay::SessionPivots => {
+                let values = indicators::session_pivots(candles, &DataProcessor::sessions(candles));
+                let styles = [
+                    (palette[7], LineStyle::Solid),
+                    (palette[3], LineStyle::Dashed),
+                    (palette[2], LineStyle::Dashed),
+                    (palette[3], LineStyle::Dotted),
+                    (palette[2], LineStyle::Dotted),
+                ];
+                
+                Pivots::NAMES
+                    .iter()
+                    .zip(styles)
+                    .enumerate()
+                    .map(|(level, (name, (color, style)))| RenderLine {
+                        name: name.to_string(),
+                        color,
+                        panel: Panel::Price,
+                        style,
+                        // Each candle spans its whole slot so sessions join as steps
+                        points: values
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(i, pivots)| pivots.map(|p| (i as f64, p.levels()[level])))
+                            .flat_map(|(x, y)| [[x - 0.5, y], [x + 0.5, y]])
+                            .collect(),
+                    })
+                    .filter(|line| !line.points.is_empty())
+                    .collect()
+            }
+            Overlay::Fractals | Overlay::RangeExpansion { .. } => Vec::new(),
+        }
+    }
+    
+    /// Computes the filled areas drawn for one overlay
+    fn overlay_fills(&self, overlay: &Overlay, candles: &[CandleStick], x_range: &AxisRange) -> Vec<RenderFill> {
+        match overlay {
+            Overlay::VolumeProfile(bins) => {
+                let profile = indicators::volume_profile(candles, *bins);
+                let max_volume = profile.iter().map(|(_, volume)| *volume).fold(0.0, f64::max);
+                if max_volume <= 0.0 {
+                    return Vec::new();
+                }
+                
+                let half_height = match profile.as_slice() {
+                    [first, second, ..] => (second.0 - first.0) / 2.0,
+                    _ => {
+                        let range = Self::price_range(candles);
+                        (range.max - range.min) * Y_PADDING
+                    }
+                };
+                let max_width = (x_range.max - x_range.min) * VOLUME_PROFILE_WIDTH;
+                let palette = self.config.palette.categories();
+                let mut point_of_control_seen = false;
+                
+                profile
+                    .iter()
+                    .filter(|(_, volume)| *volume > 0.0)
+                    .map(|&(price, volume)| {
+                        let is_point_of_control = volume == max_volume && !point_of_control_seen;
+                        point_of_control_seen |= is_point_of_control;
+                        let left = x_range.max - max_width * volume / max_volume;
+                        let (bottom, top) = (price - half_height, price + half_height);
+                        
+                        RenderFill {
+                            color: if is_point_of_control {
+                                palette[1]
+                            } else {
+                                color_utils::with_opacity(palette[0], VOLUME_PROFILE_OPACITY)
+                            },
+                            points: vec![[left, bottom], [x_range.max, bottom], [x_range.max, top], [left, top]],
+                        }
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Computes the point markers drawn for one overlay
+    fn overlay_markers(&self, overlay: &Overlay, candles: &[CandleStick]) -> Vec<RenderMarker> {
+        match overlay {
+            Overlay::Fractals => patterns::fractals(candles)
+                .into_iter()
+                .map(|(index, kind)| match kind {
+                    FractalKind::Up => RenderMarker {
+                        index,
+                        name: "Up fractal".to_string(),
+                        x: index as f64,
+                        y: candles[index].high,
+                        shape: MarkerShape::Up,
+                        color: self.config.palette.up_color(),
+                    },
+                    FractalKind::Down => RenderMarker {
+                        index,
+                        name: "Down fractal".to_string(),
+                        x: index as f64,
+                        y: candles[index].low,
+                        shape: MarkerShape::Down,
+                        color: self.config.palette.down_color(),
+                    },
+                })
+                .collect(),
+            Overlay::RangeExpansion { period, factor } => indicators::range_expansion(candles, *period, *factor)
+                .into_iter()
+                .enumerate()
+                .filter(|(_, expanded)| *expanded == Some(true))
+                .map(|(index, _)| RenderMarker {
+                    index,
+                    name: "Range expansion".to_string(),
+                    x: index as f64,
+                    y: candles[index].high,
+                    shape: MarkerShape::Up,
+                    color: self.config.palette.categories()[5],
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Computes the padded price range spanned by the candles
+    fn price_range(candles: &[CandleStick]) -> AxisRange {
+        let low = candles.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+        let high = candles.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max);
+        
+        if !low.is_finite() || !high.is_finite() {
+            return AxisRange { min: 0.0, max: 1.0 };
+        }
+        
+        Self::padded(low, high)
+    }
+    
+    /// Widens a price span by `Y_PADDING` on both sides, or by 1 if it is empty
+    fn padded(low: f64, high: f64) -> AxisRange {
+        let padding = if high > low { (high - low) * Y_PADDING } else { 1.0 };
+        AxisRange { min: low - padding, max: high + padding }
+    }
+    
+    /// Converts historical data to plot points (for future implementation)
+    ///
+    /// # Arguments
+    /// * `candlesticks` - Candlestick data to convert
+    ///
+    /// # Returns
+    /// * `Result<PlotPoints>` - Converted plot points
+    #[allow(dead_code)]
+    fn prepare_plot_data(candlesticks: &[CandleStick]) -> Result<PlotPoints<'_>> {
+        let points: Vec<[f64; 2]> = candlesticks
+            .iter()
+            .enumerate()
+            .map(|(i, candle)| [i as f64, candle.close])
+            .collect();
+        
+        Ok(PlotPoints::from(points))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downsample::{self, ThinStrategy};
+    use crate::render_model::AxisLabels;
+    
+    /// Test Plotter creation
+    #[test]
+    #[allow(unused_variables, clippy::assertions_on_constants)]
+    fn test_plotter_new() {
+        let plotter = Plotter::new();
+        // Just testing that it can be created
+        assert!(true);
+    }
+    
+    /// Test that a new plotter colors candles by direction only
+    #[test]
+    fn test_plotter_new_color_defaults() {
+        let plotter = Plotter::new();
+        assert!(plotter.config().color_by.is_none());
+        assert!(plotter.config().color_map.is_empty());
+    }
+    
+    /// Test candlestick plot creation with empty data
+    #[test]
+    fn test_create_candlestick_plot_empty_data() -> Result<()> {
+        let mut plotter = Plotter::new();
+        let mut data_map = HashMap::new();
+        data_map.insert("historical_data".to_string(), Vec::new());
+        let temp_dir = tempfile::TempDir::new()?;
+        let output_dir = temp_dir.path().join("test_output");
+        
+        let result = plotter.create_candlestick_plot(&data_map, output_dir.to_str().unwrap());
+        assert!(result.is_ok());
+        assert!(std::fs::metadata(output_dir.join("candlestick.png"))?.len() > 0);
+        
+        Ok(())
+    }
+    
+    /// Test candlestick plot creation with sample data
+    #[test]
+    fn test_create_candlestick_plot_with_data() -> Result<()> {
         let mut plotter = Plotter::new();
         let mut data_map = HashMap::new();
         
-        let sample_data = vec![
-            HistoricalData {
-                timestamp: "2023-01-01 00:00:00".to_string(),
+        let sample_data = vec![
+            HistoricalData {
+                timestamp: "2023-01-01 00:00:00".to_string(),
+                open: 100.0,
+                high: 105.0,
+                low: 95.0,
+                close: 102.0,
+                volume: 1000.0,
+                ..Default::default()
+            }
+        ];
+        
+        data_map.insert("historical_data".to_string(), sample_data);
+        let temp_dir = tempfile::TempDir::new()?;
+        let output_dir = temp_dir.path().to_str().unwrap();
+        
+        let result = plotter.create_candlestick_plot(&data_map, output_dir);
+        assert!(result.is_ok());
+        let png = std::fs::read(temp_dir.path().join("candlestick.png"))?;
+        assert!(png.len() > 8 && png.starts_with(b"\x89PNG"));
+        
+        data_map.insert("other".to_string(), Vec::new());
+        plotter.create_candlestick_plot(&data_map, output_dir)?;
+        assert!(temp_dir.path().join("candlestick_historical_data.png").exists());
+        assert!(temp_dir.path().join("candlestick_other.png").exists());
+        
+        let blocked = temp_dir.path().join("candlestick.png");
+        let err = plotter.create_candlestick_plot(&data_map, blocked.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Plotting(_))));
+        
+        Ok(())
+    }
+    
+    /// Test candle coloring by a classification column with fallback
+    #[test]
+    fn test_candle_colors_by_column() {
+        let row = |open: f64, close: f64, regime: Option<&str>| HistoricalData {
+            open,
+            close,
+            extra: regime
+                .map(|value| HashMap::from([("Regime".to_string(), value.to_string())]))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let data = vec![
+            row(100.0, 101.0, Some("bull")),
+            row(101.0, 100.0, Some("bear")),
+            row(100.0, 99.0, None),
+            row(99.0, 100.0, Some("bull")),
+            row(100.0, 101.0, Some("neutral")),
+        ];
+        
+        let overridden = Color32::from_rgb(1, 2, 3);
+        let plotter = Plotter::with_config(PlotConfig {
+            color_by: Some("Regime".to_string()),
+            color_map: HashMap::from([("neutral".to_string(), overridden)]),
+            ..Default::default()
+        });
+        let colors = plotter.candle_colors(&data);
+        
+        assert_eq!(colors[0], color_utils::CATEGORY_PALETTE[0]);
+        assert_eq!(colors[1], color_utils::CATEGORY_PALETTE[1]);
+        assert_eq!(colors[2], color_utils::DOWN_COLOR);
+        assert_eq!(colors[3], colors[0]);
+        assert_eq!(colors[4], overridden);
+    }
+    
+    /// Test up/down coloring when no column is selected
+    #[test]
+    fn test_candle_colors_up_down() {
+        let data = vec![
+            HistoricalData { open: 100.0, close: 102.0, ..Default::default() },
+            HistoricalData { open: 102.0, close: 101.0, ..Default::default() },
+        ];
+        
+        let colors = Plotter::new().candle_colors(&data);
+        assert_eq!(colors, vec![color_utils::UP_COLOR, color_utils::DOWN_COLOR]);
+    }
+    
+    /// Test the neutral band between up and down candles
+    #[test]
+    fn test_candle_colors_neutral_threshold() {
+        let data = vec![
+            HistoricalData { open: 100.0, close: 100.4, ..Default::default() },
+            HistoricalData { open: 100.0, close: 99.6, ..Default::default() },
+            HistoricalData { open: 100.0, close: 100.5, ..Default::default() },
+            HistoricalData { open: 100.0, close: 99.0, ..Default::default() },
+        ];
+        
+        let plotter = Plotter::with_config(PlotConfig { neutral_threshold: 0.5, ..Default::default() });
+        assert_eq!(
+            plotter.candle_colors(&data),
+            vec![color_utils::NEUTRAL_COLOR, color_utils::NEUTRAL_COLOR, color_utils::UP_COLOR, color_utils::DOWN_COLOR]
+        );
+        assert_eq!(Direction::of(100.0, 100.0, 0.0), Direction::Up);
+        assert_eq!(Direction::of(100.0, 99.9, 0.0), Direction::Down);
+    }
+    
+    /// Test configured neutral and doji colors
+    #[test]
+    fn test_candle_colors_neutral_and_doji() {
+        let data = vec![
+            HistoricalData { open: 100.0, close: 100.0, ..Default::default() },
+            HistoricalData { open: 100.0, close: 100.4, ..Default::default() },
+            HistoricalData { open: 100.0, close: 101.0, ..Default::default() },
+        ];
+        let neutral = Color32::from_rgb(1, 2, 3);
+        let doji = Color32::from_rgb(4, 5, 6);
+        
+        let plotter = Plotter::with_config(PlotConfig { neutral_threshold: 0.5, neutral_color: neutral, ..Default::default() });
+        assert_eq!(plotter.candle_colors(&data), vec![neutral, neutral, color_utils::UP_COLOR]);
+        
+        let plotter = Plotter::with_config(PlotConfig { neutral_threshold: 0.5, neutral_color: neutral, doji_color: Some(doji), ..Default::default() });
+        assert_eq!(plotter.candle_colors(&data), vec![doji, neutral, color_utils::UP_COLOR]);
+        
+        let plotter = Plotter::with_config(PlotConfig { doji_color: Some(doji), ..Default::default() });
+        assert_eq!(plotter.candle_colors(&data), vec![doji, color_utils::UP_COLOR, color_utils::UP_COLOR]);
+    }
+    
+    /// Test that auto contrast darkens pale custom colors and keeps strong ones
+    #[test]
+    fn test_candle_styles_auto_contrast() {
+        let data = vec![
+            HistoricalData { open: 100.0, close: 100.0, ..Default::default() },
+            HistoricalData { open: 100.0, close: 90.0, ..Default::default() },
+        ];
+        let pale = Color32::from_rgb(250, 240, 200);
+        let config = PlotConfig { doji_color: Some(pale), ..Default::default() };
+        
+        let plain = Plotter::with_config(config.clone()).candle_styles(&data);
+        assert_eq!(plain[0].body, pale);
+        
+        let styles = Plotter::with_config(PlotConfig { auto_contrast: true, ..config }).candle_styles(&data);
+        assert_ne!(styles[0].body, pale);
+        assert!(color_utils::contrast_ratio(styles[0].body, color_utils::BACKGROUND_COLOR) >= color_utils::MIN_CONTRAST_RATIO);
+        assert_eq!(styles[0].wick, styles[0].body);
+        assert_eq!(styles[1].body, plain[1].body);
+    }
+    
+    /// Test that opacity is clamped and applied to bodies and wicks separately
+    #[test]
+    fn test_candle_styles_opacity() {
+        let plotter = Plotter::with_config(PlotConfig {
+            candle_opacity: 0.5,
+            wick_opacity: 3.0,
+            ..Default::default()
+        });
+        assert_eq!(plotter.config().wick_opacity, 1.0);
+        
+        let data = vec![HistoricalData { open: 100.0, close: 102.0, ..Default::default() }];
+        let styles = plotter.candle_styles(&data);
+        
+        assert_eq!(styles[0].body.a(), 128);
+        assert_eq!(styles[0].wick.a(), 255);
+        assert_eq!(styles[0].wick, color_utils::UP_COLOR);
+    }
+    
+    /// Test render model geometry for one up and one down candle
+    #[test]
+    fn test_render_model() -> Result<()> {
+        let data = vec![
+            HistoricalData {
+                timestamp: "2023-01-01 00:00:00".to_string(),
+                open: 100.0,
+                high: 110.0,
+                low: 90.0,
+                close: 105.0,
+                volume: 1000.0,
+                ..Default::default()
+            },
+            HistoricalData {
+                timestamp: "2023-01-02 00:00:00".to_string(),
+                open: 105.0,
+                high: 106.0,
+                low: 100.0,
+                close: 101.0,
+                volume: 1000.0,
+                ..Default::default()
+            },
+        ];
+        
+        let model = Plotter::new().render_model(&data)?;
+        
+        assert_eq!(model.candles.len(), 2);
+        assert_eq!((model.candles[0].bottom, model.candles[0].top), (100.0, 105.0));
+        assert_eq!((model.candles[1].bottom, model.candles[1].top), (101.0, 105.0));
+        assert!((model.candles[1].left - 0.6).abs() < 1e-9);
+        assert_eq!(model.candles[1].fill, color_utils::DOWN_COLOR);
+        assert_eq!(model.candles[1].label, "01/02/2023");
+        assert_eq!((model.wicks[0].low, model.wicks[0].high), (90.0, 110.0));
+        assert_eq!(model.x_range, AxisRange { min: -0.5, max: 1.5 });
+        assert_eq!(model.y_range, AxisRange { min: 89.0, max: 111.0 });
+        
+        Ok(())
+    }
+    
+    /// Test that overlays add lines and a lower panel range
+    #[test]
+    fn test_render_model_overlays() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        
+        let plotter = Plotter::with_config(PlotConfig {
+            overlays: vec![Overlay::WickRatios, Overlay::BodyMidpoints, Overlay::Donchian(2)],
+            ..Default::default()
+        });
+        let model = plotter.render_model(processor.get_data())?;
+        
+        assert_eq!(model.lines.len(), 6);
+        assert_eq!(model.lines[3].name, "Donchian upper(2)");
+        assert_eq!(model.lines[3].points.len(), 2);
+        assert_eq!(model.lines[0].panel, Panel::Lower);
+        assert_eq!(model.lines[2].points[0], [0.0, 101.0]);
+        assert!(model.lower_y_range.is_some());
+        assert!(Plotter::new().render_model(processor.get_data())?.lower_y_range.is_none());
+        
+        Ok(())
+    }
+    
+    /// Test parsing moving average specs and rejecting bad tokens
+    #[test]
+    fn test_moving_average_parse() -> Result<()> {
+        let average = MovingAverage::parse("20:#ff0000:dashed")?;
+        assert_eq!(average, MovingAverage { period: 20, color: Some(Color32::RED), style: LineStyle::Dashed });
+        assert_eq!(MovingAverage::parse("50")?, MovingAverage { period: 50, color: None, style: LineStyle::Solid });
+        assert_eq!(MovingAverage::parse("5::dotted")?.style, LineStyle::Dotted);
+        
+        for bad in ["", "0", "x:#ff0000", "20:red", "20:#ff0000:wavy", "20:#ff0000:dashed:3"] {
+            assert!(MovingAverage::parse(bad).is_err(), "{}", bad);
+        }
+        
+        Ok(())
+    }
+    
+    /// Test parsing automatic and fixed label rotations
+    #[test]
+    fn test_label_rotation_parse() -> Result<()> {
+        assert_eq!(LabelRotation::parse("auto")?, LabelRotation::Auto);
+        assert_eq!(LabelRotation::parse("-45")?.degrees(), Some(-45.0));
+        assert!(LabelRotation::parse("steep").is_err());
+        assert!(LabelRotation::parse("inf").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test that x-axis labels stay horizontal when sparse and rotate when dense
+    #[test]
+    fn test_render_model_x_labels() -> Result<()> {
+        let data: Vec<HistoricalData> = (1..=28)
+            .map(|day| HistoricalData {
+                timestamp: format!("2023-02-{:02} 00:00:00", day),
+                open: 10.0,
+                high: 11.0,
+                low: 9.0,
+                close: 10.0,
+                ..Default::default()
+            })
+            .collect();
+        let labels = |size, rotation| -> Result<AxisLabels> {
+            let plotter = Plotter::with_config(PlotConfig { size, x_label_rotation: rotation, ..Default::default() });
+            plotter.render_model(&data)?.x_labels.ok_or_else(|| anyhow::anyhow!("no x labels"))
+        };
+        
+        let sparse = labels((1200, 400), LabelRotation::Auto)?;
+        assert_eq!((sparse.ticks.len(), sparse.rotation), (28usize.div_ceil(2), 0.0));
+        assert_eq!(sparse.ticks[1], (2.0, Locale::default().format_date(&DateTime::parse_from_rfc3339("2023-02-03T00:00:00Z")?.to_utc())));
+        
+        let dense = labels((250, 400), LabelRotation::Auto)?;
+        assert_eq!((dense.ticks.len(), dense.rotation), (4, -45.0));
+        assert!(dense.height > sparse.height);
+        
+        assert_eq!(labels((1200, 400), LabelRotation::Degrees(30.0))?.rotation, 30.0);
+        assert!(Plotter::new().render_model(&[])?.x_labels.is_none());
+        
+        Ok(())
+    }
+    
+    /// Test that moving averages keep their style and take palette colors in turn
+    #[test]
+    fn test_render_model_moving_averages() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let average = |period, color, style| MovingAverage { period, color, style };
+        
+        let plotter = Plotter::with_config(PlotConfig {
+            overlays: vec![
+                Overlay::Sma(average(2, None, LineStyle::Solid)),
+                Overlay::BodyMidpoints,
+                Overlay::Sma(average(3, Some(Color32::RED), LineStyle::Dashed)),
+                Overlay::Ema(average(2, None, LineStyle::Dotted)),
+            ],
+            ..Default::default()
+        });
+        let model = plotter.render_model(processor.get_data())?;
+        let palette = Palette::default().categories();
+        
+        let styled: Vec<(&str, Color32, LineStyle)> = model.lines
+            .iter()
+            .filter(|line| line.name != "Body midpoint")
+            .map(|line| (line.name.as_str(), line.color, line.style))
+            .collect();
+        assert_eq!(styled, vec![
+            ("SMA(2)", palette[0], LineStyle::Solid),
+            ("SMA(3)", Color32::RED, LineStyle::Dashed),
+            ("EMA(2)", palette[2], LineStyle::Dotted),
+        ]);
+        assert_eq!(model.lines[0].points, vec![[1.0, 104.0], [2.0, 107.0]]);
+        
+        Ok(())
+    }
+    
+    /// Test that window failures get a hint matching their cause
+    #[test]
+    fn test_window_error_hints() {
+        let hint = |message| Plotter::window_error(message).to_string();
+        
+        assert!(hint("Failed to open X11 display").contains("No display is available"));
+        assert!(hint("glutin error: no matching configs").contains("No usable GPU"));
+        assert!(hint("something else").contains("run without --window"));
+        assert!(hint("something else").starts_with("Plotting error: Could not open the chart window: something else."));
+    }
+    
+    /// Test that fractals become markers above highs and below lows
+    #[test]
+    fn test_render_model_fractals() -> Result<()> {
+        let data: Vec<HistoricalData> = [(10.0, 5.0), (11.0, 6.0), (15.0, 7.0), (12.0, 6.0), (11.0, 2.0), (12.0, 4.0), (13.0, 5.0)]
+            .iter()
+            .enumerate()
+            .map(|(day, &(high, low))| HistoricalData {
+                timestamp: format!("2023-01-{:02} 00:00:00", day + 1),
+                open: low,
+                high,
+                low,
+                close: high,
+                ..Default::default()
+            })
+            .collect();
+        
+        let plotter = Plotter::with_config(PlotConfig { overlays: vec![Overlay::Fractals], ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        
+        assert!(model.lines.is_empty());
+        assert_eq!(model.markers.len(), 2);
+        assert_eq!((model.markers[0].index, model.markers[0].y), (2, 15.0));
+        assert_eq!(model.markers[0].shape, MarkerShape::Up);
+        assert_eq!((model.markers[1].index, model.markers[1].y), (4, 2.0));
+        assert!(Plotter::new().render_model(&data)?.markers.is_empty());
+        
+        Ok(())
+    }
+    
+    /// Test the last price line across the chart and its tag
+    #[test]
+    fn test_render_model_last_price_line() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let mut data = processor.load_csv_data("non_existent_file.csv")?;
+        
+        let plotter = Plotter::with_config(PlotConfig { last_price_line: true, price_precision: 1, ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        let line = model.lines.last().ok_or_else(|| anyhow::anyhow!("no last price line"))?;
+        assert_eq!(line.name, "Last price");
+        assert_eq!(line.style, LineStyle::Dashed);
+        assert_eq!(line.points, vec![[model.x_range.min, 108.0], [model.x_range.max, 108.0]]);
+        
+        let tag = model.price_tag.ok_or_else(|| anyhow::anyhow!("no price tag"))?;
+        assert_eq!(tag.label, "108.0");
+        assert_eq!(tag.color, plotter.config().palette.up_color());
+        
+        data[2].close = 100.0;
+        let tag = plotter.render_model(&data)?.price_tag.ok_or_else(|| anyhow::anyhow!("no price tag"))?;
+        assert_eq!(tag.color, plotter.config().palette.down_color());
+        assert!(Plotter::new().render_model(&data)?.price_tag.is_none());
+        
+        Ok(())
+    }
+    
+    /// Test that the trend channel draws only the side with two swing points
+    #[test]
+    fn test_render_model_trend_channel() -> Result<()> {
+        let data: Vec<HistoricalData> = [5.0, 6.0, 9.0, 6.0, 5.0, 6.0, 10.0, 6.0, 5.0]
+            .iter()
+            .enumerate()
+            .map(|(day, &high)| HistoricalData {
+                timestamp: format!("2023-01-{:02} 00:00:00", day + 1),
+                open: 4.0,
+                high,
+                low: 4.0,
+                close: high,
+                ..Default::default()
+            })
+            .collect();
+        
+        let plotter = Plotter::with_config(PlotConfig { overlays: vec![Overlay::TrendChannel(20)], ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        
+        assert_eq!(model.lines.len(), 1);
+        assert_eq!(model.lines[0].name, "Trend channel upper");
+        assert_eq!(model.lines[0].points.first(), Some(&[2.0, 9.0]));
+        assert_eq!(model.lines[0].points.last(), Some(&[8.0, 10.5]));
+        
+        Ok(())
+    }
+    
+    /// Test session pivots drawn as steps from the previous day, skipping the first day
+    #[test]
+    fn test_render_model_session_pivots() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        
+        let plotter = Plotter::with_config(PlotConfig { overlays: vec![Overlay::SessionPivots], ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        
+        let names: Vec<&str> = model.lines.iter().map(|line| line.name.as_str()).collect();
+        assert_eq!(names, vec!["Pivot", "R1", "S1", "R2", "S2"]);
+        let pivot = (105.0 + 95.0 + 102.0) / 3.0;
+        assert_eq!(model.lines[0].points[..2], [[0.5, pivot], [1.5, pivot]]);
+        assert_eq!(model.lines[0].points.len(), 4);
+        assert_eq!(model.lines[3].points[0], [0.5, pivot + 10.0]);
+        
+        Ok(())
+    }
+    
+    /// Test making room for markers at the extremes and labels at the right edge
+    #[test]
+    fn test_render_model_annotation_padding() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::with_config(PlotConfig {
+            size: (400, 300),
+            title: Some("Annotated".to_string()),
+            events: vec![Event {
+                timestamp: "2023-01-03 00:00:00".to_string(),
+                label: "Quarterly earnings call".to_string(),
+                kind: "earnings".to_string(),
+            }],
+            ..Default::default()
+        });
+        let mut model = plotter.render_model(&data)?;
+        
+        let width = model.size.0 - 400;
+        assert!(width > 0);
+        assert_eq!(model.margins.right, width as f64);
+        assert_eq!(model.texts[0].x, model.size.0 as f64 / 2.0);
+        let price = layout::panels(&model).price;
+        let (x, _) = price.map(2.0, model.y_range.max);
+        assert!(x as f64 + 2.0 + 23.0 * 11.0 * 0.55 + 4.0 <= model.size.0 as f64);
+        
+        let high = model.y_range.max;
+        model.markers.push(RenderMarker {
+            index: 2,
+            name: "Up fractal".to_string(),
+            x: 2.0,
+            y: high,
+            shape: MarkerShape::Up,
+            color: Color32::RED,
+        });
+        layout::fit_annotations(&mut model, 4.0);
+        let price = layout::panels(&model).price;
+        let (_, y) = price.map(2.0, high);
+        assert!((y - price.top - 9.0).abs() < 1e-3);
+        assert_eq!(model.size.0 - 400, width);
+        
+        Ok(())
+    }
+    
+    /// Test parsing grid steps and listing the multiples inside a range
+    #[test]
+    fn test_price_grid() -> Result<()> {
+        assert_eq!(PriceGrid::parse("auto")?, PriceGrid::Auto);
+        assert_eq!(PriceGrid::parse("2.5")?, PriceGrid::Step(2.5));
+        assert!(PriceGrid::parse("0").is_err());
+        assert!(PriceGrid::parse("five").is_err());
+        
+        let range = AxisRange { min: 93.2, max: 112.4 };
+        assert_eq!(PriceGrid::Step(5.0).levels(&range), vec![95.0, 100.0, 105.0, 110.0]);
+        assert_eq!(PriceGrid::Auto.levels(&range), vec![95.0, 100.0, 105.0, 110.0]);
+        assert!(PriceGrid::Step(0.01).levels(&range).is_empty());
+        
+        let plotter = Plotter::with_config(PlotConfig { price_grid: Some(PriceGrid::Step(5.0)), ..Default::default() });
+        let mut processor = DataProcessor::new();
+        let model = plotter.render_model(&processor.load_csv_data("non_existent_file.csv")?)?;
+        assert_eq!(model.price_grid, vec![95.0, 100.0, 105.0, 110.0]);
+        
+        Ok(())
+    }
+    
+    /// Test the percentile rank line with unnamed guides across its defined range
+    #[test]
+    fn test_render_model_percentile_rank() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::with_config(PlotConfig { overlays: vec![Overlay::PercentileRank(2)], ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        
+        assert_eq!(model.lines.len(), 3);
+        assert_eq!(model.lines[0].points, vec![[1.0, 100.0], [2.0, 100.0]]);
+        assert_eq!(model.lines[1].points, vec![[1.0, 20.0], [2.0, 20.0]]);
+        assert_eq!(model.lines[2].points, vec![[1.0, 80.0], [2.0, 80.0]]);
+        assert!(model.lines[1..].iter().all(|line| line.name.is_empty() && line.panel == Panel::Lower));
+        
+        let plotter = Plotter::with_config(PlotConfig { overlays: vec![Overlay::PercentileRank(5)], ..Default::default() });
+        assert!(plotter.render_model(&data)?.lines.iter().all(|line| line.points.is_empty()));
+        
+        Ok(())
+    }
+    
+    /// Test that RSI, ATR and MACD overlays are drawn in the lower panel
+    #[test]
+    fn test_render_model_oscillators() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        let overlays = vec![Overlay::Rsi(1), Overlay::Atr(2), Overlay::Macd { fast: 1, slow: 2, signal: 1 }];
+        let model = Plotter::with_config(PlotConfig { overlays, ..Default::default() }).render_model(&data)?;
+        
+        let names: Vec<&str> = model.lines.iter().map(|line| line.name.as_str()).collect();
+        assert_eq!(names, ["RSI(1)", "ATR(2)", "MACD(1, 2)", "Signal(1)"]);
+        assert!(model.lines.iter().all(|line| line.panel == Panel::Lower));
+        assert_eq!(model.lines[0].points, vec![[1.0, 100.0], [2.0, 100.0]]);
+        assert_eq!(model.lines[2].points.len(), 2);
+        
+        Ok(())
+    }
+    
+    /// Test resolving layer names, aliases and unknown names into a draw order
+    #[test]
+    fn test_layer_order() {
+        let names = |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+        
+        assert_eq!(Plotter::layer_order(&[]), Layer::DEFAULT_ORDER);
+        assert_eq!(
+            Plotter::layer_order(&names(&["lines", "candles"])),
+            [Layer::Bands, Layer::Grid, Layer::Fills, Layer::Events, Layer::Markers, Layer::Lines, Layer::Candles]
+        );
+        assert_eq!(
+            Plotter::layer_order(&names(&["markers", "Bogus", "SMA", "ema", "bands"])),
+            [Layer::Grid, Layer::Fills, Layer::Candles, Layer::Events, Layer::Markers, Layer::Lines, Layer::Bands]
+        );
+    }
+    
+    /// Test parsing timeframe averages and drawing them as steps per bucket
+    #[test]
+    fn test_render_model_timeframe_sma() -> Result<()> {
+        let average = TimeframeAverage::parse("weekly:2")?;
+        assert_eq!((average.interval, average.period), (Duration::weeks(1), 2));
+        assert_eq!(TimeframeAverage::parse("4h:50")?.interval, Duration::hours(4));
+        assert!(TimeframeAverage::parse("monthly:2").is_err());
+        assert!(TimeframeAverage::parse("weekly:0").is_err());
+        assert!(TimeframeAverage::parse("weekly").is_err());
+        
+        // Two full weeks of closes 1 and 3, then a partial week at 5
+        let data: Vec<HistoricalData> = (0..16)
+            .map(|day| {
+                let close = [1.0, 3.0, 5.0][day / 7];
+                HistoricalData {
+                    timestamp: (DateTime::UNIX_EPOCH + Duration::days(19_359 + day as i64)).format("%Y-%m-%d %H:%M:%S").to_string(),
+                    open: close,
+                    high: close,
+                    low: close,
+                    close,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let plotter = Plotter::with_config(PlotConfig { overlays: vec![Overlay::TimeframeSma(average)], ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        
+        let line = &model.lines[0];
+        assert_eq!(line.name, "weekly SMA(2)");
+        assert_eq!(line.points.first(), Some(&[6.5, 2.0]));
+        assert_eq!(line.points[14], [13.5, 4.0]);
+        assert_eq!(line.points.last(), Some(&[15.5, 4.0]));
+        assert_eq!(line.points.len(), 2 * 9);
+        
+        Ok(())
+    }
+    
+    /// Test that spacing narrows candle bodies and volume bars alike
+    #[test]
+    fn test_render_model_spacing() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        
+        let model = Plotter::new().render_model(&data)?;
+        assert!((model.candles[1].right - model.candles[1].left - 0.8).abs() < 1e-9);
+        
+        let plotter = Plotter::with_config(PlotConfig { spacing: 0.5, volume_panel: true, ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        assert_eq!((model.candles[1].left, model.candles[1].right), (0.75, 1.25));
+        assert_eq!((model.volume[1].left, model.volume[1].right), (0.75, 1.25));
+        
+        Ok(())
+    }
+    
+    /// Test the warmup as the longest leading gap across indicator lines
+    #[test]
+    fn test_warmup() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let candles = processor.to_candlesticks()?;
+        let average = |period| MovingAverage { period, color: None, style: LineStyle::Solid };
+        let warmup = |overlays| Plotter::with_config(PlotConfig { overlays, ..Default::default() }).warmup(&candles);
+        
+        assert_eq!(warmup(vec![Overlay::Sma(average(2)), Overlay::Momentum(1), Overlay::Fractals]), 1);
+        assert_eq!(warmup(vec![Overlay::Sma(average(3)), Overlay::BodyMidpoints]), 2);
+        assert_eq!(warmup(vec![Overlay::Sma(average(5))]), 3);
+        assert_eq!(warmup(Vec::new()), 0);
+        
+        Ok(())
+    }
+    
+    /// Test that tooltips of merged candles report their bucket's OHLCV
+    #[test]
+    fn test_buckets() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::new();
+        
+        let thinned = downsample::thin(&data, 2, ThinStrategy::Lttb);
+        assert_eq!(thinned.sources, vec![0..2, 2..3]);
+        let mut model = plotter.render_model(&thinned.data)?;
+        model.buckets = plotter.buckets(&data, &thinned);
+        assert_eq!(model.buckets.len(), 2);
+        assert_eq!(
+            model.tooltip(0).as_deref(),
+            Some("01/01/2023 - 01/02/2023 (2 candles)\nOpen 100.0000\nHigh 108.0000\nLow 95.0000\nClose 106.0000\nVolume 2200")
+        );
+        assert!(model.tooltip(1).is_some_and(|tooltip| tooltip.starts_with("01/03/2023\nBody")));
+        
+        let unchanged = downsample::thin(&data, 3, ThinStrategy::OhlcMerge);
+        assert!(plotter.buckets(&data, &unchanged).is_empty());
+        
+        Ok(())
+    }
+    
+    /// Test the placeholder naming the source of empty data
+    #[test]
+    fn test_render_model_placeholder() -> Result<()> {
+        let plotter = Plotter::with_config(PlotConfig { data_source: Some("prices.csv".to_string()), ..Default::default() });
+        assert_eq!(plotter.render_model(&[])?.placeholder.as_deref(), Some("No data in prices.csv"));
+        assert_eq!(Plotter::new().render_model(&[])?.placeholder.as_deref(), Some("No data"));
+        
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        assert!(plotter.render_model(&data)?.placeholder.is_none());
+        
+        Ok(())
+    }
+    
+    /// Test shading runs of candles by session and leaving hours outside sessions blank
+    #[test]
+    fn test_render_model_session_bands() -> Result<()> {
+        let data: Vec<HistoricalData> = [6, 7, 8, 9, 21, 23]
+            .iter()
+            .map(|hour| HistoricalData {
+                timestamp: format!("2023-01-02 {:02}:00:00", hour),
                 open: 100.0,
-                high: 105.0,
-                low: 95.0,
-                close: 102.0,
+                high: 101.0,
+                low: 99.0,
+                close: 100.5,
+                ..Default::default()
+            })
+            .collect();
+        
+        let plotter = Plotter::with_config(PlotConfig { sessions: Some(SessionTable::default()), ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        let bands: Vec<(&str, f64, f64)> = model.bands.iter().map(|b| (b.name.as_str(), b.left, b.right)).collect();
+        assert_eq!(bands, vec![("Asia", -0.5, 1.5), ("Europe", 1.5, 3.5)]);
+        assert_ne!(model.bands[0].color, model.bands[1].color);
+        
+        let tokyo = SessionTable { offset: date_utils::parse_utc_offset("+09:00")?, ..Default::default() };
+        let plotter = Plotter::with_config(PlotConfig { sessions: Some(tokyo), ..Default::default() });
+        let names: Vec<String> = plotter.render_model(&data)?.bands.into_iter().map(|b| b.name).collect();
+        assert_eq!(names, vec!["US", "Asia", "Europe"]);
+        assert!(Plotter::new().render_model(&data)?.bands.is_empty());
+        
+        Ok(())
+    }
+    
+    /// Test calendar-day spacing with shaded weekends and holidays between candles
+    #[test]
+    fn test_render_model_nontrading_bands() -> Result<()> {
+        // Thursday, Friday, Monday and Wednesday around a Tuesday holiday
+        let data: Vec<HistoricalData> = ["2023-01-05", "2023-01-06", "2023-01-09", "2023-01-11"]
+            .iter()
+            .map(|date| HistoricalData {
+                timestamp: format!("{} 00:00:00", date),
+                open: 100.0,
+                high: 101.0,
+                low: 99.0,
+                close: 100.5,
+                ..Default::default()
+            })
+            .collect();
+        let calendar = BusinessCalendar { holidays: [NaiveDate::from_ymd_opt(2023, 1, 10).unwrap_or_default()].into() };
+        
+        let plotter = Plotter::with_config(PlotConfig { nontrading: Some(calendar), ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        let centers: Vec<f64> = model.wicks.iter().map(|wick| wick.x).collect();
+        assert_eq!(centers, vec![0.0, 1.0, 4.0, 6.0]);
+        let bands: Vec<(&str, f64, f64)> = model.bands.iter().map(|b| (b.name.as_str(), b.left, b.right)).collect();
+        assert_eq!(bands, vec![("Closed", 1.5, 3.5), ("Closed", 4.5, 5.5)]);
+        assert_eq!((model.x_range.min, model.x_range.max), (-0.5, 6.5));
+        
+        let start = date_utils::parse_timestamp("2023-01-09 00:00:00", TimestampFormat::Auto)?;
+        let zoom = Some((start, start + Duration::days(3)));
+        let zoomed = Plotter::with_config(PlotConfig { zoom, ..plotter.config().clone() }).render_model(&data)?;
+        assert_eq!(zoomed.bands.iter().map(|b| (b.left, b.right)).collect::<Vec<_>>(), vec![(4.5, 5.5)]);
+        
+        Ok(())
+    }
+    
+    /// Test placing events on candles, coloring by kind and skipping outside ones
+    #[test]
+    fn test_render_model_events() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        let event = |timestamp: &str, label: &str, kind: &str| Event {
+            timestamp: timestamp.to_string(),
+            label: label.to_string(),
+            kind: kind.to_string(),
+        };
+        
+        let plotter = Plotter::with_config(PlotConfig {
+            events: vec![
+                event("2023-01-02 09:30:00", "Q4 earnings", "earnings"),
+                event("2022-06-01 00:00:00", "Too early", "earnings"),
+                event("2023-01-03 00:00:00", "Split", "split"),
+                event("2023-01-03 12:00:00", "Q1 earnings", "earnings"),
+            ],
+            color_map: HashMap::from([("split".to_string(), Color32::WHITE)]),
+            ..Default::default()
+        });
+        let model = plotter.render_model(&data)?;
+        
+        let placed: Vec<(usize, &str)> = model.events.iter().map(|e| (e.index, e.label.as_str())).collect();
+        assert_eq!(placed, vec![(1, "Q4 earnings"), (2, "Split"), (2, "Q1 earnings")]);
+        assert_eq!(model.events[1].color, Color32::WHITE);
+        assert_eq!(model.events[0].color, model.events[2].color);
+        assert_ne!(model.events[0].color, model.events[1].color);
+        
+        Ok(())
+    }
+    
+    /// Test that thinned candles and volume bars stay in sync with summed volume
+    #[test]
+    fn test_render_model_volume_panel() -> Result<()> {
+        use crate::downsample::{self, ThinStrategy};
+        
+        let data: Vec<HistoricalData> = (0..9)
+            .map(|day| HistoricalData {
+                timestamp: format!("2023-01-{:02} 00:00:00", day + 1),
+                open: 10.0,
+                high: 12.0,
+                low: 9.0,
+                close: if day % 2 == 0 { 11.0 } else { 9.5 },
+                volume: 100.0 * (day + 1) as f64,
+                ..Default::default()
+            })
+            .collect();
+        
+        let plotter = Plotter::with_config(PlotConfig { volume_panel: true, ..Default::default() });
+        for strategy in [ThinStrategy::OhlcMerge, ThinStrategy::Skip, ThinStrategy::Lttb] {
+            let thinned = downsample::thin(&data, 4, strategy);
+            let model = plotter.render_model(&thinned.data)?;
+            
+            assert_eq!(model.volume.len(), model.candles.len(), "{:?}", strategy);
+            assert_eq!(model.volume.iter().map(|bar| bar.volume).sum::<f64>(), 4500.0);
+            assert_eq!(model.volume[1].left, model.candles[1].left);
+        }
+        
+        let model = plotter.render_model(&data)?;
+        assert_eq!(model.volume_y_range.map(|range| range.min), Some(0.0));
+        assert!(Plotter::new().render_model(&data)?.volume.is_empty());
+        
+        Ok(())
+    }
+    
+    /// Test that zooming crops the model but keeps indicators computed over the whole series
+    #[test]
+    fn test_render_model_zoom() -> Result<()> {
+        let data: Vec<HistoricalData> = (0..10)
+            .map(|day| HistoricalData {
+                timestamp: format!("2023-01-{:02} 00:00:00", day + 1),
+                open: 100.0 + day as f64,
+                high: 101.0 + day as f64,
+                low: 99.0 + day as f64,
+                close: 100.5 + day as f64,
                 volume: 1000.0,
-            }
+                ..Default::default()
+            })
+            .collect();
+        let (start, _) = date_utils::parse_period("2023-01-05")?;
+        let (_, end) = date_utils::parse_period("2023-01-06")?;
+        
+        let plotter = Plotter::with_config(PlotConfig {
+            overlays: vec![Overlay::Donchian(3)],
+            zoom: Some((start, end)),
+            ..Default::default()
+        });
+        let model = plotter.render_model(&data)?;
+        
+        assert_eq!(model.candles.iter().map(|candle| candle.index).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(model.x_range, AxisRange { min: 3.5, max: 5.5 });
+        assert!((model.y_range.min - 102.85).abs() < 1e-9);
+        let upper = &model.lines[0];
+        assert_eq!(upper.points.first().map(|point| point[0]), Some(4.0));
+        
+        let (after, _) = date_utils::parse_period("2023-02")?;
+        let empty = Plotter::with_config(PlotConfig { zoom: Some((after, end + chrono::Duration::days(60))), ..Default::default() });
+        assert!(empty.render_model(&data).is_err());
+        
+        Ok(())
+    }
+    
+    /// Test deriving the chart size from an aspect ratio and explicit dimensions
+    #[test]
+    fn test_aspect_chart_size() -> Result<()> {
+        let wide = Aspect::parse("16:9")?;
+        
+        assert_eq!(Aspect::chart_size(Some(wide), Some(1600), None), (1600, 900));
+        assert_eq!(Aspect::chart_size(Some(wide), None, Some(450)), (800, 450));
+        assert_eq!(Aspect::chart_size(Some(wide), None, None), (1200, 675));
+        assert_eq!(Aspect::chart_size(Some(wide), Some(500), Some(500)), (500, 500));
+        assert_eq!(Aspect::chart_size(None, None, Some(300)), (1200, 300));
+        assert!(Aspect::parse("16x9").is_err());
+        assert!(Aspect::parse("0:1").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test return coloring, clamping to the colormap ends and the colorbar
+    #[test]
+    fn test_render_model_color_by_return() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let model = Plotter::with_config(PlotConfig { color_by_return: true, ..Default::default() })
+            .render_model(processor.get_data())?;
+        
+        // ln(106/102) lies beyond the 95th percentile of the two returns
+        assert_eq!(model.candles[0].fill, color_utils::DIVERGING_NEUTRAL);
+        assert_eq!(model.candles[1].fill, color_utils::UP_COLOR);
+        assert!(![color_utils::UP_COLOR, color_utils::DIVERGING_NEUTRAL].contains(&model.candles[2].fill));
+        
+        let colorbar = model.colorbar.ok_or_else(|| anyhow::anyhow!("no colorbar"))?;
+        let limit = (108.0_f64 / 106.0).ln() + 0.95 * ((106.0_f64 / 102.0).ln() - (108.0_f64 / 106.0).ln());
+        assert_eq!(colorbar.stops.len(), COLORBAR_STOPS);
+        assert!((colorbar.stops[0].value + limit).abs() < 1e-12);
+        assert_eq!(colorbar.stops[0].color, color_utils::DOWN_COLOR);
+        assert_eq!(colorbar.stops[4].color, color_utils::DIVERGING_NEUTRAL);
+        assert_eq!(colorbar.stops[8].color, color_utils::UP_COLOR);
+        assert_eq!(model.margins.right, layout::COLORBAR_MARGIN);
+        
+        assert!(Plotter::new().render_model(processor.get_data())?.colorbar.is_none());
+        
+        Ok(())
+    }
+    
+    /// Test hiding candles with the price axis scaled to candles or indicators
+    #[test]
+    fn test_render_model_hide_candles() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let config = PlotConfig { overlays: vec![Overlay::BodyMidpoints], hide_candles: true, ..Default::default() };
+        
+        let hidden = Plotter::with_config(config.clone()).render_model(processor.get_data())?;
+        assert!(hidden.candles.is_empty() && hidden.wicks.is_empty());
+        assert_eq!(hidden.lines.len(), 1);
+        assert_eq!(hidden.y_range, Plotter::new().render_model(processor.get_data())?.y_range);
+        
+        let scaled = Plotter::with_config(PlotConfig { scale_to_indicators: true, ..config }).render_model(processor.get_data())?;
+        let (low, high) = (hidden.lines[0].points[0][1], hidden.lines[0].points[2][1]);
+        assert_eq!(scaled.y_range, AxisRange { min: low - (high - low) * Y_PADDING, max: high + (high - low) * Y_PADDING });
+        
+        Ok(())
+    }
+    
+    /// Test that synthetic candles are flagged and drawn faintly
+    #[test]
+    fn test_render_model_synthetic() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let mut data = processor.get_data().to_vec();
+        data[1].synthetic = true;
+        
+        let model = Plotter::new().render_model(&data)?;
+        
+        assert_eq!(model.candles.iter().map(|c| c.synthetic).collect::<Vec<_>>(), vec![false, true, false]);
+        assert_eq!(model.wicks[1].color, color_utils::with_opacity(color_utils::UP_COLOR, SYNTHETIC_OPACITY));
+        assert_eq!(model.wicks[0].color, color_utils::UP_COLOR);
+        
+        Ok(())
+    }
+    
+    /// Test that a benchmark becomes a rescaled area broken at missing timestamps
+    #[test]
+    fn test_render_model_benchmark() -> Result<()> {
+        let series = |days: &[u32], close: fn(u32) -> f64| -> Result<Vec<CandleStick>> {
+            days.iter()
+                .map(|&day| {
+                    Ok(CandleStick {
+                        timestamp: chrono::NaiveDate::from_ymd_opt(2023, 1, day)
+                            .and_then(|date| date.and_hms_opt(0, 0, 0))
+                            .ok_or_else(|| anyhow::anyhow!("invalid day"))?
+                            .and_utc(),
+                        open: close(day),
+                        high: close(day) + 1.0,
+                        low: close(day) - 1.0,
+                        close: close(day),
+                        volume: 1.0,
+                        ..Default::default()
+                    })
+                })
+                .collect()
+        };
+        let data: Vec<HistoricalData> = series(&[1, 2, 3, 4, 5], |day| 100.0 + day as f64)?
+            .iter()
+            .map(HistoricalData::from)
+            .collect();
+        let benchmark = series(&[1, 2, 4, 5, 9], |day| 4000.0 + 10.0 * day as f64)?;
+        
+        let plotter = Plotter::with_config(PlotConfig { benchmark: Some(benchmark), ..Default::default() });
+        let model = plotter.render_model(&data)?;
+        
+        assert_eq!(model.fills.len(), 2);
+        assert_eq!(model.fills[0].points[1], [0.0, 100.0]);
+        assert_eq!(model.fills[1].points[2], [4.0, 106.0]);
+        assert_eq!(model.fills[0].points[0][1], model.y_range.min);
+        assert!(Plotter::new().render_model(&data)?.fills.is_empty());
+        
+        Ok(())
+    }
+    
+    /// Test volume profile bars on the right edge and the point of control
+    #[test]
+    fn test_render_model_volume_profile() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        
+        let plotter = Plotter::with_config(PlotConfig { overlays: vec![Overlay::VolumeProfile(2)], ..Default::default() });
+        let model = plotter.render_model(processor.get_data())?;
+        
+        assert_eq!(model.fills.len(), 2);
+        assert!(model.fills.iter().all(|fill| fill.points[1][0] == model.x_range.max));
+        let point_of_control = &model.fills[1];
+        assert_eq!(point_of_control.color, color_utils::CATEGORY_PALETTE[1]);
+        assert!((point_of_control.points[0][0] - (model.x_range.max - 0.75)).abs() < 1e-9);
+        assert_eq!(model.lines[0].name, "Point of control");
+        
+        Ok(())
+    }
+    
+    /// Test the baseline chart with the default and an explicit baseline
+    #[test]
+    fn test_render_model_baseline() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        
+        let baseline = |level| -> Result<RenderModel> {
+            Plotter::with_config(PlotConfig { chart_type: ChartType::Baseline, baseline: level, ..Default::default() })
+                .render_model(processor.get_data())
+        };
+        
+        let model = baseline(None)?;
+        assert!(model.candles.is_empty());
+        assert_eq!(model.lines[0].name, "Close");
+        assert_eq!(model.lines[1].points, vec![[0.0, 102.0], [2.0, 102.0]]);
+        let up_fill = color_utils::with_opacity(color_utils::UP_COLOR, BASELINE_FILL_OPACITY);
+        assert!(model.fills.iter().all(|fill| fill.color == up_fill));
+        
+        let model = baseline(Some(107.0))?;
+        assert_eq!(model.fills.len(), 3);
+        assert_eq!(model.fills[0].color, color_utils::with_opacity(color_utils::DOWN_COLOR, BASELINE_FILL_OPACITY));
+        assert_eq!(model.fills[2].color, up_fill);
+        
+        Ok(())
+    }
+    
+    /// Test that a baseline chart of an empty series has no baseline
+    #[test]
+    fn test_render_model_baseline_empty() -> Result<()> {
+        let plotter = Plotter::with_config(PlotConfig { chart_type: ChartType::Baseline, baseline: Some(100.0), ..Default::default() });
+        let model = plotter.render_model(&[])?;
+        
+        assert!(model.fills.is_empty());
+        assert!(model.lines.iter().all(|line| line.name != "Baseline"));
+        
+        Ok(())
+    }
+    
+    /// Test that only the last candle is faded and flagged when incomplete
+    #[test]
+    fn test_render_model_last_incomplete() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        
+        let plotter = Plotter::with_config(PlotConfig { last_incomplete: true, ..Default::default() });
+        let model = plotter.render_model(processor.get_data())?;
+        
+        assert_eq!(model.candles.iter().map(|c| c.incomplete).collect::<Vec<_>>(), vec![false, false, true]);
+        assert_eq!(model.candles[1].fill, color_utils::UP_COLOR);
+        assert_eq!(model.candles[2].fill, color_utils::with_opacity(color_utils::UP_COLOR, INCOMPLETE_OPACITY));
+        assert_eq!(model.candles[2].outline.map(|o| o.color), Some(color_utils::UP_COLOR));
+        assert!(Plotter::new().render_model(processor.get_data())?.candles.iter().all(|c| !c.incomplete));
+        
+        Ok(())
+    }
+    
+    /// Test the color-blind-safe palette with hollow up candles
+    #[test]
+    fn test_cb_safe_palette() -> Result<()> {
+        let data = vec![
+            HistoricalData { timestamp: "2023-01-01 00:00:00".to_string(), open: 100.0, close: 102.0, ..Default::default() },
+            HistoricalData { timestamp: "2023-01-02 00:00:00".to_string(), open: 102.0, close: 101.0, ..Default::default() },
         ];
+        let plotter = Plotter::with_config(PlotConfig {
+            palette: Palette::CbSafe,
+            overlays: vec![Overlay::BodyMidpoints],
+            ..Default::default()
+        });
         
-        data_map.insert("historical_data".to_string(), sample_data);
+        let styles = plotter.candle_styles(&data);
+        assert_eq!(styles[0].body.a(), 0);
+        assert_eq!(styles[0].outline.map(|o| o.color), Some(color_utils::CB_SAFE_UP_COLOR));
+        assert_eq!(styles[1].body, color_utils::CB_SAFE_DOWN_COLOR);
+        assert!(styles[1].outline.is_none());
         
-        let result = plotter.create_candlestick_plot(&data_map, "test_output");
-        assert!(result.is_ok());
+        let model = plotter.render_model(&data)?;
+        assert_eq!(model.lines[0].color, color_utils::CB_SAFE_PALETTE[4]);
         
         Ok(())
     }
+    
+    /// Test absolute and percentile volume highlighting
+    #[test]
+    fn test_candle_styles_volume_highlight() {
+        let data: Vec<HistoricalData> = [100.0, 200.0, 300.0, 400.0]
+            .iter()
+            .map(|&volume| HistoricalData { open: 1.0, close: 2.0, volume, ..Default::default() })
+            .collect();
+        
+        let highlighted = |highlight| {
+            Plotter::with_config(PlotConfig { volume_highlight: Some(highlight), ..Default::default() })
+                .candle_styles(&data)
+                .iter()
+                .map(|style| style.outline.is_some())
+                .collect::<Vec<_>>()
+        };
+        
+        assert_eq!(highlighted(VolumeHighlight::Absolute(250.0)), vec![false, false, true, true]);
+        assert_eq!(highlighted(VolumeHighlight::Percentile(75.0)), vec![false, false, false, true]);
+        
+        let style = Plotter::with_config(PlotConfig {
+            volume_highlight: Some(VolumeHighlight::Absolute(0.0)),
+            ..Default::default()
+        })
+        .candle_styles(&data)[0];
+        assert_eq!(style.body, color_utils::UP_COLOR);
+        assert_eq!(style.outline.unwrap().color, color_utils::lighten(color_utils::UP_COLOR, 0.5));
+        assert!(Plotter::new().candle_styles(&data)[0].outline.is_none());
+    }
 }
@@ -0,0 +1,561 @@
+//! Software rendering of a render model into pixels
+//!
+//! The canvas is `RenderModel::size` pixels large and split into panels by
+//! `layout::panels`. Canvas texts are not rasterized; only the space they
+//! need is kept free.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use eframe::egui::Color32;
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, PixmapPaint, Rect, Stroke, StrokeDash, Transform};
+
+use crate::layout::{self, Viewport, MARKER_SIZE};
+use crate::render_model::{ComparisonModel, Layer, LineStyle, MarkerShape, Panel, RenderModel};
+use crate::utils::color_utils;
+
+/// Stroke width of indicator lines in pixels
+const LINE_WIDTH: f32 = 1.5;
+
+/// Renders a chart onto a white canvas, or a transparent one with `model.transparent`
+///
+/// # Arguments
+/// * `model` - Chart to draw
+///
+/// # Returns
+/// * `Result<Pixmap>` - Canvas of `model.size` pixels
+///
+/// # Errors
+/// * Returns error if the canvas size is zero or too large to allocate
+pub fn render(model: &RenderModel) -> Result<Pixmap> {
+    let (width, height) = model.size;
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("Cannot render a {}x{} canvas", width, height))?;
+    if !model.transparent {
+        pixmap.fill(tiny_skia::Color::WHITE);
+    }
+    // Without text rendering an empty chart stays a blank canvas
+    if model.placeholder.is_some() {
+        return Ok(pixmap);
+    }
+    
+    let panels = layout::panels(model);
+    draw_price_panel(&mut pixmap, model, &panels.price);
+    
+    if let Some(lower) = &panels.lower {
+        for line in model.lines.iter().filter(|line| line.panel == Panel::Lower) {
+            stroke_polyline(&mut pixmap, lower, &line.points, line.color, LINE_WIDTH, line.style);
+        }
+    }
+    if let Some(volume) = &panels.volume {
+        for bar in &model.volume {
+            fill_rect(&mut pixmap, volume, (bar.left, 0.0), (bar.right, bar.volume), bar.color);
+        }
+    }
+    if let (Some(colorbar), Some((left, top, width, height))) = (&model.colorbar, layout::colorbar_area(model)) {
+        let step = height / colorbar.stops.len().max(1) as f32;
+        for (i, stop) in colorbar.stops.iter().rev().enumerate() {
+            if let Some(rect) = Rect::from_xywh(left, top + i as f32 * step, width, step) {
+                pixmap.fill_rect(rect, &paint(stop.color), Transform::identity(), None);
+            }
+        }
+    }
+    
+    Ok(pixmap)
+}
+
+/// Encoding settings of PNG images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PngOptions {
+    /// Deflate level from 0 (stored) to 9 (smallest)
+    pub compression: u8,
+    /// Write an indexed image when the chart has at most 256 distinct colors
+    pub palette: bool,
+    /// Resolution stored in the pHYs chunk so print tools size the image;
+    /// the pixel size of the chart is not changed
+    pub dpi: Option<u32>,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions { compression: 6, palette: false, dpi: None }
+    }
+}
+
+/// Converts dots per inch to the pixels per meter stored in PNG files
+fn pixels_per_meter(dpi: u32) -> u32 {
+    (dpi as f64 / 0.0254).round() as u32
+}
+
+/// Renders a chart and writes it as a PNG image
+///
+/// The image is RGBA unless `options.palette` is set and the canvas has at
+/// most 256 distinct colors, in which case it is indexed with a
+/// transparency table. Anti-aliased charts usually exceed that and keep RGBA.
+/// No time or text chunks are written, so the file only depends on the chart.
+///
+/// # Arguments
+/// * `model` - Chart to draw
+/// * `path` - Destination file
+/// * `options` - Compression level and color encoding
+///
+/// # Returns
+/// * `Result<()>` - Ok if the image was written
+///
+/// # Errors
+/// * Returns error if the canvas cannot be rendered or the file cannot be written
+pub fn write_png(model: &RenderModel, path: &Path, options: &PngOptions) -> Result<()> {
+    write_pixmap(&render(model)?, path, options)
+}
+
+/// Renders both charts of a comparison next to each other
+///
+/// The canvas is as wide as both panels together and as high as the higher
+/// one. Each half is drawn by `render` with the axis ranges the comparison
+/// aligned.
+///
+/// # Arguments
+/// * `comparison` - Left and right chart
+///
+/// # Returns
+/// * `Result<Pixmap>` - Canvas holding both charts
+///
+/// # Errors
+/// * Returns error if a canvas size is zero or too large to allocate
+pub fn render_comparison(comparison: &ComparisonModel) -> Result<Pixmap> {
+    let (left, right) = (render(&comparison.left)?, render(&comparison.right)?);
+    let (width, height) = (left.width() + right.width(), left.height().max(right.height()));
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("Cannot render a {}x{} canvas", width, height))?;
+    
+    pixmap.draw_pixmap(0, 0, left.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+    pixmap.draw_pixmap(left.width() as i32, 0, right.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+    Ok(pixmap)
+}
+
+/// Renders a comparison and saves it as a PNG image
+///
+/// # Arguments
+/// * `comparison` - Left and right chart
+/// * `path` - Destination file
+/// * `options` - Compression level and color encoding
+///
+/// # Returns
+/// * `Result<()>` - Ok if the image was written
+///
+/// # Errors
+/// * Returns error if the canvas cannot be rendered or the file cannot be written
+pub fn write_comparison_png(comparison: &ComparisonModel, path: &Path, options: &PngOptions) -> Result<()> {
+    write_pixmap(&render_comparison(comparison)?, path, options)
+}
+
+/// Encodes a rendered canvas as PNG, see `write_png`
+fn write_pixmap(pixmap: &Pixmap, path: &Path, options: &PngOptions) -> Result<()> {
+    let file = File::create(path).context(format!("Failed to create image: {}", path.display()))?;
+    
+    let mut encoder = png::Encoder::new(BufWriter::new(file), pixmap.width(), pixmap.height());
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_deflate_compression(match options.compression {
+        0 => png::DeflateCompression::NoCompression,
+        level => png::DeflateCompression::Level(level.min(9)),
+    });
+    if let Some(dpi) = options.dpi {
+        let ppm = pixels_per_meter(dpi);
+        encoder.set_pixel_dims(Some(png::PixelDimensions { xppu: ppm, yppu: ppm, unit: png::Unit::Meter }));
+    }
+    let colors: Vec<[u8; 4]> = pixmap
+        .pixels()
+        .iter()
+        .map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    
+    let indexed = options.palette.then(|| palette(&colors)).flatten();
+    let pixels = match indexed {
+        Some((table, indices)) => {
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(table.iter().flat_map(|color| [color[0], color[1], color[2]]).collect::<Vec<u8>>());
+            encoder.set_trns(table.iter().map(|color| color[3]).collect::<Vec<u8>>());
+            indices
+        }
+        None => {
+            if options.palette {
+                log::info!("Chart has more than 256 colors, writing RGBA instead of a palette");
+            }
+            encoder.set_color(png::ColorType::Rgba);
+            colors.concat()
+        }
+    };
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .context(format!("Failed to encode image: {}", path.display()))?;
+    
+    let bytes = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or_default();
+    log::info!("Wrote {}x{} image of {} bytes to {}", pixmap.width(), pixmap.height(), bytes, path.display());
+    Ok(())
+}
+
+/// Builds a color table and per-pixel indices, `None` beyond 256 colors
+fn palette(colors: &[[u8; 4]]) -> Option<(Vec<[u8; 4]>, Vec<u8>)> {
+    let mut table: Vec<[u8; 4]> = Vec::new();
+    let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(colors.len());
+    
+    for color in colors {
+        let index = match lookup.get(color) {
+            Some(&index) => index,
+            None => {
+                let index = u8::try_from(table.len()).ok()?;
+                table.push(*color);
+                lookup.insert(*color, index);
+                index
+            }
+        };
+        indices.push(index);
+    }
+    
+    Some((table, indices))
+}
+
+/// Draws the layers of the price panel in `model.layers` order
+fn draw_price_panel(pixmap: &mut Pixmap, model: &RenderModel, viewport: &Viewport) {
+    let (y_min, y_max) = (viewport.y_range.min, viewport.y_range.max);
+    for layer in &model.layers {
+        match layer {
+            Layer::Bands => {
+                for band in &model.bands {
+                    fill_rect(pixmap, viewport, (band.left, y_min), (band.right, y_max), band.color);
+                }
+            }
+            Layer::Grid => {
+                for &price in &model.price_grid {
+                    let (x_min, x_max) = (viewport.x_range.min, viewport.x_range.max);
+                    stroke_polyline(pixmap, viewport, &[[x_min, price], [x_max, price]], color_utils::GRID_COLOR, 1.0, LineStyle::Solid);
+                }
+            }
+            Layer::Fills => {
+                for fill in &model.fills {
+                    let mut builder = PathBuilder::new();
+                    for (i, [x, y]) in fill.points.iter().enumerate() {
+                        let (px, py) = viewport.map(*x, *y);
+                        if i == 0 { builder.move_to(px, py) } else { builder.line_to(px, py) }
+                    }
+                    builder.close();
+                    if let Some(path) = builder.finish() {
+                        pixmap.fill_path(&path, &paint(fill.color), FillRule::Winding, Transform::identity(), None);
+                    }
+                }
+            }
+            Layer::Candles => {
+                for wick in &model.wicks {
+                    stroke_polyline(pixmap, viewport, &[[wick.x, wick.low], [wick.x, wick.high]], wick.color, 1.0, LineStyle::Solid);
+                }
+                for candle in &model.candles {
+                    fill_rect(pixmap, viewport, (candle.left, candle.bottom), (candle.right, candle.top), candle.fill);
+                    if let Some(outline) = candle.outline {
+                        let corners = [
+                            [candle.left, candle.bottom],
+                            [candle.right, candle.bottom],
+                            [candle.right, candle.top],
+                            [candle.left, candle.top],
+                            [candle.left, candle.bottom],
+                        ];
+                        stroke_polyline(pixmap, viewport, &corners, outline.color, outline.width, LineStyle::Solid);
+                    }
+                }
+            }
+            Layer::Lines => {
+                for line in model.lines.iter().filter(|line| line.panel == Panel::Price) {
+                    stroke_polyline(pixmap, viewport, &line.points, line.color, LINE_WIDTH, line.style);
+                }
+            }
+            Layer::Events => {
+                for event in &model.events {
+                    stroke_polyline(pixmap, viewport, &[[event.x, y_min], [event.x, y_max]], event.color, 1.0, LineStyle::Solid);
+                }
+            }
+            Layer::Markers => {
+                for marker in &model.markers {
+                    let (x, y) = viewport.map(marker.x, marker.y);
+                    let (tip, base) = match marker.shape {
+                        MarkerShape::Up => (y - MARKER_SIZE, y + MARKER_SIZE),
+                        MarkerShape::Down => (y + MARKER_SIZE, y - MARKER_SIZE),
+                    };
+                    let mut builder = PathBuilder::new();
+                    builder.move_to(x, tip);
+                    builder.line_to(x + MARKER_SIZE, base);
+                    builder.line_to(x - MARKER_SIZE, base);
+                    builder.close();
+                    if let Some(path) = builder.finish() {
+                        pixmap.fill_path(&path, &paint(marker.color), FillRule::Winding, Transform::identity(), None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fills the rectangle between two corners given in data coordinates
+///
+/// The rectangle is snapped to whole pixels and kept at least one pixel wide
+/// and high, so flat or densely packed candles stay visible and tiny-skia
+/// never anti-aliases a sub-pixel rectangle.
+fn fill_rect(pixmap: &mut Pixmap, viewport: &Viewport, from: (f64, f64), to: (f64, f64), color: Color32) {
+    let (x0, y0) = viewport.map(from.0, from.1);
+    let (x1, y1) = viewport.map(to.0, to.1);
+    let (left, top) = (x0.min(x1).round(), y0.min(y1).round());
+    let right = x0.max(x1).round().max(left + 1.0);
+    let bottom = y0.max(y1).round().max(top + 1.0);
+    
+    if let Some(rect) = Rect::from_ltrb(left, top, right, bottom) {
+        pixmap.fill_rect(rect, &paint(color), Transform::identity(), None);
+    }
+}
+
+/// Strokes a polyline given in data coordinates
+fn stroke_polyline(
+    pixmap: &mut Pixmap,
+    viewport: &Viewport,
+    points: &[[f64; 2]],
+    color: Color32,
+    width: f32,
+    style: LineStyle,
+) {
+    let mut builder = PathBuilder::new();
+    for (i, [x, y]) in points.iter().enumerate() {
+        let (px, py) = viewport.map(*x, *y);
+        if i == 0 { builder.move_to(px, py) } else { builder.line_to(px, py) }
+    }
+    
+    if let Some(path) = builder.finish() {
+        let dash = style.dash_pattern().and_then(|[on, off]| StrokeDash::new(vec![on, off], 0.0));
+        let stroke = Stroke { width, dash, ..Default::default() };
+        pixmap.stroke_path(&path, &paint(color), &stroke, Transform::identity(), None);
+    }
+}
+
+/// Builds an anti-aliased paint from a premultiplied egui color
+fn paint(color: Color32) -> Paint<'static> {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processor::{DataProcessor, HistoricalData};
+    use crate::plotter::{PlotConfig, Plotter};
+    use crate::utils::color_utils;
+    use std::io::BufReader;
+    
+    /// Test that candles land at their mapped pixel position in their color
+    #[test]
+    fn test_render() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::with_config(PlotConfig { size: (300, 200), ..Default::default() });
+        let model = plotter.render_model(processor.get_data())?;
+        
+        let pixmap = render(&model)?;
+        assert_eq!((pixmap.width(), pixmap.height()), (300, 200));
+        
+        let viewport = layout::panels(&model).price;
+        let candle = &model.candles[0];
+        let (x, y) = viewport.map(0.0, (candle.bottom + candle.top) / 2.0);
+        let pixel = pixmap.pixel(x as u32, y as u32).ok_or_else(|| anyhow!("pixel outside canvas"))?;
+        assert_eq!([pixel.red(), pixel.green(), pixel.blue()], color_utils::UP_COLOR.to_srgba_unmultiplied()[..3]);
+        
+        let corner = pixmap.pixel(0, 0).ok_or_else(|| anyhow!("pixel outside canvas"))?;
+        assert_eq!([corner.red(), corner.green(), corner.blue()], [255, 255, 255]);
+        
+        Ok(())
+    }
+    
+    /// Test rendering more candles than the chart has pixels for
+    #[test]
+    fn test_render_dense() -> Result<()> {
+        let start = chrono::NaiveDate::from_ymd_opt(2023, 1, 1).and_then(|day| day.and_hms_opt(0, 0, 0)).ok_or_else(|| anyhow!("bad date"))?;
+        let data: Vec<HistoricalData> = (0..1500)
+            .map(|i| {
+                let open = 100.0 + (i as f64 / 20.0).sin() * 10.0;
+                HistoricalData {
+                    timestamp: (start + chrono::Duration::hours(i)).format("%Y-%m-%d %H:%M:%S").to_string(),
+                    open,
+                    high: open + 1.0,
+                    low: open - 1.0,
+                    close: open + 0.5,
+                    volume: 1000.0,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let model = Plotter::new().render_model(&data)?;
+        assert_eq!(model.candles.len(), 1500);
+        
+        let pixmap = render(&model)?;
+        let viewport = layout::panels(&model).price;
+        let candle = &model.candles[750];
+        let (x, y) = viewport.map(750.0, (candle.bottom + candle.top) / 2.0);
+        let pixel = pixmap.pixel(x as u32, y as u32).ok_or_else(|| anyhow!("pixel outside canvas"))?;
+        assert_ne!([pixel.red(), pixel.green(), pixel.blue()], [255, 255, 255]);
+        
+        Ok(())
+    }
+    
+    /// Test drawing both charts of a comparison next to each other
+    #[test]
+    fn test_render_comparison() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::with_config(PlotConfig { size: (300, 200), ..Default::default() });
+        let comparison = plotter.comparison_model(processor.get_data(), &processor.get_data()[..1], true, true)?;
+        assert_eq!(comparison.right.size, (150, 200));
+        
+        let pixmap = render_comparison(&comparison)?;
+        assert_eq!((pixmap.width(), pixmap.height()), (300, 200));
+        
+        // The right panel is offset by the left one and drawn on the shared scale
+        let viewport = layout::panels(&comparison.right).price;
+        let candle = &comparison.right.candles[0];
+        let (x, y) = viewport.map(0.0, (candle.bottom + candle.top) / 2.0);
+        let pixel = pixmap.pixel(150 + x as u32, y as u32).ok_or_else(|| anyhow!("pixel outside canvas"))?;
+        assert_eq!([pixel.red(), pixel.green(), pixel.blue()], color_utils::UP_COLOR.to_srgba_unmultiplied()[..3]);
+        assert_eq!(comparison.right.y_range, comparison.left.y_range);
+        
+        Ok(())
+    }
+    
+    /// Test that the written PNG decodes to the rendered canvas
+    #[test]
+    fn test_write_png() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::with_config(PlotConfig { size: (120, 80), ..Default::default() });
+        let model = plotter.render_model(processor.get_data())?;
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("chart.png");
+        
+        write_png(&model, &path, &PngOptions::default())?;
+        
+        let mut reader = png::Decoder::new(BufReader::new(File::open(&path)?)).read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size().ok_or_else(|| anyhow!("image too large"))?];
+        let info = reader.next_frame(&mut pixels)?;
+        assert_eq!((info.width, info.height, info.color_type), (120, 80, png::ColorType::Rgba));
+        
+        let expected = render(&model)?;
+        let pixel = expected.pixel(60, 40).ok_or_else(|| anyhow!("pixel outside canvas"))?.demultiply();
+        let offset = (40 * 120 + 60) * 4;
+        assert_eq!(&pixels[offset..offset + 4], &[pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]);
+        
+        Ok(())
+    }
+    
+    /// Test that writing the same chart twice gives identical files
+    #[test]
+    fn test_write_png_reproducible() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let dir = tempfile::TempDir::new()?;
+        let options = PngOptions { dpi: Some(150), ..Default::default() };
+        
+        let mut files = Vec::new();
+        for name in ["first.png", "second.png"] {
+            let model = Plotter::with_config(PlotConfig { size: (120, 80), ..Default::default() }).render_model(processor.get_data())?;
+            let path = dir.path().join(name);
+            write_png(&model, &path, &options)?;
+            files.push(std::fs::read(&path)?);
+        }
+        assert_eq!(files[0], files[1]);
+        
+        Ok(())
+    }
+    
+    /// Test that the DPI is written as a pHYs chunk in pixels per meter
+    #[test]
+    fn test_write_png_dpi() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let model = Plotter::with_config(PlotConfig { size: (120, 80), ..Default::default() }).render_model(processor.get_data())?;
+        let dir = tempfile::TempDir::new()?;
+        let read_dims = |dpi| -> Result<Option<png::PixelDimensions>> {
+            let path = dir.path().join("chart.png");
+            write_png(&model, &path, &PngOptions { dpi, ..PngOptions::default() })?;
+            Ok(png::Decoder::new(BufReader::new(File::open(&path)?)).read_info()?.info().pixel_dims)
+        };
+        
+        let dims = read_dims(Some(300))?.ok_or_else(|| anyhow!("no pHYs chunk"))?;
+        assert_eq!((dims.xppu, dims.yppu, dims.unit), (11811, 11811, png::Unit::Meter));
+        assert!(read_dims(None)?.is_none());
+        assert_eq!(pixels_per_meter(72), 2835);
+        
+        Ok(())
+    }
+    
+    /// Test that compression levels shrink the file and simple charts get a palette
+    #[test]
+    fn test_write_png_options() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::with_config(PlotConfig { size: (120, 80), hide_candles: true, ..Default::default() });
+        let model = plotter.render_model(processor.get_data())?;
+        let dir = tempfile::TempDir::new()?;
+        let write = |name: &str, compression, palette| -> Result<(u64, png::ColorType)> {
+            let path = dir.path().join(name);
+            write_png(&model, &path, &PngOptions { compression, palette, dpi: None })?;
+            let reader = png::Decoder::new(BufReader::new(File::open(&path)?)).read_info()?;
+            Ok((std::fs::metadata(&path)?.len(), reader.info().color_type))
+        };
+        
+        let (stored, _) = write("stored.png", 0, false)?;
+        let (deflated, color) = write("deflated.png", 9, false)?;
+        assert!(deflated < stored, "{} >= {}", deflated, stored);
+        assert_eq!(color, png::ColorType::Rgba);
+        assert_eq!(write("palette.png", 9, true)?.1, png::ColorType::Indexed);
+        
+        Ok(())
+    }
+    
+    /// Test building a color table and giving up beyond 256 colors
+    #[test]
+    fn test_palette() {
+        let (table, indices) = palette(&[[1, 2, 3, 255], [0, 0, 0, 0], [1, 2, 3, 255]]).unwrap_or_default();
+        assert_eq!(table, vec![[1, 2, 3, 255], [0, 0, 0, 0]]);
+        assert_eq!(indices, vec![0, 1, 0]);
+        
+        let many: Vec<[u8; 4]> = (0..=256u16).map(|i| [(i % 256) as u8, (i / 256) as u8, 0, 255]).collect();
+        assert!(palette(&many).is_none());
+    }
+    
+    /// Test that a transparent background is written as alpha 0 while candles stay opaque
+    #[test]
+    fn test_write_png_transparent() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let plotter = Plotter::with_config(PlotConfig { size: (120, 80), transparent: true, ..Default::default() });
+        let model = plotter.render_model(processor.get_data())?;
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("chart.png");
+        
+        write_png(&model, &path, &PngOptions::default())?;
+        
+        let mut reader = png::Decoder::new(BufReader::new(File::open(&path)?)).read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size().ok_or_else(|| anyhow!("image too large"))?];
+        reader.next_frame(&mut pixels)?;
+        assert_eq!(pixels[3], 0);
+        
+        let candle = &model.candles[0];
+        let (x, y) = layout::panels(&model).price.map(0.0, (candle.bottom + candle.top) / 2.0);
+        let offset = (y as usize * 120 + x as usize) * 4;
+        assert_eq!(pixels[offset..offset + 4], color_utils::UP_COLOR.to_srgba_unmultiplied());
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,1056 @@
+//! Backend-independent description of a chart
+//!
+//! A `RenderModel` holds the exact geometry a renderer draws, expressed in
+//! data coordinates: x is the candle index and y is the price. Converting to
+//! pixels is left to each renderer.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use eframe::egui::Color32;
+use serde::{Serialize, Serializer};
+
+use crate::data_processor::EXPORT_SCHEMA_VERSION;
+use crate::utils::color_utils;
+
+/// Serializes a color as a `#rrggbbaa` hex string
+fn serialize_color<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&color_utils::to_hex(*color))
+}
+
+/// Inclusive range of an axis in data coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AxisRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Border drawn around a shape
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Outline {
+    /// Stroke width in pixels
+    pub width: f32,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+/// Source candles merged into one drawn candle by thinning
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CandleBucket {
+    /// Number of source candles
+    pub count: usize,
+    /// Localized dates of the first and last source candle
+    pub first: String,
+    pub last: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// Filled rectangle of a candle body
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CandleRect {
+    /// Index of the candle in the series
+    pub index: usize,
+    /// RFC 3339 timestamp of the candle
+    pub timestamp: String,
+    /// Localized date text used for axis labels and tooltips
+    pub label: String,
+    pub left: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub top: f64,
+    #[serde(serialize_with = "serialize_color")]
+    pub fill: Color32,
+    /// Emphasis border, e.g. for high-volume candles
+    pub outline: Option<Outline>,
+    /// The candle's period has not closed yet; its colors are already faded
+    /// and renderers may additionally hatch it
+    pub incomplete: bool,
+    /// The candle fills a gap in the input; its colors are already faded
+    pub synthetic: bool,
+}
+
+/// Vertical high/low segment of a candle
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WickSegment {
+    /// Index of the candle in the series
+    pub index: usize,
+    pub x: f64,
+    pub low: f64,
+    pub high: f64,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+/// Bar of the volume panel, aligned with the candle of the same index
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VolumeBar {
+    /// Index of the candle in the series
+    pub index: usize,
+    pub left: f64,
+    pub right: f64,
+    pub volume: f64,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+/// Chart area a series is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Panel {
+    /// Overlaid on the candles, sharing the price axis
+    Price,
+    /// Separate panel below the candles with its own y-axis
+    Lower,
+}
+
+/// Stroke pattern of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl LineStyle {
+    /// Names accepted by `LineStyle::parse`, in the order of the variants
+    const NAMES: [(&'static str, LineStyle); 3] = [
+        ("solid", LineStyle::Solid),
+        ("dashed", LineStyle::Dashed),
+        ("dotted", LineStyle::Dotted),
+    ];
+    
+    /// Parses a line style name, ignoring case
+    ///
+    /// # Arguments
+    /// * `name` - `solid`, `dashed` or `dotted`
+    ///
+    /// # Returns
+    /// * `Result<LineStyle>` - Matching style
+    ///
+    /// # Errors
+    /// * Returns error listing the valid names if the name is unknown
+    pub fn parse(name: &str) -> Result<LineStyle> {
+        let name = name.trim().to_lowercase();
+        LineStyle::NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, style)| *style)
+            .ok_or_else(|| anyhow::anyhow!(
+                "Unknown line style '{}', expected one of: {}",
+                name,
+                LineStyle::NAMES.map(|(known, _)| known).join(", ")
+            ))
+    }
+    
+    /// Lengths of the drawn and skipped segments in pixels, `None` for solid lines
+    pub fn dash_pattern(self) -> Option<[f32; 2]> {
+        match self {
+            LineStyle::Solid => None,
+            LineStyle::Dashed => Some([6.0, 4.0]),
+            LineStyle::Dotted => Some([1.5, 3.0]),
+        }
+    }
+}
+
+/// Polyline such as an indicator overlay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderLine {
+    pub name: String,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+    pub panel: Panel,
+    pub style: LineStyle,
+    pub points: Vec<[f64; 2]>,
+}
+
+impl RenderLine {
+    /// Builds a line from an indicator series, skipping undefined entries
+    ///
+    /// # Arguments
+    /// * `name` - Legend name of the series
+    /// * `color` - Stroke color
+    /// * `panel` - Chart area to draw in
+    /// * `values` - One value per candle, the index is used as x
+    ///
+    /// # Returns
+    /// * `RenderLine` - Solid line through the defined values
+    pub fn from_series(name: &str, color: Color32, panel: Panel, values: &[Option<f64>]) -> Self {
+        let points = values
+            .iter()
+            .enumerate()
+            .filter_map(|(i, value)| value.map(|v| [i as f64, v]))
+            .collect();
+        
+        RenderLine { name: name.to_string(), color, panel, style: LineStyle::Solid, points }
+    }
+}
+
+/// Filled convex polygon such as the area of a baseline chart
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderFill {
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+    /// Corners in drawing order
+    pub points: Vec<[f64; 2]>,
+}
+
+/// Background band behind the price panel, spanning its full height
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderBand {
+    pub name: String,
+    pub left: f64,
+    pub right: f64,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+impl RenderFill {
+    /// Fills the area between a polyline and a horizontal baseline
+    ///
+    /// Every segment becomes its own polygon, split where it crosses the
+    /// baseline, so each polygon is convex and lies entirely on one side.
+    ///
+    /// # Arguments
+    /// * `points` - Polyline vertices ordered by x
+    /// * `baseline` - Price the area is filled to
+    /// * `above` - Fill color where the line is above the baseline
+    /// * `below` - Fill color where the line is below the baseline
+    ///
+    /// # Returns
+    /// * `Vec<RenderFill>` - Polygons in x order, empty for fewer than two points
+    pub fn split_at_baseline(points: &[[f64; 2]], baseline: f64, above: Color32, below: Color32) -> Vec<RenderFill> {
+        let mut fills = Vec::new();
+        let mut push = |from: [f64; 2], to: [f64; 2]| {
+            let color = if from[1] + to[1] >= 2.0 * baseline { above } else { below };
+            fills.push(RenderFill { color, points: vec![[from[0], baseline], from, to, [to[0], baseline]] });
+        };
+        
+        for segment in points.windows(2) {
+            let ([x0, y0], [x1, y1]) = (segment[0], segment[1]);
+            
+            if (y0 - baseline) * (y1 - baseline) < 0.0 {
+                let crossing = [x0 + (x1 - x0) * (baseline - y0) / (y1 - y0), baseline];
+                push([x0, y0], crossing);
+                push(crossing, [x1, y1]);
+            } else {
+                push([x0, y0], [x1, y1]);
+            }
+        }
+        
+        fills
+    }
+}
+
+/// Shape of a point marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerShape {
+    /// Triangle pointing up
+    Up,
+    /// Triangle pointing down
+    Down,
+}
+
+/// Group of price panel elements drawn together
+///
+/// Renderers draw the layers in `RenderModel.layers` order, so later layers
+/// cover earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layer {
+    /// Shaded time ranges such as trading sessions
+    Bands,
+    /// Horizontal price gridlines
+    Grid,
+    /// Filled areas between indicator lines
+    Fills,
+    /// Candle bodies and wicks
+    Candles,
+    /// Indicator lines on the price panel
+    Lines,
+    /// Vertical event lines and their labels
+    Events,
+    /// Pattern and signal markers
+    Markers,
+}
+
+impl Layer {
+    /// Draw order used unless configured otherwise, from bottom to top
+    pub const DEFAULT_ORDER: [Layer; 7] =
+        [Layer::Bands, Layer::Grid, Layer::Fills, Layer::Candles, Layer::Lines, Layer::Events, Layer::Markers];
+}
+
+/// Point annotation such as a detected pattern
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderMarker {
+    /// Index of the annotated candle
+    pub index: usize,
+    pub name: String,
+    pub x: f64,
+    /// Price the marker refers to, e.g. the high of an up fractal
+    pub y: f64,
+    pub shape: MarkerShape,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+/// Vertical line marking a dated event such as earnings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderEvent {
+    /// Index of the candle the event falls into
+    pub index: usize,
+    pub x: f64,
+    /// Text drawn next to the line
+    pub label: String,
+    pub kind: String,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+/// Purpose of a piece of canvas text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextRole {
+    Title,
+    Subtitle,
+    Caption,
+}
+
+/// Horizontal alignment of canvas text relative to its anchor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    Left,
+    Center,
+}
+
+/// Text drawn on the canvas outside the plot area, positioned in pixels
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CanvasText {
+    pub role: TextRole,
+    /// Text already wrapped or truncated to the canvas width, one entry per line
+    pub lines: Vec<String>,
+    /// Anchor from the left edge, interpreted according to `align`
+    pub x: f64,
+    /// Top of the first line from the top edge
+    pub y: f64,
+    pub font_size: f32,
+    pub bold: bool,
+    pub align: TextAlign,
+}
+
+/// Tick labels of the x-axis, drawn below the lowest panel by the SVG export
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AxisLabels {
+    /// Candle index and text of each labeled tick
+    pub ticks: Vec<(f64, String)>,
+    /// Clockwise rotation of every label in degrees, 0 for horizontal text
+    pub rotation: f32,
+    pub font_size: f32,
+    /// Vertical space the rotated labels take in pixels, included in the bottom margin
+    pub height: f64,
+}
+
+/// Price label at the right edge of the price panel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceTag {
+    pub price: f64,
+    /// Price formatted with `price_precision` decimals
+    pub label: String,
+    /// Background of the tag
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+/// Space in pixels kept free of the plot area for canvas text
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Margins {
+    pub top: f64,
+    pub bottom: f64,
+    /// Width kept free at the right edge, for the colorbar
+    pub right: f64,
+}
+
+/// Point of the canvas a watermark is centered on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatermarkPosition {
+    #[default]
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl WatermarkPosition {
+    /// Center of the watermark as fractions of the canvas width and height
+    pub fn anchor(self) -> (f32, f32) {
+        match self {
+            WatermarkPosition::Center => (0.5, 0.5),
+            WatermarkPosition::TopLeft => (0.25, 0.25),
+            WatermarkPosition::TopRight => (0.75, 0.25),
+            WatermarkPosition::BottomLeft => (0.25, 0.75),
+            WatermarkPosition::BottomRight => (0.75, 0.75),
+        }
+    }
+}
+
+/// Text such as `DRAFT` drawn faintly over the whole chart
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Watermark {
+    pub text: String,
+    /// 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f32,
+    pub position: WatermarkPosition,
+    /// Clockwise rotation in degrees around the text center
+    pub rotation: f32,
+}
+
+/// Sample of a continuous color scale
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ColorStop {
+    pub value: f64,
+    #[serde(serialize_with = "serialize_color")]
+    pub color: Color32,
+}
+
+/// Legend of a continuous candle color scale, drawn right of the panels
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Colorbar {
+    pub title: String,
+    /// Evenly spaced samples from the lowest to the highest value
+    pub stops: Vec<ColorStop>,
+}
+
+impl Colorbar {
+    /// Formats a value of the scale as a signed percentage of a log return
+    pub fn label(value: f64) -> String {
+        format!("{:+.2}%", value * 100.0)
+    }
+}
+
+/// Everything a renderer needs to draw a chart
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderModel {
+    pub candles: Vec<CandleRect>,
+    pub wicks: Vec<WickSegment>,
+    pub lines: Vec<RenderLine>,
+    pub markers: Vec<RenderMarker>,
+    pub fills: Vec<RenderFill>,
+    /// Shading of time ranges on the price panel, e.g. trading sessions
+    pub bands: Vec<RenderBand>,
+    pub events: Vec<RenderEvent>,
+    /// Bars of the volume panel, empty when it is not shown
+    pub volume: Vec<VolumeBar>,
+    pub x_range: AxisRange,
+    pub y_range: AxisRange,
+    /// Y-range of the lower panel, present when any line is drawn there
+    pub lower_y_range: Option<AxisRange>,
+    /// Y-range of the volume panel, present when it is shown
+    pub volume_y_range: Option<AxisRange>,
+    /// Digits after the decimal point of price axis labels
+    pub price_precision: usize,
+    /// Digits after the decimal point of prices in tooltips, usually more
+    /// than the axis shows so hovering reveals exact values
+    pub tooltip_precision: usize,
+    /// Canvas width and height in pixels that `texts` and `margins` were laid out for
+    pub size: (u32, u32),
+    /// Title, subtitle and caption
+    pub texts: Vec<CanvasText>,
+    pub margins: Margins,
+    /// Scale of continuously colored candles, present with `--color-by-return`
+    pub colorbar: Option<Colorbar>,
+    /// Text composited over everything else, after all other layers
+    pub watermark: Option<Watermark>,
+    /// Leave the canvas background fully transparent instead of white
+    pub transparent: bool,
+    /// Dates below the lowest panel, `None` when there are no candles
+    pub x_labels: Option<AxisLabels>,
+    /// Tag of the last close drawn by the window and the SVG export,
+    /// `None` unless the last price line is enabled
+    pub price_tag: Option<PriceTag>,
+    /// Title of the price axis, `None` for plain prices
+    pub y_label: Option<String>,
+    /// Message renderers draw centered in place of the chart, present when
+    /// there are no candles
+    pub placeholder: Option<String>,
+    /// Prices of horizontal gridlines on the price panel, replacing the
+    /// window's automatic grid when not empty
+    pub price_grid: Vec<f64>,
+    /// Draw order of the price panel layers, from bottom to top
+    pub layers: Vec<Layer>,
+    /// Source candles behind each drawn candle by series index, empty when
+    /// the series was not thinned
+    pub buckets: Vec<CandleBucket>,
+}
+
+impl RenderModel {
+    /// Writes the model as pretty-printed JSON
+    ///
+    /// # Arguments
+    /// * `path` - Destination file
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the file was written
+    ///
+    /// # Errors
+    /// * Returns error if serialization or writing fails
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        write_json(self, path)
+    }
+    
+    /// Keeps only the geometry inside `x_range` and makes it the visible range
+    ///
+    /// Candles, wicks, markers, events and volume bars are kept when their
+    /// center lies inside the range, fills when all their corners do, and lines
+    /// keep the points inside. Bands are cut to the range. Axis ranges other than x are left unchanged.
+    ///
+    /// # Arguments
+    /// * `x_range` - Range to keep, in data coordinates
+    pub fn crop(&mut self, x_range: AxisRange) {
+        let inside = |x: f64| x >= x_range.min && x <= x_range.max;
+        
+        self.candles.retain(|candle| inside((candle.left + candle.right) / 2.0));
+        self.wicks.retain(|wick| inside(wick.x));
+        self.markers.retain(|marker| inside(marker.x));
+        self.events.retain(|event| inside(event.x));
+        self.volume.retain(|bar| inside((bar.left + bar.right) / 2.0));
+        self.fills.retain(|fill| fill.points.iter().all(|[x, _]| inside(*x)));
+        self.bands.retain(|band| band.right > x_range.min && band.left < x_range.max);
+        for band in &mut self.bands {
+            (band.left, band.right) = (band.left.max(x_range.min), band.right.min(x_range.max));
+        }
+        for line in &mut self.lines {
+            line.points.retain(|[x, _]| inside(*x));
+        }
+        self.lines.retain(|line| !line.points.is_empty());
+        self.x_range = x_range;
+    }
+    
+    /// Moves every candle slot from its index to a new x position
+    ///
+    /// Coordinates keep their offset from the nearest candle index, so bodies
+    /// keep their width; coordinates beyond the first or last candle move
+    /// with it.
+    ///
+    /// # Arguments
+    /// * `positions` - New x of each candle index, non-decreasing
+    pub fn remap_x(&mut self, positions: &[f64]) {
+        if positions.is_empty() {
+            return;
+        }
+        let map = |x: f64| {
+            let index = x.round().clamp(0.0, (positions.len() - 1) as f64);
+            positions[index as usize] + (x - index)
+        };
+        
+        for candle in &mut self.candles {
+            (candle.left, candle.right) = (map(candle.left), map(candle.right));
+        }
+        for wick in &mut self.wicks {
+            wick.x = map(wick.x);
+        }
+        for marker in &mut self.markers {
+            marker.x = map(marker.x);
+        }
+        for event in &mut self.events {
+            event.x = map(event.x);
+        }
+        for bar in &mut self.volume {
+            (bar.left, bar.right) = (map(bar.left), map(bar.right));
+        }
+        for band in &mut self.bands {
+            (band.left, band.right) = (map(band.left), map(band.right));
+        }
+        for point in self.lines.iter_mut().flat_map(|line| &mut line.points).chain(self.fills.iter_mut().flat_map(|fill| &mut fill.points)) {
+            point[0] = map(point[0]);
+        }
+        if let Some(labels) = &mut self.x_labels {
+            for (x, _) in &mut labels.ticks {
+                *x = map(*x);
+            }
+        }
+        self.x_range = AxisRange { min: map(self.x_range.min), max: map(self.x_range.max) };
+    }
+    
+    /// Formats a price axis label with `price_precision` decimals
+    ///
+    /// # Arguments
+    /// * `price` - Value at the tick mark
+    ///
+    /// # Returns
+    /// * `String` - Label text
+    pub fn axis_label(&self, price: f64) -> String {
+        format!("{:.*}", self.price_precision, price)
+    }
+    
+    /// Formats the tooltip of a candle with `tooltip_precision` decimals
+    ///
+    /// A candle merged from several source candles by thinning reports the
+    /// aggregated OHLCV of its bucket and how many candles it covers.
+    ///
+    /// # Arguments
+    /// * `index` - Index of the hovered candle
+    ///
+    /// # Returns
+    /// * `Option<String>` - Date, body range, high and low, or the bucket's
+    ///   date range and OHLCV, `None` if there is no such candle
+    pub fn tooltip(&self, index: usize) -> Option<String> {
+        let (candle, wick) = (self.candles.get(index)?, self.wicks.get(index)?);
+        let precision = self.tooltip_precision;
+        
+        if let Some(bucket) = self.buckets.get(candle.index).filter(|bucket| bucket.count > 1) {
+            return Some(format!(
+                "{} - {} ({} candles)\nOpen {:.*}\nHigh {:.*}\nLow {:.*}\nClose {:.*}\nVolume {}",
+                bucket.first, bucket.last, bucket.count, precision, bucket.open, precision, bucket.high,
+                precision, bucket.low, precision, bucket.close, bucket.volume
+            ));
+        }
+        Some(format!(
+            "{}\nBody {:.*} - {:.*}\nHigh {:.*}\nLow {:.*}",
+            candle.label, precision, candle.bottom, precision, candle.top, precision, wick.high, precision, wick.low
+        ))
+    }
+}
+
+/// Two charts displayed side by side
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComparisonModel {
+    pub left: RenderModel,
+    pub right: RenderModel,
+    /// Both panels use the union of their price ranges
+    pub shared_scale: bool,
+    /// Horizontal zoom and pan are synchronized between the panels
+    pub link_axes: bool,
+}
+
+impl ComparisonModel {
+    /// Combines two single-chart models, aligning the axes as requested
+    ///
+    /// # Arguments
+    /// * `left` - Model of the left panel
+    /// * `right` - Model of the right panel
+    /// * `shared_scale` - Give both panels the same y-range
+    /// * `link_axes` - Give both panels the same x-range
+    ///
+    /// # Returns
+    /// * `ComparisonModel` - Combined model
+    pub fn new(mut left: RenderModel, mut right: RenderModel, shared_scale: bool, link_axes: bool) -> Self {
+        if shared_scale {
+            let y_range = left.y_range.union(&right.y_range);
+            left.y_range = y_range;
+            right.y_range = y_range;
+        }
+        
+        if link_axes {
+            let x_range = left.x_range.union(&right.x_range);
+            left.x_range = x_range;
+            right.x_range = x_range;
+        }
+        
+        ComparisonModel { left, right, shared_scale, link_axes }
+    }
+    
+    /// Writes the model as pretty-printed JSON
+    ///
+    /// # Arguments
+    /// * `path` - Destination file
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the file was written
+    ///
+    /// # Errors
+    /// * Returns error if serialization or writing fails
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        write_json(self, path)
+    }
+}
+
+impl AxisRange {
+    /// Returns the range spanned by the y values of the given lines
+    ///
+    /// # Arguments
+    /// * `lines` - Lines to measure
+    ///
+    /// # Returns
+    /// * `Option<AxisRange>` - Range of all points, `None` if there are none
+    pub fn of_lines<'a>(lines: impl IntoIterator<Item = &'a RenderLine>) -> Option<AxisRange> {
+        lines
+            .into_iter()
+            .flat_map(|line| line.points.iter())
+            .map(|[_, y]| AxisRange { min: *y, max: *y })
+            .reduce(|a, b| a.union(&b))
+    }
+    
+    /// Returns the smallest range containing both ranges
+    pub fn union(&self, other: &AxisRange) -> AxisRange {
+        AxisRange { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+}
+
+/// Lookup between x coordinates, candles and time, e.g. for the candle under
+/// the cursor or the position of an event
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeIndex {
+    /// Center x of every candle in chronological order
+    positions: Vec<f64>,
+    /// Start time of every candle, parallel to `positions`
+    timestamps: Vec<DateTime<Utc>>,
+}
+
+impl TimeIndex {
+    /// Indexes the candle centers of a model
+    ///
+    /// # Arguments
+    /// * `model` - Model whose wicks give the candle centers
+    ///
+    /// # Returns
+    /// * `TimeIndex` - Index over the candles
+    pub fn new(model: &RenderModel) -> Self {
+        TimeIndex {
+            positions: model.wicks.iter().map(|wick| wick.x).collect(),
+            timestamps: model.candles
+                .iter()
+                .filter_map(|candle| DateTime::parse_from_rfc3339(&candle.timestamp).ok())
+                .map(|timestamp| timestamp.with_timezone(&Utc))
+                .collect(),
+        }
+    }
+    
+    /// Indexes candles placed at x = index, as in every render model
+    ///
+    /// # Arguments
+    /// * `timestamps` - Start time of every candle in chronological order
+    ///
+    /// # Returns
+    /// * `TimeIndex` - Index over the candles
+    pub fn from_timestamps(timestamps: Vec<DateTime<Utc>>) -> Self {
+        TimeIndex { positions: (0..timestamps.len()).map(|i| i as f64).collect(), timestamps }
+    }
+    
+    /// Finds the candle whose period contains `timestamp`
+    ///
+    /// A candle's period runs until the next candle starts; the last candle
+    /// is assumed to last as long as the one before it.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Time to look up
+    ///
+    /// # Returns
+    /// * `Option<(usize, f64)>` - Candle index and center x, `None` if the
+    ///   time lies outside the indexed candles
+    pub fn locate(&self, timestamp: DateTime<Utc>) -> Option<(usize, f64)> {
+        let index = self.timestamps.partition_point(|start| *start <= timestamp).checked_sub(1)?;
+        
+        if index + 1 == self.timestamps.len() {
+            let length = match index {
+                0 => chrono::Duration::zero(),
+                _ => self.timestamps[index] - self.timestamps[index - 1],
+            };
+            if timestamp > self.timestamps[index] + length {
+                return None;
+            }
+        }
+        
+        self.positions.get(index).map(|x| (index, *x))
+    }
+    
+    /// Finds the candle whose center is closest to `x`
+    ///
+    /// # Arguments
+    /// * `x` - Position in data coordinates
+    ///
+    /// # Returns
+    /// * `Option<usize>` - Candle index, `None` if there are no candles
+    pub fn nearest(&self, x: f64) -> Option<usize> {
+        let after = self.positions.partition_point(|position| *position < x);
+        
+        match (after.checked_sub(1), self.positions.get(after)) {
+            (Some(before), Some(next)) if x - self.positions[before] <= next - x => Some(before),
+            (_, Some(_)) => Some(after),
+            (Some(before), None) => Some(before),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A model tagged with the export schema version
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    model: &'a T,
+}
+
+/// Serializes any model as pretty-printed JSON into a file
+///
+/// The top-level object carries a `schema_version` field holding
+/// `EXPORT_SCHEMA_VERSION`.
+fn write_json<T: Serialize>(model: &T, path: &Path) -> Result<()> {
+    let versioned = Versioned { schema_version: EXPORT_SCHEMA_VERSION, model };
+    let json = serde_json::to_string_pretty(&versioned).context("Failed to serialize render model")?;
+    fs::write(path, json)
+        .context(format!("Failed to write render model: {}", path.display()))?;
+    log::info!("Wrote render model to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    /// Builds an empty model with the given axis ranges
+    fn model_with_ranges(x: (f64, f64), y: (f64, f64)) -> RenderModel {
+        RenderModel {
+            candles: Vec::new(),
+            wicks: Vec::new(),
+            lines: Vec::new(),
+            markers: Vec::new(),
+            fills: Vec::new(),
+            bands: Vec::new(),
+            events: Vec::new(),
+            volume: Vec::new(),
+            x_range: AxisRange { min: x.0, max: x.1 },
+            y_range: AxisRange { min: y.0, max: y.1 },
+            lower_y_range: None,
+            volume_y_range: None,
+            price_precision: 2,
+            tooltip_precision: 4,
+            size: (1200, 800),
+            texts: Vec::new(),
+            margins: Margins::default(),
+            colorbar: None,
+            watermark: None,
+            transparent: false,
+            x_labels: None,
+            price_tag: None,
+            y_label: None,
+            placeholder: None,
+            price_grid: Vec::new(),
+            layers: Layer::DEFAULT_ORDER.to_vec(),
+            buckets: Vec::new(),
+        }
+    }
+    
+    /// Test shared and independent scaling of comparison panels
+    #[test]
+    fn test_comparison_model() {
+        let left = model_with_ranges((-0.5, 9.5), (10.0, 20.0));
+        let right = model_with_ranges((-0.5, 4.5), (100.0, 200.0));
+        
+        let independent = ComparisonModel::new(left.clone(), right.clone(), false, false);
+        assert_eq!(independent.left.y_range, AxisRange { min: 10.0, max: 20.0 });
+        assert_eq!(independent.right.x_range, AxisRange { min: -0.5, max: 4.5 });
+        
+        let shared = ComparisonModel::new(left, right, true, true);
+        assert_eq!(shared.left.y_range, AxisRange { min: 10.0, max: 200.0 });
+        assert_eq!(shared.right.y_range, shared.left.y_range);
+        assert_eq!(shared.right.x_range, AxisRange { min: -0.5, max: 9.5 });
+    }
+    
+    /// Test building lines from series with gaps and measuring their range
+    #[test]
+    fn test_render_line_from_series() {
+        let line = RenderLine::from_series("test", Color32::RED, Panel::Lower, &[None, Some(2.0), Some(-1.0)]);
+        
+        assert_eq!(line.points, vec![[1.0, 2.0], [2.0, -1.0]]);
+        assert_eq!(AxisRange::of_lines([&line]), Some(AxisRange { min: -1.0, max: 2.0 }));
+        assert_eq!(AxisRange::of_lines(Vec::<&RenderLine>::new()), None);
+    }
+    
+    /// Test parsing line style names
+    #[test]
+    fn test_line_style_parse() -> Result<()> {
+        assert_eq!(LineStyle::parse("Dashed")?, LineStyle::Dashed);
+        assert_eq!(LineStyle::parse(" dotted ")?, LineStyle::Dotted);
+        assert_eq!(LineStyle::parse("solid")?.dash_pattern(), None);
+        
+        let err = LineStyle::parse("wavy").unwrap_err().to_string();
+        assert!(err.contains("solid, dashed, dotted"), "{}", err);
+        
+        Ok(())
+    }
+    
+    /// Test splitting a baseline fill where the line crosses the baseline
+    #[test]
+    fn test_split_at_baseline() {
+        let (above, below) = (Color32::GREEN, Color32::RED);
+        let fills = RenderFill::split_at_baseline(&[[0.0, 12.0], [1.0, 8.0], [2.0, 9.0]], 10.0, above, below);
+        
+        assert_eq!(fills.len(), 3);
+        assert_eq!(fills[0].color, above);
+        assert_eq!(fills[0].points, vec![[0.0, 10.0], [0.0, 12.0], [0.5, 10.0], [0.5, 10.0]]);
+        assert_eq!(fills[1].color, below);
+        assert_eq!(fills[1].points[1], [0.5, 10.0]);
+        assert_eq!(fills[2].color, below);
+        assert!(RenderFill::split_at_baseline(&[[0.0, 1.0]], 0.0, above, below).is_empty());
+    }
+    
+    /// Test nearest candle lookup inside, between and outside the candles
+    #[test]
+    fn test_time_index_nearest() {
+        let mut model = model_with_ranges((-0.5, 2.5), (0.0, 1.0));
+        model.wicks = (0..3)
+            .map(|index| WickSegment { index, x: index as f64, low: 0.0, high: 1.0, color: Color32::RED })
+            .collect();
+        let index = TimeIndex::new(&model);
+        
+        assert_eq!(index.nearest(1.2), Some(1));
+        assert_eq!(index.nearest(1.5), Some(1));
+        assert_eq!(index.nearest(1.6), Some(2));
+        assert_eq!(index.nearest(-3.0), Some(0));
+        assert_eq!(index.nearest(99.0), Some(2));
+        assert_eq!(TimeIndex::new(&model_with_ranges((0.0, 1.0), (0.0, 1.0))).nearest(0.0), None);
+    }
+    
+    /// Test locating times inside, between and outside daily candles
+    #[test]
+    fn test_time_index_locate() -> Result<()> {
+        let day = |d: u32| -> Result<DateTime<Utc>> {
+            Ok(DateTime::parse_from_rfc3339(&format!("2023-01-{:02}T00:00:00+00:00", d))?.with_timezone(&Utc))
+        };
+        let index = TimeIndex::from_timestamps(vec![day(2)?, day(3)?, day(4)?]);
+        
+        assert_eq!(index.locate(day(2)?), Some((0, 0.0)));
+        assert_eq!(index.locate(day(3)? + chrono::Duration::hours(12)), Some((1, 1.0)));
+        assert_eq!(index.locate(day(4)? + chrono::Duration::hours(23)), Some((2, 2.0)));
+        assert_eq!(index.locate(day(6)?), None);
+        assert_eq!(index.locate(day(1)?), None);
+        
+        Ok(())
+    }
+    
+    /// Test cropping geometry to an x-range
+    #[test]
+    fn test_crop() {
+        let mut model = model_with_ranges((-0.5, 3.5), (0.0, 10.0));
+        model.wicks = (0..4)
+            .map(|index| WickSegment { index, x: index as f64, low: 0.0, high: 1.0, color: Color32::RED })
+            .collect();
+        model.lines = vec![
+            RenderLine::from_series("kept", Color32::RED, Panel::Price, &[Some(1.0), Some(2.0), Some(3.0), Some(4.0)]),
+            RenderLine::from_series("dropped", Color32::RED, Panel::Price, &[Some(1.0), None, None, None]),
+        ];
+        model.fills = RenderFill::split_at_baseline(&model.lines[0].points.clone(), 0.0, Color32::GREEN, Color32::RED);
+        
+        let x_range = AxisRange { min: 0.5, max: 2.5 };
+        model.crop(x_range);
+        
+        assert_eq!(model.x_range, x_range);
+        assert_eq!(model.wicks.iter().map(|wick| wick.index).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(model.lines.len(), 1);
+        assert_eq!(model.lines[0].points, vec![[1.0, 2.0], [2.0, 3.0]]);
+        assert_eq!(model.fills.len(), 1);
+        assert_eq!(model.y_range, AxisRange { min: 0.0, max: 10.0 });
+    }
+    
+    /// Test moving candle indices onto other x positions
+    #[test]
+    fn test_remap_x() {
+        let mut model = model_with_ranges((-0.5, 2.5), (0.0, 10.0));
+        model.wicks = (0..3)
+            .map(|index| WickSegment { index, x: index as f64, low: 0.0, high: 1.0, color: Color32::RED })
+            .collect();
+        model.lines = vec![RenderLine::from_series("line", Color32::RED, Panel::Price, &[Some(1.0), Some(2.0), Some(3.0)])];
+        
+        model.remap_x(&[0.0, 3.0, 4.0]);
+        
+        assert_eq!(model.wicks.iter().map(|wick| wick.x).collect::<Vec<_>>(), vec![0.0, 3.0, 4.0]);
+        assert_eq!(model.lines[0].points, vec![[0.0, 1.0], [3.0, 2.0], [4.0, 3.0]]);
+        assert_eq!(model.x_range, AxisRange { min: -0.5, max: 4.5 });
+    }
+    
+    /// Test that tooltips and axis labels use their own precision
+    #[test]
+    fn test_tooltip_precision() {
+        let mut model = model_with_ranges((-0.5, 0.5), (0.0, 2.0));
+        model.candles = vec![CandleRect {
+            index: 0,
+            timestamp: "2023-01-01T00:00:00+00:00".to_string(),
+            label: "01/01/2023".to_string(),
+            left: -0.4,
+            right: 0.4,
+            bottom: 1.23456,
+            top: 1.5,
+            fill: Color32::GREEN,
+            outline: None,
+            incomplete: false,
+            synthetic: false,
+        }];
+        model.wicks = vec![WickSegment { index: 0, x: 0.0, low: 1.0, high: 1.98767, color: Color32::GREEN }];
+        
+        assert_eq!(model.axis_label(1.23456), "1.23");
+        assert_eq!(model.tooltip(0).as_deref(), Some("01/01/2023\nBody 1.2346 - 1.5000\nHigh 1.9877\nLow 1.0000"));
+        assert_eq!(model.tooltip(1), None);
+        
+        model.tooltip_precision = 1;
+        assert!(model.tooltip(0).is_some_and(|text| text.contains("High 2.0")));
+    }
+    
+    /// Test JSON serialization of colors and geometry
+    #[test]
+    fn test_write_json() -> Result<()> {
+        let model = RenderModel {
+            candles: vec![CandleRect {
+                index: 0,
+                timestamp: "2023-01-01T00:00:00+00:00".to_string(),
+                label: "01/01/2023".to_string(),
+                left: -0.4,
+                right: 0.4,
+                bottom: 100.0,
+                top: 102.0,
+                fill: Color32::from_rgb(255, 0, 0),
+                outline: None,
+                incomplete: false,
+                synthetic: false,
+            }],
+            wicks: Vec::new(),
+            lines: Vec::new(),
+            markers: Vec::new(),
+            fills: Vec::new(),
+            bands: Vec::new(),
+            events: Vec::new(),
+            volume: Vec::new(),
+            x_range: AxisRange { min: -0.5, max: 0.5 },
+            y_range: AxisRange { min: 95.0, max: 105.0 },
+            lower_y_range: None,
+            volume_y_range: None,
+            price_precision: 2,
+            tooltip_precision: 4,
+            size: (1200, 800),
+            texts: Vec::new(),
+            margins: Margins::default(),
+            colorbar: None,
+            watermark: None,
+            transparent: false,
+            x_labels: None,
+            price_tag: None,
+            y_label: None,
+            placeholder: None,
+            price_grid: Vec::new(),
+            layers: Layer::DEFAULT_ORDER.to_vec(),
+            buckets: Vec::new(),
+        };
+        
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("model.json");
+        model.write_json(&path)?;
+        
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(json["candles"][0]["fill"], "#ff0000ff");
+        assert_eq!(json["candles"][0]["top"], 102.0);
+        assert_eq!(json["y_range"]["min"], 95.0);
+        assert_eq!(json["schema_version"], EXPORT_SCHEMA_VERSION);
+        
+        Ok(())
+    }
+}
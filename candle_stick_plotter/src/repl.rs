@@ -0,0 +1,287 @@
+//! Interactive command mode for exploring loaded data
+//!
+//! Each line read from the input is parsed into a `Command`, applied to the
+//! current view of the data, and the plot is refreshed afterwards. Indicator
+//! commands also add their indicator to the chart for the rest of the session.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use anyhow::{Result, Context};
+
+use crate::data_processor::{DataProcessor, HistoricalData};
+use crate::indicators;
+use crate::plotter::{MovingAverage, Overlay, PlotConfig, Plotter, DEFAULT_SERIES};
+use crate::render_model::LineStyle;
+use crate::utils::date_utils;
+use crate::utils::locale_utils::Locale;
+
+/// Help text printed for `help` and for unknown commands
+const HELP: &str = "\
+Commands:
+  sma <period>        Add the simple moving average of the close
+  ema <period>        Add the exponential moving average of the close
+  rsi <period>        Add the relative strength index of the close
+  atr <period>        Add the average true range
+  macd                Add MACD(12, 26, 9) of the close
+  range <from> <to>   Restrict the view to a period (YYYY, YYYY-MM or YYYY-MM-DD)
+  reset               Show all loaded data again
+  help                Show this help
+  quit                Leave interactive mode";
+
+/// A parsed interactive command
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Sma(usize),
+    Ema(usize),
+    Rsi(usize),
+    Atr(usize),
+    Macd,
+    Range(String, String),
+    Reset,
+    Help,
+    Quit,
+}
+
+/// Parses one input line into a command
+///
+/// # Arguments
+/// * `line` - Raw input line
+///
+/// # Returns
+/// * `Option<Command>` - Parsed command, `None` if the line is not understood
+pub fn parse_command(line: &str) -> Option<Command> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    match tokens.as_slice() {
+        ["sma", period] => period.parse().ok().map(Command::Sma),
+        ["ema", period] => period.parse().ok().map(Command::Ema),
+        ["rsi", period] => period.parse().ok().map(Command::Rsi),
+        ["atr", period] => period.parse().ok().map(Command::Atr),
+        ["macd"] => Some(Command::Macd),
+        ["range", from, to] => Some(Command::Range(from.to_string(), to.to_string())),
+        ["reset"] => Some(Command::Reset),
+        ["help"] => Some(Command::Help),
+        ["quit"] | ["exit"] => Some(Command::Quit),
+        _ => None,
+    }
+}
+
+/// Runs the interactive loop until `quit` or end of input
+///
+/// # Arguments
+/// * `input` - Source of command lines
+/// * `output` - Destination for prompts and results
+/// * `data` - Loaded historical data
+/// * `plotter` - Plotter used to refresh the chart
+/// * `output_dir` - Directory to save plot outputs
+///
+/// # Returns
+/// * `Result<()>` - Ok when the session ends normally
+///
+/// # Errors
+/// * Returns error if reading input, writing output or plotting fails
+pub fn run<R: BufRead, W: Write>(
+    input: R,
+    output: &mut W,
+    data: Vec<HistoricalData>,
+    plotter: &mut Plotter,
+    output_dir: &str,
+) -> Result<()> {
+    let mut view = data.clone();
+    writeln!(output, "Interactive mode: {} candles loaded, type 'help' for commands", data.len())?;
+
+    for line in input.lines() {
+        let line = line.context("Failed to read interactive input")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = match parse_command(&line) {
+            Some(Command::Quit) => break,
+            Some(command) => command,
+            None => {
+                writeln!(output, "Unknown command: {}\n{}", line.trim(), HELP)?;
+                continue;
+            }
+        };
+
+        let locale = plotter.config().locale;
+        let mut overlays = plotter.config().overlays.clone();
+        if let Err(err) = execute(&command, &data, &mut view, &mut overlays, &locale, output) {
+            writeln!(output, "Error: {:#}", err)?;
+            continue;
+        }
+
+        if command != Command::Help {
+            if overlays != plotter.config().overlays {
+                *plotter = Plotter::with_config(PlotConfig { overlays, ..plotter.config().clone() });
+            }
+            let data_map = HashMap::from([(DEFAULT_SERIES.to_string(), view.clone())]);
+            plotter.create_candlestick_plot(&data_map, output_dir)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies a single command to the current view
+///
+/// Indicator commands add their overlay to `overlays` unless it is already drawn.
+fn execute<W: Write>(
+    command: &Command,
+    data: &[HistoricalData],
+    view: &mut Vec<HistoricalData>,
+    overlays: &mut Vec<Overlay>,
+    locale: &Locale,
+    output: &mut W,
+) -> Result<()> {
+    let average = |period: usize| MovingAverage { period, color: None, style: LineStyle::Solid };
+    let overlay = match command {
+        Command::Sma(period) => {
+            let values = DataProcessor::with_data(view.clone()).simple_moving_average(*period)?;
+            report_indicator(output, locale, &format!("SMA({})", period), &values)?;
+            Some(Overlay::Sma(average(*period)))
+        }
+        Command::Ema(period) => {
+            let values = DataProcessor::with_data(view.clone()).exponential_moving_average(*period)?;
+            report_indicator(output, locale, &format!("EMA({})", period), &values)?;
+            Some(Overlay::Ema(average(*period)))
+        }
+        Command::Rsi(period) => {
+            let values = indicators::rsi(&DataProcessor::candles_from(view)?, *period);
+            report_indicator(output, locale, &format!("RSI({})", period), &values)?;
+            Some(Overlay::Rsi(*period))
+        }
+        Command::Atr(period) => {
+            let values = indicators::atr(&DataProcessor::candles_from(view)?, *period);
+            report_indicator(output, locale, &format!("ATR({})", period), &values)?;
+            Some(Overlay::Atr(*period))
+        }
+        Command::Macd => {
+            let result = indicators::macd(&DataProcessor::candles_from(view)?, 12, 26, 9);
+            report_indicator(output, locale, "MACD", &result.macd)?;
+            report_indicator(output, locale, "MACD signal", &result.signal)?;
+            report_indicator(output, locale, "MACD histogram", &result.histogram)?;
+            Some(Overlay::Macd { fast: 12, slow: 26, signal: 9 })
+        }
+        Command::Range(from, to) => {
+            let (start, _) = date_utils::parse_period(from)?;
+            let (_, end) = date_utils::parse_period(to)?;
+            *view = DataProcessor::filter_range(data, start, end)?;
+            writeln!(output, "Showing {} of {} candles", view.len(), data.len())?;
+            None
+        }
+        Command::Reset => {
+            *view = data.to_vec();
+            writeln!(output, "Showing all {} candles", view.len())?;
+            None
+        }
+        Command::Help => {
+            writeln!(output, "{}", HELP)?;
+            None
+        }
+        Command::Quit => None,
+    };
+
+    if let Some(overlay) = overlay
+        && !overlays.contains(&overlay)
+    {
+        overlays.push(overlay);
+    }
+
+    Ok(())
+}
+
+/// Prints the latest value and coverage of an indicator series
+fn report_indicator<W: Write>(
+    output: &mut W,
+    locale: &Locale,
+    name: &str,
+    values: &[Option<f64>],
+) -> Result<()> {
+    let defined = values.iter().filter(|v| v.is_some()).count();
+
+    match values.iter().rev().find_map(|v| *v) {
+        Some(last) => writeln!(
+            output,
+            "{}: last {} ({} of {} candles)",
+            name,
+            locale.format_number(last, 2),
+            defined,
+            values.len()
+        )?,
+        None => writeln!(output, "{}: not enough data ({} candles)", name, values.len())?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Builds a small daily series
+    fn sample_rows() -> Vec<HistoricalData> {
+        ["2023-01-15", "2023-02-15", "2023-03-15", "2023-04-15"]
+            .iter()
+            .enumerate()
+            .map(|(i, date)| HistoricalData {
+                timestamp: format!("{} 00:00:00", date),
+                open: 100.0 + i as f64,
+                high: 102.0 + i as f64,
+                low: 99.0 + i as f64,
+                close: 101.0 + i as f64,
+                volume: 1000.0,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Test command parsing
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("sma 20"), Some(Command::Sma(20)));
+        assert_eq!(parse_command("  rsi 14 "), Some(Command::Rsi(14)));
+        assert_eq!(
+            parse_command("range 2023-01 2023-03"),
+            Some(Command::Range("2023-01".to_string(), "2023-03".to_string()))
+        );
+        assert_eq!(parse_command("exit"), Some(Command::Quit));
+        assert_eq!(parse_command("sma twenty"), None);
+        assert_eq!(parse_command("macd"), Some(Command::Macd));
+        assert_eq!(parse_command("macd 12"), None);
+    }
+
+    /// Test a scripted session including an unknown command
+    #[test]
+    fn test_run_session() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let script = "sma 2\nrange 2023-01 2023-02\nbogus\nrsi 14\nsma 2\nema 3\nquit\nsma 3\n";
+        let mut output = Vec::new();
+        let mut plotter = Plotter::new();
+
+        run(
+            script.as_bytes(),
+            &mut output,
+            sample_rows(),
+            &mut plotter,
+            temp_dir.path().to_str().unwrap(),
+        )?;
+
+        let average = MovingAverage { period: 2, color: None, style: LineStyle::Solid };
+        assert_eq!(plotter.config().overlays, vec![Overlay::Sma(average), Overlay::Rsi(14)]);
+        let model = plotter.render_model(&sample_rows())?;
+        assert!(model.lines.iter().any(|line| line.name == "SMA(2)" && line.points.len() == 3));
+
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("SMA(2): last 103.50 (3 of 4 candles)"));
+        assert!(output.contains("Showing 2 of 4 candles"));
+        assert!(output.contains("Unknown command: bogus"));
+        assert!(output.contains("RSI(14): not enough data (2 candles)"));
+        assert!(output.contains("Error: Data processing error: Moving average period must be between 1 and 2, got 3"));
+        assert!(!output.contains("SMA(3)"));
+
+        Ok(())
+    }
+}
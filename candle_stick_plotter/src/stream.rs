@@ -0,0 +1,202 @@
+//! Live candle updates from a websocket feed
+//!
+//! Only compiled with the `stream` feature. Unlike the file loader, this path
+//! keeps a connection open, merges every received candle into a
+//! `DataProcessor` and reconnects with exponential backoff when the feed drops.
+
+use std::thread;
+use std::time::Duration;
+use anyhow::{Result, Context, anyhow};
+use tungstenite::Message;
+
+use crate::data_processor::{DataProcessor, HistoricalData};
+
+/// Longest wait between reconnection attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Connection settings for a websocket feed
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    /// `ws://` URL of the feed
+    pub url: String,
+    /// Oldest candles beyond this count are dropped
+    pub max_candles: usize,
+    /// Give up after this many consecutive failed connections, `None` retries forever
+    pub max_reconnects: Option<usize>,
+}
+
+/// Parses a feed message holding one OHLCV object or an array of them
+///
+/// # Arguments
+/// * `text` - JSON message text
+///
+/// # Returns
+/// * `Result<Vec<HistoricalData>>` - Candles contained in the message
+///
+/// # Errors
+/// * Returns error if the message is not a valid OHLCV object or array
+pub fn parse_message(text: &str) -> Result<Vec<HistoricalData>> {
+    let value: serde_json::Value = serde_json::from_str(text).context("Invalid JSON message")?;
+    
+    if value.is_array() {
+        serde_json::from_value(value).context("Invalid OHLCV array")
+    } else {
+        Ok(vec![serde_json::from_value(value).context("Invalid OHLCV object")?])
+    }
+}
+
+/// Drops rows whose timestamp cannot be parsed, logging each one
+fn valid_rows(rows: Vec<HistoricalData>) -> Vec<HistoricalData> {
+    rows.into_iter()
+        .filter(|row| match row.parsed_timestamp() {
+            Ok(_) => true,
+            Err(err) => {
+                log::warn!("Skipping stream row: {:#}", err);
+                false
+            }
+        })
+        .collect()
+}
+
+/// Delay before the given reconnection attempt (1-based)
+///
+/// # Arguments
+/// * `attempt` - Number of consecutive failed attempts so far
+///
+/// # Returns
+/// * `Duration` - Doubling delay starting at one second, capped at 30 seconds
+pub fn backoff(attempt: usize) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(5) as u32;
+    Duration::from_secs(2u64.pow(exponent)).min(MAX_BACKOFF)
+}
+
+/// Streams candles into `processor` until the reconnect budget is exhausted
+///
+/// # Arguments
+/// * `config` - Feed URL and limits
+/// * `processor` - Receives the merged candles
+/// * `on_update` - Called with the current data after every merged message
+///
+/// # Returns
+/// * `Result<()>` - Only returns once reconnecting has been given up
+///
+/// # Errors
+/// * Returns error when `max_reconnects` consecutive attempts failed, or if
+///   `on_update` fails
+pub fn run<F>(config: &StreamConfig, processor: &mut DataProcessor, mut on_update: F) -> Result<()>
+where
+    F: FnMut(&[HistoricalData]) -> Result<()>,
+{
+    let mut failures = 0;
+    
+    loop {
+        match tungstenite::connect(config.url.as_str()) {
+            Ok((mut socket, _)) => {
+                log::info!("Connected to {}", config.url);
+                failures = 0;
+                
+                loop {
+                    let text = match socket.read() {
+                        Ok(Message::Text(text)) => text.to_string(),
+                        Ok(Message::Close(_)) => break,
+                        Ok(_) => continue,
+                        Err(err) => {
+                            log::warn!("Stream read failed: {}", err);
+                            break;
+                        }
+                    };
+                    
+                    match parse_message(&text).map(valid_rows) {
+                        Ok(rows) if rows.is_empty() => {}
+                        Ok(rows) => {
+                            processor.append_rows(rows, Some(config.max_candles))?;
+                            on_update(processor.get_data())?;
+                        }
+                        Err(err) => log::warn!("Skipping stream message: {:#}", err),
+                    }
+                }
+                
+                log::warn!("Disconnected from {}", config.url);
+            }
+            Err(err) => log::warn!("Failed to connect to {}: {}", config.url, err),
+        }
+        
+        failures += 1;
+        if config.max_reconnects.is_some_and(|max| failures > max) {
+            return Err(anyhow!("Giving up on {} after {} failed attempts", config.url, failures));
+        }
+        
+        let delay = backoff(failures);
+        log::info!("Reconnecting in {:?}", delay);
+        thread::sleep(delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    
+    /// Test parsing single and batched messages
+    #[test]
+    fn test_parse_message() -> Result<()> {
+        let single = r#"{"timestamp":"2023-01-01 00:00:00","open":1,"high":2,"low":0.5,"close":1.5,"volume":10}"#;
+        assert_eq!(parse_message(single)?[0].close, 1.5);
+        
+        let batch = format!("[{},{}]", single, single.replace("01 00", "01 01"));
+        assert_eq!(parse_message(&batch)?.len(), 2);
+        
+        assert!(parse_message(r#"{"open":1}"#).is_err());
+        Ok(())
+    }
+    
+    /// Test that the backoff doubles and is capped
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(4));
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+    
+    /// Test receiving candles from a local websocket server
+    #[test]
+    fn test_run_against_local_server() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("ws://{}", listener.local_addr()?);
+        
+        let server = thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(tcp).unwrap();
+            for hour in 0..3 {
+                let message = format!(
+                    r#"{{"timestamp":"2023-01-01 0{}:00:00","open":1,"high":2,"low":0.5,"close":1.5,"volume":10}}"#,
+                    hour
+                );
+                socket.send(Message::text(message)).unwrap();
+            }
+            socket.send(Message::text("not json")).unwrap();
+            let malformed = r#"{"timestamp":"yesterday","open":1,"high":2,"low":0.5,"close":1.5,"volume":10}"#;
+            socket.send(Message::text(malformed)).unwrap();
+            socket.send(Message::text(
+                r#"{"timestamp":"2023-01-01 03:00:00","open":1,"high":2,"low":0.5,"close":1.5,"volume":10}"#,
+            )).unwrap();
+            socket.close(None).unwrap();
+            while socket.read().is_ok() {}
+        });
+        
+        let config = StreamConfig { url, max_candles: 2, max_reconnects: Some(0) };
+        let mut processor = DataProcessor::new();
+        let mut updates = 0;
+        let result = run(&config, &mut processor, |_| {
+            updates += 1;
+            Ok(())
+        });
+        server.join().unwrap();
+        
+        assert!(result.is_err());
+        assert_eq!(updates, 4);
+        assert_eq!(processor.get_data().len(), 2);
+        assert_eq!(processor.get_data()[1].timestamp, "2023-01-01 03:00:00");
+        Ok(())
+    }
+}
@@ -0,0 +1,536 @@
+//! Vector rendering of a render model as an SVG document
+//!
+//! Panels are placed by `layout::panels` exactly like the software renderer
+//! places them, and canvas texts and x-axis labels are written as `<text>`
+//! elements at their laid-out positions.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result};
+use eframe::egui::Color32;
+
+use crate::data_processor::EMBEDDED_DATA_ID;
+use crate::layout::{self, Viewport, EVENT_FONT_SIZE, MARKER_SIZE};
+use crate::render_model::{AxisLabels, Colorbar, Layer, LineStyle, MarkerShape, Panel, RenderModel, TextAlign};
+use crate::utils::{color_utils, string_utils};
+
+/// Stroke width of indicator lines in pixels
+const LINE_WIDTH: f32 = 1.5;
+
+/// Font size of the last price tag in pixels
+const PRICE_TAG_FONT_SIZE: f32 = 11.0;
+
+/// Font size of the message drawn in place of an empty chart in pixels
+const PLACEHOLDER_FONT_SIZE: f32 = 16.0;
+
+/// Color of watermark text before its opacity is applied
+const WATERMARK_COLOR: Color32 = Color32::from_rgb(96, 96, 96);
+
+/// Renders a chart as a standalone SVG document
+///
+/// The background is white unless `model.transparent` leaves it out. The
+/// markup depends on the model alone, with no timestamps or generator
+/// details, so identical charts give identical files for golden tests.
+///
+/// # Arguments
+/// * `model` - Chart to draw
+///
+/// # Returns
+/// * `String` - SVG markup of `model.size` pixels
+pub fn render(model: &RenderModel) -> String {
+    let (width, height) = model.size;
+    let panels = layout::panels(model);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        width, height
+    );
+    if !model.transparent {
+        svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n");
+    }
+    if let Some(message) = &model.placeholder {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" {}>{}</text>",
+            width as f32 / 2.0, height as f32 / 2.0, PLACEHOLDER_FONT_SIZE, paint("fill", WATERMARK_COLOR),
+            string_utils::escape_markup(message)
+        );
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+    
+    draw_price_panel(&mut svg, model, &panels.price);
+    if let Some(lower) = &panels.lower {
+        for line in model.lines.iter().filter(|line| line.panel == Panel::Lower) {
+            polyline(&mut svg, lower, &line.points, line.color, LINE_WIDTH, line.style);
+        }
+    }
+    if let Some(volume) = &panels.volume {
+        for bar in &model.volume {
+            rect(&mut svg, volume, (bar.left, 0.0), (bar.right, bar.volume), &paint("fill", bar.color));
+        }
+    }
+    if let (Some(colorbar), Some(area)) = (&model.colorbar, layout::colorbar_area(model)) {
+        draw_colorbar(&mut svg, colorbar, area);
+    }
+    
+    for text in &model.texts {
+        let anchor = match text.align {
+            TextAlign::Left => "start",
+            TextAlign::Center => "middle",
+        };
+        let weight = if text.bold { " font-weight=\"bold\"" } else { "" };
+        for (i, line) in text.lines.iter().enumerate() {
+            let y = text.y + i as f64 * text.font_size as f64 * layout::LINE_HEIGHT;
+            let _ = writeln!(
+                svg,
+                "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\"{} text-anchor=\"{}\" dominant-baseline=\"hanging\">{}</text>",
+                text.x, y, text.font_size, weight, anchor, string_utils::escape_markup(line)
+            );
+        }
+    }
+    
+    if let Some(labels) = &model.x_labels {
+        draw_x_labels(&mut svg, model, labels);
+    }
+    if let Some(label) = &model.y_label {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\" dominant-baseline=\"hanging\" {}>{}</text>",
+            panels.price.left + 4.0, panels.price.top + 4.0, layout::AXIS_LABEL_SIZE, paint("fill", WATERMARK_COLOR),
+            string_utils::escape_markup(label)
+        );
+    }
+    
+    if let Some(watermark) = &model.watermark {
+        let (x, y) = watermark.position.anchor();
+        let (x, y) = (x * width as f32, y * height as f32);
+        let color = color_utils::with_opacity(WATERMARK_COLOR, watermark.opacity);
+        let _ = writeln!(
+            svg,
+            "<text x=\"{0:.2}\" y=\"{1:.2}\" font-family=\"sans-serif\" font-size=\"{2:.1}\" font-weight=\"bold\" text-anchor=\"middle\" dominant-baseline=\"central\" transform=\"rotate({3} {0:.2} {1:.2})\" {4}>{5}</text>",
+            x, y, layout::watermark_font_size(width as f32, &watermark.text), watermark.rotation,
+            paint("fill", color), string_utils::escape_markup(&watermark.text)
+        );
+    }
+    
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Writes a chart as an SVG file
+///
+/// # Arguments
+/// * `model` - Chart to draw
+/// * `embedded` - Candle JSON written into a `<metadata>` element right
+///   after the opening tag, omitted if `None`
+/// * `path` - Destination file
+///
+/// # Returns
+/// * `Result<()>` - Ok if the file was written
+///
+/// # Errors
+/// * Returns error if writing fails
+pub fn write_svg(model: &RenderModel, embedded: Option<&str>, path: &Path) -> Result<()> {
+    let mut svg = render(model);
+    if let Some(json) = embedded {
+        let body = svg.find('\n').map_or(svg.len(), |end| end + 1);
+        svg.insert_str(body, &format!("<metadata id=\"{}\">{}</metadata>\n", EMBEDDED_DATA_ID, json));
+    }
+    fs::write(path, svg).context(format!("Failed to write SVG: {}", path.display()))?;
+    log::info!("Wrote SVG chart to {}", path.display());
+    Ok(())
+}
+
+/// Draws x-axis tick labels below the lowest panel
+///
+/// Rotated labels end at their tick, so they hang below the axis whichever
+/// way they are turned.
+fn draw_x_labels(svg: &mut String, model: &RenderModel, labels: &AxisLabels) {
+    let viewport = layout::panels(model).price;
+    let y = layout::x_axis_top(model);
+    let (anchor, baseline) = match labels.rotation {
+        r if r < 0.0 => ("end", "central"),
+        r if r > 0.0 => ("start", "central"),
+        _ => ("middle", "hanging"),
+    };
+    
+    for (index, text) in &labels.ticks {
+        let (x, _) = viewport.map(*index, viewport.y_range.min);
+        let transform = match labels.rotation == 0.0 {
+            true => String::new(),
+            false => format!(" transform=\"rotate({} {:.2} {:.2})\"", labels.rotation, x, y),
+        };
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"{}\" dominant-baseline=\"{}\"{}>{}</text>",
+            x, y, labels.font_size, anchor, baseline, transform, string_utils::escape_markup(text)
+        );
+    }
+}
+
+/// Draws bands, gridlines, fills, candles, price lines, events and markers
+fn draw_price_panel(svg: &mut String, model: &RenderModel, viewport: &Viewport) {
+    let (y_min, y_max) = (viewport.y_range.min, viewport.y_range.max);
+    for layer in &model.layers {
+        match layer {
+            Layer::Bands => {
+                for band in &model.bands {
+                    rect(svg, viewport, (band.left, y_min), (band.right, y_max), &paint("fill", band.color));
+                }
+            }
+            Layer::Grid => {
+                for &price in &model.price_grid {
+                    let (x_min, x_max) = (viewport.x_range.min, viewport.x_range.max);
+                    polyline(svg, viewport, &[[x_min, price], [x_max, price]], color_utils::GRID_COLOR, 1.0, LineStyle::Solid);
+                }
+            }
+            Layer::Fills => {
+                for fill in &model.fills {
+                    let _ = writeln!(svg, "<polygon points=\"{}\" {}/>", points(viewport, &fill.points), paint("fill", fill.color));
+                }
+            }
+            Layer::Candles => {
+                for wick in &model.wicks {
+                    polyline(svg, viewport, &[[wick.x, wick.low], [wick.x, wick.high]], wick.color, 1.0, LineStyle::Solid);
+                }
+                for candle in &model.candles {
+                    let mut style = paint("fill", candle.fill);
+                    if let Some(outline) = candle.outline {
+                        let _ = write!(style, " {} stroke-width=\"{}\"", paint("stroke", outline.color), outline.width);
+                    }
+                    rect(svg, viewport, (candle.left, candle.bottom), (candle.right, candle.top), &style);
+                }
+            }
+            Layer::Lines => {
+                for line in model.lines.iter().filter(|line| line.panel == Panel::Price) {
+                    polyline(svg, viewport, &line.points, line.color, LINE_WIDTH, line.style);
+                }
+            }
+            Layer::Events => {
+                for event in &model.events {
+                    polyline(svg, viewport, &[[event.x, y_min], [event.x, y_max]], event.color, 1.0, LineStyle::Solid);
+                    let (x, y) = viewport.map(event.x, y_max);
+                    let _ = writeln!(
+                        svg,
+                        "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\" dominant-baseline=\"hanging\" {}>{}</text>",
+                        x + 2.0, y, EVENT_FONT_SIZE, paint("fill", event.color), string_utils::escape_markup(&event.label)
+                    );
+                }
+            }
+            Layer::Markers => {
+                for marker in &model.markers {
+                    let (x, y) = viewport.map(marker.x, marker.y);
+                    let (tip, base) = match marker.shape {
+                        MarkerShape::Up => (y - MARKER_SIZE, y + MARKER_SIZE),
+                        MarkerShape::Down => (y + MARKER_SIZE, y - MARKER_SIZE),
+                    };
+                    let _ = writeln!(
+                        svg,
+                        "<polygon points=\"{:.2},{:.2} {:.2},{:.2} {:.2},{:.2}\" {}/>",
+                        x, tip, x + MARKER_SIZE, base, x - MARKER_SIZE, base, paint("fill", marker.color)
+                    );
+                }
+            }
+        }
+    }
+    
+    if let Some(tag) = &model.price_tag {
+        // Sans-serif digits are about 0.6 em wide
+        let (right, y) = viewport.map(viewport.x_range.max, tag.price);
+        let width = (tag.label.chars().count() as f32 * 0.6 + 1.0) * PRICE_TAG_FONT_SIZE;
+        let height = PRICE_TAG_FONT_SIZE * 1.4;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+            right - width, y - height / 2.0, width, height, paint("fill", tag.color)
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"end\" dominant-baseline=\"central\" fill=\"#ffffff\">{}</text>",
+            right - PRICE_TAG_FONT_SIZE / 2.0, y, PRICE_TAG_FONT_SIZE, string_utils::escape_markup(&tag.label)
+        );
+    }
+}
+
+/// Draws a colorbar top to bottom from its highest to its lowest value, with
+/// the end values beside it and the title running down its middle
+fn draw_colorbar(svg: &mut String, colorbar: &Colorbar, (left, top, width, height): (f32, f32, f32, f32)) {
+    let step = height / colorbar.stops.len().max(1) as f32;
+    for (i, stop) in colorbar.stops.iter().rev().enumerate() {
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+            left, top + i as f32 * step, width, step, paint("fill", stop.color)
+        );
+    }
+    
+    let x = left + width + 4.0;
+    let label = |svg: &mut String, y: f32, baseline: &str, text: &str| {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"sans-serif\" font-size=\"{}\" dominant-baseline=\"{}\">{}</text>",
+            x, y, EVENT_FONT_SIZE, baseline, string_utils::escape_markup(text)
+        );
+    };
+    if let (Some(lowest), Some(highest)) = (colorbar.stops.first(), colorbar.stops.last()) {
+        label(svg, top, "hanging", &Colorbar::label(highest.value));
+        label(svg, top + height, "auto", &Colorbar::label(lowest.value));
+    }
+    let middle = top + height / 2.0;
+    let _ = writeln!(
+        svg,
+        "<text x=\"{0:.2}\" y=\"{1:.2}\" font-family=\"sans-serif\" font-size=\"{2}\" text-anchor=\"middle\" transform=\"rotate(90 {0:.2} {1:.2})\">{3}</text>",
+        x + EVENT_FONT_SIZE, middle, EVENT_FONT_SIZE, string_utils::escape_markup(&colorbar.title)
+    );
+}
+
+/// Writes the rectangle between two corners given in data coordinates,
+/// at least one pixel high so flat candles stay visible
+fn rect(svg: &mut String, viewport: &Viewport, from: (f64, f64), to: (f64, f64), style: &str) {
+    let (x0, y0) = viewport.map(from.0, from.1);
+    let (x1, y1) = viewport.map(to.0, to.1);
+    let _ = writeln!(
+        svg,
+        "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+        x0.min(x1), y0.min(y1), (x1 - x0).abs(), (y1 - y0).abs().max(1.0), style
+    );
+}
+
+/// Writes a polyline given in data coordinates
+fn polyline(svg: &mut String, viewport: &Viewport, data: &[[f64; 2]], color: Color32, width: f32, style: LineStyle) {
+    let dash = style.dash_pattern()
+        .map(|[on, off]| format!(" stroke-dasharray=\"{} {}\"", on, off))
+        .unwrap_or_default();
+    let _ = writeln!(
+        svg,
+        "<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{}\"{}/>",
+        points(viewport, data), paint("stroke", color), width, dash
+    );
+}
+
+/// Formats data coordinates as an SVG point list in pixels
+fn points(viewport: &Viewport, data: &[[f64; 2]]) -> String {
+    data.iter()
+        .map(|[x, y]| {
+            let (px, py) = viewport.map(*x, *y);
+            format!("{:.2},{:.2}", px, py)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Formats a premultiplied egui color as a `fill` or `stroke` attribute,
+/// with an opacity attribute for translucent colors
+fn paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    match a {
+        255 => format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, r, g, b),
+        _ => format!("{0}=\"#{1:02x}{2:02x}{3:02x}\" {0}-opacity=\"{4:.3}\"", attribute, r, g, b, a as f32 / 255.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processor::{DataProcessor, Event};
+    use crate::plotter::{LabelRotation, MovingAverage, Overlay, PlotConfig, Plotter};
+    use crate::render_model::{Watermark, WatermarkPosition};
+    
+    /// Test that separately built charts of the same input render byte for byte alike
+    #[test]
+    fn test_render_reproducible() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let config = || PlotConfig {
+            overlays: vec![Overlay::Sma(MovingAverage { period: 2, color: None, style: LineStyle::Solid })],
+            events: ["split", "earnings", "dividend"]
+                .iter()
+                .map(|kind| Event { timestamp: "2023-01-02 00:00:00".to_string(), label: kind.to_string(), kind: kind.to_string() })
+                .collect(),
+            ..Default::default()
+        };
+        
+        let first = render(&Plotter::with_config(config()).render_model(processor.get_data())?);
+        let second = render(&Plotter::with_config(config()).render_model(processor.get_data())?);
+        assert_eq!(first, second);
+        
+        Ok(())
+    }
+    
+    /// Test that embedded data is written as a metadata element only when given
+    #[test]
+    fn test_write_svg_embedded() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        let data = processor.load_csv_data("non_existent_file.csv")?;
+        let model = Plotter::new().render_model(&data)?;
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("chart.svg");
+        
+        write_svg(&model, None, &path)?;
+        assert_eq!(std::fs::read_to_string(&path)?, render(&model));
+        
+        write_svg(&model, Some(&DataProcessor::embed_json(&data)?), &path)?;
+        let loaded = processor.load_embedded(&path)?;
+        assert_eq!(loaded.len(), data.len());
+        assert_eq!((loaded[2].timestamp.as_str(), loaded[2].close), (data[2].timestamp.as_str(), data[2].close));
+        
+        Ok(())
+    }
+    
+    /// Test that the layer order decides whether lines or candles are drawn on top
+    #[test]
+    fn test_render_layer_order() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        // Positions of the SMA line and the first candle body in the markup
+        let positions = |layers: Vec<Layer>| -> Result<(usize, usize)> {
+            let overlays = vec![Overlay::Sma(MovingAverage { period: 2, color: None, style: LineStyle::Solid })];
+            let model = Plotter::with_config(PlotConfig { overlays, layers, ..Default::default() }).render_model(processor.get_data())?;
+            let svg = render(&model);
+            let line = svg.find(&format!("stroke-width=\"{}\"", LINE_WIDTH)).ok_or_else(|| anyhow::anyhow!("no line"))?;
+            let body = svg.find(&paint("fill", model.candles[0].fill)).ok_or_else(|| anyhow::anyhow!("no candle"))?;
+            Ok((line, body))
+        };
+        
+        let (line, body) = positions(Layer::DEFAULT_ORDER.to_vec())?;
+        assert!(body < line);
+        let (line, body) = positions(Plotter::layer_order(&["lines".to_string(), "candles".to_string()]))?;
+        assert!(line < body);
+        
+        Ok(())
+    }
+    
+    /// Test that empty data draws only the centered placeholder
+    #[test]
+    fn test_render_placeholder() -> Result<()> {
+        let config = PlotConfig { size: (300, 200), data_source: Some("a&b.csv".to_string()), ..Default::default() };
+        let svg = render(&Plotter::with_config(config).render_model(&[])?);
+        
+        assert!(svg.contains("x=\"150.00\" y=\"100.00\""));
+        assert!(svg.contains(">No data in a&amp;b.csv</text>"));
+        assert_eq!(svg.matches("<text ").count(), 1);
+        assert!(svg.ends_with("</svg>\n"));
+        
+        Ok(())
+    }
+    
+    /// Test the document frame, one body per candle, dashed lines and escaped canvas text
+    #[test]
+    fn test_render() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let config = PlotConfig {
+            size: (300, 200),
+            title: Some("S&P <500>".to_string()),
+            overlays: vec![Overlay::Sma(MovingAverage { period: 2, color: None, style: LineStyle::Dashed })],
+            ..Default::default()
+        };
+        let model = Plotter::with_config(config).render_model(processor.get_data())?;
+        
+        let svg = render(&model);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"300\" height=\"200\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect ").count(), 1 + model.candles.len());
+        assert_eq!(svg.matches(&paint("fill", color_utils::UP_COLOR)).count(), 3);
+        assert_eq!(svg.matches("stroke-dasharray=\"6 4\"").count(), 1);
+        assert!(svg.contains("font-weight=\"bold\" text-anchor=\"middle\" dominant-baseline=\"hanging\">S&amp;P &lt;500&gt;</text>"));
+        
+        Ok(())
+    }
+    
+    /// Test horizontal date labels on the axis and rotated ones ending at their tick
+    #[test]
+    fn test_render_x_labels() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let render_with = |rotation| -> Result<String> {
+            let config = PlotConfig { x_label_rotation: rotation, ..Default::default() };
+            Ok(render(&Plotter::with_config(config).render_model(processor.get_data())?))
+        };
+        
+        let svg = render_with(LabelRotation::Auto)?;
+        assert_eq!(svg.matches("text-anchor=\"middle\" dominant-baseline=\"hanging\">").count(), 3);
+        
+        let svg = render_with(LabelRotation::Degrees(-45.0))?;
+        assert_eq!(svg.matches("text-anchor=\"end\" dominant-baseline=\"central\" transform=\"rotate(-45 ").count(), 3);
+        
+        Ok(())
+    }
+    
+    /// Test the last price tag ending at the right edge of the price panel
+    #[test]
+    fn test_render_price_tag() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let config = PlotConfig { last_price_line: true, ..Default::default() };
+        let model = Plotter::with_config(config).render_model(processor.get_data())?;
+        
+        let svg = render(&model);
+        assert_eq!(svg.matches("<rect ").count(), 2 + model.candles.len());
+        assert!(svg.contains("text-anchor=\"end\" dominant-baseline=\"central\" fill=\"#ffffff\">108.00</text>"));
+        
+        Ok(())
+    }
+    
+    /// Test the price axis title in the top left corner of the price panel
+    #[test]
+    fn test_render_y_label() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let config = PlotConfig { y_label: Some("Ratio to SPX".to_string()), ..Default::default() };
+        let svg = render(&Plotter::with_config(config).render_model(processor.get_data())?);
+        
+        assert_eq!(svg.matches(">Ratio to SPX</text>").count(), 1);
+        assert!(!render(&Plotter::new().render_model(processor.get_data())?).contains("Ratio to"));
+        
+        Ok(())
+    }
+    
+    /// Test that a transparent background leaves out the background rectangle
+    #[test]
+    fn test_render_transparent() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let config = PlotConfig { transparent: true, ..Default::default() };
+        let model = Plotter::with_config(config).render_model(processor.get_data())?;
+        
+        let svg = render(&model);
+        assert!(!svg.contains("<rect width=\"100%\""));
+        assert_eq!(svg.matches("<rect ").count(), model.candles.len());
+        
+        Ok(())
+    }
+    
+    /// Test that the watermark is drawn last, rotated about its anchor
+    #[test]
+    fn test_render_watermark() -> Result<()> {
+        let mut processor = DataProcessor::new();
+        processor.load_csv_data("non_existent_file.csv")?;
+        let watermark = Watermark {
+            text: "DRAFT".to_string(),
+            opacity: 0.2,
+            position: WatermarkPosition::TopRight,
+            rotation: -30.0,
+        };
+        let config = PlotConfig { size: (400, 200), watermark: Some(watermark), ..Default::default() };
+        let svg = render(&Plotter::with_config(config).render_model(processor.get_data())?);
+        
+        let last = svg.lines().rev().nth(1).unwrap_or_default();
+        assert!(last.starts_with("<text x=\"300.00\" y=\"50.00\""), "{}", last);
+        assert!(last.contains("transform=\"rotate(-30 300.00 50.00)\""));
+        assert!(last.contains("fill-opacity=\"0.200\">DRAFT</text>"));
+        
+        Ok(())
+    }
+    
+    /// Test opaque and translucent color attributes
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("fill", Color32::from_rgb(255, 0, 16)), "fill=\"#ff0010\"");
+        assert_eq!(
+            paint("stroke", Color32::from_rgba_unmultiplied(0, 0, 255, 51)),
+            "stroke=\"#0000ff\" stroke-opacity=\"0.200\""
+        );
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::fs;
 use std::path::Path;
-use anyhow::{Result, Context};
+use anyhow::{Result, Context, anyhow};
 
 /// File utility functions
 pub mod file_utils {
@@ -39,9 +39,50 @@ pub mod file_utils {
     ///
     /// # Returns
     /// * `bool` - True if file exists, false otherwise
+    #[allow(dead_code)]
     pub fn file_exists(file_path: &str) -> bool {
         Path::new(file_path).exists()
     }
+    
+    /// Matches a file name against a wildcard pattern
+    ///
+    /// `*` matches any run of characters and `?` matches a single character.
+    ///
+    /// # Arguments
+    /// * `name` - File name to test
+    /// * `pattern` - Wildcard pattern such as `*.csv`
+    ///
+    /// # Returns
+    /// * `bool` - True if the whole name matches the pattern
+    pub fn matches_pattern(name: &str, pattern: &str) -> bool {
+        let name: Vec<char> = name.chars().collect();
+        let pattern: Vec<char> = pattern.chars().collect();
+        let (mut n, mut p) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None;
+        
+        while n < name.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, n));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == name[n] => {
+                    n += 1;
+                    p += 1;
+                }
+                _ => match backtrack {
+                    Some((star, matched)) => {
+                        p = star + 1;
+                        n = matched + 1;
+                        backtrack = Some((star, matched + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        
+        pattern[p..].iter().all(|&c| c == '*')
+    }
 }
 
 /// String utility functions
@@ -53,6 +94,7 @@ pub mod string_utils {
     ///
     /// # Returns
     /// * `String` - Uppercase version of the input string
+    #[allow(dead_code)]
     pub fn to_uppercase(input: &str) -> String {
         input.to_uppercase()
     }
@@ -64,9 +106,955 @@ pub mod string_utils {
     ///
     /// # Returns
     /// * `String` - Trimmed version of the input string
+    #[allow(dead_code)]
     pub fn trim_string(input: &str) -> String {
         input.trim().to_string()
     }
+    
+    /// Escapes the characters with special meaning in HTML and XML text and attributes
+    ///
+    /// # Arguments
+    /// * `input` - Text to embed in markup
+    ///
+    /// # Returns
+    /// * `String` - Text with `&`, `<`, `>`, `"` and `'` replaced by entities
+    pub fn escape_markup(input: &str) -> String {
+        let mut escaped = String::with_capacity(input.len());
+        for c in input.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
+
+/// Date utility functions
+pub mod date_utils {
+    use super::*;
+    use std::collections::HashSet;
+    use chrono::{DateTime, Datelike, Days, Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc, Weekday};
+    
+    /// Parses a sampling interval such as `15m`, `4h`, `1d` or `1w`
+    ///
+    /// # Arguments
+    /// * `interval` - Positive count followed by a unit: `s`, `m`, `h`, `d` or `w`
+    ///
+    /// # Returns
+    /// * `Result<Duration>` - Length of the interval
+    ///
+    /// # Errors
+    /// * Returns error if the count or unit is invalid
+    pub fn parse_interval(interval: &str) -> Result<Duration> {
+        let interval = interval.trim();
+        let invalid = || anyhow!("Invalid interval '{}': expected e.g. 15m, 4h, 1d or 1w", interval);
+        
+        let split = interval.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let count: i64 = interval[..split].parse().map_err(|_| invalid())?;
+        if count <= 0 {
+            return Err(invalid());
+        }
+        
+        match &interval[split..] {
+            "s" => Ok(Duration::seconds(count)),
+            "m" => Ok(Duration::minutes(count)),
+            "h" => Ok(Duration::hours(count)),
+            "d" => Ok(Duration::days(count)),
+            "w" => Ok(Duration::weeks(count)),
+            _ => Err(invalid()),
+        }
+    }
+    
+    /// Parses a calendar period such as `2023`, `2023-01` or `2023-01-15`
+    ///
+    /// # Arguments
+    /// * `period` - Year, year-month or full date
+    ///
+    /// # Returns
+    /// * `Result<(DateTime<Utc>, DateTime<Utc>)>` - Inclusive start and exclusive
+    ///   end of the period
+    ///
+    /// # Errors
+    /// * Returns error if the period is not a valid date prefix
+    pub fn parse_period(period: &str) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let period = period.trim();
+        let parts = period.split('-').count();
+        let invalid = || anyhow!("Invalid period '{}': expected YYYY, YYYY-MM or YYYY-MM-DD", period);
+        
+        let (start, months, days) = match parts {
+            1 => (format!("{}-01-01", period), 12, 0),
+            2 => (format!("{}-01", period), 1, 0),
+            3 => (period.to_string(), 0, 1),
+            _ => return Err(invalid()),
+        };
+        
+        let start = NaiveDate::parse_from_str(&start, "%Y-%m-%d").map_err(|_| invalid())?;
+        let end = start
+            .checked_add_months(Months::new(months))
+            .and_then(|date| date.checked_add_days(Days::new(days)))
+            .ok_or_else(invalid)?;
+        
+        Ok((start.and_time(NaiveTime::MIN).and_utc(), end.and_time(NaiveTime::MIN).and_utc()))
+    }
+    
+    /// Parses a `FROM:TO` range of calendar periods such as `2023-02-01:2023-02-15`
+    ///
+    /// # Arguments
+    /// * `range` - Two periods accepted by `parse_period`, separated by `:`
+    ///
+    /// # Returns
+    /// * `Result<(DateTime<Utc>, DateTime<Utc>)>` - Start of `FROM` and the
+    ///   exclusive end of `TO`, so the last period is included
+    ///
+    /// # Errors
+    /// * Returns error if a period is invalid or `TO` ends before `FROM` starts
+    pub fn parse_period_range(range: &str) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let (from, to) = range
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid range '{}': expected FROM:TO, e.g. 2023-02-01:2023-02-15", range))?;
+        let (start, _) = parse_period(from)?;
+        let (_, end) = parse_period(to)?;
+        
+        if end <= start {
+            return Err(anyhow!("Invalid range '{}': {} is before {}", range, to.trim(), from.trim()));
+        }
+        Ok((start, end))
+    }
+    
+    /// How the timestamp column of the input is interpreted
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum TimestampFormat {
+        /// `%Y-%m-%d %H:%M:%S` text with optional fractional seconds, or epoch
+        /// seconds/millis chosen by digit count
+        #[default]
+        Auto,
+        /// Unix epoch seconds
+        #[value(name = "epoch_s")]
+        EpochS,
+        /// Unix epoch milliseconds
+        #[value(name = "epoch_ms")]
+        EpochMs,
+    }
+    
+    /// Parses a timestamp as a UTC date-time
+    ///
+    /// In `Auto` mode a purely numeric timestamp is read as epoch seconds when
+    /// it has 10 digits and as epoch milliseconds when it has 13.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Timestamp text
+    /// * `format` - How the text is interpreted
+    ///
+    /// # Returns
+    /// * `Result<DateTime<Utc>>` - Parsed timestamp
+    ///
+    /// # Errors
+    /// * Returns error if the text does not match the format, or a numeric
+    ///   timestamp's precision cannot be inferred from its digit count
+    pub fn parse_timestamp(timestamp: &str, format: TimestampFormat) -> Result<DateTime<Utc>> {
+        let timestamp = timestamp.trim();
+        let digits = timestamp.strip_prefix('-').unwrap_or(timestamp);
+        let numeric = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+        
+        let format = match format {
+            TimestampFormat::Auto if numeric => match digits.len() {
+                10 => TimestampFormat::EpochS,
+                13 => TimestampFormat::EpochMs,
+                count => {
+                    return Err(anyhow!(
+                        "Cannot infer the precision of numeric timestamp '{}' ({} digits); use --timestamp-format epoch_s or epoch_ms",
+                        timestamp,
+                        count
+                    ));
+                }
+            },
+            format => format,
+        };
+        
+        let epoch = |text: &str| text.parse::<i64>().context(format!("Invalid epoch timestamp '{}'", timestamp));
+        let parsed = match format {
+            TimestampFormat::Auto => {
+                return NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")
+                    .map(|naive| naive.and_utc())
+                    .context(format!("Failed to parse timestamp '{}'", timestamp));
+            }
+            TimestampFormat::EpochS => DateTime::from_timestamp(epoch(timestamp)?, 0),
+            TimestampFormat::EpochMs => DateTime::from_timestamp_millis(epoch(timestamp)?),
+        };
+        parsed.ok_or_else(|| anyhow!("Epoch timestamp '{}' is out of range", timestamp))
+    }
+    
+    /// Formats a timestamp as `%Y-%m-%d %H:%M:%S` text
+    ///
+    /// Fractional seconds are appended only when the timestamp has them, so
+    /// whole-second timestamps keep the standard layout.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Timestamp to format
+    ///
+    /// # Returns
+    /// * `String` - Text that `parse_timestamp` reads back unchanged
+    pub fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+        timestamp.format("%Y-%m-%d %H:%M:%S%.f").to_string()
+    }
+    
+    /// Parses a timestamp with a `chrono` format string
+    ///
+    /// # Arguments
+    /// * `timestamp` - Timestamp text
+    /// * `format` - Format such as `%d.%m.%Y %H:%M`; formats without a time
+    ///   of day give midnight
+    ///
+    /// # Returns
+    /// * `Result<DateTime<Utc>>` - Parsed timestamp
+    ///
+    /// # Errors
+    /// * Returns error if the text does not match the format
+    pub fn parse_with_format(timestamp: &str, format: &str) -> Result<DateTime<Utc>> {
+        let timestamp = timestamp.trim();
+        NaiveDateTime::parse_from_str(timestamp, format)
+            .or_else(|_| NaiveDate::parse_from_str(timestamp, format).map(|date| date.and_time(NaiveTime::MIN)))
+            .map(|naive| naive.and_utc())
+            .context(format!("Timestamp '{}' does not match format '{}'", timestamp, format))
+    }
+    
+    /// Weekdays except holidays, the days a business-day axis counts
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct BusinessCalendar {
+        pub holidays: HashSet<NaiveDate>,
+    }
+    
+    impl BusinessCalendar {
+        /// Loads holidays from a file with one `YYYY-MM-DD` date per line
+        ///
+        /// Blank lines, lines starting with `#`, a `Date` header and anything
+        /// after a comma, such as a holiday name, are ignored.
+        ///
+        /// # Arguments
+        /// * `path` - Holiday file
+        ///
+        /// # Returns
+        /// * `Result<BusinessCalendar>` - Calendar skipping weekends and the holidays
+        ///
+        /// # Errors
+        /// * Returns error if the file cannot be read or a line holds no valid date
+        pub fn load(path: &Path) -> Result<Self> {
+            let text = fs::read_to_string(path).context(format!("Failed to read holidays: {}", path.display()))?;
+            let mut holidays = HashSet::new();
+            
+            for (number, line) in text.lines().enumerate() {
+                let field = line.split(',').next().unwrap_or_default().trim();
+                if field.is_empty() || field.starts_with('#') || (number == 0 && field.eq_ignore_ascii_case("date")) {
+                    continue;
+                }
+                let date = NaiveDate::parse_from_str(field, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("Invalid holiday '{}' on line {} of {}", field, number + 1, path.display()))?;
+                holidays.insert(date);
+            }
+            
+            Ok(BusinessCalendar { holidays })
+        }
+        
+        /// Whether a date is neither a Saturday, a Sunday nor a holiday
+        pub fn is_business_day(&self, date: NaiveDate) -> bool {
+            !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.holidays.contains(&date)
+        }
+        
+        /// Numbers dates by the business days elapsed since the first one
+        ///
+        /// # Arguments
+        /// * `dates` - Dates in non-decreasing order
+        ///
+        /// # Returns
+        /// * `Vec<f64>` - 0 for the first date, then the count of business
+        ///   days after it up to and including each date; equal dates share
+        ///   a position
+        pub fn positions(&self, dates: &[NaiveDate]) -> Vec<f64> {
+            let mut position = 0.0;
+            let mut previous: Option<NaiveDate> = None;
+            
+            dates
+                .iter()
+                .map(|&date| {
+                    if let Some(start) = previous {
+                        position += start.iter_days().skip(1).take_while(|day| *day <= date).filter(|day| self.is_business_day(*day)).count() as f64;
+                    }
+                    previous = Some(date);
+                    position
+                })
+                .collect()
+        }
+    }
+    
+    /// Trading session spanning a range of hours of the day
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Session {
+        pub name: String,
+        /// First hour of the session, 0-23
+        pub start: u32,
+        /// Hour the session ends before, 0-24; below `start` for sessions
+        /// running past midnight
+        pub end: u32,
+    }
+    
+    impl Session {
+        /// Parses `NAME=START-END` such as `Asia=0-8` or `Pacific=22-2`
+        ///
+        /// # Arguments
+        /// * `text` - Session name and hour range, the end hour exclusive
+        ///
+        /// # Returns
+        /// * `Result<Session>` - Parsed session
+        ///
+        /// # Errors
+        /// * Returns error if the name is empty, the hours are not numbers in
+        ///   range or both hours are equal
+        pub fn parse(text: &str) -> Result<Session> {
+            let (name, hours) = text.split_once('=')
+                .ok_or_else(|| anyhow!("Expected NAME=START-END, got '{}'", text))?;
+            let (start, end) = hours.split_once('-')
+                .ok_or_else(|| anyhow!("Expected an hour range START-END, got '{}'", hours))?;
+            let hour = |text: &str, max: u32| text.trim().parse::<u32>().ok().filter(|hour| *hour <= max);
+            let (Some(start), Some(end)) = (hour(start, 23), hour(end, 24)) else {
+                return Err(anyhow!("Session hours must be 0-23 and 0-24, got '{}'", hours));
+            };
+            if name.trim().is_empty() || start == end {
+                return Err(anyhow!("Session '{}' needs a name and a non-empty hour range", text));
+            }
+            
+            Ok(Session { name: name.trim().to_string(), start, end })
+        }
+        
+        /// Whether an hour of the day falls into the session
+        pub fn contains(&self, hour: u32) -> bool {
+            match self.start < self.end {
+                true => (self.start..self.end).contains(&hour),
+                false => hour >= self.start || hour < self.end,
+            }
+        }
+    }
+    
+    /// Sessions matched against timestamps in a fixed timezone
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SessionTable {
+        /// Sessions in priority order, the first one containing an hour wins
+        pub sessions: Vec<Session>,
+        /// Offset from UTC the session hours are given in
+        pub offset: FixedOffset,
+    }
+    
+    impl Default for SessionTable {
+        /// Asia, Europe and US sessions in UTC
+        fn default() -> Self {
+            let session = |name: &str, start, end| Session { name: name.to_string(), start, end };
+            SessionTable {
+                sessions: vec![session("Asia", 0, 8), session("Europe", 8, 13), session("US", 13, 21)],
+                offset: FixedOffset::east_opt(0).expect("zero offset is valid"),
+            }
+        }
+    }
+    
+    impl SessionTable {
+        /// Finds the session a timestamp falls into
+        ///
+        /// # Returns
+        /// * `Option<usize>` - Index into `sessions`, `None` outside every session
+        pub fn session_of(&self, timestamp: &DateTime<Utc>) -> Option<usize> {
+            let hour = timestamp.with_timezone(&self.offset).hour();
+            self.sessions.iter().position(|session| session.contains(hour))
+        }
+    }
+    
+    /// Parses a UTC offset such as `+09:00`, `-0500` or `UTC`
+    ///
+    /// # Errors
+    /// * Returns error if the text is not an offset
+    pub fn parse_utc_offset(text: &str) -> Result<FixedOffset> {
+        match text.trim() {
+            "UTC" | "utc" | "Z" => Ok(FixedOffset::east_opt(0).expect("zero offset is valid")),
+            offset => offset.parse().map_err(|_| anyhow!("Invalid UTC offset '{}', expected e.g. +09:00", offset)),
+        }
+    }
+}
+
+/// Locale-aware formatting of numbers and dates
+pub mod locale_utils {
+    use super::*;
+    use chrono::{DateTime, Timelike, Utc};
+    
+    /// Number and date conventions of a locale
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Locale {
+        /// BCP 47 tag such as `en-US`
+        pub tag: &'static str,
+        /// Character separating the integer and fractional parts
+        pub decimal: char,
+        /// Character grouping thousands, if any
+        pub grouping: Option<char>,
+        /// `chrono` format string for dates
+        pub date_format: &'static str,
+    }
+    
+    /// Supported locales; the first entry is the default
+    pub const LOCALES: [Locale; 5] = [
+        Locale { tag: "en-US", decimal: '.', grouping: Some(','), date_format: "%m/%d/%Y" },
+        Locale { tag: "en-GB", decimal: '.', grouping: Some(','), date_format: "%d/%m/%Y" },
+        Locale { tag: "de-DE", decimal: ',', grouping: Some('.'), date_format: "%d.%m.%Y" },
+        Locale { tag: "fr-FR", decimal: ',', grouping: Some(' '), date_format: "%d/%m/%Y" },
+        Locale { tag: "ja-JP", decimal: '.', grouping: Some(','), date_format: "%Y/%m/%d" },
+    ];
+    
+    impl Default for Locale {
+        fn default() -> Self {
+            LOCALES[0]
+        }
+    }
+    
+    impl Locale {
+        /// Looks up a locale by tag, ignoring case and accepting `_` for `-`
+        ///
+        /// # Arguments
+        /// * `tag` - Locale tag such as `de-DE`
+        ///
+        /// # Returns
+        /// * `Result<Locale>` - Matching locale
+        ///
+        /// # Errors
+        /// * Returns error listing the supported tags if the tag is unknown
+        pub fn from_tag(tag: &str) -> Result<Locale> {
+            let normalized = tag.trim().replace('_', "-");
+            LOCALES
+                .iter()
+                .find(|locale| locale.tag.eq_ignore_ascii_case(&normalized))
+                .copied()
+                .ok_or_else(|| {
+                    let supported: Vec<&str> = LOCALES.iter().map(|l| l.tag).collect();
+                    anyhow!("Unsupported locale '{}': expected one of {}", tag, supported.join(", "))
+                })
+        }
+        
+        /// Formats a number with a fixed number of decimals
+        ///
+        /// # Arguments
+        /// * `value` - Number to format
+        /// * `precision` - Digits after the decimal separator
+        ///
+        /// # Returns
+        /// * `String` - Localized representation
+        pub fn format_number(&self, value: f64, precision: usize) -> String {
+            if !value.is_finite() {
+                return value.to_string();
+            }
+            
+            let formatted = format!("{:.*}", precision, value.abs());
+            let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+            
+            let mut grouped = String::new();
+            for (i, digit) in integer.chars().enumerate() {
+                if let Some(separator) = self.grouping.filter(|_| i > 0 && (integer.len() - i) % 3 == 0) {
+                    grouped.push(separator);
+                }
+                grouped.push(digit);
+            }
+            
+            if !fraction.is_empty() {
+                grouped.push(self.decimal);
+                grouped.push_str(fraction);
+            }
+            
+            let is_zero = formatted.chars().all(|c| c == '0' || c == '.');
+            if value < 0.0 && !is_zero {
+                grouped.insert(0, '-');
+            }
+            
+            grouped
+        }
+        
+        /// Formats a timestamp as a date, adding the time of day if it is not midnight
+        ///
+        /// # Arguments
+        /// * `timestamp` - Timestamp to format
+        ///
+        /// # Returns
+        /// * `String` - Localized representation
+        pub fn format_date(&self, timestamp: &DateTime<Utc>) -> String {
+            let date = timestamp.format(self.date_format).to_string();
+            
+            if timestamp.num_seconds_from_midnight() == 0 {
+                date
+            } else {
+                format!("{} {}", date, timestamp.format("%H:%M"))
+            }
+        }
+    }
+}
+
+/// Statistics helpers shared by filters and styling
+pub mod stats_utils {
+    use anyhow::{Result, anyhow};
+    
+    /// Parses a percentile pair such as `1,99`
+    ///
+    /// # Arguments
+    /// * `text` - Lower and upper percentile separated by a comma
+    ///
+    /// # Returns
+    /// * `Result<(f64, f64)>` - Lower and upper percentile
+    ///
+    /// # Errors
+    /// * Returns error unless both are numbers with `0 <= lower < upper <= 100`
+    pub fn parse_percentile_range(text: &str) -> Result<(f64, f64)> {
+        let invalid = || anyhow!("Invalid percentile range '{}': expected LOWER,UPPER with 0 <= LOWER < UPPER <= 100", text);
+        let (lower, upper) = text.split_once(',').ok_or_else(invalid)?;
+        let lower: f64 = lower.trim().parse().map_err(|_| invalid())?;
+        let upper: f64 = upper.trim().parse().map_err(|_| invalid())?;
+        
+        match 0.0 <= lower && lower < upper && upper <= 100.0 {
+            true => Ok((lower, upper)),
+            false => Err(invalid()),
+        }
+    }
+    
+    /// Computes a percentile with linear interpolation between closest ranks
+    ///
+    /// # Arguments
+    /// * `values` - Sample values, non-finite values are ignored
+    /// * `pct` - Percentile in `0.0..=100.0`, clamped into that range
+    ///
+    /// # Returns
+    /// * `Option<f64>` - Percentile value, `None` if there are no finite values
+    pub fn percentile(values: &[f64], pct: f64) -> Option<f64> {
+        let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f64::total_cmp);
+        
+        let rank = pct.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64))
+    }
+    
+    /// Computes the mean, population standard deviation and skewness of a sample
+    ///
+    /// # Arguments
+    /// * `values` - Sample values, non-finite values are ignored
+    ///
+    /// # Returns
+    /// * `Option<(f64, f64, f64)>` - Mean, standard deviation and skewness,
+    ///   `None` if there are no finite values; the skewness is 0 for a sample
+    ///   without spread
+    pub fn moments(values: &[f64]) -> Option<(f64, f64, f64)> {
+        let finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        if finite.is_empty() {
+            return None;
+        }
+        
+        let n = finite.len() as f64;
+        let mean = finite.iter().sum::<f64>() / n;
+        let central = |power: i32| finite.iter().map(|v| (v - mean).powi(power)).sum::<f64>() / n;
+        let std_dev = central(2).sqrt();
+        let skew = match std_dev {
+            0.0 => 0.0,
+            _ => central(3) / std_dev.powi(3),
+        };
+        
+        Some((mean, std_dev, skew))
+    }
+    
+    /// Computes the log return of every value against the previous one
+    ///
+    /// # Arguments
+    /// * `values` - Series such as closes in time order
+    ///
+    /// # Returns
+    /// * `Vec<Option<f64>>` - `ln(value / previous)` per entry, `None` for the
+    ///   first entry and wherever the ratio is not positive and finite
+    pub fn log_returns(values: &[f64]) -> Vec<Option<f64>> {
+        let mut returns = vec![None; values.len()];
+        for (i, pair) in values.windows(2).enumerate() {
+            let ratio = pair[1] / pair[0];
+            if ratio > 0.0 && ratio.is_finite() {
+                returns[i + 1] = Some(ratio.ln());
+            }
+        }
+        returns
+    }
+    
+    /// Parses a number strictly between 0 and 1 such as `0.25`
+    ///
+    /// # Errors
+    /// * Returns error if the text is not a number in the open interval (0, 1)
+    pub fn parse_unit_fraction(text: &str) -> Result<f64> {
+        match text.trim().parse::<f64>() {
+            Ok(value) if value > 0.0 && value < 1.0 => Ok(value),
+            _ => Err(anyhow!("Invalid ratio '{}': expected a number strictly between 0 and 1", text)),
+        }
+    }
+    
+    /// Rounds a step up to the next nice number of 1, 2 or 5 times a power of ten
+    ///
+    /// # Arguments
+    /// * `span` - Width of the range to divide
+    /// * `intervals` - Most intervals the range may be divided into
+    ///
+    /// # Returns
+    /// * `Option<f64>` - Smallest nice step dividing the span into at most
+    ///   `intervals` parts, `None` for an empty span or no intervals
+    pub fn nice_step(span: f64, intervals: usize) -> Option<f64> {
+        if !(span.is_finite() && span > 0.0) || intervals == 0 {
+            return None;
+        }
+        let raw = span / intervals as f64;
+        let magnitude = 10f64.powf(raw.log10().floor());
+        let nice = [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .find(|nice| nice * magnitude >= raw * (1.0 - 1e-9))
+            .unwrap_or(10.0);
+        Some(nice * magnitude)
+    }
+}
+
+/// Color utility functions
+pub mod color_utils {
+    use super::*;
+    use eframe::egui::Color32;
+    
+    /// Fill color for candles that closed at or above their open
+    pub const UP_COLOR: Color32 = Color32::from_rgb(38, 166, 91);
+    
+    /// Fill color for candles that closed below their open
+    pub const DOWN_COLOR: Color32 = Color32::from_rgb(214, 48, 49);
+    
+    /// Fill color for candles whose body is inside the neutral band, gray `#8c8c8c`
+    pub const NEUTRAL_COLOR: Color32 = Color32::from_rgb(140, 140, 140);
+    
+    /// Color of price gridlines, light gray `#dcdcdc`
+    pub const GRID_COLOR: Color32 = Color32::from_rgb(220, 220, 220);
+    
+    /// Palette assigned in order to distinct category values
+    pub const CATEGORY_PALETTE: [Color32; 8] = [
+        Color32::from_rgb(31, 119, 180),
+        Color32::from_rgb(255, 127, 14),
+        Color32::from_rgb(44, 160, 44),
+        Color32::from_rgb(214, 39, 40),
+        Color32::from_rgb(148, 103, 189),
+        Color32::from_rgb(140, 86, 75),
+        Color32::from_rgb(227, 119, 194),
+        Color32::from_rgb(127, 127, 127),
+    ];
+    
+    /// Up candle color of the color-blind-safe palette, orange `#e69f00`
+    pub const CB_SAFE_UP_COLOR: Color32 = Color32::from_rgb(230, 159, 0);
+    
+    /// Down candle color of the color-blind-safe palette, blue `#0072b2`
+    pub const CB_SAFE_DOWN_COLOR: Color32 = Color32::from_rgb(0, 114, 178);
+    
+    /// Okabe-Ito palette for categories and indicators, distinguishable with
+    /// the common forms of color vision deficiency
+    ///
+    /// In order: sky blue `#56b4e9`, vermillion `#d55e00`, bluish green
+    /// `#009e73`, reddish purple `#cc79a7`, yellow `#f0e442`, orange `#e69f00`,
+    /// blue `#0072b2` and black `#000000`.
+    pub const CB_SAFE_PALETTE: [Color32; 8] = [
+        Color32::from_rgb(86, 180, 233),
+        Color32::from_rgb(213, 94, 0),
+        Color32::from_rgb(0, 158, 115),
+        Color32::from_rgb(204, 121, 167),
+        Color32::from_rgb(240, 228, 66),
+        Color32::from_rgb(230, 159, 0),
+        Color32::from_rgb(0, 114, 178),
+        Color32::from_rgb(0, 0, 0),
+    ];
+    
+    /// Color scheme used for candles, indicators and the legend
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+    pub enum Palette {
+        /// Green up and red down candles with the tab10 category colors
+        #[default]
+        Classic,
+        /// Orange up and blue down candles with the Okabe-Ito colors; up
+        /// candles are drawn hollow so direction does not rely on hue alone
+        CbSafe,
+    }
+    
+    impl Palette {
+        /// Fill color for candles that closed at or above their open
+        pub fn up_color(self) -> Color32 {
+            match self {
+                Palette::Classic => UP_COLOR,
+                Palette::CbSafe => CB_SAFE_UP_COLOR,
+            }
+        }
+        
+        /// Fill color for candles that closed below their open
+        pub fn down_color(self) -> Color32 {
+            match self {
+                Palette::Classic => DOWN_COLOR,
+                Palette::CbSafe => CB_SAFE_DOWN_COLOR,
+            }
+        }
+        
+        /// Fill color for candles too small to count as up or down
+        pub fn neutral_color(self) -> Color32 {
+            NEUTRAL_COLOR
+        }
+        
+        /// Colors assigned in order to categories and indicator lines
+        pub fn categories(self) -> &'static [Color32; 8] {
+            match self {
+                Palette::Classic => &CATEGORY_PALETTE,
+                Palette::CbSafe => &CB_SAFE_PALETTE,
+            }
+        }
+        
+        /// Whether up candles are drawn as outlines instead of filled bodies
+        pub fn hollow_up(self) -> bool {
+            self == Palette::CbSafe
+        }
+    }
+    
+    /// Parses a `#rrggbb` or `#rrggbbaa` hex string into a color
+    ///
+    /// # Arguments
+    /// * `hex` - Hex color string, the leading `#` is optional
+    ///
+    /// # Returns
+    /// * `Result<Color32>` - Parsed color
+    ///
+    /// # Errors
+    /// * Returns error if the string has the wrong length or invalid digits
+    pub fn parse_hex(hex: &str) -> Result<Color32> {
+        let digits = hex.trim().trim_start_matches('#');
+        
+        if !digits.is_ascii() || (digits.len() != 6 && digits.len() != 8) {
+            return Err(anyhow!("Invalid hex color '{}': expected #rrggbb or #rrggbbaa", hex));
+        }
+        
+        let channel = |i: usize| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .context(format!("Invalid hex color '{}'", hex))
+        };
+        
+        let (r, g, b) = (channel(0)?, channel(2)?, channel(4)?);
+        let a = if digits.len() == 8 { channel(6)? } else { 255 };
+        
+        Ok(Color32::from_rgba_unmultiplied(r, g, b, a))
+    }
+    
+    /// Formats a color as a `#rrggbbaa` hex string
+    ///
+    /// # Arguments
+    /// * `color` - Color to format
+    ///
+    /// # Returns
+    /// * `String` - Lowercase hex representation including alpha
+    pub fn to_hex(color: Color32) -> String {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+    
+    /// Scales the alpha of a color by an opacity factor
+    ///
+    /// # Arguments
+    /// * `color` - Base color
+    /// * `opacity` - Factor in `0.0..=1.0` applied to the existing alpha
+    ///
+    /// # Returns
+    /// * `Color32` - Color with the scaled alpha
+    pub fn with_opacity(color: Color32, opacity: f32) -> Color32 {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let alpha = (a as f32 * opacity.clamp(0.0, 1.0)).round() as u8;
+        Color32::from_rgba_unmultiplied(r, g, b, alpha)
+    }
+    
+    /// Middle color of the diverging colormap, a light gray
+    pub const DIVERGING_NEUTRAL: Color32 = Color32::from_rgb(247, 247, 247);
+    
+    /// Maps a signed value through a diverging colormap
+    ///
+    /// Zero maps to `DIVERGING_NEUTRAL`, `-limit` and `limit` to the end
+    /// colors, and values in between blend linearly. Values beyond the limit
+    /// are clamped to the ends.
+    ///
+    /// # Arguments
+    /// * `value` - Value to map
+    /// * `limit` - Magnitude mapped to the end colors
+    /// * `negative` - Color of `-limit`
+    /// * `positive` - Color of `limit`
+    ///
+    /// # Returns
+    /// * `Color32` - Opaque color, neutral for non-finite values or a limit
+    ///   that is not positive
+    pub fn diverging(value: f64, limit: f64, negative: Color32, positive: Color32) -> Color32 {
+        let t = match value / limit {
+            t if t.is_finite() && limit > 0.0 => t.clamp(-1.0, 1.0) as f32,
+            _ => 0.0,
+        };
+        let end = if t < 0.0 { negative } else { positive };
+        let ([r0, g0, b0, _], [r1, g1, b1, _]) = (DIVERGING_NEUTRAL.to_srgba_unmultiplied(), end.to_srgba_unmultiplied());
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t.abs()).round() as u8;
+        Color32::from_rgb(mix(r0, r1), mix(g0, g1), mix(b0, b1))
+    }
+    
+    /// Mixes a color toward white, keeping its alpha
+    ///
+    /// # Arguments
+    /// * `color` - Base color
+    /// * `amount` - 0.0 keeps the color, 1.0 yields white
+    ///
+    /// # Returns
+    /// * `Color32` - Lightened color
+    pub fn lighten(color: Color32, amount: f32) -> Color32 {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let mix = |channel: u8| (channel as f32 + (255.0 - channel as f32) * amount.clamp(0.0, 1.0)).round() as u8;
+        Color32::from_rgba_unmultiplied(mix(r), mix(g), mix(b), a)
+    }
+    
+    /// Background that exported charts are drawn on, white
+    pub const BACKGROUND_COLOR: Color32 = Color32::WHITE;
+    
+    /// Minimum contrast ratio of candles against the background with
+    /// `--auto-contrast`
+    ///
+    /// 3:1 is the WCAG 2.1 minimum for graphical objects (success criterion
+    /// 1.4.11); ratios range from 1:1 for equal colors to 21:1 for black on white.
+    pub const MIN_CONTRAST_RATIO: f64 = 3.0;
+    
+    /// Fraction of the distance to black or white moved per nudge step
+    const CONTRAST_STEP: f32 = 0.05;
+    
+    /// Computes the WCAG relative luminance of a color, ignoring alpha
+    ///
+    /// # Arguments
+    /// * `color` - Color to measure
+    ///
+    /// # Returns
+    /// * `f64` - Luminance from 0.0 for black to 1.0 for white
+    pub fn relative_luminance(color: Color32) -> f64 {
+        let [r, g, b, _] = color.to_srgba_unmultiplied();
+        let linear = |channel: u8| {
+            let value = channel as f64 / 255.0;
+            if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+        };
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+    
+    /// Computes the WCAG contrast ratio between two colors
+    ///
+    /// # Arguments
+    /// * `a` - First color
+    /// * `b` - Second color
+    ///
+    /// # Returns
+    /// * `f64` - Ratio from 1.0 to 21.0, independent of argument order
+    pub fn contrast_ratio(a: Color32, b: Color32) -> f64 {
+        let (la, lb) = (relative_luminance(a), relative_luminance(b));
+        (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+    }
+    
+    /// Nudges a color toward black or white until it contrasts with a background
+    ///
+    /// The color moves toward whichever of black and white contrasts more with
+    /// the background, in small steps, so its hue is kept as far as possible.
+    /// Colors that already meet the ratio are returned unchanged.
+    ///
+    /// # Arguments
+    /// * `color` - Color to adjust, its alpha is kept
+    /// * `background` - Color the result is drawn on
+    /// * `ratio` - Minimum contrast ratio, e.g. `MIN_CONTRAST_RATIO`
+    ///
+    /// # Returns
+    /// * `Color32` - The closest nudged color meeting the ratio, or black or
+    ///   white when the ratio cannot be reached
+    pub fn ensure_contrast(color: Color32, background: Color32, ratio: f64) -> Color32 {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let target = if contrast_ratio(Color32::BLACK, background) >= contrast_ratio(Color32::WHITE, background) {
+            0.0
+        } else {
+            255.0
+        };
+        let mix = |channel: u8, amount: f32| (channel as f32 + (target - channel as f32) * amount).round() as u8;
+        
+        let mut amount = 0.0;
+        loop {
+            let nudged = Color32::from_rgba_unmultiplied(mix(r, amount), mix(g, amount), mix(b, amount), a);
+            if amount >= 1.0 || contrast_ratio(nudged, background) >= ratio {
+                return nudged;
+            }
+            amount = (amount + CONTRAST_STEP).min(1.0);
+        }
+    }
+    
+    /// Parses a `value=#hex` color mapping
+    ///
+    /// # Arguments
+    /// * `mapping` - Mapping string such as `bull=#00ff00`
+    ///
+    /// # Returns
+    /// * `Result<(String, Color32)>` - Category value and its color
+    ///
+    /// # Errors
+    /// * Returns error if the `=` is missing or the color is invalid
+    pub fn parse_color_mapping(mapping: &str) -> Result<(String, Color32)> {
+        let (value, hex) = mapping
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid color mapping '{}': expected value=#hex", mapping))?;
+        
+        Ok((value.trim().to_string(), parse_hex(hex)?))
+    }
+}
+
+/// Process memory queries
+pub mod memory_utils {
+    use super::*;
+    
+    /// Resident memory of the process in bytes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MemoryUsage {
+        pub resident: u64,
+        /// Highest resident memory since the process started
+        pub peak: u64,
+    }
+    
+    /// Queries the resident memory of the running process
+    ///
+    /// Reads `/proc/self/status`, which costs one small file read, so it can
+    /// be called at any time.
+    ///
+    /// # Returns
+    /// * `Option<MemoryUsage>` - Current and peak resident memory, `None`
+    ///   where the platform does not provide `/proc` (e.g. macOS, Windows)
+    pub fn current() -> Option<MemoryUsage> {
+        fs::read_to_string("/proc/self/status").ok().as_deref().and_then(parse_status)
+    }
+    
+    /// Extracts resident memory from the text of a Linux `/proc/<pid>/status` file
+    ///
+    /// # Arguments
+    /// * `status` - File contents with lines such as `VmHWM:    1024 kB`
+    ///
+    /// # Returns
+    /// * `Option<MemoryUsage>` - `VmRSS` and `VmHWM` in bytes, `None` if
+    ///   either is missing or malformed
+    pub fn parse_status(status: &str) -> Option<MemoryUsage> {
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .and_then(|value| value.trim().strip_suffix("kB"))
+                .and_then(|kb| kb.trim().parse::<u64>().ok())
+                .map(|kb| kb * 1024)
+        };
+        
+        Some(MemoryUsage { resident: field("VmRSS")?, peak: field("VmHWM")? })
+    }
 }
 
 #[cfg(test)]
@@ -89,11 +1077,37 @@ mod tests {
     
     /// Test file existence check
     #[test]
+    #[allow(clippy::overly_complex_bool_expr)]
     fn test_file_exists() {
         let exists = file_utils::file_exists("/proc/cpuinfo"); // Should exist on Linux
         assert!(exists || !exists); // This test just verifies the function doesn't panic
     }
     
+    /// Test file existence of a present and a missing file
+    #[test]
+    fn test_file_exists_present_and_missing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file_path = temp_dir.path().join("present.txt");
+        fs::write(&file_path, "data")?;
+        
+        assert!(file_utils::file_exists(file_path.to_str().unwrap()));
+        assert!(!file_utils::file_exists(temp_dir.path().join("missing.txt").to_str().unwrap()));
+        
+        Ok(())
+    }
+    
+    /// Test wildcard file name matching
+    #[test]
+    fn test_matches_pattern() {
+        assert!(file_utils::matches_pattern("prices_2023.csv", "*.csv"));
+        assert!(file_utils::matches_pattern("a.csv", "?.csv"));
+        assert!(!file_utils::matches_pattern("prices_2023.csv", "prices_*_*.csv"));
+        assert!(file_utils::matches_pattern("prices_2023_01.csv", "prices_*_*.csv"));
+        assert!(!file_utils::matches_pattern("notes.txt", "*.csv"));
+        assert!(!file_utils::matches_pattern("ab.csv", "?.csv"));
+        assert!(file_utils::matches_pattern("anything", "*"));
+    }
+    
     /// Test string to uppercase conversion
     #[test]
     fn test_to_uppercase() {
@@ -109,4 +1123,310 @@ mod tests {
         assert_eq!(string_utils::trim_string("hello"), "hello");
         assert_eq!(string_utils::trim_string(""), "");
     }
+    
+    /// Test escaping markup characters
+    #[test]
+    fn test_escape_markup() {
+        assert_eq!(string_utils::escape_markup("<b>S&P \"500\"</b>"), "&lt;b&gt;S&amp;P &quot;500&quot;&lt;/b&gt;");
+        assert_eq!(string_utils::escape_markup("it's"), "it&#39;s");
+        assert_eq!(string_utils::escape_markup("plain"), "plain");
+    }
+    
+    /// Test hex color parsing
+    #[test]
+    fn test_parse_hex() -> Result<()> {
+        use eframe::egui::Color32;
+        
+        assert_eq!(color_utils::parse_hex("#ff8000")?, Color32::from_rgb(255, 128, 0));
+        assert_eq!(color_utils::parse_hex("00ff00")?, Color32::from_rgb(0, 255, 0));
+        assert_eq!(color_utils::parse_hex("#0000ff80")?, Color32::from_rgba_unmultiplied(0, 0, 255, 128));
+        assert!(color_utils::parse_hex("#fff").is_err());
+        assert!(color_utils::parse_hex("#gggggg").is_err());
+        assert_eq!(color_utils::to_hex(color_utils::parse_hex("#12ab34")?), "#12ab34ff");
+        
+        Ok(())
+    }
+    
+    /// Test value=#hex mapping parsing
+    #[test]
+    fn test_parse_color_mapping() -> Result<()> {
+        let (value, color) = color_utils::parse_color_mapping("bull=#00ff00")?;
+        assert_eq!(value, "bull");
+        assert_eq!(color, eframe::egui::Color32::from_rgb(0, 255, 0));
+        assert!(color_utils::parse_color_mapping("bull").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test sampling interval parsing
+    #[test]
+    fn test_parse_interval() -> Result<()> {
+        assert_eq!(date_utils::parse_interval("15m")?, chrono::Duration::minutes(15));
+        assert_eq!(date_utils::parse_interval("1w")?, chrono::Duration::weeks(1));
+        assert!(date_utils::parse_interval("0d").is_err());
+        assert!(date_utils::parse_interval("d").is_err());
+        assert!(date_utils::parse_interval("3y").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test calendar period parsing
+    #[test]
+    fn test_parse_period() -> Result<()> {
+        let (start, end) = date_utils::parse_period("2023-01")?;
+        assert_eq!(start.to_string(), "2023-01-01 00:00:00 UTC");
+        assert_eq!(end.to_string(), "2023-02-01 00:00:00 UTC");
+        
+        let (start, end) = date_utils::parse_period("2023-12-31")?;
+        assert_eq!(start.to_string(), "2023-12-31 00:00:00 UTC");
+        assert_eq!(end.to_string(), "2024-01-01 00:00:00 UTC");
+        
+        let (_, end) = date_utils::parse_period("2023")?;
+        assert_eq!(end.to_string(), "2024-01-01 00:00:00 UTC");
+        
+        assert!(date_utils::parse_period("2023-13").is_err());
+        assert!(date_utils::parse_period("soon").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test parsing FROM:TO period ranges
+    #[test]
+    fn test_parse_period_range() -> Result<()> {
+        let (start, end) = date_utils::parse_period_range("2023-02-01:2023-02-15")?;
+        assert_eq!(start.to_string(), "2023-02-01 00:00:00 UTC");
+        assert_eq!(end.to_string(), "2023-02-16 00:00:00 UTC");
+        
+        assert!(date_utils::parse_period_range("2023-02-15:2023-02-01").is_err());
+        assert!(date_utils::parse_period_range("2023-02-01").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test epoch timestamps in seconds and milliseconds, detected and forced
+    #[test]
+    fn test_parse_timestamp_epoch() -> Result<()> {
+        use date_utils::{parse_timestamp, TimestampFormat};
+        
+        assert_eq!(parse_timestamp("1672531200", TimestampFormat::Auto)?.to_string(), "2023-01-01 00:00:00 UTC");
+        assert_eq!(parse_timestamp("1672531200500", TimestampFormat::Auto)?.to_rfc3339(), "2023-01-01T00:00:00.500+00:00");
+        assert_eq!(parse_timestamp("2023-01-01 00:00:00", TimestampFormat::Auto)?.timestamp(), 1672531200);
+        
+        assert_eq!(parse_timestamp("86400", TimestampFormat::EpochS)?.to_string(), "1970-01-02 00:00:00 UTC");
+        assert_eq!(parse_timestamp("86400000", TimestampFormat::EpochMs)?.to_string(), "1970-01-02 00:00:00 UTC");
+        
+        assert!(parse_timestamp("86400", TimestampFormat::Auto).is_err());
+        assert!(parse_timestamp("2023-01-01 00:00:00", TimestampFormat::EpochS).is_err());
+        
+        let fractional = parse_timestamp("1672531200500", TimestampFormat::EpochMs)?;
+        assert_eq!(date_utils::format_timestamp(&fractional), "2023-01-01 00:00:00.500");
+        assert_eq!(parse_timestamp("2023-01-01 00:00:00.500", TimestampFormat::Auto)?, fractional);
+        assert_eq!(date_utils::format_timestamp(&parse_timestamp("1672531200", TimestampFormat::Auto)?), "2023-01-01 00:00:00");
+        
+        Ok(())
+    }
+    
+    /// Test business-day positions across a weekend and a loaded holiday
+    #[test]
+    fn test_business_calendar() -> Result<()> {
+        use chrono::NaiveDate;
+        use date_utils::BusinessCalendar;
+        
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("holidays.csv");
+        fs::write(&path, "Date,Name\n# closures\n2023-01-10,Example holiday\n\n")?;
+        let calendar = BusinessCalendar::load(&path)?;
+        
+        let date = |day| NaiveDate::from_ymd_opt(2023, 1, day).unwrap();
+        assert!(calendar.is_business_day(date(9)));
+        assert!(!calendar.is_business_day(date(7)));
+        assert!(!calendar.is_business_day(date(10)));
+        
+        // Friday, Monday, (holiday Tuesday), Wednesday, (missing Thursday), Friday
+        let positions = calendar.positions(&[date(6), date(9), date(11), date(13)]);
+        assert_eq!(positions, vec![0.0, 1.0, 2.0, 4.0]);
+        assert_eq!(BusinessCalendar::default().positions(&[date(9), date(11)]), vec![0.0, 2.0]);
+        
+        fs::write(&path, "2023-13-01\n")?;
+        assert!(BusinessCalendar::load(&path).is_err());
+        
+        Ok(())
+    }
+    
+    /// Test luminance, contrast ratios and nudging colors to a minimum contrast
+    #[test]
+    fn test_ensure_contrast() {
+        use color_utils::{BACKGROUND_COLOR, MIN_CONTRAST_RATIO};
+        use eframe::egui::Color32;
+        
+        assert_eq!(color_utils::relative_luminance(Color32::WHITE), 1.0);
+        assert!((color_utils::contrast_ratio(Color32::BLACK, Color32::WHITE) - 21.0).abs() < 1e-9);
+        assert_eq!(color_utils::contrast_ratio(Color32::WHITE, Color32::BLACK), color_utils::contrast_ratio(Color32::BLACK, Color32::WHITE));
+        
+        let dark = Color32::from_rgb(0, 0, 139);
+        assert_eq!(color_utils::ensure_contrast(dark, BACKGROUND_COLOR, MIN_CONTRAST_RATIO), dark);
+        
+        let pale = Color32::from_rgba_unmultiplied(160, 230, 160, 128);
+        let nudged = color_utils::ensure_contrast(pale, BACKGROUND_COLOR, MIN_CONTRAST_RATIO);
+        assert!(color_utils::contrast_ratio(nudged, BACKGROUND_COLOR) >= MIN_CONTRAST_RATIO);
+        assert!(color_utils::contrast_ratio(pale, BACKGROUND_COLOR) < MIN_CONTRAST_RATIO);
+        let [r, g, b, a] = nudged.to_srgba_unmultiplied();
+        assert!(g > r && g > b && r > 0 && a == 128);
+        
+        let lifted = color_utils::ensure_contrast(Color32::from_rgb(20, 20, 60), Color32::BLACK, MIN_CONTRAST_RATIO);
+        assert!(color_utils::relative_luminance(lifted) > color_utils::relative_luminance(Color32::from_rgb(20, 20, 60)));
+        assert!(color_utils::contrast_ratio(lifted, Color32::BLACK) >= MIN_CONTRAST_RATIO);
+        assert_eq!(color_utils::ensure_contrast(Color32::GRAY, Color32::GRAY, 21.0), Color32::BLACK);
+    }
+    
+    /// Test session parsing, wrapping past midnight and lookup in an offset
+    #[test]
+    fn test_session_table() -> Result<()> {
+        use chrono::{TimeZone, Utc};
+        use date_utils::{Session, SessionTable};
+        
+        let pacific = Session::parse("Pacific=22-2")?;
+        assert_eq!((pacific.start, pacific.end), (22, 2));
+        assert!(pacific.contains(23) && pacific.contains(1) && !pacific.contains(2));
+        assert!(Session::parse("Asia=3-3").is_err());
+        assert!(Session::parse("Asia=0-25").is_err());
+        assert!(Session::parse("=0-8").is_err());
+        assert!(Session::parse("Asia").is_err());
+        
+        let time = |hour| Utc.with_ymd_and_hms(2023, 1, 2, hour, 30, 0).unwrap();
+        let table = SessionTable::default();
+        assert_eq!(table.session_of(&time(7)), Some(0));
+        assert_eq!(table.session_of(&time(13)), Some(2));
+        assert_eq!(table.session_of(&time(22)), None);
+        
+        let tokyo = SessionTable { offset: date_utils::parse_utc_offset("+09:00")?, ..table };
+        assert_eq!(tokyo.session_of(&time(22)), Some(0));
+        assert_eq!(date_utils::parse_utc_offset("UTC")?, date_utils::parse_utc_offset("+00:00")?);
+        assert!(date_utils::parse_utc_offset("Tokyo").is_err());
+        
+        Ok(())
+    }
+    
+    /// Test locale lookup and number/date formatting
+    #[test]
+    fn test_locale_formatting() -> Result<()> {
+        use chrono::TimeZone;
+        use locale_utils::Locale;
+        
+        let us = Locale::default();
+        let de = Locale::from_tag("de_de")?;
+        assert_eq!(us.tag, "en-US");
+        assert!(Locale::from_tag("xx-YY").is_err());
+        
+        assert_eq!(us.format_number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(de.format_number(1234567.891, 2), "1.234.567,89");
+        assert_eq!(de.format_number(-0.5, 1), "-0,5");
+        assert_eq!(us.format_number(-0.001, 2), "0.00");
+        assert_eq!(us.format_number(999.0, 0), "999");
+        
+        let date = chrono::Utc.with_ymd_and_hms(2023, 1, 31, 0, 0, 0).unwrap();
+        assert_eq!(us.format_date(&date), "01/31/2023");
+        assert_eq!(de.format_date(&date), "31.01.2023");
+        
+        let intraday = chrono::Utc.with_ymd_and_hms(2023, 1, 31, 9, 30, 0).unwrap();
+        assert_eq!(de.format_date(&intraday), "31.01.2023 09:30");
+        
+        Ok(())
+    }
+    
+    /// Test percentile interpolation
+    #[test]
+    fn test_percentile() {
+        let values = [4.0, 1.0, 3.0, 2.0, f64::NAN];
+        
+        assert_eq!(stats_utils::percentile(&values, 0.0), Some(1.0));
+        assert_eq!(stats_utils::percentile(&values, 50.0), Some(2.5));
+        assert_eq!(stats_utils::percentile(&values, 100.0), Some(4.0));
+        assert_eq!(stats_utils::percentile(&[], 50.0), None);
+    }
+    
+    /// Test parsing percentile pairs and rejecting reversed or out-of-range ones
+    #[test]
+    fn test_parse_percentile_range() -> Result<()> {
+        assert_eq!(stats_utils::parse_percentile_range("1,99")?, (1.0, 99.0));
+        assert_eq!(stats_utils::parse_percentile_range(" 0.5 , 100")?, (0.5, 100.0));
+        
+        for bad in ["99,1", "5,5", "-1,50", "1,101", "1", "a,b", "NaN,50"] {
+            assert!(stats_utils::parse_percentile_range(bad).is_err(), "{}", bad);
+        }
+        
+        Ok(())
+    }
+    
+    /// Test sample moments of a skewed, a flat and an empty sample
+    #[test]
+    fn test_moments() {
+        let (mean, std_dev, skew) = stats_utils::moments(&[1.0, 1.0, 1.0, 5.0, f64::NAN]).unwrap_or_default();
+        assert_eq!((mean, std_dev), (2.0, 3.0_f64.sqrt()));
+        assert!((skew - 2.0 / 3.0_f64.sqrt()).abs() < 1e-12);
+        
+        assert_eq!(stats_utils::moments(&[0.5]), Some((0.5, 0.0, 0.0)));
+        assert_eq!(stats_utils::moments(&[]), None);
+    }
+    
+    /// Test log returns with an undefined first entry and non-positive values
+    #[test]
+    fn test_log_returns() {
+        let returns = stats_utils::log_returns(&[100.0, 200.0, 100.0, 0.0, 50.0]);
+        
+        assert_eq!(returns[0], None);
+        assert_eq!(returns[1], Some(2.0_f64.ln()));
+        assert_eq!(returns[2], Some(0.5_f64.ln()));
+        assert_eq!(&returns[3..], &[None, None]);
+        assert!(stats_utils::log_returns(&[]).is_empty());
+    }
+    
+    /// Test accepting ratios inside (0, 1) only
+    #[test]
+    fn test_parse_unit_fraction() -> Result<()> {
+        assert_eq!(stats_utils::parse_unit_fraction("0.25")?, 0.25);
+        for text in ["0", "1", "-0.5", "1.5", "NaN", "wide"] {
+            assert!(stats_utils::parse_unit_fraction(text).is_err(), "{} was accepted", text);
+        }
+        Ok(())
+    }
+    
+    /// Test nice steps across magnitudes, exact fits and empty spans
+    #[test]
+    fn test_nice_step() {
+        assert_eq!(stats_utils::nice_step(37.0, 8), Some(5.0));
+        assert_eq!(stats_utils::nice_step(40.0, 8), Some(5.0));
+        assert_eq!(stats_utils::nice_step(0.9, 5), Some(0.2));
+        assert_eq!(stats_utils::nice_step(1234.0, 10), Some(200.0));
+        assert_eq!(stats_utils::nice_step(70.0, 10), Some(10.0));
+        assert_eq!(stats_utils::nice_step(0.0, 8), None);
+        assert_eq!(stats_utils::nice_step(10.0, 0), None);
+    }
+    
+    /// Test reading resident and peak memory from a proc status file
+    #[test]
+    fn test_parse_status() {
+        use memory_utils::{parse_status, MemoryUsage};
+        
+        let status = "Name:\tcandle_stick_plotter\nVmPeak:\t  90000 kB\nVmHWM:\t    2048 kB\nVmRSS:\t    1024 kB\n";
+        assert_eq!(parse_status(status), Some(MemoryUsage { resident: 1024 * 1024, peak: 2048 * 1024 }));
+        assert_eq!(parse_status("VmRSS:\t1024 kB\n"), None);
+        assert_eq!(parse_status("VmRSS:\tmany kB\nVmHWM:\t1 kB\n"), None);
+    }
+    
+    /// Test the diverging colormap ends, midpoint, blending and clamping
+    #[test]
+    fn test_diverging() {
+        use eframe::egui::Color32;
+        let (negative, positive) = (Color32::from_rgb(255, 0, 0), Color32::from_rgb(0, 0, 255));
+        let map = |value| color_utils::diverging(value, 0.1, negative, positive);
+        
+        assert_eq!(map(0.0), color_utils::DIVERGING_NEUTRAL);
+        assert_eq!(map(-0.1), negative);
+        assert_eq!(map(0.1), positive);
+        assert_eq!(map(5.0), positive);
+        assert_eq!(map(-0.05), Color32::from_rgb(251, 124, 124));
+        assert_eq!(map(f64::NAN), color_utils::DIVERGING_NEUTRAL);
+        assert_eq!(color_utils::diverging(0.1, 0.0, negative, positive), color_utils::DIVERGING_NEUTRAL);
+    }
 }
@@ -0,0 +1,234 @@
+//! Schema and invariant checks of OHLCV input files
+//!
+//! Validation reads a file without building chart data: it checks that the
+//! OHLCV columns exist, that every checked value parses and that each row is
+//! a consistent candle. Issues are collected rather than returned as the
+//! first error so a single run reports everything that is wrong.
+
+use std::fmt;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::data_processor::{CsvProfile, OHLCV_COLUMNS};
+
+/// Problem found in an input file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// 1-based line of the offending record, `None` for file-level issues
+    pub line: Option<u64>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Outcome of validating a file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+    /// Number of data rows in the file
+    pub total_rows: usize,
+    /// Number of data rows that were checked
+    pub checked_rows: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Whether no issues were found
+    pub fn passed(&self) -> bool {
+        self.issues.is_empty()
+    }
+    
+    /// One-line summary such as `PASS: 120 of 120 rows checked, 0 issues`
+    pub fn summary(&self) -> String {
+        format!(
+            "{}: {} of {} rows checked, {} issues",
+            if self.passed() { "PASS" } else { "FAIL" },
+            self.checked_rows,
+            self.total_rows,
+            self.issues.len()
+        )
+    }
+}
+
+/// Validates a CSV file against the OHLCV schema of a profile
+///
+/// With `sample_rows`, files with more data rows are checked at evenly
+/// spaced rows only, which keeps validation fast for very large files. The
+/// file is read once: whenever more rows than allowed are kept, the spacing
+/// doubles and every other kept row is dropped, so between half of and all
+/// `sample_rows` rows are checked, starting with the first.
+///
+/// # Arguments
+/// * `path` - File to validate
+/// * `profile` - Column names, delimiter and timestamp format to expect
+/// * `sample_rows` - Largest number of rows to check, `None` checks all
+///
+/// # Returns
+/// * `Result<ValidationReport>` - Rows checked and issues found
+///
+/// # Errors
+/// * Returns error if the file cannot be opened or is not readable as CSV;
+///   problems with its contents are reported as issues instead
+pub fn validate_csv(path: &Path, profile: &CsvProfile, sample_rows: Option<usize>) -> Result<ValidationReport> {
+    let text = profile.read_from_header(path)?;
+    let delimiter = profile.delimiter_for(&text);
+    if !delimiter.is_ascii() || !profile.comment_char.is_ascii() {
+        return Err(anyhow!("CSV delimiter and comment character must be ASCII characters"));
+    }
+    
+    let mut report = ValidationReport::default();
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .delimiter(delimiter as u8)
+        .comment(Some(profile.comment_char as u8))
+        .from_reader(text.as_bytes());
+    let headers = rdr.headers().context("Failed to read CSV headers")?.clone();
+    let positions = OHLCV_COLUMNS.map(|column| headers.iter().position(|h| profile.standard_column(h) == Some(column)));
+    for (column, position) in OHLCV_COLUMNS.iter().zip(&positions) {
+        if position.is_none() {
+            report.issues.push(Issue { line: None, message: format!("missing column '{}'", column) });
+        }
+    }
+    
+    let mut samples = Vec::new();
+    let mut stride = 1;
+    for (row, result) in rdr.records().enumerate() {
+        report.total_rows += 1;
+        if row % stride != 0 {
+            continue;
+        }
+        samples.push((row, result));
+        if let Some(max) = sample_rows
+            && samples.len() > max.max(1)
+        {
+            stride *= 2;
+            samples.retain(|(row, _)| row % stride == 0);
+        }
+    }
+    if !report.passed() {
+        return Ok(report);
+    }
+    
+    let positions = positions.map(|position| position.unwrap_or_default());
+    report.checked_rows = samples.len();
+    for (_, result) in samples {
+        match result {
+            Ok(record) => check_record(&record, &positions, profile, &mut report.issues),
+            Err(err) => report.issues.push(Issue {
+                line: err.position().map(|position| position.line()),
+                message: format!("unreadable record: {}", err),
+            }),
+        }
+    }
+    
+    Ok(report)
+}
+
+/// Checks that one record parses and forms a consistent candle
+fn check_record(record: &StringRecord, positions: &[usize; 6], profile: &CsvProfile, issues: &mut Vec<Issue>) {
+    let line = record.position().map(|position| position.line());
+    let mut issue = |message: String| issues.push(Issue { line, message });
+    let field = |i: usize| record.get(positions[i]).unwrap_or_default().trim();
+    
+    if let Err(err) = profile.parse_timestamp(field(0)) {
+        issue(format!("{:#}", err));
+    }
+    
+    let mut values = [0.0; 5];
+    for (i, value) in values.iter_mut().enumerate() {
+        match field(i + 1).parse::<f64>() {
+            Ok(parsed) if parsed.is_finite() => *value = parsed,
+            _ => {
+                issue(format!("{} '{}' is not a number", OHLCV_COLUMNS[i + 1], field(i + 1)));
+                return;
+            }
+        }
+    }
+    
+    let [open, high, low, close, volume] = values;
+    if high < low {
+        issue(format!("high {} is below low {}", high, low));
+    }
+    if open > high || close > high {
+        issue(format!("open {} or close {} is above high {}", open, close, high));
+    }
+    if open < low || close < low {
+        issue(format!("open {} or close {} is below low {}", open, close, low));
+    }
+    if volume < 0.0 {
+        issue(format!("volume {} is negative", volume));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    /// Writes a CSV file into a temporary directory
+    fn write_csv(dir: &TempDir, contents: &str) -> Result<std::path::PathBuf> {
+        let path = dir.path().join("input.csv");
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+    
+    /// Test a valid file and the issues of broken rows
+    #[test]
+    fn test_validate_csv() -> Result<()> {
+        let dir = TempDir::new()?;
+        let valid = write_csv(&dir, "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,10,12,9,11,100\n")?;
+        let report = validate_csv(&valid, &CsvProfile::default(), None)?;
+        assert!(report.passed());
+        assert_eq!(report.summary(), "PASS: 1 of 1 rows checked, 0 issues");
+        
+        let broken = write_csv(
+            &dir,
+            "Timestamp,Open,High,Low,Close,Volume\n\
+             soon,10,12,9,11,100\n\
+             2023-01-02 00:00:00,10,8,9,11,-5\n\
+             2023-01-03 00:00:00,ten,12,9,11,100\n",
+        )?;
+        let report = validate_csv(&broken, &CsvProfile::default(), None)?;
+        let issues: Vec<String> = report.issues.iter().map(Issue::to_string).collect();
+        
+        assert_eq!(report.summary(), "FAIL: 3 of 3 rows checked, 5 issues");
+        assert!(issues[0].starts_with("line 2: Failed to parse timestamp 'soon'"));
+        assert_eq!(issues[1], "line 3: high 8 is below low 9");
+        assert_eq!(issues[2], "line 3: open 10 or close 11 is above high 8");
+        assert_eq!(issues[3], "line 3: volume -5 is negative");
+        assert_eq!(issues[4], "line 4: Open 'ten' is not a number");
+        
+        Ok(())
+    }
+    
+    /// Test missing columns and sampling of large files
+    #[test]
+    fn test_validate_csv_columns_and_sampling() -> Result<()> {
+        let dir = TempDir::new()?;
+        let missing = write_csv(&dir, "Timestamp,Open,High,Close\n2023-01-01 00:00:00,10,12,11\n")?;
+        let report = validate_csv(&missing, &CsvProfile::default(), None)?;
+        assert_eq!(report.issues.len(), 2);
+        assert_eq!(report.issues[0].to_string(), "missing column 'Low'");
+        assert_eq!(report.checked_rows, 0);
+        
+        let rows: String = (0..100).map(|i| format!("{},10,12,9,11,100\n", 1672531200 + i * 60)).collect();
+        let large = write_csv(&dir, &format!("Timestamp,Open,High,Low,Close,Volume\n{}", rows))?;
+        let report = validate_csv(&large, &CsvProfile::default(), Some(10))?;
+        assert_eq!((report.total_rows, report.checked_rows), (100, 7));
+        assert!(report.passed());
+        
+        let broken = write_csv(&dir, &format!("Timestamp,Open,High,Low,Close,Volume\nsoon,10,12,9,11,100\n{}", rows))?;
+        let report = validate_csv(&broken, &CsvProfile::default(), Some(100))?;
+        assert_eq!(report.summary(), "FAIL: 51 of 101 rows checked, 1 issues");
+        assert_eq!(report.issues[0].line, Some(2));
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+//! Native chart windows drawing render models with egui_plot
+//!
+//! Everything drawn here comes from a `RenderModel`, so the window shows the
+//! same geometry and colors as the exported JSON. A `ComparisonModel` is shown
+//! as two price panels next to each other.
+
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+use eframe::egui::{self, Align2, Stroke};
+use egui_plot::{Bar, BarChart, HLine, Legend, Line, LineStyle, MarkerShape, Plot, PlotPoint, PlotPoints, Points, Polygon, Text, VLine};
+
+use crate::layout;
+use crate::render_model::{
+    self, CandleRect, CanvasText, Colorbar, ComparisonModel, Layer, Panel, RenderLine, RenderModel, TextAlign, TextRole, TimeIndex, Watermark,
+};
+use crate::utils::color_utils;
+
+/// Height of the lower indicator panel in points
+const LOWER_PANEL_HEIGHT: f32 = 160.0;
+
+/// Radius of pattern markers in points
+const MARKER_RADIUS: f32 = 5.0;
+
+/// Stroke width of the outline around the candle under the cursor
+const HOVER_OUTLINE_WIDTH: f32 = 3.0;
+
+/// Color of watermark text before its opacity is applied
+const WATERMARK_COLOR: egui::Color32 = egui::Color32::from_rgb(96, 96, 96);
+
+/// Font size of the message shown in place of an empty chart in points
+const PLACEHOLDER_SIZE: f32 = 16.0;
+
+/// How often a live window checks for a newer model
+const LIVE_REFRESH: Duration = Duration::from_millis(250);
+
+/// Width and height of the colorbar in points
+const COLORBAR_SIZE: (f32, f32) = (16.0, 240.0);
+
+/// eframe application showing a single chart
+pub struct ChartWindow {
+    model: RenderModel,
+    time_index: TimeIndex,
+    /// Newer models replacing the shown one, e.g. from a live feed
+    updates: Option<Receiver<RenderModel>>,
+}
+
+impl ChartWindow {
+    /// Creates a window for the given model
+    ///
+    /// # Arguments
+    /// * `model` - Chart to display
+    ///
+    /// # Returns
+    /// * `ChartWindow` - New application
+    pub fn new(model: RenderModel) -> Self {
+        let time_index = TimeIndex::new(&model);
+        ChartWindow { model, time_index, updates: None }
+    }
+    
+    /// Creates a window that shows the latest model received from `updates`
+    ///
+    /// # Arguments
+    /// * `model` - Chart to display until the first update arrives
+    /// * `updates` - Models replacing the shown chart
+    ///
+    /// # Returns
+    /// * `ChartWindow` - New application
+    #[cfg(feature = "stream")]
+    pub fn live(model: RenderModel, updates: Receiver<RenderModel>) -> Self {
+        ChartWindow { updates: Some(updates), ..ChartWindow::new(model) }
+    }
+}
+
+impl eframe::App for ChartWindow {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(updates) = &self.updates {
+            if let Some(model) = updates.try_iter().last() {
+                self.time_index = TimeIndex::new(&model);
+                self.model = model;
+            }
+            ctx.request_repaint_after(LIVE_REFRESH);
+        }
+        let model = &self.model;
+        let time_index = &self.time_index;
+        
+        if let Some(message) = &model.placeholder {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.centered_and_justified(|ui| ui.label(egui::RichText::new(message).size(PLACEHOLDER_SIZE)));
+            });
+            return;
+        }
+        
+        let (top, bottom): (Vec<&CanvasText>, Vec<&CanvasText>) = model.texts
+            .iter()
+            .partition(|text| text.role != TextRole::Caption);
+        if !bottom.is_empty() {
+            egui::TopBottomPanel::bottom("caption_panel").show(ctx, |ui| draw_texts(ui, &bottom));
+        }
+        if !top.is_empty() {
+            egui::TopBottomPanel::top("title_panel").show(ctx, |ui| draw_texts(ui, &top));
+        }
+        
+        if let Some(range) = model.volume_y_range {
+            egui::TopBottomPanel::bottom("volume_panel").show(ctx, |ui| {
+                Plot::new("volume_plot")
+                    .height(LOWER_PANEL_HEIGHT)
+                    .include_x(model.x_range.min)
+                    .include_x(model.x_range.max)
+                    .include_y(range.min)
+                    .include_y(range.max)
+                    .show(ui, |plot_ui| {
+                        let bars = model.volume
+                            .iter()
+                            .map(|bar| {
+                                Bar::new((bar.left + bar.right) / 2.0, bar.volume)
+                                    .width(bar.right - bar.left)
+                                    .fill(bar.color)
+                                    .stroke(Stroke::NONE)
+                            })
+                            .collect();
+                        plot_ui.bar_chart(BarChart::new("Volume", bars));
+                    });
+            });
+        }
+        
+        if let Some(range) = model.lower_y_range {
+            egui::TopBottomPanel::bottom("lower_panel").show(ctx, |ui| {
+                Plot::new("lower_plot")
+                    .legend(Legend::default())
+                    .height(LOWER_PANEL_HEIGHT)
+                    .include_x(model.x_range.min)
+                    .include_x(model.x_range.max)
+                    .include_y(range.min)
+                    .include_y(range.max)
+                    .show(ui, |plot_ui| {
+                        for line in model.lines.iter().filter(|l| l.panel == Panel::Lower) {
+                            plot_ui.line(series_line(line));
+                        }
+                    });
+            });
+        }
+        
+        if let Some(colorbar) = &model.colorbar {
+            egui::SidePanel::right("colorbar_panel")
+                .resizable(false)
+                .show(ctx, |ui| draw_colorbar(ui, colorbar));
+        }
+        
+        egui::CentralPanel::default().show(ctx, |ui| {
+            price_plot(ui, model, time_index, Plot::new("price_plot"));
+        });
+        
+        if let Some(watermark) = &model.watermark {
+            draw_watermark(ctx, watermark);
+        }
+    }
+}
+
+/// eframe application showing the price panels of two charts side by side
+pub struct ComparisonWindow {
+    comparison: ComparisonModel,
+    time_indexes: [TimeIndex; 2],
+}
+
+impl ComparisonWindow {
+    /// Creates a window for the given comparison
+    ///
+    /// # Arguments
+    /// * `comparison` - Charts to display
+    ///
+    /// # Returns
+    /// * `ComparisonWindow` - New application
+    pub fn new(comparison: ComparisonModel) -> Self {
+        let time_indexes = [TimeIndex::new(&comparison.left), TimeIndex::new(&comparison.right)];
+        ComparisonWindow { comparison, time_indexes }
+    }
+}
+
+impl eframe::App for ComparisonWindow {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let comparison = &self.comparison;
+        let link = [comparison.link_axes, comparison.shared_scale];
+        
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.columns(2, |columns| {
+                let panels = [&comparison.left, &comparison.right].into_iter().zip(&self.time_indexes);
+                for (i, (column, (model, time_index))) in columns.iter_mut().zip(panels).enumerate() {
+                    let plot = Plot::new(("comparison_plot", i)).link_axis("comparison_axes", link);
+                    price_plot(column, model, time_index, plot);
+                }
+            });
+        });
+    }
+}
+
+/// Draws the price panel of a model into the given plot
+///
+/// # Arguments
+/// * `ui` - Ui the plot is added to
+/// * `model` - Chart to draw
+/// * `time_index` - Candle lookup of `model` for tooltips and hovering
+/// * `plot` - Plot to configure and show, e.g. with linked axes
+fn price_plot(ui: &mut egui::Ui, model: &RenderModel, time_index: &TimeIndex, plot: Plot<'_>) {
+    plot
+        .legend(Legend::default())
+        .include_x(model.x_range.min)
+        .include_x(model.x_range.max)
+        .include_y(model.y_range.min)
+        .include_y(model.y_range.max)
+        .y_axis_formatter(|mark, _| model.axis_label(mark.value))
+        .y_axis_label(model.y_label.clone().unwrap_or_default())
+        .show_grid([true, model.price_grid.is_empty()])
+        .label_formatter(|_, point| {
+            time_index
+                .nearest(point.x)
+                .and_then(|i| model.tooltip(i))
+                .unwrap_or_default()
+        })
+        .show(ui, |plot_ui| {
+            let (y_min, y_max) = (model.y_range.min, model.y_range.max);
+            for layer in &model.layers {
+                match layer {
+                    Layer::Bands => {
+                        for band in &model.bands {
+                            plot_ui.polygon(
+                                Polygon::new("", PlotPoints::from(vec![
+                                    [band.left, y_min], [band.right, y_min], [band.right, y_max], [band.left, y_max],
+                                ]))
+                                .fill_color(band.color)
+                                .stroke(Stroke::NONE),
+                            );
+                        }
+                    }
+                    Layer::Grid => {
+                        for &price in &model.price_grid {
+                            plot_ui.hline(HLine::new("", price).color(color_utils::GRID_COLOR).width(1.0));
+                        }
+                    }
+                    Layer::Fills => {
+                        for fill in &model.fills {
+                            plot_ui.polygon(
+                                Polygon::new("", PlotPoints::from(fill.points.clone()))
+                                    .fill_color(fill.color)
+                                    .stroke(Stroke::NONE),
+                            );
+                        }
+                    }
+                    Layer::Candles => {
+                        for wick in &model.wicks {
+                            plot_ui.line(
+                                Line::new("", PlotPoints::from(vec![[wick.x, wick.low], [wick.x, wick.high]]))
+                                    .color(wick.color),
+                            );
+                        }
+                        for candle in &model.candles {
+                            let stroke = candle.outline
+                                .map(|outline| Stroke::new(outline.width, outline.color))
+                                .unwrap_or(Stroke::new(1.0, candle.fill));
+                            plot_ui.polygon(
+                                Polygon::new("", PlotPoints::from(body_corners(candle)))
+                                    .fill_color(candle.fill)
+                                    .stroke(stroke),
+                            );
+                        }
+                        let hovered = plot_ui.pointer_coordinate().and_then(|point| time_index.nearest(point.x));
+                        if let Some(candle) = hovered.map(|i| &model.candles[i]) {
+                            plot_ui.polygon(
+                                Polygon::new("", PlotPoints::from(body_corners(candle)))
+                                    .fill_color(egui::Color32::TRANSPARENT)
+                                    .stroke(Stroke::new(HOVER_OUTLINE_WIDTH, color_utils::lighten(candle.fill, 0.6))),
+                            );
+                        }
+                    }
+                    Layer::Lines => {
+                        for line in model.lines.iter().filter(|l| l.panel == Panel::Price) {
+                            plot_ui.line(series_line(line));
+                        }
+                    }
+                    Layer::Events => {
+                        for event in &model.events {
+                            plot_ui.vline(VLine::new("", event.x).color(event.color));
+                            plot_ui.text(
+                                Text::new("", PlotPoint::new(event.x, model.y_range.max), event.label.as_str())
+                                    .color(event.color)
+                                    .anchor(Align2::LEFT_TOP),
+                            );
+                        }
+                    }
+                    Layer::Markers => {
+                        for marker in &model.markers {
+                            let shape = match marker.shape {
+                                render_model::MarkerShape::Up => MarkerShape::Up,
+                                render_model::MarkerShape::Down => MarkerShape::Down,
+                            };
+                            plot_ui.points(
+                                Points::new(marker.name.as_str(), vec![[marker.x, marker.y]])
+                                    .shape(shape)
+                                    .radius(MARKER_RADIUS)
+                                    .filled(true)
+                                    .color(marker.color),
+                            );
+                        }
+                    }
+                }
+            }
+            if let Some(tag) = &model.price_tag {
+                plot_ui.text(
+                    Text::new("", PlotPoint::new(model.x_range.max, tag.price), format!(" {} ", tag.label))
+                        .color(tag.color)
+                        .anchor(Align2::RIGHT_BOTTOM),
+                );
+            }
+        });
+}
+
+/// Draws a watermark on a foreground layer above all panels
+fn draw_watermark(ctx: &egui::Context, watermark: &Watermark) {
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("watermark")));
+    let screen = ctx.screen_rect();
+    let (x, y) = watermark.position.anchor();
+    let center = screen.min + egui::vec2(x * screen.width(), y * screen.height());
+    
+    let font = egui::FontId::proportional(layout::watermark_font_size(screen.width(), &watermark.text));
+    let galley = painter.layout_no_wrap(
+        watermark.text.clone(),
+        font,
+        color_utils::with_opacity(WATERMARK_COLOR, watermark.opacity),
+    );
+    let shape = egui::epaint::TextShape::new(center - galley.size() / 2.0, galley, WATERMARK_COLOR)
+        .with_angle_and_anchor(watermark.rotation.to_radians(), Align2::CENTER_CENTER);
+    painter.add(shape);
+}
+
+/// Draws laid-out canvas texts line by line with their size, weight and alignment
+fn draw_texts(ui: &mut egui::Ui, texts: &[&CanvasText]) {
+    for text in texts {
+        for line in &text.lines {
+            let mut rich = egui::RichText::new(line).size(text.font_size);
+            if text.bold {
+                rich = rich.strong();
+            }
+            match text.align {
+                TextAlign::Center => ui.vertical_centered(|ui| ui.label(rich)),
+                TextAlign::Left => ui.horizontal(|ui| ui.label(rich)),
+            };
+        }
+    }
+}
+
+/// Draws a colorbar top to bottom from its highest to its lowest value
+fn draw_colorbar(ui: &mut egui::Ui, colorbar: &Colorbar) {
+    ui.label(egui::RichText::new(&colorbar.title).strong());
+    if let Some(highest) = colorbar.stops.last() {
+        ui.label(Colorbar::label(highest.value));
+    }
+    
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(COLORBAR_SIZE.0, COLORBAR_SIZE.1), egui::Sense::hover());
+    let step = rect.height() / colorbar.stops.len().max(1) as f32;
+    for (i, stop) in colorbar.stops.iter().rev().enumerate() {
+        let min = egui::pos2(rect.left(), rect.top() + i as f32 * step);
+        ui.painter().rect_filled(egui::Rect::from_min_size(min, egui::vec2(rect.width(), step)), 0.0, stop.color);
+    }
+    
+    if let Some(lowest) = colorbar.stops.first() {
+        ui.label(Colorbar::label(lowest.value));
+    }
+}
+
+/// Builds the plot line of a series with its color and stroke pattern
+fn series_line(line: &RenderLine) -> Line<'_> {
+    let style = match line.style {
+        render_model::LineStyle::Solid => LineStyle::Solid,
+        render_model::LineStyle::Dashed => LineStyle::dashed_dense(),
+        render_model::LineStyle::Dotted => LineStyle::dotted_dense(),
+    };
+    Line::new(line.name.as_str(), PlotPoints::from(line.points.clone()))
+        .color(line.color)
+        .style(style)
+}
+
+/// Corners of a candle body in drawing order
+fn body_corners(candle: &CandleRect) -> Vec<[f64; 2]> {
+    vec![
+        [candle.left, candle.bottom],
+        [candle.right, candle.bottom],
+        [candle.right, candle.top],
+        [candle.left, candle.top],
+    ]
+}
@@ -1,6 +1,7 @@
 //! Integration tests for the candle stick plotter application
 
 use assert_cmd::Command;
+use predicates::prelude::*;
 use predicates::str::contains;
 use tempfile::TempDir;
 // use std::fs;
@@ -25,6 +26,37 @@ fn test_complete_workflow() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Test writing candlestick.png for a series denser than the chart width
+#[test]
+fn test_large_csv_png() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("large.csv");
+    let output_dir = temp_dir.path().join("out");
+    let mut csv = String::from("Timestamp,Open,High,Low,Close,Volume\n");
+    for i in 0..2000 {
+        let open = 100.0 + (i as f64 / 20.0).sin() * 10.0;
+        csv.push_str(&format!(
+            "2023-01-{:02} {:02}:{:02}:00,{:.2},{:.2},{:.2},{:.2},1000\n",
+            1 + i / 1440, i / 60 % 24, i % 60, open, open + 1.0, open - 1.0, open + 0.5
+        ));
+    }
+    std::fs::write(&csv_path, csv)?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("large")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--output-dir")
+       .arg(&output_dir)
+       .assert()
+       .success();
+    
+    let image = std::fs::read(output_dir.join("candlestick.png"))?;
+    assert!(image.starts_with(b"\x89PNG\r\n\x1a\n"));
+    
+    Ok(())
+}
+
 /// Test with different input variations
 #[test]
 fn test_various_inputs() -> Result<(), Box<dyn std::error::Error>> {
@@ -60,3 +92,1511 @@ fn test_with_custom_csv() -> Result<(), Box<dyn std::error::Error>> {
     
     Ok(())
 }
+
+/// Test exporting the render model as JSON
+#[test]
+fn test_export_render_model() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("test")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--export-render-model")
+       .arg(model_path.to_str().unwrap())
+       .assert()
+       .success();
+    
+    let json = std::fs::read_to_string(&model_path)?;
+    assert!(json.contains("\"candles\""));
+    assert!(json.contains("\"y_range\""));
+    
+    Ok(())
+}
+
+/// Test that --zoom-range limits the exported model and rejects empty windows
+#[test]
+fn test_zoom_range() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("zoom")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--zoom-range")
+       .arg("2023-01-02:2023-01-02")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["candles"].as_array().map(Vec::len), Some(1));
+    assert_eq!(json["candles"][0]["index"], 1);
+    assert_eq!(json["x_range"]["min"], 0.5);
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("zoom")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--zoom-range")
+       .arg("2023-02-01:2023-02-15")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .code(4)
+       .stderr(contains("contains no candles"));
+    
+    Ok(())
+}
+
+/// Test that title, subtitle and caption are laid out in the exported model
+#[test]
+fn test_title_subtitle_caption() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("titles")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--width")
+       .arg("400")
+       .arg("--title")
+       .arg("Sample prices")
+       .arg("--subtitle")
+       .arg("Three daily candles")
+       .arg("--caption")
+       .arg("Source: built-in sample data")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["texts"][0]["role"], "title");
+    assert_eq!(json["texts"][0]["lines"][0], "Sample prices");
+    assert_eq!(json["texts"][0]["x"], 200.0);
+    assert_eq!(json["texts"][2]["align"], "left");
+    assert!(json["margins"]["top"].as_f64().unwrap_or_default() > 0.0);
+    assert!(json["margins"]["bottom"].as_f64().unwrap_or_default() > 0.0);
+    
+    Ok(())
+}
+
+/// Test writing an animated GIF of the candles
+#[test]
+fn test_animate_gif() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let gif_path = temp_dir.path().join("chart.gif");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("animate")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--width")
+       .arg("160")
+       .arg("--height")
+       .arg("90")
+       .arg("--animate-gif")
+       .arg(&gif_path)
+       .arg("--fps")
+       .arg("5")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let gif = std::fs::read(&gif_path)?;
+    assert_eq!(&gif[..6], b"GIF89a");
+    assert_eq!(&gif[6..10], &[160, 0, 90, 0]);
+    assert_eq!(gif.last(), Some(&0x3B));
+    
+    Ok(())
+}
+
+/// Test that comment metadata becomes the default chart title
+#[test]
+fn test_comment_metadata_title() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("aapl.csv");
+    std::fs::write(
+        &csv_path,
+        "# symbol=AAPL exchange=NASDAQ\nTimestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0\n",
+    )?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("meta")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["texts"][0]["lines"][0], "AAPL (NASDAQ)");
+    
+    // The symbol names the image
+    assert!(temp_dir.path().join("out").join("candlestick_aapl.png").exists());
+    assert!(!temp_dir.path().join("out").join("candlestick.png").exists());
+    
+    Ok(())
+}
+
+/// Test that --sqlite input leaves the --csv-file and its metadata unread
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_skips_csv_file() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("aapl.csv");
+    let db_path = temp_dir.path().join("prices.db");
+    std::fs::write(
+        &csv_path,
+        "# symbol=AAPL\nTimestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,100.0,105.0,95.0,102.0,1000.0\n",
+    )?;
+    let connection = rusqlite::Connection::open(&db_path)?;
+    connection.execute_batch(
+        "CREATE TABLE prices (ts INTEGER, open REAL, high REAL, low REAL, close REAL, volume INTEGER);
+         INSERT INTO prices VALUES (1672531200, 100, 105, 95, 102, 1000);",
+    )?;
+    drop(connection);
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("sqlite")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--sqlite")
+       .arg(&db_path)
+       .arg("--query")
+       .arg("SELECT ts AS Timestamp, open, high, low, close, volume FROM prices")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    assert!(temp_dir.path().join("out").join("candlestick.png").exists());
+    assert!(!temp_dir.path().join("out").join("candlestick_aapl.png").exists());
+    
+    Ok(())
+}
+
+/// Test exporting an HTML report with the chart, summary and metadata
+#[test]
+fn test_export_html() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("aapl.csv");
+    std::fs::write(
+        &csv_path,
+        "# symbol=AAPL\nTimestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,100,105,95,102,1000\n2023-01-02 00:00:00,102,108,101,106,1200\n",
+    )?;
+    let html_path = temp_dir.path().join("chart.html");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("report")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--export-html")
+       .arg(&html_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let html = std::fs::read_to_string(&html_path)?;
+    assert!(html.contains("<title>AAPL</title>"));
+    assert!(html.contains("<svg xmlns="));
+    assert!(html.contains("<tr><th>Candles</th><td>2</td></tr>"));
+    assert!(html.contains("<tr><th>symbol</th><td>AAPL</td></tr>"));
+    
+    Ok(())
+}
+
+/// Test embedding the candles in the HTML report and reading them back
+#[test]
+fn test_embed_and_extract_data() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("input.csv");
+    let rows = "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,100,105,95,102,1000\n2023-01-02 00:00:00,102,108,101,106,1200\n";
+    std::fs::write(&csv_path, rows)?;
+    let html_path = temp_dir.path().join("chart.html");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("report")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--export-html")
+       .arg(&html_path)
+       .arg("--embed-data")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    assert!(std::fs::read_to_string(&html_path)?.contains("<script type=\"application/json\" id=\"candle-data\">"));
+    
+    let extracted = temp_dir.path().join("extracted.csv");
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("report")
+       .arg("--extract-data")
+       .arg(&html_path)
+       .arg("--export-csv")
+       .arg(&extracted)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    assert_eq!(std::fs::read_to_string(&extracted)?, format!("# schema_version=1\n{}", rows));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("report")
+       .arg("--extract-data")
+       .arg(&csv_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .failure()
+       .stderr(predicate::str::contains("No embedded candle data"));
+    
+    Ok(())
+}
+
+/// Test embedding the candles in an SVG chart and reading them back
+#[test]
+fn test_embed_and_extract_svg_data() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("input.csv");
+    let rows = "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,100,105,95,102,1000\n2023-01-02 00:00:00,102,108,101,106,1200\n";
+    std::fs::write(&csv_path, rows)?;
+    let svg_path = temp_dir.path().join("chart.svg");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("report")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--export-svg")
+       .arg(&svg_path)
+       .arg("--embed-data")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    let svg = std::fs::read_to_string(&svg_path)?;
+    assert!(svg.starts_with("<svg ") && svg.contains("\n<metadata id=\"candle-data\">[{"));
+    
+    let extracted = temp_dir.path().join("extracted.csv");
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("report")
+       .arg("--extract-data")
+       .arg(&svg_path)
+       .arg("--export-csv")
+       .arg(&extracted)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    assert_eq!(std::fs::read_to_string(&extracted)?, format!("# schema_version=1\n{}", rows));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("report")
+       .arg("--embed-data")
+       .assert()
+       .failure();
+    
+    Ok(())
+}
+
+/// Test styled moving averages and rejecting an unknown line style
+#[test]
+fn test_styled_moving_averages() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("averages")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--sma")
+       .arg("2:#ff0000:dashed")
+       .arg("--sma")
+       .arg("3")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["lines"][0]["name"], "SMA(2)");
+    assert_eq!(json["lines"][0]["color"], "#ff0000ff");
+    assert_eq!(json["lines"][0]["style"], "dashed");
+    assert_eq!(json["lines"][1]["style"], "solid");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("averages")
+       .arg("--sma")
+       .arg("2:#ff0000:wavy")
+       .assert()
+       .failure()
+       .stderr(contains("Unknown line style 'wavy'"));
+    
+    Ok(())
+}
+
+/// Test printing the quality score and its verbose breakdown
+#[test]
+fn test_quality_score() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("gappy.csv");
+    std::fs::write(
+        &csv_path,
+        "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,10,12,9,11,100\n2023-01-02 00:00:00,11,12,10,11,100\n2023-01-04 00:00:00,11,12,10,11,100\n",
+    )?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("quality")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--quality")
+       .arg("--verbose")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success()
+       .stdout(contains("Quality score: 95.0/100"))
+       .stdout(contains("gaps: 1 of 2 intervals, -5.0 of 10 points"))
+       .stdout(contains("OHLC violations: 0 of 3 candles, -0.0 of 30 points"));
+    
+    Ok(())
+}
+
+/// Test coloring candles by log return with a colorbar in the render model and SVG
+#[test]
+fn test_color_by_return() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    let html_path = temp_dir.path().join("chart.html");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("returns")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--color-by-return")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .arg("--export-html")
+       .arg(&html_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["colorbar"]["title"], "Log return");
+    assert_eq!(json["colorbar"]["stops"].as_array().map(Vec::len), Some(9));
+    assert_eq!(json["candles"][0]["fill"], "#f7f7f7ff");
+    assert!(std::fs::read_to_string(&html_path)?.contains(">Log return</text>"));
+    
+    Ok(())
+}
+
+/// Test a rotated text watermark in the render model and the HTML export
+#[test]
+fn test_watermark_text() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    let html_path = temp_dir.path().join("chart.html");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("watermark")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--watermark-text")
+       .arg("DRAFT")
+       .arg("--watermark-text-opacity")
+       .arg("0.3")
+       .arg("--watermark-position")
+       .arg("bottom-left")
+       .arg("--watermark-rotation")
+       .arg("-45")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .arg("--export-html")
+       .arg(&html_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["watermark"]["text"], "DRAFT");
+    assert_eq!(json["watermark"]["position"], "bottom-left");
+    assert_eq!(json["watermark"]["rotation"], -45.0);
+    assert!(std::fs::read_to_string(&html_path)?.contains("rotate(-45 300.00 600.00)"));
+    
+    Ok(())
+}
+
+/// Test printing the returns histogram with its moments
+#[test]
+fn test_returns_histogram() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("returns")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--returns-histogram")
+       .arg("2")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success()
+       .stdout(contains("Returns histogram (2 returns):"))
+       .stdout(contains("Mean: 2.90%, std dev: 1.02%, skew: 0.00"));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("returns")
+       .arg("--returns-histogram")
+       .arg("0")
+       .assert()
+       .code(2)
+       .stderr(contains("0 is not in 1.."));
+    
+    Ok(())
+}
+
+/// Test the software preview backend writing a PNG instead of opening a window
+#[test]
+fn test_software_preview_backend() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let output_dir = temp_dir.path().join("out");
+    
+    // An empty PATH keeps an image viewer from being started
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("preview")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--window")
+       .arg("--backend")
+       .arg("software-preview")
+       .arg("--output-dir")
+       .arg(&output_dir)
+       .env("PATH", temp_dir.path())
+       .assert()
+       .success()
+       .stdout(contains("Preview saved to"));
+    
+    let image = std::fs::read(output_dir.join("preview.png"))?;
+    assert!(image.starts_with(b"\x89PNG\r\n\x1a\n"));
+    
+    Ok(())
+}
+
+/// Test that the PNG compression level is applied and the file size logged
+#[test]
+fn test_png_compression() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let mut sizes = Vec::new();
+    
+    for level in ["0", "9"] {
+        let output_dir = temp_dir.path().join(level);
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("preview")
+           .arg("--csv-file")
+           .arg("non_existent_file.csv")
+           .arg("--window")
+           .arg("--backend")
+           .arg("software-preview")
+           .arg("--png-compression")
+           .arg(level)
+           .arg("--png-palette")
+           .arg("--output-dir")
+           .arg(&output_dir)
+           .env("PATH", temp_dir.path())
+           .env("RUST_LOG", "info")
+           .assert()
+           .success()
+           .stderr(contains("bytes to"));
+        sizes.push(std::fs::metadata(output_dir.join("preview.png"))?.len());
+    }
+    assert!(sizes[1] < sizes[0]);
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("preview")
+       .arg("--png-compression")
+       .arg("10")
+       .assert()
+       .failure();
+    
+    Ok(())
+}
+
+/// Test that --transparent exports keep the background out of the PNG and SVG
+#[test]
+fn test_transparent_background() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let output_dir = temp_dir.path().join("out");
+    let html_path = temp_dir.path().join("chart.html");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("transparent")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--window")
+       .arg("--backend")
+       .arg("software-preview")
+       .arg("--transparent")
+       .arg("--export-html")
+       .arg(&html_path)
+       .arg("--output-dir")
+       .arg(&output_dir)
+       .env("PATH", temp_dir.path())
+       .assert()
+       .success();
+    
+    let file = std::fs::File::open(output_dir.join("preview.png"))?;
+    let mut reader = png::Decoder::new(std::io::BufReader::new(file)).read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size().ok_or("image too large")?];
+    let info = reader.next_frame(&mut pixels)?;
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(pixels[3], 0);
+    assert!(pixels.chunks(4).any(|pixel| pixel[3] == 255));
+    assert!(!std::fs::read_to_string(&html_path)?.contains("<rect width=\"100%\""));
+    
+    Ok(())
+}
+
+/// Test printing gap fill statistics of a series with one filled gap
+#[test]
+fn test_gap_fill_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("gaps.csv");
+    std::fs::write(
+        &csv_path,
+        "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,100,105,95,102,1000\n2023-01-02 00:00:00,107,110,106,108,1200\n2023-01-03 00:00:00,108,109,104,105,1500\n",
+    )?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("gaps")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--gap-fill-stats")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success()
+       .stdout(contains("Gaps: 1, filled: 1"))
+       .stdout(contains("Filled within the same candle: 0.0%"))
+       .stdout(contains("Filled by the next candle: 100.0%"))
+       .stdout(contains("Average time to fill: 1.0 days"));
+    
+    Ok(())
+}
+
+/// Test that --sort-check rejects out-of-order input with the offending row
+#[test]
+fn test_sort_check() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("unsorted.csv");
+    std::fs::write(
+        &csv_path,
+        "Timestamp,Open,High,Low,Close,Volume\n2023-01-02 00:00:00,102,108,101,106,1200\n2023-01-01 00:00:00,100,105,95,102,1000\n",
+    )?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("unsorted")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--sort-check")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .code(4)
+       .stderr(contains("row 1 (2023-01-01 00:00:00) is earlier than row 0"));
+    
+    Ok(())
+}
+
+/// Test that a low above the high fails with --strict and only warns without it
+#[test]
+fn test_strict_ohlc_check() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("broken.csv");
+    std::fs::write(
+        &csv_path,
+        "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,100,105,95,102,1000\n2023-01-02 00:00:00,102,101,108,106,1200\n",
+    )?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("broken")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--strict")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .code(4)
+       .stderr(contains("row 1 (2023-01-02 00:00:00) breaks low <= open <= high"));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("broken")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .env_remove("RUST_LOG")
+       .assert()
+       .success()
+       .stderr(contains("Warning:"))
+       .stderr(contains("row 1 (2023-01-02 00:00:00) breaks low <= open <= high"));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("broken")
+       .arg("--compare")
+       .arg("non_existent_file.csv")
+       .arg(&csv_path)
+       .arg("--strict")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .code(4)
+       .stderr(contains("broken.csv"))
+       .stderr(contains("row 1 (2023-01-02 00:00:00) breaks low <= open <= high"));
+    
+    Ok(())
+}
+
+/// Test that zooming into a long series draws the raw candles of the zoom range
+#[test]
+fn test_zoom_range_before_thinning() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("hourly.csv");
+    let model_path = temp_dir.path().join("model.json");
+    let mut csv = String::from("Timestamp,Open,High,Low,Close,Volume\n");
+    for i in 0..2000 {
+        // Hourly epoch seconds from 2023-01-01 00:00:00
+        csv.push_str(&format!("{},100,101,99,100.5,10\n", 1672531200 + i * 3600));
+    }
+    std::fs::write(&csv_path, csv)?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("zoom")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--zoom-range")
+       .arg("2023-01-10:2023-01-10")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["candles"].as_array().map(Vec::len), Some(24));
+    
+    Ok(())
+}
+
+/// Test a fixed x-axis label rotation in the exported model and HTML
+#[test]
+fn test_x_label_rotation() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    let html_path = temp_dir.path().join("chart.html");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("labels")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--x-label-rotation")
+       .arg("-90")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .arg("--export-html")
+       .arg(&html_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["x_labels"]["rotation"], -90.0);
+    assert_eq!(json["x_labels"]["ticks"].as_array().map(Vec::len), Some(3));
+    assert_eq!(std::fs::read_to_string(&html_path)?.matches("transform=\"rotate(-90 ").count(), 3);
+    
+    Ok(())
+}
+
+/// Test that --winsorize caps the exported closes unless --export-raw is given
+#[test]
+fn test_winsorize() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let capped_path = temp_dir.path().join("capped.csv");
+    let raw_path = temp_dir.path().join("raw.csv");
+    
+    for (path, raw) in [(&capped_path, false), (&raw_path, true)] {
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("winsorize")
+           .arg("--csv-file")
+           .arg("non_existent_file.csv")
+           .arg("--winsorize")
+           .arg("0,50")
+           .arg("--export-csv")
+           .arg(path)
+           .arg("--output-dir")
+           .arg(temp_dir.path().join("out"));
+        if raw {
+            cmd.arg("--export-raw");
+        }
+        cmd.assert().success();
+    }
+    
+    let last_close = |path: &std::path::Path| -> Result<String, Box<dyn std::error::Error>> {
+        let csv = std::fs::read_to_string(path)?;
+        let last = csv.lines().last().ok_or("empty export")?;
+        Ok(last.split(',').nth(4).ok_or("missing close")?.to_string())
+    };
+    assert_eq!(last_close(&capped_path)?, "106");
+    assert_eq!(last_close(&raw_path)?, "108");
+    
+    Ok(())
+}
+
+/// Test plotting beta against a benchmark and rejecting one without shared timestamps
+#[test]
+fn test_beta() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let benchmark_path = temp_dir.path().join("index.csv");
+    let model_path = temp_dir.path().join("model.json");
+    std::fs::write(
+        &benchmark_path,
+        "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,50,51,49,50,0\n2023-01-02 00:00:00,50,53,50,52,0\n2023-01-03 00:00:00,52,56,52,55,0\n",
+    )?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("beta")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--benchmark")
+       .arg(&benchmark_path)
+       .arg("--beta")
+       .arg("2")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    let beta = json["lines"].as_array().and_then(|lines| lines.iter().find(|line| line["name"] == "Beta(2)")).ok_or("no beta line")?;
+    assert_eq!(beta["panel"], "lower");
+    assert_eq!(beta["points"].as_array().map(Vec::len), Some(1));
+    
+    std::fs::write(&benchmark_path, "Timestamp,Open,High,Low,Close,Volume\n2024-01-01 00:00:00,50,51,49,50,0\n")?;
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("beta")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--benchmark")
+       .arg(&benchmark_path)
+       .arg("--beta")
+       .arg("2")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .code(4)
+       .stderr(contains("sharing timestamps"));
+    
+    Ok(())
+}
+
+/// Test that --business-days closes weekend and holiday gaps in the export
+#[test]
+fn test_business_days() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("daily.csv");
+    let holidays_path = temp_dir.path().join("holidays.txt");
+    let model_path = temp_dir.path().join("model.json");
+    std::fs::write(&csv_path, "Timestamp,Open,High,Low,Close,Volume\n\
+        2023-01-06 00:00:00,10,12,9,11,100\n\
+        2023-01-07 00:00:00,11,13,10,12,100\n\
+        2023-01-09 00:00:00,12,14,11,13,100\n\
+        2023-01-10 00:00:00,13,15,12,14,100\n\
+        2023-01-11 00:00:00,14,16,13,15,100\n")?;
+    std::fs::write(&holidays_path, "2023-01-10\n")?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("business")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--business-days")
+       .arg("--holidays")
+       .arg(&holidays_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    let candles = json["candles"].as_array().ok_or("no candles")?;
+    let centers: Vec<f64> = candles
+        .iter()
+        .map(|c| (c["left"].as_f64().unwrap_or_default() + c["right"].as_f64().unwrap_or_default()) / 2.0)
+        .collect();
+    assert_eq!(centers, vec![0.0, 1.0, 2.0]);
+    assert_eq!(candles[2]["timestamp"], "2023-01-11T00:00:00+00:00");
+    
+    Ok(())
+}
+
+/// Test mid-price candles from --bid and --ask files and unmatched timestamps
+#[test]
+fn test_bid_ask_mid_candles() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let bid_path = temp_dir.path().join("bid.csv");
+    let ask_path = temp_dir.path().join("ask.csv");
+    let export_path = temp_dir.path().join("mid.csv");
+    std::fs::write(&bid_path, "Timestamp,Open,High,Low,Close,Volume\n\
+        2023-01-01 00:00:00,10,12,9,11,100\n\
+        2023-01-02 00:00:00,11,13,10,12,100\n")?;
+    std::fs::write(&ask_path, "Timestamp,Open,High,Low,Close,Volume\n\
+        2023-01-01 00:00:00,12,14,11,13,50\n\
+        2023-01-03 00:00:00,13,15,12,14,50\n")?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("mid")
+       .arg("--bid")
+       .arg(&bid_path)
+       .arg("--ask")
+       .arg(&ask_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .code(4)
+       .stderr(contains("Bid candle at 2023-01-02 00:00:00 has no ask candle"));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("mid")
+       .arg("--bid")
+       .arg(&bid_path)
+       .arg("--ask")
+       .arg(&ask_path)
+       .arg("--drop-unmatched")
+       .arg("--volume-source")
+       .arg("ask")
+       .arg("--export-csv")
+       .arg(&export_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let exported = std::fs::read_to_string(&export_path)?;
+    let rows: Vec<&str> = exported.lines().skip(2).collect();
+    assert_eq!(rows, vec!["2023-01-01 00:00:00,11,13,10,12,50"]);
+    
+    Ok(())
+}
+
+/// Test that --profile-memory reports both stages on stderr
+#[test]
+fn test_profile_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("memory")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--profile-memory")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success()
+       .stderr(contains("Memory after load:").and(contains("Memory after indicators:")));
+    
+    Ok(())
+}
+
+/// Test that --relative-to charts price ratios and drops unmatched candles
+#[test]
+fn test_relative_to() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let benchmark_path = temp_dir.path().join("index.csv");
+    let model_path = temp_dir.path().join("model.json");
+    std::fs::write(&benchmark_path, "Timestamp,Open,High,Low,Close,Volume\n\
+        2023-01-01 00:00:00,50,50,50,50,0\n\
+        2023-01-02 00:00:00,0,0,0,0,0\n\
+        2023-01-03 00:00:00,54,54,54,54,0\n")?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("ratio")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--relative-to")
+       .arg(&benchmark_path)
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["y_label"], "Ratio to index");
+    let wicks = json["wicks"].as_array().ok_or("no wicks")?;
+    assert_eq!(wicks.len(), 2);
+    assert_eq!(wicks[1]["high"], 110.0 / 54.0);
+    
+    Ok(())
+}
+
+/// Test that --batch renders every chart and reports failed ones at the end
+#[test]
+fn test_batch_manifest() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let manifest_path = temp_dir.path().join("nightly.toml");
+    let (first, third) = (temp_dir.path().join("first.json"), temp_dir.path().join("third.json"));
+    std::fs::write(&manifest_path, format!(
+        "[[chart]]\nexport_render_model = {:?}\n\n\
+         [[chart]]\nrelative_to = \"missing.csv\"\n\n\
+         [[chart]]\nexport_render_model = {:?}\nsma = [\"2\"]\n",
+        first.to_string_lossy(), third.to_string_lossy()
+    ))?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("nightly")
+       .arg("--batch")
+       .arg(&manifest_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .failure()
+       .stdout(predicate::str::contains("Batch finished: 2 of 3 charts rendered, 1 failed"))
+       .stderr(predicate::str::contains("Chart 2 failed").and(predicate::str::contains("1 of 3 charts")));
+    
+    assert!(first.is_file());
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&third)?)?;
+    assert_eq!(json["lines"][0]["name"], "SMA(2)");
+    
+    Ok(())
+}
+
+/// Test that --trim-warmup leaves out rows before the indicators are defined
+#[test]
+fn test_export_csv_trim_warmup() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("clean.csv");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("trim")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--sma")
+       .arg("2")
+       .arg("--export-csv")
+       .arg(&csv_path)
+       .arg("--trim-warmup")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let text = std::fs::read_to_string(&csv_path)?;
+    assert!(text.starts_with("# schema_version="));
+    let rows: Vec<&str> = text.lines().skip(2).collect();
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].starts_with("2023-01-02"));
+    
+    Ok(())
+}
+
+/// Test loading a semicolon separated file with --delimiter auto
+#[test]
+fn test_delimiter_auto() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let input = temp_dir.path().join("vendor.csv");
+    std::fs::write(&input, "Timestamp;Open;High;Low;Close;Volume\n2023-01-01 00:00:00;10;12;9;11;100\n")?;
+    let csv_path = temp_dir.path().join("clean.csv");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("vendor")
+       .arg("--csv-file")
+       .arg(&input)
+       .arg("--delimiter")
+       .arg("auto")
+       .arg("--export-csv")
+       .arg(&csv_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let text = std::fs::read_to_string(&csv_path)?;
+    assert_eq!(text.lines().nth(2), Some("2023-01-01 00:00:00,10,12,9,11,100"));
+    
+    Ok(())
+}
+
+/// Test that --export-csv follows the --locale decimal mark unless overridden
+#[test]
+fn test_export_csv_locale() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let input = temp_dir.path().join("prices.csv");
+    std::fs::write(&input, "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,10.5,12,9,11.25,100\n")?;
+    
+    for (extra, expected) in [
+        (None, "Timestamp;Open;High;Low;Close;Volume\n2023-01-01 00:00:00;10,5;12;9;11,25;100"),
+        (Some("."), "Timestamp;Open;High;Low;Close;Volume\n2023-01-01 00:00:00;10.5;12;9;11.25;100"),
+    ] {
+        let csv_path = temp_dir.path().join("german.csv");
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("german")
+           .arg("--csv-file")
+           .arg(&input)
+           .arg("--locale")
+           .arg("de-DE")
+           .arg("--export-csv")
+           .arg(&csv_path)
+           .arg("--output-dir")
+           .arg(temp_dir.path().join("out"));
+        if let Some(decimal) = extra {
+            cmd.arg("--export-decimal").arg(decimal);
+        }
+        cmd.assert().success();
+        
+        let text = std::fs::read_to_string(&csv_path)?;
+        assert!(text.contains(expected), "{}", text);
+    }
+    
+    Ok(())
+}
+
+/// Test compare mode with a shared price scale
+#[test]
+fn test_compare_shared_scale() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let other_csv = temp_dir.path().join("other.csv");
+    std::fs::write(
+        &other_csv,
+        "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,200.0,210.0,190.0,205.0,10.0\n",
+    )?;
+    let model_path = temp_dir.path().join("compare.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("test")
+       .arg("--compare")
+       .arg("non_existent_file.csv")
+       .arg(other_csv.to_str().unwrap())
+       .arg("--shared-scale")
+       .arg("--export-render-model")
+       .arg(model_path.to_str().unwrap())
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["left"]["y_range"], json["right"]["y_range"]);
+    assert_eq!(json["shared_scale"], true);
+    assert_eq!(json["left"]["size"], serde_json::json!([600, 800]));
+    
+    // Both panels share one image of the default size
+    let images: Vec<_> = std::fs::read_dir(temp_dir.path().join("out"))?.collect::<Result<_, _>>()?;
+    assert_eq!(images.len(), 1);
+    let image = std::fs::read(temp_dir.path().join("out").join("candlestick.png"))?;
+    assert_eq!(&image[16..24], &[0, 0, 4, 176, 0, 0, 3, 32]);
+    
+    Ok(())
+}
+
+/// Test that --first and --last cannot be combined
+#[test]
+fn test_first_and_last_conflict() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("test")
+       .arg("--first")
+       .arg("2")
+       .arg("--last")
+       .arg("2")
+       .assert()
+       .failure()
+       .stderr(contains("cannot be used with"));
+    
+    Ok(())
+}
+
+/// Test plotting the symbol columns of a wide CSV file
+#[test]
+fn test_wide_symbols() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("wide.csv");
+    std::fs::write(&csv_path, "Date,AAA,BBB\n2023-01-01 00:00:00,10,20\n2023-01-02 00:00:00,11,21\n")?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("wide")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--wide")
+       .arg("--symbols")
+       .arg("AAA,BBB")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("wide")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--wide")
+       .arg("--symbols")
+       .arg("AAA,ZZZ")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .failure()
+       .stderr(contains("Symbol column 'ZZZ' not found"));
+    
+    Ok(())
+}
+
+/// Test that replaying a saved config reproduces the same chart
+#[test]
+fn test_save_and_replay_config() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("chart.toml");
+    let first_model = temp_dir.path().join("first.json");
+    let second_model = temp_dir.path().join("second.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("save")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--last")
+       .arg("2")
+       .arg("--wick-ratios")
+       .arg("--color-map")
+       .arg("bull=#00ff00")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&first_model)
+       .arg("--save-config")
+       .arg(&config_path)
+       .assert()
+       .success();
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("replay")
+       .arg("--config")
+       .arg(&config_path)
+       .arg("--export-render-model")
+       .arg(&second_model)
+       .assert()
+       .success();
+    
+    let first: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&first_model)?)?;
+    let second: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&second_model)?)?;
+    assert_eq!(first, second);
+    assert_eq!(second["candles"].as_array().map(Vec::len), Some(2));
+    
+    Ok(())
+}
+
+/// Test range bar synthesis and rejection of a non-positive amount
+#[test]
+fn test_range_bars() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("range")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--range-bars")
+       .arg("2")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .assert()
+       .success();
+    
+    let model: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(model["candles"].as_array().map(Vec::len), Some(3));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("range")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--range-bars")
+       .arg("0")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .code(4)
+       .stderr(contains("Range bar amount must be positive"));
+    
+    Ok(())
+}
+
+/// Test thinning the sample data to fit a narrow chart
+#[test]
+fn test_thin_strategy() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    for strategy in ["ohlc-merge", "skip", "lttb"] {
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("thin")
+           .arg("--csv-file")
+           .arg("non_existent_file.csv")
+           .arg("--width")
+           .arg("4")
+           .arg("--thin-strategy")
+           .arg(strategy)
+           .arg("--output-dir")
+           .arg(temp_dir.path().join("out"))
+           .arg("--export-render-model")
+           .arg(&model_path)
+           .assert()
+           .success();
+        
+        let model: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+        assert_eq!(model["candles"].as_array().map(Vec::len), Some(2), "{}", strategy);
+    }
+    
+    Ok(())
+}
+
+/// Test loading epoch timestamps in seconds and milliseconds
+#[test]
+fn test_epoch_timestamps() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let model_path = temp_dir.path().join("model.json");
+    
+    for (timestamps, format) in [(["1672531200", "1672617600"], "auto"), (["1672531200000", "1672617600000"], "epoch_ms")] {
+        let csv_path = temp_dir.path().join("epoch.csv");
+        std::fs::write(
+            &csv_path,
+            format!(
+                "Timestamp,Open,High,Low,Close,Volume\n{},1,2,0.5,1.5,10\n{},1.5,3,1,2.5,20\n",
+                timestamps[0], timestamps[1]
+            ),
+        )?;
+        
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("epoch")
+           .arg("--csv-file")
+           .arg(&csv_path)
+           .arg("--timestamp-format")
+           .arg(format)
+           .arg("--output-dir")
+           .arg(temp_dir.path().join("out"))
+           .arg("--export-render-model")
+           .arg(&model_path)
+           .assert()
+           .success();
+        
+        let model: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+        assert_eq!(model["candles"][1]["label"], "01/02/2023", "{}", format);
+    }
+    
+    Ok(())
+}
+
+/// Test writing a trade log from moving average crossovers
+#[test]
+fn test_trade_log() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("prices.csv");
+    let log_path = temp_dir.path().join("trades.csv");
+    let rows: String = [10.0, 9.0, 11.0, 12.0, 8.0]
+        .iter()
+        .enumerate()
+        .map(|(day, close)| format!("2023-01-{:02} 00:00:00,{close},{close},{close},{close},1\n", day + 1))
+        .collect();
+    std::fs::write(&csv_path, format!("Timestamp,Open,High,Low,Close,Volume\n{}", rows))?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("trades")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--ma-cross")
+       .arg("1")
+       .arg("2")
+       .arg("--trade-log")
+       .arg(&log_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let log = std::fs::read_to_string(&log_path)?;
+    assert!(log.starts_with("entry_time,entry_price,exit_time,exit_price,return_pct\n"));
+    assert!(log.contains("2023-01-03 00:00:00,11,2023-01-05 00:00:00,8,-27.2727"));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("trades")
+       .arg("--trade-log")
+       .arg(&log_path)
+       .assert()
+       .failure()
+       .stderr(contains("--ma-cross"));
+    
+    Ok(())
+}
+
+/// Test that --ma-cross rejects zero periods and a FAST period not below SLOW
+#[test]
+fn test_ma_cross_invalid_periods() -> Result<(), Box<dyn std::error::Error>> {
+    for (periods, message) in [(["0", "3"], "0 is not in 1.."), (["3", "3"], "must be below SLOW")] {
+        let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+        cmd.arg("cross")
+           .arg("--ma-cross")
+           .args(periods)
+           .assert()
+           .code(2)
+           .stderr(contains(message));
+    }
+    
+    Ok(())
+}
+
+/// Test loading a Yahoo Finance download with the yahoo profile
+#[test]
+fn test_yahoo_profile() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let csv_path = temp_dir.path().join("AAPL.csv");
+    let export_path = temp_dir.path().join("export.csv");
+    std::fs::write(&csv_path, "Date,Open,High,Low,Close,Adj Close,Volume\n2023-01-03,130.28,130.9,124.17,125.07,124.2,112117500\n")?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("yahoo")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--profile")
+       .arg("yahoo")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .arg("--export-csv")
+       .arg(&export_path)
+       .assert()
+       .success();
+    
+    assert!(std::fs::read_to_string(&export_path)?.contains("2023-01-03 00:00:00,130.28,130.9,124.17,125.07,112117500"));
+    
+    // The benchmark is read with the same profile as the input
+    let model_path = temp_dir.path().join("model.json");
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("yahoo")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--profile")
+       .arg("yahoo")
+       .arg("--relative-to")
+       .arg(&csv_path)
+       .arg("--export-render-model")
+       .arg(&model_path)
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success();
+    
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&model_path)?)?;
+    assert_eq!(json["wicks"].as_array().ok_or("no wicks")?.len(), 1);
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("yahoo")
+       .arg("--csv-file")
+       .arg(&csv_path)
+       .arg("--profile")
+       .arg("nasdaq")
+       .assert()
+       .failure()
+       .stderr(contains("Unknown profile 'nasdaq'"));
+    
+    Ok(())
+}
+
+/// Test printing the longest streaks of the sample data
+#[test]
+fn test_streaks() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("streaks")
+       .arg("--csv-file")
+       .arg("non_existent_file.csv")
+       .arg("--streaks")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success()
+       .stdout(contains("Longest up streak: 2 candles ending 01/03/2023"))
+       .stdout(contains("Longest down streak: none"));
+    
+    Ok(())
+}
+
+/// Test validating files that pass and fail the schema check
+#[test]
+fn test_validate_only() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let good = temp_dir.path().join("good.csv");
+    let bad = temp_dir.path().join("bad.csv");
+    std::fs::write(&good, "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,10,12,9,11,100\n")?;
+    std::fs::write(&bad, "Timestamp,Open,High,Low,Close,Volume\n2023-01-01 00:00:00,10,8,9,11,100\n")?;
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("validate")
+       .arg("--csv-file")
+       .arg(&good)
+       .arg("--validate-only")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .success()
+       .stdout(contains("PASS: 1 of 1 rows checked, 0 issues"));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("validate")
+       .arg("--csv-file")
+       .arg(&bad)
+       .arg("--validate-only")
+       .arg("--output-dir")
+       .arg(temp_dir.path().join("out"))
+       .assert()
+       .code(4)
+       .stdout(contains("FAIL: 1 of 1 rows checked, 2 issues"))
+       .stdout(contains("line 2: high 8 is below low 9"));
+    
+    let mut cmd = Command::cargo_bin("candle_stick_plotter")?;
+    cmd.arg("validate")
+       .arg("--csv-file")
+       .arg(&good)
+       .arg("--validate-only")
+       .arg("--sample-rows")
+       .arg("0")
+       .assert()
+       .code(2)
+       .stderr(contains("0 is not in 1.."));
+    
+    Ok(())
+}